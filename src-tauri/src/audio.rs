@@ -1,30 +1,117 @@
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::io::{BufRead, BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::{Child, ChildStdin, Command, Stdio};
-use std::sync::{mpsc, Arc, Mutex};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
 use std::thread;
-use tauri::{AppHandle, Emitter};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
 
-/// Returns the path for the persistent "last audio config" file.
-/// On Windows: %APPDATA%/com.kuro7983.auralynhost/last_audio_config.json
+/// Returns the path for a persistent host file.
+/// On Windows: %APPDATA%/com.kuro7983.auralynhost/<file_name>
 /// Fallback: next to the executable.
-fn last_config_path() -> PathBuf {
+fn app_data_path(file_name: &str) -> PathBuf {
     #[cfg(windows)]
     {
         if let Ok(appdata) = std::env::var("APPDATA") {
             let dir = PathBuf::from(appdata).join("com.kuro7983.auralynhost");
             let _ = std::fs::create_dir_all(&dir);
-            return dir.join("last_audio_config.json");
+            return dir.join(file_name);
         }
     }
     // Fallback
     let mut p = std::env::current_exe().unwrap_or_default();
-    p.set_file_name("last_audio_config.json");
+    p.set_file_name(file_name);
     p
 }
 
+/// The "last audio config" file used for fast auto-start.
+fn last_config_path() -> PathBuf {
+    app_data_path("last_audio_config.json")
+}
+
+/// Engine session autosave (see `crate::session`).
+fn session_path() -> PathBuf {
+    app_data_path("session.json")
+}
+
+/// Written by the engine's crash handler (see `audio_engine::crash_guard`).
+fn crash_marker_path() -> PathBuf {
+    app_data_path("engine_crash.json")
+}
+
+/// MIDI controller mappings, kept with presets and the blacklist in the config dir.
+pub fn midi_mappings_path(app: &AppHandle) -> Result<PathBuf> {
+    Ok(midi_map::mappings_path(&app.path().app_config_dir()?))
+}
+
+/// Changes the global mute from a hardware control (the Alt+M hotkey, the tray menu, a MIDI
+/// mapping) without blocking the caller's thread behind a slow engine command. `None`
+/// toggles.
+pub fn spawn_global_mute(app: &AppHandle, active: Option<bool>) {
+    let Some(state) = app.try_state::<AudioState>() else {
+        return;
+    };
+    let state = state.inner().clone();
+    tauri::async_runtime::spawn(async move {
+        let result = match active {
+            Some(active) => state.set_global_mute(active).await,
+            None => state.toggle_global_mute().await,
+        };
+        if let Err(e) = result {
+            log::warn!("Global mute toggle failed: {}", e);
+        }
+    });
+}
+
+/// Held while the mappings file is read or written, so a mapping learned while the user
+/// edits the list is not lost.
+static MIDI_MAPPINGS_LOCK: Mutex<()> = Mutex::new(());
+
+fn lock_midi_mappings() -> Result<MutexGuard<'static, ()>> {
+    MIDI_MAPPINGS_LOCK
+        .lock()
+        .map_err(|_| anyhow!("Failed to lock MIDI mappings"))
+}
+
+/// Saves a mapping made in learn mode on a blocking thread, then reports it as
+/// "midi-learned". The caller is the engine reader, which must not wait on the disk.
+fn spawn_save_learned_mapping(app: &AppHandle, mapping: MidiMapping) {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = save_learned_mapping(&app, mapping.clone()) {
+            log::warn!("Failed to save MIDI mapping: {:#}", e);
+        }
+        let _ = app.emit("midi-learned", mapping);
+    });
+}
+
+/// Saves a mapping made in learn mode, replacing the target's previous one like the
+/// engine did.
+fn save_learned_mapping(app: &AppHandle, mapping: MidiMapping) -> Result<()> {
+    let path = midi_mappings_path(app)?;
+    let _lock = lock_midi_mappings()?;
+    let mut mappings = midi_map::load_mappings(&path)?;
+    midi_map::upsert(&mut mappings, mapping);
+    midi_map::save_mappings(&path, &mappings)
+}
+
+/// Reads and removes the culprit the last engine process left behind, if any.
+fn take_crash_marker() -> Option<CrashCulprit> {
+    let path = crash_marker_path();
+    let json = std::fs::read(&path).ok()?;
+    let _ = std::fs::remove_file(&path);
+    match serde_json::from_slice(&json) {
+        Ok(culprit) => Some(culprit),
+        Err(e) => {
+            log::warn!("Ignoring unreadable crash marker {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
 #[cfg(windows)]
 mod win_job {
     use windows::Win32::Foundation::{CloseHandle, HANDLE};
@@ -76,7 +163,95 @@ mod win_job {
 }
 
 // Use shared IPC types
-use crate::ipc::{Command as IpcCommand, EngineEvent, OutputMessage, Response as IpcResponse};
+use crate::audio_engine::crash_guard::CRASH_MARKER_ENV;
+use crate::ipc::{
+    AppliedPlugin, Command as IpcCommand, CrashCulprit, CrashPhase, EngineEvent, EngineSnapshot,
+    EngineVersion, MidiAssignment, OutputMessage, Request as IpcRequest, RequestId,
+    Response as IpcResponse, TransportSettings, PROTOCOL_VERSION,
+};
+use crate::ipc_transport::{self, HostChannel};
+use crate::midi_map::{self, MidiMapping, MidiTarget};
+use crate::session::{self, SessionState};
+use crate::supervisor::{EngineFailure, Quarantine, QuarantineAction};
+use crate::vst_host::params::PluginParameter;
+use crate::vst_host::presets::PresetPlugin;
+use crate::vst_host::programs::ProgramList;
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+pub const SESSION_AUTOSAVE_INTERVAL: Duration = Duration::from_secs(30);
+// How long quitting waits for the last autosave; a hung engine must not keep the app open.
+pub const SESSION_QUIT_SAVE_TIMEOUT: Duration = Duration::from_secs(3);
+// The engine answers Hello before touching any device, so this can be short.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+// Covers COM/GDI+ startup in the sidecar before it connects back.
+const IPC_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// The engine's event loop sends a heartbeat every second. Loading a plugin or restoring its
+// state blocks the loop, so the engine gets much longer while it reports doing that.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(15);
+const BUSY_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Requests in flight for one engine process, keyed by request id.
+type PendingReplies = Arc<Mutex<HashMap<RequestId, mpsc::Sender<IpcResponse>>>>;
+
+/// Liveness of one engine process, kept up to date by its IPC reader thread.
+struct EngineHealth {
+    last_heartbeat: Instant,
+    /// Plugin the engine's main thread last reported being busy with.
+    activity: Option<CrashCulprit>,
+    channel_closed: bool,
+}
+
+type SharedHealth = Arc<Mutex<EngineHealth>>;
+
+fn new_health() -> SharedHealth {
+    Arc::new(Mutex::new(EngineHealth {
+        last_heartbeat: Instant::now(),
+        activity: None,
+        channel_closed: false,
+    }))
+}
+
+/// Handle to a request that has been written to the engine but not answered yet.
+/// Dropping it unregisters the id, so a reply arriving later is discarded.
+pub struct PendingReply {
+    id: RequestId,
+    rx: mpsc::Receiver<IpcResponse>,
+    pending: PendingReplies,
+}
+
+impl PendingReply {
+    /// Blocks until the engine answers this request. Must not be called while holding
+    /// the `AudioState` lock if other commands should stay responsive.
+    pub fn wait(self) -> Result<IpcResponse> {
+        self.wait_timeout(REPLY_TIMEOUT)
+    }
+
+    pub fn wait_timeout(self, timeout: Duration) -> Result<IpcResponse> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(resp) => Ok(resp),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                Err(anyhow!("Timeout waiting for engine response"))
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => Err(anyhow!("Engine Crashed/Exited")),
+        }
+    }
+}
+
+impl Drop for PendingReply {
+    fn drop(&mut self) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&self.id);
+        }
+    }
+}
+
+fn expect_success(resp: IpcResponse) -> Result<()> {
+    match resp {
+        IpcResponse::Success => Ok(()),
+        IpcResponse::Error(e) => Err(anyhow!(e)),
+        _ => Err(anyhow!("Unexpected response type")),
+    }
+}
 
 // Re-export for frontend
 #[derive(Debug, Serialize, Clone)]
@@ -111,6 +286,22 @@ pub struct ActiveAudioConfig {
     pub sample_rate: Option<u32>,
 }
 
+/// Result of `set_parameter`: what the plugin made of the value, for the slider and label.
+#[derive(Debug, Serialize, Clone)]
+pub struct ParameterValue {
+    pub param_id: u32,
+    pub value: f64,
+    pub display: String,
+}
+
+/// A plugin's state chunks (Base64), as stored in `PresetPlugin`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PluginStateData {
+    pub state: String,
+    #[serde(default)]
+    pub controller_state: Option<String>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct AudioStateInfo {
     pub is_running: bool,
@@ -158,17 +349,185 @@ pub struct EngineRuntimeStats {
     pub noise_reduction_mode: String,
 }
 
+/// Routes one engine message: replies to their waiting request, events to the UI.
+fn dispatch_engine_message(
+    msg: OutputMessage,
+    pending: &PendingReplies,
+    health: &SharedHealth,
+    emitter: &Arc<Mutex<Option<AppHandle>>>,
+) {
+    match msg {
+        OutputMessage::Response(reply) => {
+            let waiter = reply.id.and_then(|id| pending.lock().unwrap().remove(&id));
+            match waiter {
+                Some(tx) => {
+                    let _ = tx.send(reply.response);
+                }
+                // Caller timed out or went away; nothing to deliver to.
+                None => log::debug!(
+                    "Dropping reply for request {:?}: {:?}",
+                    reply.id,
+                    reply.response
+                ),
+            }
+        }
+        OutputMessage::Event(evt) => match evt {
+            EngineEvent::Log(s) => log::info!("[Engine] {}", s),
+            EngineEvent::Error(s) => {
+                log::error!("[Engine] {}", s);
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    let _ = h.emit("audio-stream-error", &s);
+                }
+            }
+            EngineEvent::LevelMeter(levels) => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    if let Err(e) = h.emit("audio-level", levels) {
+                        log::warn!("Failed to emit audio-level: {}", e);
+                    }
+                }
+            }
+            EngineEvent::ChannelLevels(levels) => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    if let Err(e) = h.emit("audio-channel-scan", levels) {
+                        log::warn!("Failed to emit audio-channel-scan: {}", e);
+                    }
+                }
+            }
+            EngineEvent::Heartbeat => {
+                health.lock().unwrap().last_heartbeat = Instant::now();
+            }
+            EngineEvent::Activity(activity) => {
+                // Also proof of life: a long chain load sends these between heartbeats
+                let mut health = health.lock().unwrap();
+                health.last_heartbeat = Instant::now();
+                health.activity = activity;
+            }
+            EngineEvent::Started {
+                sample_rate,
+                buffer_size,
+            } => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    #[derive(serde::Serialize, Clone)]
+                    struct StartedPayload {
+                        sample_rate: u32,
+                        buffer_size: u32,
+                    }
+                    let _ = h.emit(
+                        "audio-started",
+                        StartedPayload {
+                            sample_rate,
+                            buffer_size,
+                        },
+                    );
+                }
+            }
+            EngineEvent::ParameterChanged {
+                id,
+                param_id,
+                value,
+                display,
+            } => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    #[derive(serde::Serialize, Clone)]
+                    struct ParameterChangedPayload {
+                        id: String,
+                        param_id: u32,
+                        value: f64,
+                        display: String,
+                    }
+                    let _ = h.emit(
+                        "plugin-parameter-changed",
+                        ParameterChangedPayload {
+                            id,
+                            param_id,
+                            value,
+                            display,
+                        },
+                    );
+                }
+            }
+            EngineEvent::ParametersChanged { id } => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    let _ = h.emit("plugin-parameters-changed", id);
+                }
+            }
+            EngineEvent::MidiLearned(mapping) => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    spawn_save_learned_mapping(h, mapping);
+                }
+            }
+            EngineEvent::MidiApplied(cmd) => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    let _ = h.emit("midi-control", cmd);
+                }
+            }
+            EngineEvent::GlobalMuteRequested { active } => {
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    spawn_global_mute(h, active);
+                }
+            }
+            EngineEvent::PluginRestarted {
+                id,
+                latency_samples,
+                total_chain_latency_samples,
+            } => {
+                log::info!(
+                    "Plugin {} restarted (latency {} samples, chain {} samples)",
+                    id,
+                    latency_samples,
+                    total_chain_latency_samples
+                );
+                if let Some(h) = emitter.lock().unwrap().as_ref() {
+                    #[derive(serde::Serialize, Clone)]
+                    struct PluginRestartedPayload {
+                        id: String,
+                        latency_samples: u32,
+                        total_chain_latency_samples: u32,
+                    }
+                    let _ = h.emit(
+                        "plugin-restarted",
+                        PluginRestartedPayload {
+                            id,
+                            latency_samples,
+                            total_chain_latency_samples,
+                        },
+                    );
+                }
+            }
+        },
+    }
+}
+
 pub struct AudioHost {
     child: Option<Child>,
     stdin: Option<BufWriter<ChildStdin>>,
-    // We store a sender to satisfy a waiting command.
-    // Since we assume sequential commands from the UI (mutex locked AudioState),
-    // we only have one pending request at a time.
-    pending_reply_tx: Arc<Mutex<Option<mpsc::Sender<IpcResponse>>>>,
+    // Replies are routed by request id, so several commands can be in flight at once.
+    // Each engine process gets its own map (see ensure_engine_running).
+    pending_replies: PendingReplies,
+    health: SharedHealth,
+    next_request_id: RequestId,
+    engine_version: Option<EngineVersion>,
     emitter: Arc<Mutex<Option<AppHandle>>>,
     cached_devices: Option<AudioDeviceList>,
     active_config: Option<ActiveAudioConfig>,
     is_global_muted: bool,
+    // Set once the saved session has been replayed into the current engine process.
+    // Autosave stays off until then, so a fresh (empty) engine never overwrites the file.
+    session_restored: bool,
+    // A restore is in flight (without the lock); keeps a second one from starting.
+    session_restoring: bool,
+    // From app setup until the launch warmup, and the restore it starts, has finished.
+    warming_up: bool,
+    // Notified whenever `warming_up` or `session_restoring` clears.
+    restore_done: Arc<Condvar>,
+    // Audio was started and not stopped since; the supervisor restarts it after a crash.
+    audio_active: bool,
+    // Unexpected exit noticed but not yet picked up by the supervisor.
+    failure: Option<EngineFailure>,
+    // Between the supervisor picking up a failure and restarting the engine. Nothing else
+    // may spawn an engine meanwhile, or it would come up without the restored session.
+    restart_pending: bool,
+    quarantine: Quarantine,
     engine_tuning: EngineTuningConfig,
     #[cfg(windows)]
     engine_job: Option<win_job::Job>,
@@ -179,11 +538,22 @@ impl AudioHost {
         Self {
             child: None,
             stdin: None,
-            pending_reply_tx: Arc::new(Mutex::new(None)),
+            pending_replies: Arc::new(Mutex::new(HashMap::new())),
+            health: new_health(),
+            next_request_id: 0,
+            engine_version: None,
             emitter: Arc::new(Mutex::new(None)),
             cached_devices: None,
             active_config: None,
             is_global_muted: false,
+            session_restored: false,
+            session_restoring: false,
+            warming_up: false,
+            restore_done: Arc::new(Condvar::new()),
+            audio_active: false,
+            failure: None,
+            restart_pending: false,
+            quarantine: Quarantine::default(),
             engine_tuning: EngineTuningConfig::default(),
             #[cfg(windows)]
             engine_job: None,
@@ -237,39 +607,18 @@ impl AudioHost {
     }
 
     fn ensure_engine_running(&mut self) -> Result<()> {
-        // ... (check child logic same as before) ...
-        if self.child.is_some() {
-            // Check if still alive
-            if let Some(c) = self.child.as_mut() {
-                match c.try_wait() {
-                    Ok(Some(status)) => {
-                        log::error!(
-                            "Audio Engine exited unexpectedly! Status: {}",
-                            status
-                        );
-                        log::warn!("Forcing restart with empty state");
-                        self.child = None;
-                        self.stdin = None;
-                        #[cfg(windows)]
-                        {
-                            self.engine_job = None;
-                        }
-                    }
-                    Ok(None) => return Ok(()), // Running
-                    Err(e) => {
-                        log::error!("Error waiting on audio engine child process: {}", e);
-                        self.child = None;
-                        self.stdin = None;
-                        #[cfg(windows)]
-                        {
-                            self.engine_job = None;
-                        }
-                    }
-                }
-            }
+        if self.engine_alive() {
+            return Ok(());
+        }
+        if self.failure.is_some() || self.restart_pending {
+            // The supervisor respawns it with the previous config and chain
+            return Err(anyhow!("Audio Engine crashed and is being restarted"));
         }
 
         log::info!("Spawning Audio Engine Sidecar...");
+        self.session_restored = false;
+        self.session_restoring = false;
+        self.restore_done.notify_all();
         let cwd = std::env::current_dir()?;
         log::debug!("  CWD: {:?}", cwd);
 
@@ -351,28 +700,35 @@ impl AudioHost {
 
         log::info!("Found engine at: {:?}", binary_path);
 
+        // Dedicated channel for replies/events; stdout stays free for plugin prints.
+        let channel = HostChannel::bind()?;
+
         #[cfg(windows)]
         let mut child = {
             use std::os::windows::process::CommandExt;
             const CREATE_NO_WINDOW: u32 = 0x08000000;
-            let mut command = Command::new(binary_path);
+            let mut command = Command::new(&binary_path);
             command
                 .stdin(Stdio::piped())
                 .stdout(Stdio::piped())
                 .stderr(Stdio::inherit())
                 .creation_flags(CREATE_NO_WINDOW);
             self.apply_engine_tuning_env(&mut command);
+            command.envs(channel.env());
+            command.env(CRASH_MARKER_ENV, crash_marker_path());
             command.spawn()?
         };
 
         #[cfg(not(windows))]
         let mut child = {
-            let mut command = Command::new(binary_path);
+            let mut command = Command::new(&binary_path);
             command
                 .stdin(Stdio::piped())
                 .stdout(Stdio::piped())
                 .stderr(Stdio::inherit());
             self.apply_engine_tuning_env(&mut command);
+            command.envs(channel.env());
+            command.env(CRASH_MARKER_ENV, crash_marker_path());
             command.spawn()?
         };
 
@@ -395,136 +751,169 @@ impl AudioHost {
         self.stdin = Some(stdin);
         self.child = Some(child);
 
+        // Stdout now only carries whatever plugins print.
+        thread::spawn(move || {
+            for line in stdout.lines() {
+                let Ok(l) = line else { break };
+                if !l.trim().is_empty() {
+                    log::info!("[Plugin stdout] {}", l.trim_end());
+                }
+            }
+        });
+
+        let mut ipc_stream = match channel.accept(IPC_CONNECT_TIMEOUT) {
+            Ok(stream) => BufReader::new(stream),
+            Err(e) => {
+                log::error!("Audio Engine IPC channel failed ({:?}): {}", binary_path, e);
+                self.kill_engine();
+                return Err(anyhow!(
+                    "{}. audio_engine.exe is probably outdated; please reinstall Auralyn.",
+                    e
+                ));
+            }
+        };
+
+        // Fresh reply map and health: the old reader thread may still be failing its own requests.
+        self.pending_replies = Arc::new(Mutex::new(HashMap::new()));
+        self.health = new_health();
+
         // Spawn Output Reader Thread
-        let pending_tx_clone = self.pending_reply_tx.clone();
+        let pending_clone = self.pending_replies.clone();
+        let health_clone = self.health.clone();
         let emitter_clone = self.emitter.clone();
 
         thread::spawn(move || {
-            for line in stdout.lines() {
-                if let Ok(l) = line {
-                    if l.trim().is_empty() {
-                        continue;
-                    }
-
-                    if l.starts_with("IPC:") {
-                        let json_str = &l[4..]; // Strip "IPC:"
-                        match serde_json::from_str::<OutputMessage>(json_str) {
-                            Ok(msg) => match msg {
-                                OutputMessage::Response(resp) => {
-                                    let mut lock = pending_tx_clone.lock().unwrap();
-                                    if let Some(tx) = lock.take() {
-                                        let _ = tx.send(resp);
-                                    } else {
-                                        log::warn!(
-                                            "Received Response but no one waiting: {:?}",
-                                            resp
-                                        );
-                                    }
-                                }
-                                OutputMessage::Event(evt) => match evt {
-                                    EngineEvent::Log(s) => log::info!("[Engine] {}", s),
-                                    EngineEvent::Error(s) => {
-                                        log::error!("[Engine] {}", s);
-                                        if let Some(h) = emitter_clone.lock().unwrap().as_ref() {
-                                            let _ = h.emit("audio-stream-error", &s);
-                                        }
-                                    }
-                                    EngineEvent::LevelMeter(levels) => {
-                                        if let Some(h) = emitter_clone.lock().unwrap().as_ref() {
-                                            if let Err(e) = h.emit("audio-level", levels) {
-                                                log::warn!("Failed to emit audio-level: {}", e);
-                                            }
-                                        }
-                                    }
-                                    EngineEvent::ChannelLevels(levels) => {
-                                        if let Some(h) = emitter_clone.lock().unwrap().as_ref() {
-                                            if let Err(e) = h.emit("audio-channel-scan", levels) {
-                                                log::warn!("Failed to emit audio-channel-scan: {}", e);
-                                            }
-                                        }
-                                    }
-                                    EngineEvent::Started {
-                                        sample_rate,
-                                        buffer_size,
-                                    } => {
-                                        if let Some(h) = emitter_clone.lock().unwrap().as_ref() {
-                                            #[derive(serde::Serialize, Clone)]
-                                            struct StartedPayload {
-                                                sample_rate: u32,
-                                                buffer_size: u32,
-                                            }
-                                            let _ = h.emit(
-                                                "audio-started",
-                                                StartedPayload {
-                                                    sample_rate,
-                                                    buffer_size,
-                                                },
-                                            );
-                                        }
-                                    }
-                                },
-                            },
-                            Err(e) => {
-                                log::error!("Engine IPC parse error: {} (Line: {})", e, l);
-                            }
+            loop {
+                match ipc_transport::read_raw_frame(&mut ipc_stream) {
+                    Ok(Some((encoding, payload))) => {
+                        match ipc_transport::decode_message(encoding, &payload) {
+                            Ok(msg) => dispatch_engine_message(
+                                msg,
+                                &pending_clone,
+                                &health_clone,
+                                &emitter_clone,
+                            ),
+                            // The frame itself was intact, so the next one can still be read
+                            Err(e) => log::warn!("Skipping undecodable engine message: {}", e),
                         }
-                    } else {
-                        // Non-IPC line (External Log from VST or simple print)
-                        log::trace!("[Engine Raw] {}", l);
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        // Framing is lost after a bad frame; treat it like a dead engine.
+                        log::error!("Engine IPC read error: {}", e);
+                        break;
                     }
                 }
             }
 
-            log::warn!("Engine stdout closed.");
-            // Notify Frontend of crash/exit
-            if let Some(h) = emitter_clone.lock().unwrap().as_ref() {
-                let _ = h.emit("audio-error", "Audio Engine Process Exited (Crash?)");
-            }
+            // The supervisor notices the exit and tells the UI what happened
+            log::warn!("Engine IPC channel closed.");
+            health_clone.lock().unwrap().channel_closed = true;
 
-            // UX FIX: Abort any pending command to prevent 10s timeout
+            // UX FIX: Abort pending commands to prevent 10s timeout
             {
-                let mut lock = pending_tx_clone.lock().unwrap();
-                if let Some(tx) = lock.take() {
-                    log::warn!("Aborting pending command due to engine exit.");
+                let mut lock = pending_clone.lock().unwrap();
+                if !lock.is_empty() {
+                    log::warn!(
+                        "Aborting {} pending command(s) due to engine exit.",
+                        lock.len()
+                    );
+                }
+                for (_, tx) in lock.drain() {
                     let _ = tx.send(IpcResponse::Error("Engine Crashed/Exited".to_string()));
                 }
             }
         });
 
+        if let Err(e) = self.handshake() {
+            log::error!("Audio Engine handshake failed ({:?}): {}", binary_path, e);
+            self.kill_engine();
+            return Err(e);
+        }
+
         Ok(())
     }
 
-    fn execute_command(&mut self, cmd: IpcCommand) -> Result<IpcResponse> {
+    /// Checks that the freshly spawned engine speaks our protocol. A stale
+    /// `audio_engine.exe` left next to a newer UI is the usual culprit.
+    fn handshake(&mut self) -> Result<()> {
+        let reply = self
+            .send_request(IpcCommand::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            })?
+            .wait_timeout(HANDSHAKE_TIMEOUT)
+            .map_err(|e| {
+                anyhow!(
+                    "Audio engine did not answer the version handshake ({}). \
+                     audio_engine.exe is probably outdated; please reinstall Auralyn.",
+                    e
+                )
+            })?;
+
+        let version = match reply {
+            IpcResponse::Version(version) => version,
+            IpcResponse::Error(e) => return Err(anyhow!("Audio engine rejected handshake: {}", e)),
+            other => return Err(anyhow!("Unexpected handshake response: {:?}", other)),
+        };
+
+        if version.protocol_version != PROTOCOL_VERSION {
+            return Err(anyhow!(
+                "Incompatible audio engine {} (protocol v{}, this app needs v{}). \
+                 Please reinstall Auralyn so audio_engine.exe matches the app.",
+                version.engine_version,
+                version.protocol_version,
+                PROTOCOL_VERSION
+            ));
+        }
+
+        log::info!(
+            "Audio Engine {} ready (protocol v{}, hosts={:?}, max_plugins={})",
+            version.engine_version,
+            version.protocol_version,
+            version.capabilities.hosts,
+            version.capabilities.max_plugins
+        );
+        self.engine_version = Some(version);
+        Ok(())
+    }
+
+    /// Version and capabilities reported by the running engine.
+    pub fn engine_version(&self) -> Option<&EngineVersion> {
+        self.engine_version.as_ref()
+    }
+
+    /// Writes `cmd` to the engine and returns without waiting for the reply.
+    pub fn submit(&mut self, cmd: IpcCommand) -> Result<PendingReply> {
         self.ensure_engine_running()?;
+        self.send_request(cmd)
+    }
 
-        let json = serde_json::to_string(&cmd)?;
+    fn send_request(&mut self, cmd: IpcCommand) -> Result<PendingReply> {
+        self.next_request_id += 1;
+        let id = self.next_request_id;
+        let json = serde_json::to_string(&IpcRequest { id, command: cmd })?;
 
-        // Create Channel
+        // Register before writing so a fast reply cannot be missed
         let (tx, rx) = mpsc::channel();
-        {
-            let mut lock = self.pending_reply_tx.lock().unwrap();
-            *lock = Some(tx);
-        }
+        self.pending_replies.lock().unwrap().insert(id, tx);
+        let reply = PendingReply {
+            id,
+            rx,
+            pending: self.pending_replies.clone(),
+        };
 
-        // Send
-        if let Some(stdin) = &mut self.stdin {
-            writeln!(stdin, "{}", json)?;
-            stdin.flush()?;
-        } else {
-            return Err(anyhow!("Stdin not available"));
-        }
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| anyhow!("Stdin not available"))?;
+        writeln!(stdin, "{}", json)?;
+        stdin.flush()?;
 
-        // Wait
-        // Timeout? 5 seconds?
-        match rx.recv_timeout(std::time::Duration::from_secs(10)) {
-            Ok(resp) => Ok(resp),
-            Err(_) => {
-                // Clear pending
-                let mut lock = self.pending_reply_tx.lock().unwrap();
-                *lock = None;
-                Err(anyhow!("Timeout waiting for engine response"))
-            }
-        }
+        Ok(reply)
+    }
+
+    fn execute_command(&mut self, cmd: IpcCommand) -> Result<IpcResponse> {
+        self.submit(cmd)?.wait()
     }
 
     pub fn enumerate_devices(&mut self, force_refresh: bool) -> Result<AudioDeviceList> {
@@ -586,6 +975,8 @@ impl AudioHost {
                 sample_rate,
                 buffer_size,
             } => {
+                self.audio_active = true;
+
                 // Restore global mute state if active (because engine process is fresh)
                 if self.is_global_muted {
                     log::info!("Restoring Global Mute State...");
@@ -595,6 +986,9 @@ impl AudioHost {
                     }
                 }
 
+                // The session and MIDI mappings are replayed by the caller once the lock is
+                // released (`AudioState::restore_engine_state`)
+
                 // Update active config
                 let config = ActiveAudioConfig {
                     host: host_name.clone().unwrap_or("ASIO".to_string()),
@@ -635,184 +1029,818 @@ impl AudioHost {
         }
     }
 
-    pub fn stop(&mut self) {
-        let _ = self.execute_command(IpcCommand::Stop);
+    /// Records how replaying the autosaved session went. A session that failed to apply
+    /// (missing plugin, engine crash while loading) is moved aside so the next start does
+    /// not hit the same problem again.
+    fn finish_session_restore(&mut self, path: &Path, result: Result<Option<usize>>) {
+        match result {
+            Ok(Some(plugin_count)) => log::info!(
+                "Restored session ({} plugins) from {:?}",
+                plugin_count,
+                path
+            ),
+            Ok(None) => log::debug!("No saved session at {:?}", path),
+            Err(e) if self.engine_lost() => {
+                // Most likely a plugin in the chain took the engine down; the supervisor
+                // restarts it and quarantines the plugin if it keeps doing that.
+                log::warn!("Engine died while restoring session: {:#}", e);
+            }
+            Err(e) => {
+                log::warn!("Failed to restore session: {:#}", e);
+                match session::set_aside(path) {
+                    Ok(failed) => log::warn!("Moved unusable session to {:?}", failed),
+                    Err(e) => log::warn!("{:#}", e),
+                }
+                if let Some(h) = self.emitter.lock().unwrap().as_ref() {
+                    let _ = h.emit("session-restore-failed", format!("{:#}", e));
+                }
+            }
+        }
+        self.session_restoring = false;
+        self.session_restored = true;
+        self.restore_done.notify_all();
     }
 
-    pub fn load_plugin(&mut self, path: &str) -> Result<String> {
-        match self.execute_command(IpcCommand::LoadPlugin {
-            path: path.to_string(),
-        })? {
-            IpcResponse::PluginLoaded {
-                id,
-                name: _,
-                vendor: _,
-            } => Ok(id),
-            IpcResponse::Error(e) => Err(anyhow!(e)),
-            _ => Err(anyhow!("Unexpected response type")),
+    pub fn set_engine_tuning_config(&mut self, mut config: EngineTuningConfig) {
+        config.affinity_mask = config
+            .affinity_mask
+            .as_ref()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        self.engine_tuning = config;
+    }
+
+    pub fn get_engine_tuning_config(&self) -> EngineTuningConfig {
+        self.engine_tuning.clone()
+    }
+
+    /// Reaps an engine process that exited on its own. Returns false if none is running.
+    fn engine_alive(&mut self) -> bool {
+        let Some(child) = self.child.as_mut() else {
+            return false;
+        };
+        match child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                self.reap_engine(format!("exited with {}", status));
+                false
+            }
+            Err(e) => {
+                self.reap_engine(format!("could not be waited on: {}", e));
+                false
+            }
         }
     }
 
-    pub fn remove_plugin(&mut self, id: &str) -> Result<()> {
-        match self.execute_command(IpcCommand::UnloadPlugin { id: id.to_string() })? {
-            IpcResponse::Success => Ok(()),
-            IpcResponse::Error(e) => Err(anyhow!(e)),
-            _ => Err(anyhow!("Unexpected response type")),
+    /// True if the engine is gone or about to be (IPC channel closed, exit not reaped yet).
+    fn engine_lost(&mut self) -> bool {
+        !self.engine_alive() || self.health.lock().unwrap().channel_closed
+    }
+
+    /// Forgets a dead engine process and records why it died for the supervisor.
+    fn reap_engine(&mut self, reason: String) {
+        let culprit = take_crash_marker().or_else(|| self.health.lock().unwrap().activity.clone());
+        log::error!("Audio Engine {} (culprit: {:?})", reason, culprit);
+        self.failure = Some(EngineFailure { reason, culprit });
+        self.stdin = None;
+        self.child = None;
+        self.engine_version = None;
+        #[cfg(windows)]
+        {
+            self.engine_job = None;
         }
     }
 
-    pub fn reorder_plugins(&mut self, order: Vec<String>) -> Result<()> {
-        match self.execute_command(IpcCommand::ReorderPlugins { order })? {
-            IpcResponse::Success => Ok(()),
-            IpcResponse::Error(e) => Err(anyhow!(e)),
-            _ => Err(anyhow!("Unexpected response type")),
+    /// Called by the supervisor: returns a failure of the current engine (exit, or no
+    /// heartbeat for too long, in which case the engine is killed) and blocks respawning
+    /// until `restart_after_crash` or `cancel_restart`.
+    pub fn check_engine_health(&mut self) -> Option<EngineFailure> {
+        if self.engine_alive() {
+            let (silence, busy) = {
+                let health = self.health.lock().unwrap();
+                let busy = health
+                    .activity
+                    .as_ref()
+                    .is_some_and(|a| matches!(a.phase, CrashPhase::Loading | CrashPhase::State));
+                (health.last_heartbeat.elapsed(), busy)
+            };
+            let timeout = if busy {
+                BUSY_HEARTBEAT_TIMEOUT
+            } else {
+                HEARTBEAT_TIMEOUT
+            };
+            if silence > timeout {
+                if let Some(child) = self.child.as_mut() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                }
+                self.reap_engine(format!("stopped responding for {}s", silence.as_secs()));
+            }
         }
+
+        let failure = self.failure.take()?;
+        self.restart_pending = true;
+        Some(failure)
     }
 
-    pub fn set_bypass(&mut self, id: &str, active: bool) -> Result<()> {
-        match self.execute_command(IpcCommand::SetBypass {
-            id: id.to_string(),
-            active,
-        })? {
-            IpcResponse::Success => Ok(()),
-            IpcResponse::Error(e) => Err(anyhow!(e)),
-            _ => Err(anyhow!("Unexpected response type")),
+    /// Brings the engine back after a failure: restarts audio with the last device config
+    /// if it was running. Returns the new audio config, `None` if audio was not running.
+    /// The session is replayed by `AudioState::restart_after_crash`.
+    fn restart_after_crash(&mut self) -> Result<Option<AudioConfig>> {
+        self.restart_pending = false;
+        if self.engine_alive() {
+            // Restarted by hand in the meantime
+            return Ok(None);
+        }
+
+        match self.active_config.clone().filter(|_| self.audio_active) {
+            Some(config) => self
+                .start(
+                    Some(config.host),
+                    config.input,
+                    config.output,
+                    config.buffer_size,
+                    config.sample_rate,
+                )
+                .map(Some),
+            None => {
+                self.ensure_engine_running()?;
+                Ok(None)
+            }
         }
     }
 
-    pub fn set_mute(&mut self, id: &str, active: bool) -> Result<()> {
-        match self.execute_command(IpcCommand::SetMute {
-            id: id.to_string(),
-            active,
-        })? {
-            IpcResponse::Success => Ok(()),
-            IpcResponse::Error(e) => Err(anyhow!(e)),
-            _ => Err(anyhow!("Unexpected response type")),
+    /// The supervisor gave up; the user restarts the engine by hand.
+    pub fn cancel_restart(&mut self) {
+        self.restart_pending = false;
+    }
+
+    /// Keeps a plugin that crashed the engine repeatedly out of automatic restores.
+    pub fn quarantine_plugin(&mut self, path: &str, action: QuarantineAction) {
+        self.quarantine.insert(path, action);
+    }
+
+    pub fn kill_engine(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            log::warn!("Force Killing Audio Engine...");
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        self.stdin = None;
+        self.child = None;
+        self.engine_version = None;
+        // Deliberate: nothing for the supervisor to recover
+        self.failure = None;
+        self.restart_pending = false;
+        #[cfg(windows)]
+        {
+            self.engine_job = None;
         }
     }
 
-    pub fn set_gain(&mut self, id: &str, value: f32) -> Result<()> {
-        match self.execute_command(IpcCommand::SetGain {
-            id: id.to_string(),
-            value,
-        })? {
-            IpcResponse::Success => Ok(()),
+    /// Spawns the engine and starts audio with the last working config, if there is one.
+    /// Returns whether audio was started.
+    fn warmup(&mut self) -> Result<bool> {
+        self.ensure_engine_running()?;
+
+        // Fast auto-start: read last successful config and start immediately
+        // This avoids waiting for the frontend to load and send the Start command.
+        let path = last_config_path();
+        match std::fs::read_to_string(&path) {
+            Ok(json) => match serde_json::from_str::<ActiveAudioConfig>(&json) {
+                Ok(config) if !config.host.is_empty() => {
+                    log::info!("Auto-starting audio with last config: {:?}", config);
+                    match self.start(
+                        Some(config.host),
+                        config.input,
+                        config.output,
+                        config.buffer_size,
+                        config.sample_rate,
+                    ) {
+                        Ok(res) => {
+                            log::info!(
+                                "Auto-start successful (SR={}, Buf={})",
+                                res.sample_rate,
+                                res.buffer_size
+                            );
+                            return Ok(true);
+                        }
+                        Err(e) => {
+                            log::warn!("Auto-start failed (user can start manually): {}", e);
+                        }
+                    }
+                }
+                Ok(_) => log::debug!("Last config has empty host, skipping auto-start"),
+                Err(e) => log::debug!("Failed to parse last config: {}", e),
+            },
+            Err(_) => log::debug!("No last audio config found, skipping auto-start"),
+        }
+
+        Ok(false)
+    }
+
+    pub fn get_state(&self) -> AudioStateInfo {
+        AudioStateInfo {
+            is_running: self.child.is_some(),
+            config: self.active_config.clone(),
+        }
+    }
+}
+
+impl Drop for AudioHost {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            log::info!("Killing Audio Engine Sidecar (Drop)...");
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        #[cfg(windows)]
+        {
+            self.engine_job = None;
+        }
+    }
+}
+
+// Global state container
+#[derive(Clone)]
+pub struct AudioState(pub Arc<Mutex<AudioHost>>);
+
+/// Async front for UI-driven commands. The host lock is held only while a request is
+/// written, never while waiting for the reply, so a slow `LoadPlugin` does not block the
+/// mute hotkey, the tray or stats polling.
+impl AudioState {
+    fn lock(&self) -> Result<MutexGuard<'_, AudioHost>> {
+        self.0
+            .lock()
+            .map_err(|_| anyhow!("Failed to lock audio state"))
+    }
+
+    /// Runs `f` on a blocking worker so waiting for the engine never stalls the async runtime.
+    async fn run_blocking<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(AudioState) -> Result<T> + Send + 'static,
+    {
+        let state = self.clone();
+        tauri::async_runtime::spawn_blocking(move || f(state))
+            .await
+            .map_err(|e| anyhow!("Engine request task failed: {}", e))?
+    }
+
+    /// Locks the host once neither the launch warmup nor a session restore is in flight, so
+    /// a UI request that reads or builds the chain can't race the engine's own replay of it.
+    fn lock_restored(&self) -> Result<MutexGuard<'_, AudioHost>> {
+        let mut host = self.lock()?;
+        let restore_done = host.restore_done.clone();
+        while host.warming_up || host.session_restoring {
+            host = restore_done
+                .wait(host)
+                .map_err(|_| anyhow!("Failed to lock audio state"))?;
+        }
+        Ok(host)
+    }
+
+    /// Submits a request that builds the chain from the UI. The chain is then the UI's, so
+    /// the engine doesn't replay its autosaved session over it later.
+    fn submit_ui_chain(&self, cmd: IpcCommand) -> Result<PendingReply> {
+        let mut host = self.lock_restored()?;
+        let pending = host.submit(cmd)?;
+        host.session_restored = true;
+        Ok(pending)
+    }
+
+    pub async fn request(&self, cmd: IpcCommand) -> Result<IpcResponse> {
+        self.run_blocking(move |state| {
+            let pending = state.lock()?.submit(cmd)?;
+            pending.wait()
+        })
+        .await
+    }
+
+    pub async fn start(
+        &self,
+        host: Option<String>,
+        input: Option<String>,
+        output: Option<String>,
+        buffer_size: Option<u32>,
+        sample_rate: Option<u32>,
+    ) -> Result<AudioConfig> {
+        self.run_blocking(move |state| {
+            let config = state
+                .lock()?
+                .start(host, input, output, buffer_size, sample_rate)?;
+            state.restore_engine_state();
+            Ok(config)
+        })
+        .await
+    }
+
+    /// Kills the engine and starts a fresh one with the given devices.
+    pub async fn restart(
+        &self,
+        host: Option<String>,
+        input: Option<String>,
+        output: Option<String>,
+        buffer_size: Option<u32>,
+        sample_rate: Option<u32>,
+    ) -> Result<AudioConfig> {
+        self.run_blocking(move |state| {
+            let config = {
+                let mut audio_host = state.lock()?;
+                audio_host.kill_engine();
+                audio_host.start(host, input, output, buffer_size, sample_rate)?
+            };
+            state.restore_engine_state();
+            Ok(config)
+        })
+        .await
+    }
+
+    pub async fn stop(&self) -> Result<()> {
+        self.run_blocking(|state| {
+            let pending = {
+                let mut host = state.lock()?;
+                host.audio_active = false;
+                host.submit(IpcCommand::Stop)
+            };
+            // Audio counts as stopped even if the engine doesn't answer
+            if let Ok(pending) = pending {
+                let _ = pending.wait();
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    /// Marks the launch warmup as pending before its thread starts, so UI requests that
+    /// arrive first wait for it (see `lock_restored`).
+    pub fn begin_warmup(&self) {
+        if let Ok(mut host) = self.lock() {
+            host.warming_up = true;
+        }
+    }
+
+    /// Spawns the engine at launch and auto-starts audio with the last config.
+    pub fn warmup(&self) -> Result<()> {
+        let result = self.lock().and_then(|mut host| host.warmup());
+        if matches!(result, Ok(true)) {
+            self.restore_engine_state();
+        }
+        let mut host = self.lock()?;
+        host.warming_up = false;
+        host.restore_done.notify_all();
+        result.map(|_| ())
+    }
+
+    /// Called by the supervisor after a crash: respawns the engine and replays the
+    /// autosaved session minus quarantined plugins.
+    pub fn restart_after_crash(&self) -> Result<Option<AudioConfig>> {
+        let audio = self.lock()?.restart_after_crash()?;
+        self.restore_engine_state();
+        Ok(audio)
+    }
+
+    /// Replays the autosaved session (once per engine process) and the MIDI mappings into
+    /// a freshly started engine. Requests are written under the lock but waited for without
+    /// it: `ApplyChain` may take `REPLY_TIMEOUT` per plugin, and holding the lock that long
+    /// would freeze the mute hotkey, the tray and every other command.
+    fn restore_engine_state(&self) {
+        if let Err(e) = self.restore_session() {
+            log::warn!("Failed to restore session: {:#}", e);
+        }
+        if let Err(e) = self.restore_midi_mappings() {
+            log::warn!("Failed to restore MIDI mappings: {:#}", e);
+        }
+    }
+
+    fn restore_session(&self) -> Result<()> {
+        {
+            let mut host = self.lock()?;
+            if host.session_restored || host.session_restoring {
+                return Ok(());
+            }
+            host.session_restoring = true;
+        }
+
+        let path = session_path();
+        let result = session::load_session(&path).and_then(|saved| match saved {
+            Some(saved) => self.apply_session(saved).map(Some),
+            None => Ok(None),
+        });
+        self.lock()?.finish_session_restore(&path, result);
+        Ok(())
+    }
+
+    /// Settings first, then the chain, as long as the settings applied.
+    fn apply_session(&self, mut saved: SessionState) -> Result<usize> {
+        let (left, right) = saved.input_channels;
+        let settings = [
+            IpcCommand::SetInputGain {
+                value: saved.input_gain,
+            },
+            IpcCommand::SetOutputGain {
+                value: saved.output_gain,
+            },
+            IpcCommand::SetNoiseReduction {
+                active: saved.noise_reduction_enabled,
+                mode: Some(saved.noise_reduction_mode),
+            },
+            IpcCommand::SetInputChannels { left, right },
+            IpcCommand::SetTransport(saved.transport),
+        ];
+        let pending = {
+            let mut host = self.lock()?;
+            for (name, action) in host.quarantine.apply(&mut saved.plugins) {
+                log::warn!("Restoring {} as quarantined ({:?})", name, action);
+            }
+            settings
+                .into_iter()
+                .map(|cmd| host.submit(cmd))
+                .collect::<Result<Vec<_>>>()?
+        };
+        for reply in pending {
+            expect_success(reply.wait()?)?;
+        }
+
+        let plugin_count = saved.plugins.len();
+        if plugin_count == 0 {
+            return Ok(0);
+        }
+        // Every plugin may take as long as a single LoadPlugin
+        let timeout = REPLY_TIMEOUT * plugin_count as u32;
+        let pending = self.lock()?.submit(IpcCommand::ApplyChain {
+            plugins: saved.plugins,
+        })?;
+        match pending.wait_timeout(timeout)? {
+            IpcResponse::ChainApplied(_) => Ok(plugin_count),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn open_editor(&mut self, id: &str) -> Result<()> {
-        match self.execute_command(IpcCommand::OpenEditor { id: id.to_string() })? {
-            IpcResponse::Success => Ok(()),
+    /// Hands the saved MIDI mappings to the engine.
+    fn restore_midi_mappings(&self) -> Result<()> {
+        let app = self.lock()?.emitter.lock().unwrap().clone();
+        let Some(app) = app else {
+            return Ok(());
+        };
+        let mappings = midi_map::load_mappings(&midi_mappings_path(&app)?)?;
+        if mappings.is_empty() {
+            return Ok(());
+        }
+        let pending = self.lock()?.submit(IpcCommand::SetMidiMappings(mappings))?;
+        expect_success(pending.wait()?)
+    }
+
+    pub async fn load_plugin(&self, path: String, class_id: Option<String>) -> Result<String> {
+        let cmd = IpcCommand::LoadPlugin { path, class_id };
+        let resp = self
+            .run_blocking(move |state| state.submit_ui_chain(cmd)?.wait())
+            .await?;
+        match resp {
+            IpcResponse::PluginLoaded {
+                id,
+                name: _,
+                vendor: _,
+            } => Ok(id),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn set_global_mute(&mut self, active: bool) -> Result<()> {
-        self.is_global_muted = active;
-        match self.execute_command(IpcCommand::SetGlobalMute { active })? {
-            IpcResponse::Success => {
-                // Emit event for UI update
-                if let Some(h) = self.emitter.lock().unwrap().as_ref() {
-                    let _ = h.emit("global-mute-changed", active);
-                }
-                Ok(())
-            }
+    /// Replaces the rack with `plugins` in one engine request (see `Command::ApplyChain`).
+    pub async fn apply_chain(&self, plugins: Vec<PresetPlugin>) -> Result<Vec<AppliedPlugin>> {
+        // Every new plugin may take as long as a single LoadPlugin
+        let timeout = REPLY_TIMEOUT * plugins.len().max(1) as u32;
+        let resp = self
+            .run_blocking(move |state| {
+                let pending = state.submit_ui_chain(IpcCommand::ApplyChain { plugins })?;
+                pending.wait_timeout(timeout)
+            })
+            .await?;
+
+        match resp {
+            IpcResponse::ChainApplied(applied) => Ok(applied),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn toggle_global_mute(&mut self) -> Result<()> {
-        let new_state = !self.is_global_muted;
-        self.set_global_mute(new_state)
+    pub async fn remove_plugin(&self, id: String) -> Result<()> {
+        expect_success(self.request(IpcCommand::UnloadPlugin { id }).await?)
+    }
+
+    pub async fn reorder_plugins(&self, order: Vec<String>) -> Result<()> {
+        expect_success(self.request(IpcCommand::ReorderPlugins { order }).await?)
+    }
+
+    pub async fn set_bypass(&self, id: String, active: bool) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetBypass { id, active }).await?)
+    }
+
+    pub async fn set_mute(&self, id: String, active: bool) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetMute { id, active }).await?)
+    }
+
+    pub async fn set_gain(&self, id: String, value: f32) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetGain { id, value }).await?)
+    }
+
+    pub async fn open_editor(&self, id: String) -> Result<()> {
+        expect_success(self.request(IpcCommand::OpenEditor { id }).await?)
     }
 
-    pub fn set_input_gain(&mut self, value: f32) -> Result<()> {
-        match self.execute_command(IpcCommand::SetInputGain { value })? {
-            IpcResponse::Success => Ok(()),
+    pub async fn get_parameters(&self, id: String) -> Result<Vec<PluginParameter>> {
+        match self.request(IpcCommand::GetParameters { id }).await? {
+            IpcResponse::Parameters { id: _, parameters } => Ok(parameters),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn set_noise_reduction(&mut self, active: bool, mode: Option<String>) -> Result<()> {
-        match self.execute_command(IpcCommand::SetNoiseReduction { active, mode })? {
-            IpcResponse::Success => Ok(()),
+    /// `value` is normalized (0.0 to 1.0).
+    pub async fn set_parameter(
+        &self,
+        id: String,
+        param_id: u32,
+        value: f64,
+    ) -> Result<ParameterValue> {
+        let cmd = IpcCommand::SetParameter {
+            id,
+            param_id,
+            value,
+        };
+        match self.request(cmd).await? {
+            IpcResponse::ParameterValue {
+                id: _,
+                param_id,
+                value,
+                display,
+            } => Ok(ParameterValue {
+                param_id,
+                value,
+                display,
+            }),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn set_output_gain(&mut self, value: f32) -> Result<()> {
-        match self.execute_command(IpcCommand::SetOutputGain { value })? {
-            IpcResponse::Success => Ok(()),
+    pub async fn get_plugin_state(&self, id: String) -> Result<PluginStateData> {
+        match self.request(IpcCommand::GetPluginState { id }).await? {
+            IpcResponse::PluginState {
+                id: _,
+                state,
+                controller_state,
+            } => Ok(PluginStateData {
+                state,
+                controller_state,
+            }),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn set_global_bypass(&mut self, active: bool) -> Result<()> {
-        match self.execute_command(IpcCommand::SetGlobalBypass { active })? {
-            IpcResponse::Success => Ok(()),
+    pub async fn set_plugin_state(&self, id: String, data: PluginStateData) -> Result<()> {
+        let cmd = IpcCommand::SetPluginState {
+            id,
+            state: data.state,
+            controller_state: data.controller_state,
+        };
+        expect_success(self.request(cmd).await?)
+    }
+
+    pub async fn export_vstpreset(&self, id: String, path: String) -> Result<()> {
+        expect_success(
+            self.request(IpcCommand::ExportVstPreset { id, path })
+                .await?,
+        )
+    }
+
+    pub async fn import_vstpreset(&self, id: String, path: String) -> Result<()> {
+        expect_success(
+            self.request(IpcCommand::ImportVstPreset { id, path })
+                .await?,
+        )
+    }
+
+    pub async fn get_programs(&self, id: String) -> Result<Vec<ProgramList>> {
+        match self.request(IpcCommand::GetPrograms { id }).await? {
+            IpcResponse::Programs { id: _, lists } => Ok(lists),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn set_input_channels(&mut self, left: usize, right: usize) -> Result<()> {
-        match self.execute_command(IpcCommand::SetInputChannels { left, right })? {
-            IpcResponse::Success => Ok(()),
+    /// Returns the program-change parameter's new value, like `set_parameter`.
+    pub async fn select_program(
+        &self,
+        id: String,
+        list_id: i32,
+        program_index: usize,
+    ) -> Result<ParameterValue> {
+        let cmd = IpcCommand::SelectProgram {
+            id,
+            list_id,
+            program_index,
+        };
+        match self.request(cmd).await? {
+            IpcResponse::ParameterValue {
+                id: _,
+                param_id,
+                value,
+                display,
+            } => Ok(ParameterValue {
+                param_id,
+                value,
+                display,
+            }),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn set_channel_scan(&mut self, active: bool) -> Result<()> {
-        match self.execute_command(IpcCommand::SetChannelScan { active })? {
-            IpcResponse::Success => Ok(()),
+    pub async fn set_global_mute(&self, active: bool) -> Result<()> {
+        self.apply_global_mute(move |_| active).await
+    }
+
+    pub async fn toggle_global_mute(&self) -> Result<()> {
+        self.apply_global_mute(|current| !current).await
+    }
+
+    /// Flips the cached state and sends it in one critical section, so two quick
+    /// toggles (hotkey + tray) cannot both read the same old value.
+    async fn apply_global_mute<F>(&self, next: F) -> Result<()>
+    where
+        F: FnOnce(bool) -> bool + Send + 'static,
+    {
+        self.run_blocking(move |state| {
+            let (active, pending, emitter) = {
+                let mut host = state.lock()?;
+                let active = next(host.is_global_muted);
+                host.is_global_muted = active;
+                let pending = host.submit(IpcCommand::SetGlobalMute { active })?;
+                (active, pending, host.emitter.clone())
+            };
+            expect_success(pending.wait()?)?;
+
+            // Emit event for UI update
+            if let Some(h) = emitter.lock().unwrap().as_ref() {
+                let _ = h.emit("global-mute-changed", active);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_input_gain(&self, value: f32) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetInputGain { value }).await?)
+    }
+
+    pub async fn set_noise_reduction(&self, active: bool, mode: Option<String>) -> Result<()> {
+        expect_success(
+            self.request(IpcCommand::SetNoiseReduction { active, mode })
+                .await?,
+        )
+    }
+
+    pub async fn set_output_gain(&self, value: f32) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetOutputGain { value }).await?)
+    }
+
+    pub async fn set_global_bypass(&self, active: bool) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetGlobalBypass { active }).await?)
+    }
+
+    pub async fn set_input_channels(&self, left: usize, right: usize) -> Result<()> {
+        expect_success(
+            self.request(IpcCommand::SetInputChannels { left, right })
+                .await?,
+        )
+    }
+
+    pub async fn set_channel_scan(&self, active: bool) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetChannelScan { active }).await?)
+    }
+
+    pub async fn set_transport(&self, settings: TransportSettings) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetTransport(settings)).await?)
+    }
+
+    pub async fn get_midi_ports(&self) -> Result<Vec<String>> {
+        match self.request(IpcCommand::GetMidiPorts).await? {
+            IpcResponse::MidiPorts(ports) => Ok(ports),
             IpcResponse::Error(e) => Err(anyhow!(e)),
             _ => Err(anyhow!("Unexpected response type")),
         }
     }
 
-    pub fn set_engine_tuning_config(&mut self, mut config: EngineTuningConfig) {
-        config.affinity_mask = config
-            .affinity_mask
-            .as_ref()
-            .map(|s| s.trim().to_string())
-            .filter(|s| !s.is_empty());
-        self.engine_tuning = config;
+    /// `input: None` disconnects the plugin from MIDI.
+    pub async fn set_midi_input(&self, id: String, input: Option<MidiAssignment>) -> Result<()> {
+        expect_success(self.request(IpcCommand::SetMidiInput { id, input }).await?)
     }
 
-    pub fn get_engine_tuning_config(&self) -> EngineTuningConfig {
-        self.engine_tuning.clone()
+    /// Hands the mappings to the engine and, once it took them, saves them to `path`.
+    pub async fn set_midi_mappings(&self, path: PathBuf, mappings: Vec<MidiMapping>) -> Result<()> {
+        for mapping in &mappings {
+            mapping.validate()?;
+        }
+        self.run_blocking(move |state| {
+            // Held across the request so a mapping learned meanwhile is saved after this list
+            let _lock = lock_midi_mappings()?;
+            let pending = state
+                .lock()?
+                .submit(IpcCommand::SetMidiMappings(mappings.clone()))?;
+            expect_success(pending.wait()?)?;
+            midi_map::save_mappings(&path, &mappings)
+        })
+        .await
     }
 
-    pub fn get_engine_runtime_stats(&mut self) -> Result<EngineRuntimeStats> {
-        if self.child.is_none() {
-            return Ok(EngineRuntimeStats::default());
-        }
+    /// Binds the next CC or note to `target` (`None` cancels); the result arrives as the
+    /// "midi-learned" event.
+    pub async fn midi_learn(&self, target: Option<MidiTarget>) -> Result<()> {
+        expect_success(self.request(IpcCommand::MidiLearn { target }).await?)
+    }
 
-        if let Some(child) = self.child.as_mut() {
-            match child.try_wait() {
-                Ok(Some(_)) | Err(_) => {
-                    self.stdin = None;
-                    self.child = None;
-                    #[cfg(windows)]
-                    {
-                        self.engine_job = None;
-                    }
-                    return Ok(EngineRuntimeStats::default());
+    /// Writes the engine's current session to disk. Returns false (nothing written) when no
+    /// engine is running or its saved session has not been restored yet.
+    pub async fn save_session(&self) -> Result<bool> {
+        self.run_blocking(|state| {
+            let pending = {
+                let mut host = state.lock()?;
+                if !host.engine_alive() || !host.session_restored {
+                    return Ok(false);
                 }
-                Ok(None) => {}
+                host.submit(IpcCommand::GetSession)?
+            };
+            match pending.wait()? {
+                IpcResponse::Session {
+                    session: saved,
+                    ids,
+                } => {
+                    session::save_session(&session_path(), &saved)?;
+                    // Only once the state chunks are on disk may the plugins count as clean
+                    let pending = state.lock()?.submit(IpcCommand::SessionSaved { ids })?;
+                    expect_success(pending.wait()?)?;
+                    Ok(true)
+                }
+                IpcResponse::Error(e) => Err(anyhow!(e)),
+                _ => Err(anyhow!("Unexpected response type")),
             }
+        })
+        .await
+    }
+
+    /// Forgets the saved session, e.g. before recovering from a plugin crash so the culprit
+    /// is not restored again.
+    pub fn discard_session(&self) -> Result<()> {
+        session::discard(&session_path())
+    }
+
+    /// Asks the running engine what it is doing. `None` if no engine is running, so a
+    /// reloaded webview doesn't spawn one just to find it empty. Waits for the launch warmup
+    /// and any session restore first, so the chain reported is the one the engine keeps.
+    pub async fn get_engine_snapshot(&self) -> Result<Option<EngineSnapshot>> {
+        let resp = self
+            .run_blocking(|state| {
+                let pending = {
+                    let mut host = state.lock_restored()?;
+                    if !host.engine_alive() {
+                        return Ok(None);
+                    }
+                    host.submit(IpcCommand::GetSnapshot)?
+                };
+                pending.wait().map(Some)
+            })
+            .await?;
+
+        match resp {
+            None => Ok(None),
+            Some(IpcResponse::Snapshot(snapshot)) => Ok(Some(snapshot)),
+            Some(IpcResponse::Error(e)) => Err(anyhow!(e)),
+            Some(_) => Err(anyhow!("Unexpected response type")),
         }
+    }
+
+    pub async fn get_engine_runtime_stats(&self) -> Result<EngineRuntimeStats> {
+        let resp = self
+            .run_blocking(|state| {
+                let pending = {
+                    let mut host = state.lock()?;
+                    // No engine yet (or it died): report zeros instead of spawning one for polling.
+                    if !host.engine_alive() {
+                        return Ok(None);
+                    }
+                    host.submit(IpcCommand::GetRuntimeStats)?
+                };
+                pending.wait().map(Some)
+            })
+            .await?;
 
-        match self.execute_command(IpcCommand::GetRuntimeStats)? {
-            IpcResponse::RuntimeStats {
+        match resp {
+            None => Ok(EngineRuntimeStats::default()),
+            Some(IpcResponse::RuntimeStats {
                 active_plugin_count,
                 enabled_plugin_count,
                 pending_unload_count,
@@ -829,7 +1857,7 @@ impl AudioHost {
                 noise_reduction_enabled,
                 noise_reduction_active,
                 noise_reduction_mode,
-            } => Ok(EngineRuntimeStats {
+            }) => Ok(EngineRuntimeStats {
                 active_plugin_count,
                 enabled_plugin_count,
                 pending_unload_count,
@@ -847,88 +1875,12 @@ impl AudioHost {
                 noise_reduction_active,
                 noise_reduction_mode,
             }),
-            IpcResponse::Error(e) => Err(anyhow!(e)),
-            _ => Err(anyhow!("Unexpected response type")),
-        }
-    }
-
-    pub fn kill_engine(&mut self) {
-        if let Some(mut child) = self.child.take() {
-            log::warn!("Force Killing Audio Engine...");
-            let _ = child.kill();
-            let _ = child.wait();
-        }
-        self.stdin = None;
-        self.child = None;
-        #[cfg(windows)]
-        {
-            self.engine_job = None;
-        }
-    }
-
-    pub fn warmup(&mut self) -> Result<()> {
-        self.ensure_engine_running()?;
-
-        // Fast auto-start: read last successful config and start immediately
-        // This avoids waiting for the frontend to load and send the Start command.
-        let path = last_config_path();
-        match std::fs::read_to_string(&path) {
-            Ok(json) => match serde_json::from_str::<ActiveAudioConfig>(&json) {
-                Ok(config) if !config.host.is_empty() => {
-                    log::info!("Auto-starting audio with last config: {:?}", config);
-                    match self.start(
-                        Some(config.host),
-                        config.input,
-                        config.output,
-                        config.buffer_size,
-                        config.sample_rate,
-                    ) {
-                        Ok(res) => {
-                            log::info!(
-                                "Auto-start successful (SR={}, Buf={})",
-                                res.sample_rate,
-                                res.buffer_size
-                            );
-                        }
-                        Err(e) => {
-                            log::warn!("Auto-start failed (user can start manually): {}", e);
-                        }
-                    }
-                }
-                Ok(_) => log::debug!("Last config has empty host, skipping auto-start"),
-                Err(e) => log::debug!("Failed to parse last config: {}", e),
-            },
-            Err(_) => log::debug!("No last audio config found, skipping auto-start"),
-        }
-
-        Ok(())
-    }
-
-    pub fn get_state(&self) -> AudioStateInfo {
-        AudioStateInfo {
-            is_running: self.child.is_some(),
-            config: self.active_config.clone(),
+            Some(IpcResponse::Error(e)) => Err(anyhow!(e)),
+            Some(_) => Err(anyhow!("Unexpected response type")),
         }
     }
 }
 
-impl Drop for AudioHost {
-    fn drop(&mut self) {
-        if let Some(mut child) = self.child.take() {
-            log::info!("Killing Audio Engine Sidecar (Drop)...");
-            let _ = child.kill();
-            let _ = child.wait();
-        }
-        #[cfg(windows)]
-        {
-            self.engine_job = None;
-        }
-    }
-}
-
-// Global state container
-pub struct AudioState(pub Arc<Mutex<AudioHost>>);
-
 /// Translate common audio engine errors into user-friendly Japanese messages.
 pub fn localize_audio_error(e: String) -> String {
     let lower = e.to_lowercase();
@@ -1,11 +1,12 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use log;
-use nnnoiseless::DenoiseState;
 use ringbuf::traits::{Consumer, Observer, Producer, Split};
 use ringbuf::HeapRb;
 use serde_json;
-use std::io::{self, BufRead, Write};
+use std::collections::HashSet;
+use std::io::{self, BufRead};
+use std::path::Path;
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, Ordering},
     Arc, OnceLock,
@@ -16,66 +17,67 @@ use std::time::{Duration, Instant};
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoopBuilder};
 
-use crate::ipc::{Command, EngineEvent, MeterLevels, OutputMessage, Response};
+use crate::ipc::{
+    AppliedPlugin, AudioConfigSnapshot, Command, CrashPhase, EngineCapabilities, EngineEvent,
+    EngineSnapshot, EngineVersion, MeterLevels, MidiAssignment, OutputMessage, PluginSnapshot,
+    Reply, Request, RequestId, Response, TransportSettings, PROTOCOL_VERSION,
+};
+use crate::ipc_transport;
+use crate::midi_map::{MidiAction, MidiMapper, MidiMapping, MidiTarget, PluginRef};
+use crate::session::{SessionState, SESSION_VERSION};
+use crate::vst_host::c_api::{
+    K_IO_CHANGED, K_LATENCY_CHANGED, K_PARAM_TITLES_CHANGED, K_PARAM_VALUES_CHANGED,
+};
 use crate::vst_host::instance::VstProcessor;
+use crate::vst_host::presets::PresetPlugin;
 
 // New Managers
+use super::crash_guard;
 use super::devices::DeviceManager;
+use super::dsp::{
+    noise_reduction_mix_from_mode, normalize_noise_reduction_mode, PluginChain, RtNoiseReducer,
+    NOISE_REDUCTION_MODE_HIGH, NOISE_REDUCTION_MODE_LOW,
+};
 use super::editors::EditorManager;
+pub use super::graph::{AudioThreadMessage, RetiredProcessor};
+use super::graph::{
+    ChannelConsumer, CmdProducer, GraphConfig, LevelConsumer, ProcessingGraph, RetireConsumer,
+};
+use super::midi::MidiManager;
+use super::null_host::{
+    is_null_host, NullStream, NULL_CHANNELS, NULL_DEFAULT_BLOCK_SIZE, NULL_DEFAULT_SAMPLE_RATE,
+    NULL_HOST_NAME,
+};
 use super::plugins::PluginManager;
 use super::plugins::MAX_PLUGINS;
-
-pub enum AudioThreadMessage {
-    AddProcessor {
-        index: u8,
-        processor: VstProcessor,
-        initial_gain: f32,
-    },
-    RemoveProcessor {
-        index: u8,
-    },
-    ReorderProcessors {
-        order: [u8; MAX_PLUGINS],
-        len: u8,
-    },
-    SetBypass {
-        index: u8,
-        active: bool,
-    },
-    SetMute {
-        index: u8,
-        active: bool,
-    },
-    SetGain {
-        index: u8,
-        value: f32,
-    },
-    SetGlobalMute(bool),
-    SetGlobalBypass(bool),
-    SetInputGain(f32),
-    SetNoiseReduction { active: bool, mix: f32 },
-    SetOutputGain(f32),
-    SetInputChannels(usize, usize), // (Left, Right)
-    SetChannelScan(bool),           // Enable/Disable background scanning
-    Stop,
+use super::transport;
+
+/// Work done by `ApplyChain` before anything reaches the audio thread, kept so it can be
+/// undone if a later step fails.
+#[derive(Default)]
+struct StagedChain {
+    /// Final order, one id per requested plugin.
+    ids: Vec<String>,
+    reused: HashSet<String>,
+    /// Newly loaded instances (removed again on rollback).
+    loaded: Vec<String>,
+    processors: Vec<(String, u8, VstProcessor)>,
+    /// State of reused instances (component, controller) before the preset's state was
+    /// applied.
+    previous_states: Vec<(String, String, Option<String>)>,
 }
 
-pub struct RetiredProcessor {
-    pub index: u8,
-    pub processor: VstProcessor,
-}
+/// Minimum time between two `ParameterChanged` events (editor drags are coalesced).
+const PARAM_EVENT_INTERVAL: Duration = Duration::from_millis(50);
 
 // Custom Event for Winit Loop
 #[derive(Debug)]
 pub enum UserEvent {
-    Command(Command),
+    Command(Option<RequestId>, Command),
     Timer,
 }
 
-type CmdProducer = <HeapRb<AudioThreadMessage> as Split>::Prod;
-type LevelConsumer = <HeapRb<MeterLevels> as Split>::Cons;
-type ChannelConsumer = <HeapRb<[f32; 32]> as Split>::Cons;
-type RetireConsumer = <HeapRb<RetiredProcessor> as Split>::Cons;
+type AudioProducer = <HeapRb<f32> as Split>::Prod;
 
 fn time_critical_audio_threads_enabled() -> bool {
     static ENABLED: OnceLock<bool> = OnceLock::new();
@@ -88,187 +90,36 @@ fn time_critical_audio_threads_enabled() -> bool {
     })
 }
 
-// Smoother Implementation
-struct Smoother {
-    current: f32,
-    target: f32,
-    coeff: f32,
-}
-
-impl Smoother {
-    fn new(initial_value: f32) -> Self {
-        Self {
-            current: initial_value,
-            target: initial_value,
-            coeff: 0.005,
-        }
-    }
-
-    fn new_ramp(start: f32, end: f32) -> Self {
-        Self {
-            current: start,
-            target: end,
-            coeff: 0.005,
-        }
-    }
-
-    fn set_target(&mut self, target: f32) {
-        self.target = target;
-    }
-
-    fn next(&mut self) -> f32 {
-        if (self.current - self.target).abs() < 0.0001 {
-            self.current = self.target;
-        } else {
-            self.current += (self.target - self.current) * self.coeff;
-        }
-        self.current
-    }
-}
-
-const DENOISE_FRAME_SIZE: usize = DenoiseState::FRAME_SIZE;
-const DENOISE_SCALE: f32 = 32768.0;
-const NOISE_REDUCTION_MODE_LOW: &str = "low";
-const NOISE_REDUCTION_MODE_HIGH: &str = "high";
-
-fn normalize_noise_reduction_mode(mode: Option<&str>) -> &'static str {
-    match mode.map(|m| m.trim().to_ascii_lowercase()) {
-        Some(m) if m == NOISE_REDUCTION_MODE_HIGH => NOISE_REDUCTION_MODE_HIGH,
-        _ => NOISE_REDUCTION_MODE_LOW,
-    }
-}
-
-fn noise_reduction_mix_from_mode(mode: &str) -> f32 {
-    if mode == NOISE_REDUCTION_MODE_HIGH {
-        1.0
-    } else {
-        0.6
-    }
-}
-
-struct RtNoiseReducer {
-    states: [Box<DenoiseState<'static>>; 2],
-    input_frame_size: usize,
-    input_frames: [Vec<f32>; 2],
-    output_frames: [Vec<f32>; 2],
-    denoise_input: [[f32; DENOISE_FRAME_SIZE]; 2],
-    denoise_output: [[f32; DENOISE_FRAME_SIZE]; 2],
-    input_pos: usize,
-    output_pos: usize,
-    output_ready: usize,
-}
-
-impl RtNoiseReducer {
-    fn new(sample_rate_hz: u32) -> Self {
-        let frame_size = ((sample_rate_hz.max(8_000) + 50) / 100) as usize;
-        Self {
-            states: std::array::from_fn(|_| DenoiseState::new()),
-            input_frame_size: frame_size,
-            input_frames: [vec![0.0; frame_size], vec![0.0; frame_size]],
-            output_frames: [vec![0.0; frame_size], vec![0.0; frame_size]],
-            denoise_input: [[0.0; DENOISE_FRAME_SIZE]; 2],
-            denoise_output: [[0.0; DENOISE_FRAME_SIZE]; 2],
-            input_pos: 0,
-            output_pos: 0,
-            output_ready: 0,
-        }
-    }
-
-    fn reset_state(&mut self) {
-        self.states = std::array::from_fn(|_| DenoiseState::new());
-        self.input_pos = 0;
-        self.output_pos = 0;
-        self.output_ready = 0;
-        for ch in 0..2 {
-            self.input_frames[ch].fill(0.0);
-            self.output_frames[ch].fill(0.0);
-            self.denoise_input[ch].fill(0.0);
-            self.denoise_output[ch].fill(0.0);
-        }
-    }
-
-    fn resample_linear(input: &[f32], output: &mut [f32]) {
-        if input.is_empty() || output.is_empty() {
-            return;
-        }
-        if input.len() == 1 {
-            output.fill(input[0]);
-            return;
-        }
-        if output.len() == 1 {
-            output[0] = input[0];
-            return;
-        }
-
-        let in_last = (input.len() - 1) as f32;
-        let out_last = (output.len() - 1) as f32;
-        for (i, out) in output.iter_mut().enumerate() {
-            let pos = (i as f32) * in_last / out_last;
-            let idx0 = pos.floor() as usize;
-            let idx1 = (idx0 + 1).min(input.len() - 1);
-            let frac = pos - idx0 as f32;
-            *out = input[idx0] * (1.0 - frac) + input[idx1] * frac;
-        }
-    }
-
-    fn process_sample(&mut self, left: f32, right: f32) -> (f32, f32) {
-        self.input_frames[0][self.input_pos] = (left * DENOISE_SCALE).clamp(-32768.0, 32767.0);
-        self.input_frames[1][self.input_pos] = (right * DENOISE_SCALE).clamp(-32768.0, 32767.0);
-        self.input_pos += 1;
-
-        if self.input_pos >= self.input_frame_size {
-            for ch in 0..2 {
-                if self.input_frame_size == DENOISE_FRAME_SIZE {
-                    self.denoise_input[ch]
-                        .copy_from_slice(&self.input_frames[ch][..DENOISE_FRAME_SIZE]);
-                } else {
-                    Self::resample_linear(
-                        &self.input_frames[ch][..self.input_frame_size],
-                        &mut self.denoise_input[ch],
-                    );
-                }
-            }
-
-            self.states[0].process_frame(&mut self.denoise_output[0], &self.denoise_input[0]);
-            self.states[1].process_frame(&mut self.denoise_output[1], &self.denoise_input[1]);
-
-            for ch in 0..2 {
-                if self.input_frame_size == DENOISE_FRAME_SIZE {
-                    self.output_frames[ch][..DENOISE_FRAME_SIZE]
-                        .copy_from_slice(&self.denoise_output[ch]);
-                } else {
-                    Self::resample_linear(
-                        &self.denoise_output[ch],
-                        &mut self.output_frames[ch][..self.input_frame_size],
-                    );
-                }
-            }
-
-            self.input_pos = 0;
-            self.output_pos = 0;
-            self.output_ready = self.input_frame_size;
-        }
-
-        if self.output_ready == 0 {
-            return (0.0, 0.0);
-        }
-
-        let l = (self.output_frames[0][self.output_pos] / DENOISE_SCALE).clamp(-1.0, 1.0);
-        let r = (self.output_frames[1][self.output_pos] / DENOISE_SCALE).clamp(-1.0, 1.0);
-        self.output_pos += 1;
-        self.output_ready -= 1;
-        (l, r)
+fn engine_version() -> EngineVersion {
+    EngineVersion {
+        protocol_version: PROTOCOL_VERSION,
+        engine_version: env!("CARGO_PKG_VERSION").to_string(),
+        capabilities: EngineCapabilities {
+            hosts: vec![
+                "ASIO".to_string(),
+                "WASAPI".to_string(),
+                NULL_HOST_NAME.to_string(),
+            ],
+            noise_reduction_modes: vec![
+                NOISE_REDUCTION_MODE_LOW.to_string(),
+                NOISE_REDUCTION_MODE_HIGH.to_string(),
+            ],
+            max_plugins: MAX_PLUGINS,
+        },
     }
 }
 
 pub struct Engine {
     input_stream: Option<cpal::Stream>,
     output_stream: Option<cpal::Stream>,
+    null_stream: Option<NullStream>,
 
     // Sub-Modules
     pub device_manager: DeviceManager,
     pub editor_manager: EditorManager,
     pub plugin_manager: PluginManager,
+    pub midi_manager: MidiManager,
+    midi_mapper: MidiMapper,
 
     command_tx: Option<CmdProducer>,
     level_rx: Option<LevelConsumer>,
@@ -287,12 +138,24 @@ pub struct Engine {
     input_channel_r: usize,
     scan_enabled: bool,
     global_bypass: bool,
+    global_mute: bool,
+    input_gain: f32,
+    output_gain: f32,
     noise_reduction_enabled: bool,
     noise_reduction_mode: String,
+    transport: TransportSettings,
 
     // Diagnostics
     stats_max_jitter: Arc<AtomicU64>,
     stats_glitches: Arc<AtomicU64>,
+
+    // Running device config as reported by GetSnapshot (None while stopped)
+    active_audio: Option<AudioConfigSnapshot>,
+
+    // Id of the request being handled; attached to its response.
+    current_request_id: Option<RequestId>,
+    // Set while a MIDI mapping runs a command: there is no request to answer.
+    applying_midi: bool,
 }
 
 impl Engine {
@@ -300,9 +163,12 @@ impl Engine {
         Self {
             input_stream: None,
             output_stream: None,
+            null_stream: None,
             device_manager: DeviceManager::new(),
             editor_manager: EditorManager::new(),
             plugin_manager: PluginManager::new(),
+            midi_manager: MidiManager::new(),
+            midi_mapper: MidiMapper::new(),
             command_tx: None,
             level_rx: None,
             channel_rx: None,
@@ -316,10 +182,17 @@ impl Engine {
             input_channel_r: 1,
             scan_enabled: true, // Auto-enable scan for smart selector
             global_bypass: false,
+            global_mute: false,
+            input_gain: 1.0,
+            output_gain: 1.0,
             noise_reduction_enabled: false,
             noise_reduction_mode: NOISE_REDUCTION_MODE_LOW.to_string(),
+            transport: TransportSettings::default(),
             stats_max_jitter: Arc::new(AtomicU64::new(0)),
             stats_glitches: Arc::new(AtomicU64::new(0)),
+            active_audio: None,
+            current_request_id: None,
+            applying_midi: false,
         }
     }
 
@@ -330,6 +203,15 @@ impl Engine {
             .unwrap();
         let proxy = event_loop.create_proxy();
 
+        // Replies/events go over the host's dedicated channel when it gave us one
+        match ipc_transport::connect_from_env() {
+            Ok(true) => log::info!("[AudioEngine] IPC channel connected"),
+            Ok(false) => log::info!("[AudioEngine] No IPC channel, using stdout"),
+            Err(e) => log::error!("[AudioEngine] IPC channel failed, using stdout: {}", e),
+        }
+        // Lets the host blame the right plugin if we go down
+        crash_guard::install();
+
         // Spawn stdin reader thread
         thread::spawn(move || {
             let stdin = io::stdin();
@@ -345,9 +227,13 @@ impl Engine {
                         if trim.is_empty() {
                             continue;
                         }
-                        match serde_json::from_str::<Command>(trim) {
-                            Ok(cmd) => {
-                                if let Err(_) = proxy.send_event(UserEvent::Command(cmd)) {
+                        // Host sends `{"id", "command"}`; a bare Command is still accepted.
+                        let parsed = serde_json::from_str::<Request>(trim)
+                            .map(|req| (Some(req.id), req.command))
+                            .or_else(|_| serde_json::from_str::<Command>(trim).map(|c| (None, c)));
+                        match parsed {
+                            Ok((id, cmd)) => {
+                                if let Err(_) = proxy.send_event(UserEvent::Command(id, cmd)) {
                                     break; // Loop closed
                                 }
                             }
@@ -373,6 +259,7 @@ impl Engine {
         let mut updates_received = 0;
         let mut last_data_time = Instant::now();
         let mut last_heartbeat = Instant::now();
+        let mut last_param_events = Instant::now();
 
         // Run Event Loop
         let _ = event_loop.run(move |event, target| {
@@ -382,29 +269,37 @@ impl Engine {
             ));
 
             match event {
-                Event::UserEvent(UserEvent::Command(cmd)) => {
+                Event::UserEvent(UserEvent::Command(id, cmd)) => {
+                    self.current_request_id = id;
                     self.handle_command(cmd, target);
+                    self.current_request_id = None;
                 }
                 Event::UserEvent(UserEvent::Timer) => {}
                 Event::AboutToWait => {
+                    self.handle_midi_controls(target);
                     self.flush_pending_audio_msgs();
 
                     // Retire processors off the audio callback thread (safe place to drop VST objects)
+                    let mut restarted = Vec::new();
                     if let Some(retire_cons) = &mut self.retire_rx {
                         while let Some(retired) = retire_cons.try_pop() {
                             drop(retired.processor);
-                            self.plugin_manager.on_processor_retired(retired.index);
+                            if let Some(id) =
+                                self.plugin_manager.on_processor_retired(retired.index)
+                            {
+                                restarted.push(id);
+                            }
                         }
                     }
+                    for id in restarted {
+                        self.finish_restart(&id);
+                    }
 
                     // --- Deferred Initialization ---
                     if !self.plugin_manager.pending_init.is_empty() {
                         let pending_ids = std::mem::take(&mut self.plugin_manager.pending_init);
                         for id in pending_ids {
                             let index = self.plugin_manager.rt_index_of(&id);
-                            let initial_gain = *self.plugin_manager.gains.get(&id).unwrap_or(&1.0);
-                            let is_bypassed = self.plugin_manager.bypassed.contains(&id);
-                            let is_muted = self.plugin_manager.muted.contains(&id);
 
                             let mut created_processor: Option<VstProcessor> = None;
 
@@ -416,8 +311,13 @@ impl Engine {
                                     "Executing Deferred Init (Activate -> Connect) for {}",
                                     instance.name
                                 );
+                                let _activity = crash_guard::track(
+                                    CrashPhase::Loading,
+                                    &instance.path,
+                                    &instance.name,
+                                );
 
-                                if self.output_stream.is_some() {
+                                if self.is_audio_running() {
                                     let sr = self.current_sample_rate;
                                     let bs = 4096usize.max(self.current_block_size) as i32;
                                     let ch = self.current_channels as i32;
@@ -437,26 +337,9 @@ impl Engine {
                                 log::error!("Error finalizing deferred connection for {}", id);
                             }
 
-                            if self.output_stream.is_some() {
+                            if self.is_audio_running() {
                                 if let (Some(index), Some(proc)) = (index, created_processor) {
-                                    self.queue_audio_msg(AudioThreadMessage::AddProcessor {
-                                        index,
-                                        processor: proc,
-                                        initial_gain,
-                                    });
-                                    if is_bypassed {
-                                        self.queue_audio_msg(AudioThreadMessage::SetBypass {
-                                            index,
-                                            active: true,
-                                        });
-                                    }
-                                    if is_muted {
-                                        self.queue_audio_msg(AudioThreadMessage::SetMute {
-                                            index,
-                                            active: true,
-                                        });
-                                    }
-                                    self.queue_audio_msg(self.make_reorder_message());
+                                    self.queue_add_processor(&id, index, proc);
                                 }
                             }
                         }
@@ -476,9 +359,17 @@ impl Engine {
                             };
                             let _prio_class = GetPriorityClass(GetCurrentProcess());
                         }
+                        self.send_event(EngineEvent::Heartbeat);
                         last_heartbeat = Instant::now();
                     }
 
+                    // Edits and restart requests from plugin controllers
+                    if last_param_events.elapsed() >= PARAM_EVENT_INTERVAL {
+                        self.send_param_edits();
+                        self.handle_restart_requests();
+                        last_param_events = Instant::now();
+                    }
+
                     // Meter Processing
                     let mut meter_event_to_send = None;
                     if let Some(consumer) = &mut self.level_rx {
@@ -586,25 +477,20 @@ impl Engine {
     }
 
     fn send_response(&self, resp: Response) {
-        let msg = OutputMessage::Response(resp);
-        match serde_json::to_string(&msg) {
-            Ok(json) => {
-                println!("IPC:{}", json);
-                let _ = io::stdout().flush();
+        if self.applying_midi {
+            if let Response::Error(e) = resp {
+                log::warn!("MIDI mapping failed: {}", e);
             }
-            Err(e) => log::error!("JSON Serialize Error (Response): {}", e),
+            return;
         }
+        ipc_transport::send(&OutputMessage::Response(Reply {
+            id: self.current_request_id,
+            response: resp,
+        }));
     }
 
     fn send_event(&self, evt: EngineEvent) {
-        let msg = OutputMessage::Event(evt);
-        match serde_json::to_string(&msg) {
-            Ok(json) => {
-                println!("IPC:{}", json);
-                let _ = io::stdout().flush();
-            }
-            Err(e) => log::error!("JSON Serialize Error (Event): {}", e),
-        }
+        ipc_transport::send(&OutputMessage::Event(evt));
     }
 
     fn send_error(&self, msg: String) {
@@ -617,6 +503,17 @@ impl Engine {
         target: &winit::event_loop::EventLoopWindowTarget<T>,
     ) {
         match cmd {
+            Command::Hello { protocol_version } => {
+                if protocol_version != PROTOCOL_VERSION {
+                    // The host decides what to do; we still answer so it can report the mismatch.
+                    log::warn!(
+                        "Host speaks protocol v{}, engine speaks v{}",
+                        protocol_version,
+                        PROTOCOL_VERSION
+                    );
+                }
+                self.send_response(Response::Version(engine_version()));
+            }
             Command::GetDevices => {
                 // Delegated to DeviceManager
                 match self.device_manager.enumerate() {
@@ -641,39 +538,20 @@ impl Engine {
                 self.stop_audio();
                 self.send_response(Response::Success);
             }
-            Command::LoadPlugin { path } => {
+            Command::LoadPlugin { path, class_id } => {
                 // Delegated to PluginManager
                 match self.plugin_manager.load_plugin(
                     &path,
+                    class_id.as_deref(),
                     self.current_sample_rate,
                     4096usize.max(self.current_block_size),
                     self.current_channels,
-                    self.output_stream.is_some(),
+                    self.is_audio_running(),
                 ) {
                     Ok((id, name, index, processor_opt)) => {
                         // If Audio Thread is active and manager returned a processor, push it
                         if let Some(proc) = processor_opt {
-                            let initial_gain = *self.plugin_manager.gains.get(&id).unwrap_or(&1.0);
-                            self.queue_audio_msg(AudioThreadMessage::AddProcessor {
-                                index,
-                                processor: proc,
-                                initial_gain,
-                            });
-
-                            if self.plugin_manager.bypassed.contains(&id) {
-                                self.queue_audio_msg(AudioThreadMessage::SetBypass {
-                                    index,
-                                    active: true,
-                                });
-                            }
-                            if self.plugin_manager.muted.contains(&id) {
-                                self.queue_audio_msg(AudioThreadMessage::SetMute {
-                                    index,
-                                    active: true,
-                                });
-                            }
-
-                            self.queue_audio_msg(self.make_reorder_message());
+                            self.queue_add_processor(&id, index, proc);
                         }
                         self.send_response(Response::PluginLoaded {
                             id,
@@ -687,8 +565,9 @@ impl Engine {
             Command::UnloadPlugin { id } => {
                 // Always close editor if open
                 self.editor_manager.close_editor(&id);
+                self.midi_manager.unassign(&id);
 
-                if self.output_stream.is_some() {
+                if self.is_audio_running() {
                     match self.plugin_manager.begin_unload(&id) {
                         Ok(index) => {
                             self.queue_audio_msg(AudioThreadMessage::RemoveProcessor { index });
@@ -709,6 +588,10 @@ impl Engine {
                 self.queue_audio_msg(self.make_reorder_message());
                 self.send_response(Response::Success);
             }
+            Command::ApplyChain { plugins } => match self.apply_chain(&plugins) {
+                Ok(applied) => self.send_response(Response::ChainApplied(applied)),
+                Err(e) => self.send_error(format!("Failed to apply chain: {:#}", e)),
+            },
             Command::OpenEditor { id } => {
                 // Delegated to EditorManager, but needs Instance from PluginManager
                 match self.plugin_manager.get_mut(&id) {
@@ -749,6 +632,7 @@ impl Engine {
                 self.send_response(Response::Success);
             }
             Command::SetGlobalMute { active } => {
+                self.global_mute = active;
                 self.queue_audio_msg(AudioThreadMessage::SetGlobalMute(active));
                 self.send_response(Response::Success);
             }
@@ -758,6 +642,7 @@ impl Engine {
                 self.send_response(Response::Success);
             }
             Command::SetInputGain { value } => {
+                self.input_gain = value;
                 self.queue_audio_msg(AudioThreadMessage::SetInputGain(value));
                 self.send_response(Response::Success);
             }
@@ -772,6 +657,7 @@ impl Engine {
                 self.send_response(Response::Success);
             }
             Command::SetOutputGain { value } => {
+                self.output_gain = value;
                 self.queue_audio_msg(AudioThreadMessage::SetOutputGain(value));
                 self.send_response(Response::Success);
             }
@@ -786,6 +672,31 @@ impl Engine {
                 self.queue_audio_msg(AudioThreadMessage::SetChannelScan(active));
                 self.send_response(Response::Success);
             }
+            Command::SetTransport(settings) => match transport::validate(&settings) {
+                Ok(()) => {
+                    self.transport = settings;
+                    self.queue_audio_msg(AudioThreadMessage::SetTransport(settings));
+                    self.send_response(Response::Success);
+                }
+                Err(e) => self.send_error(e.to_string()),
+            },
+            Command::GetSnapshot => {
+                let snapshot = self.snapshot();
+                self.send_response(Response::Snapshot(snapshot));
+            }
+            Command::GetSession => {
+                let (session, ids) = self.session();
+                self.send_response(Response::Session { session, ids });
+            }
+            Command::SessionSaved { ids } => {
+                // Not at `GetSession`: a session that never reached the disk leaves them dirty
+                for id in &ids {
+                    if let Some(instance) = self.plugin_manager.get(id) {
+                        instance.take_dirty();
+                    }
+                }
+                self.send_response(Response::Success);
+            }
             Command::GetRuntimeStats => {
                 let (active_plugin_count, pending_unload_count, burned_library_count) =
                     self.plugin_manager.runtime_stats();
@@ -793,11 +704,7 @@ impl Engine {
                     self.plugin_manager.enabled_plugin_count(self.global_bypass);
                 let total_plugin_latency_samples =
                     self.plugin_manager.total_latency_samples(self.global_bypass);
-                let noise_reduction_latency_samples = if self.noise_reduction_enabled {
-                    ((self.current_sample_rate / 100.0).round() as u32).max(1)
-                } else {
-                    0
-                };
+                let noise_reduction_latency_samples = self.noise_reduction_latency_samples();
                 let total_chain_latency_samples =
                     total_plugin_latency_samples.saturating_add(noise_reduction_latency_samples);
                 let total_plugin_latency_ms = if self.current_sample_rate > 0.0 {
@@ -836,19 +743,469 @@ impl Engine {
             }
             Command::GetPluginState { id } => match self.plugin_manager.get(&id) {
                 Some(instance) => match instance.get_state() {
-                    Ok(state) => self.send_response(Response::PluginState { id, state }),
+                    Ok(state) => self.send_response(Response::PluginState {
+                        id,
+                        state,
+                        controller_state: instance.get_controller_state(),
+                    }),
                     Err(e) => self.send_error(format!("Failed to get state: {}", e)),
                 },
                 None => self.send_error("Plugin not found".to_string()),
             },
-            Command::SetPluginState { id, state } => match self.plugin_manager.get(&id) {
-                Some(instance) => match instance.set_state(&state) {
-                    Ok(_) => self.send_response(Response::Success),
-                    Err(e) => self.send_error(format!("Failed to set state: {}", e)),
+            Command::SetPluginState {
+                id,
+                state,
+                controller_state,
+            } => match self.plugin_manager.get(&id) {
+                Some(instance) => {
+                    let _activity =
+                        crash_guard::track(CrashPhase::State, &instance.path, &instance.name);
+                    match instance.set_state(&state, controller_state.as_deref()) {
+                        Ok(_) => self.send_response(Response::Success),
+                        Err(e) => self.send_error(format!("Failed to set state: {}", e)),
+                    }
+                }
+                None => self.send_error("Plugin not found".to_string()),
+            },
+            Command::ExportVstPreset { id, path } => match self.plugin_manager.get(&id) {
+                Some(instance) => {
+                    let _activity =
+                        crash_guard::track(CrashPhase::State, &instance.path, &instance.name);
+                    match instance.export_vstpreset(Path::new(&path)) {
+                        Ok(()) => self.send_response(Response::Success),
+                        Err(e) => self.send_error(format!("Failed to export preset: {:#}", e)),
+                    }
+                }
+                None => self.send_error("Plugin not found".to_string()),
+            },
+            Command::ImportVstPreset { id, path } => match self.plugin_manager.get(&id) {
+                Some(instance) => {
+                    let _activity =
+                        crash_guard::track(CrashPhase::State, &instance.path, &instance.name);
+                    match instance.import_vstpreset(Path::new(&path)) {
+                        Ok(()) => {
+                            self.send_response(Response::Success);
+                            // Every value may have changed
+                            self.send_event(EngineEvent::ParametersChanged { id });
+                        }
+                        Err(e) => self.send_error(format!("Failed to import preset: {:#}", e)),
+                    }
+                }
+                None => self.send_error("Plugin not found".to_string()),
+            },
+            Command::GetParameters { id } => match self.plugin_manager.get(&id) {
+                Some(instance) => match instance.parameters() {
+                    Ok(parameters) => self.send_response(Response::Parameters { id, parameters }),
+                    Err(e) => self.send_error(format!("Failed to get parameters: {}", e)),
+                },
+                None => self.send_error("Plugin not found".to_string()),
+            },
+            Command::SetParameter {
+                id,
+                param_id,
+                value,
+            } => match self.plugin_manager.get_mut(&id) {
+                Some(instance) => match instance.set_parameter(param_id, value) {
+                    Ok(value) => {
+                        let display = instance.param_display(param_id, value);
+                        self.send_response(Response::ParameterValue {
+                            id,
+                            param_id,
+                            value,
+                            display,
+                        })
+                    }
+                    Err(e) => self.send_error(format!("Failed to set parameter: {}", e)),
+                },
+                None => self.send_error("Plugin not found".to_string()),
+            },
+            Command::GetPrograms { id } => match self.plugin_manager.get(&id) {
+                Some(instance) => match instance.programs() {
+                    Ok(lists) => self.send_response(Response::Programs { id, lists }),
+                    Err(e) => self.send_error(format!("Failed to get programs: {}", e)),
+                },
+                None => self.send_error("Plugin not found".to_string()),
+            },
+            Command::SelectProgram {
+                id,
+                list_id,
+                program_index,
+            } => match self.plugin_manager.get_mut(&id) {
+                Some(instance) => match instance.select_program(list_id, program_index) {
+                    Ok((param_id, value)) => {
+                        let display = instance.param_display(param_id, value);
+                        self.send_response(Response::ParameterValue {
+                            id,
+                            param_id,
+                            value,
+                            display,
+                        })
+                    }
+                    Err(e) => self.send_error(format!("Failed to select program: {}", e)),
                 },
                 None => self.send_error("Plugin not found".to_string()),
             },
+            Command::GetMidiPorts => match MidiManager::list_ports() {
+                Ok(ports) => self.send_response(Response::MidiPorts(ports)),
+                Err(e) => self.send_error(e.to_string()),
+            },
+            Command::SetMidiInput { id, input } => match self.set_midi_input(&id, input) {
+                Ok(()) => self.send_response(Response::Success),
+                Err(e) => self.send_error(e.to_string()),
+            },
+            Command::SetMidiMappings(mappings) => match self.set_midi_mappings(mappings) {
+                Ok(()) => self.send_response(Response::Success),
+                Err(e) => self.send_error(e.to_string()),
+            },
+            Command::MidiLearn { target } => {
+                self.midi_mapper.learn(target);
+                self.update_control_ports();
+                self.send_response(Response::Success);
+            }
+        }
+    }
+
+    fn set_midi_mappings(&mut self, mappings: Vec<MidiMapping>) -> Result<()> {
+        for mapping in &mappings {
+            mapping.validate()?;
+        }
+        self.midi_mapper.set_mappings(mappings);
+        self.update_control_ports();
+        Ok(())
+    }
+
+    /// Listens on the ports the mappings use, or on every port while learning.
+    fn update_control_ports(&mut self) {
+        let mut ports = self.midi_mapper.ports();
+        if self.midi_mapper.is_learning() {
+            match MidiManager::list_ports() {
+                Ok(all) => ports.extend(all),
+                Err(e) => log::warn!("MIDI learn: {}", e),
+            }
+        }
+        self.midi_manager.set_control_ports(ports);
+    }
+
+    /// Runs the mappings (or learn mode) on what arrived from control ports.
+    fn handle_midi_controls<T>(&mut self, target: &winit::event_loop::EventLoopWindowTarget<T>) {
+        let messages: Vec<_> = self.midi_manager.control_messages().collect();
+        // Latest command per target, reported once the batch is done
+        let mut applied: Vec<(MidiTarget, Command)> = Vec::new();
+
+        for (port, msg) in messages {
+            if let Some(mapping) = self.midi_mapper.try_learn(&port, &msg) {
+                log::info!("MIDI learn: {:?} -> {:?}", mapping.trigger, mapping.target);
+                self.send_event(EngineEvent::MidiLearned(mapping));
+                self.update_control_ports();
+                continue;
+            }
+            for (midi_target, action) in self.midi_mapper.handle(&port, &msg) {
+                if midi_target == MidiTarget::GlobalMute {
+                    let active = match action {
+                        MidiAction::Switch(active) => Some(active),
+                        MidiAction::Toggle => None,
+                        MidiAction::Set(value) => Some(value >= 0.5),
+                    };
+                    self.send_event(EngineEvent::GlobalMuteRequested { active });
+                    continue;
+                }
+                let Some(cmd) = self.midi_command(&midi_target, action) else {
+                    continue;
+                };
+                self.applying_midi = true;
+                self.handle_command(cmd.clone(), target);
+                self.applying_midi = false;
+
+                applied.retain(|(t, _)| *t != midi_target);
+                applied.push((midi_target, cmd));
+            }
+        }
+
+        for (_, cmd) in applied {
+            self.send_event(EngineEvent::MidiApplied(cmd));
+        }
+    }
+
+    /// The command a mapping's action amounts to; `None` if its plugin is not in the chain.
+    fn midi_command(&self, target: &MidiTarget, action: MidiAction) -> Option<Command> {
+        let value = match action {
+            MidiAction::Set(value) => value,
+            MidiAction::Switch(active) => f64::from(u8::from(active)),
+            MidiAction::Toggle => 0.0,
+        };
+        let switch = |current: bool| match action {
+            MidiAction::Set(value) => value >= 0.5,
+            MidiAction::Switch(active) => active,
+            MidiAction::Toggle => !current,
+        };
+        let pm = &self.plugin_manager;
+
+        let cmd = match target {
+            MidiTarget::Gain { plugin } => Command::SetGain {
+                id: self.resolve_plugin(plugin)?,
+                value: value.max(0.0) as f32,
+            },
+            MidiTarget::Bypass { plugin } => {
+                let id = self.resolve_plugin(plugin)?;
+                let active = switch(pm.bypassed.contains(&id));
+                Command::SetBypass { id, active }
+            }
+            MidiTarget::Mute { plugin } => {
+                let id = self.resolve_plugin(plugin)?;
+                let active = switch(pm.muted.contains(&id));
+                Command::SetMute { id, active }
+            }
+            MidiTarget::Parameter { plugin, param_id } => Command::SetParameter {
+                id: self.resolve_plugin(plugin)?,
+                param_id: *param_id,
+                value: value.clamp(0.0, 1.0),
+            },
+            MidiTarget::GlobalMute => Command::SetGlobalMute {
+                active: switch(self.global_mute),
+            },
+            MidiTarget::InputGain => Command::SetInputGain {
+                value: value.max(0.0) as f32,
+            },
+            MidiTarget::OutputGain => Command::SetOutputGain {
+                value: value.max(0.0) as f32,
+            },
+            MidiTarget::NoiseReduction => Command::SetNoiseReduction {
+                active: switch(self.noise_reduction_enabled),
+                mode: Some(self.noise_reduction_mode.clone()),
+            },
+        };
+        Some(cmd)
+    }
+
+    /// Id of the `occurrence`-th plugin loaded from `plugin.path` (and class, if given), in
+    /// chain order.
+    fn resolve_plugin(&self, plugin: &PluginRef) -> Option<String> {
+        let pm = &self.plugin_manager;
+        pm.order
+            .iter()
+            .filter(|id| {
+                pm.get(id).is_some_and(|instance| {
+                    instance.path == plugin.path
+                        && plugin.class_id.as_deref().is_none_or(|class_id| {
+                            class_id.eq_ignore_ascii_case(&instance.class_id)
+                        })
+                })
+            })
+            .nth(plugin.occurrence)
+            .cloned()
+    }
+
+    fn set_midi_input(&mut self, id: &str, input: Option<MidiAssignment>) -> Result<()> {
+        let instance = self
+            .plugin_manager
+            .get(id)
+            .ok_or_else(|| anyhow!("Plugin not found"))?;
+        if input.is_some() && !instance.accepts_midi() {
+            return Err(anyhow!("{} has no MIDI input", instance.name));
+        }
+        self.midi_manager.assign(id, instance.midi_inlet(), input)
+    }
+
+    /// Replaces the rack with `entries`. Loading and state restore happen first; the audio
+    /// thread only sees the result (removals, additions and a single reorder) once all of
+    /// them succeeded. On failure the previous chain is restored and nothing is queued.
+    fn apply_chain(&mut self, entries: &[PresetPlugin]) -> Result<Vec<AppliedPlugin>> {
+        if entries.len() > MAX_PLUGINS {
+            return Err(anyhow!(
+                "Chain has {} plugins (MAX_PLUGINS={})",
+                entries.len(),
+                MAX_PLUGINS
+            ));
+        }
+
+        let previous_order = self.plugin_manager.order.clone();
+        let mut staged = StagedChain::default();
+        if let Err(e) = self.stage_chain(entries, &mut staged) {
+            self.rollback_chain(staged, previous_order);
+            return Err(e);
+        }
+
+        Ok(self.commit_chain(entries, staged, &previous_order))
+    }
+
+    fn stage_chain(&mut self, entries: &[PresetPlugin], staged: &mut StagedChain) -> Result<()> {
+        let wanted: Vec<(&str, Option<&str>)> = entries
+            .iter()
+            .map(|e| (e.path.as_str(), e.class_id.as_deref()))
+            .collect();
+        let reusable = self.plugin_manager.find_reusable(&wanted);
+
+        for (entry, reuse) in entries.iter().zip(reusable) {
+            let id = match reuse {
+                Some(id) => {
+                    staged.reused.insert(id.clone());
+                    id
+                }
+                None => {
+                    let (id, _name, index, processor) = self
+                        .plugin_manager
+                        .load_plugin(
+                            &entry.path,
+                            entry.class_id.as_deref(),
+                            self.current_sample_rate,
+                            4096usize.max(self.current_block_size),
+                            self.current_channels,
+                            self.is_audio_running(),
+                        )
+                        .with_context(|| format!("Failed to load {}", entry.path))?;
+                    staged.loaded.push(id.clone());
+                    if let Some(proc) = processor {
+                        staged.processors.push((id.clone(), index, proc));
+                    }
+                    id
+                }
+            };
+
+            let instance = self
+                .plugin_manager
+                .get(&id)
+                .ok_or_else(|| anyhow!("Plugin vanished during load: {}", id))?;
+            if let Some(class_id) = &entry.class_id {
+                if !class_id.eq_ignore_ascii_case(&instance.class_id) {
+                    return Err(anyhow!(
+                        "{} does not provide class {} (got {})",
+                        entry.path,
+                        class_id,
+                        instance.class_id
+                    ));
+                }
+            }
+            if let Some(state) = &entry.state {
+                if staged.reused.contains(&id) {
+                    let previous = instance
+                        .get_state()
+                        .with_context(|| format!("Failed to save state of {}", instance.name))?;
+                    staged.previous_states.push((
+                        id.clone(),
+                        previous,
+                        instance.get_controller_state(),
+                    ));
+                }
+                let _activity =
+                    crash_guard::track(CrashPhase::State, &instance.path, &instance.name);
+                instance
+                    .set_state(state, entry.controller_state.as_deref())
+                    .with_context(|| format!("Failed to restore state of {}", instance.name))?;
+            }
+
+            staged.ids.push(id);
+        }
+
+        Ok(())
+    }
+
+    fn rollback_chain(&mut self, staged: StagedChain, previous_order: Vec<String>) {
+        // Processors must be released before their instances.
+        drop(staged.processors);
+        for id in &staged.loaded {
+            if let Err(e) = self.plugin_manager.remove_plugin(id) {
+                log::warn!("ApplyChain rollback: failed to remove {}: {}", id, e);
+            }
+        }
+        for (id, state, controller_state) in staged.previous_states.iter().rev() {
+            if let Some(instance) = self.plugin_manager.get(id) {
+                if let Err(e) = instance.set_state(state, controller_state.as_deref()) {
+                    log::warn!("ApplyChain rollback: failed to restore {}: {}", id, e);
+                }
+            }
+        }
+        self.plugin_manager.order = previous_order;
+    }
+
+    fn commit_chain(
+        &mut self,
+        entries: &[PresetPlugin],
+        staged: StagedChain,
+        previous_order: &[String],
+    ) -> Vec<AppliedPlugin> {
+        let running = self.is_audio_running();
+
+        let keep: HashSet<&String> = staged.ids.iter().collect();
+        for id in previous_order.iter().filter(|id| !keep.contains(id)) {
+            self.editor_manager.close_editor(id);
+            self.midi_manager.unassign(id);
+            if running {
+                match self.plugin_manager.begin_unload(id) {
+                    Ok(index) => {
+                        self.queue_audio_msg(AudioThreadMessage::RemoveProcessor { index })
+                    }
+                    Err(e) => log::warn!("ApplyChain: failed to unload {}: {}", id, e),
+                }
+            } else if let Err(e) = self.plugin_manager.remove_plugin(id) {
+                log::warn!("ApplyChain: failed to remove {}: {}", id, e);
+            }
+        }
+
+        for (entry, id) in entries.iter().zip(&staged.ids) {
+            let pm = &mut self.plugin_manager;
+            if entry.enabled {
+                pm.bypassed.remove(id);
+            } else {
+                pm.bypassed.insert(id.clone());
+            }
+            if entry.muted {
+                pm.muted.insert(id.clone());
+            } else {
+                pm.muted.remove(id);
+            }
+            pm.gains.insert(id.clone(), entry.gain);
+
+            if entry.midi_input.is_some() {
+                if let Err(e) = self.set_midi_input(id, entry.midi_input.clone()) {
+                    log::warn!("ApplyChain: MIDI input for {} not restored: {}", id, e);
+                }
+            }
+        }
+
+        if running {
+            for (id, index, processor) in staged.processors {
+                let initial_gain = *self.plugin_manager.gains.get(&id).unwrap_or(&1.0);
+                self.queue_audio_msg(AudioThreadMessage::AddProcessor {
+                    index,
+                    processor,
+                    initial_gain,
+                });
+            }
+            for (entry, id) in entries.iter().zip(&staged.ids) {
+                let Some(index) = self.plugin_manager.rt_index_of(id) else {
+                    continue;
+                };
+                self.queue_audio_msg(AudioThreadMessage::SetBypass {
+                    index,
+                    active: !entry.enabled,
+                });
+                self.queue_audio_msg(AudioThreadMessage::SetMute {
+                    index,
+                    active: entry.muted,
+                });
+                self.queue_audio_msg(AudioThreadMessage::SetGain {
+                    index,
+                    value: entry.gain,
+                });
+            }
+        }
+
+        self.plugin_manager.order = staged.ids.clone();
+        if running {
+            self.queue_audio_msg(self.make_reorder_message());
         }
+
+        staged
+            .ids
+            .iter()
+            .filter_map(|id| self.plugin_manager.get(id))
+            .map(|instance| AppliedPlugin {
+                id: instance.id.clone(),
+                name: instance.name.clone(),
+                path: instance.path.clone(),
+                reused: staged.reused.contains(&instance.id),
+            })
+            .collect()
     }
 
     fn make_reorder_message(&self) -> AudioThreadMessage {
@@ -912,31 +1269,289 @@ impl Engine {
         sample_rate: Option<u32>,
         buffer_size: Option<u32>,
     ) -> Result<()> {
+        self.active_audio = None;
         self.start_audio_impl(
-            host_name,
-            input_device,
-            output_device,
+            host_name.clone(),
+            input_device.clone(),
+            output_device.clone(),
             sample_rate,
             buffer_size,
             true,
-        )
+        )?;
+
+        let host = host_name.unwrap_or_default();
+        let (input, output) = if is_null_host(&host) {
+            (input_device, output_device)
+        } else {
+            // Resolved names (defaults are filled in by start_audio_impl)
+            (
+                self.device_manager
+                    .active_input
+                    .as_ref()
+                    .map(|d| d.name.clone()),
+                self.device_manager
+                    .active_output
+                    .as_ref()
+                    .map(|d| d.name.clone()),
+            )
+        };
+        self.active_audio = Some(AudioConfigSnapshot {
+            host,
+            input,
+            output,
+            sample_rate: self.current_sample_rate as u32,
+            buffer_size: self.current_block_size as u32,
+            channels: self.current_channels as u32,
+        });
+        Ok(())
     }
 
-    #[allow(deprecated)]
-    fn start_audio_impl(
-        &mut self,
-        host_name: Option<String>,
-        input_name: Option<String>,
-        output_name: Option<String>,
-        sample_rate: Option<u32>,
-        buffer_size: Option<u32>,
-        allow_fallback: bool,
-    ) -> Result<()> {
-        if self.output_stream.is_some() {
-            self.stop_audio();
+    /// Reports parameters changed in plugin editors since the last call, latest value only.
+    fn send_param_edits(&self) {
+        for id in &self.plugin_manager.order {
+            let Some(instance) = self.plugin_manager.get(id) else {
+                continue;
+            };
+            for (param_id, value) in instance.take_param_edits() {
+                self.send_event(EngineEvent::ParameterChanged {
+                    id: id.clone(),
+                    param_id,
+                    value,
+                    display: instance.param_display(param_id, value),
+                });
+            }
         }
+    }
 
-        self.send_event(EngineEvent::Log(format!(
+    /// Acts on `restartComponent` requests raised since the last call.
+    fn handle_restart_requests(&mut self) {
+        let requests: Vec<(String, i32)> = self
+            .plugin_manager
+            .order
+            .iter()
+            .filter_map(|id| {
+                let flags = self.plugin_manager.get(id)?.take_restart_flags();
+                (flags != 0).then(|| (id.clone(), flags))
+            })
+            .collect();
+
+        for (id, flags) in requests {
+            log::info!("Plugin {} requested restart (flags {:#x})", id, flags);
+            if flags & (K_PARAM_VALUES_CHANGED | K_PARAM_TITLES_CHANGED) != 0 {
+                self.send_event(EngineEvent::ParametersChanged { id: id.clone() });
+            }
+            if flags & (K_LATENCY_CHANGED | K_IO_CHANGED) == 0 {
+                continue;
+            }
+            if !self.is_audio_running() || self.plugin_manager.pending_init.contains(&id) {
+                // Nothing is processing yet; the next activation picks up the new setup
+                self.send_plugin_restarted(&id);
+                continue;
+            }
+            let Some(instance) = self.plugin_manager.get(&id) else {
+                continue;
+            };
+            if !instance.needs_restart(flags) {
+                // Latency and buses are as prepared; re-preparing would change nothing
+                continue;
+            }
+            // The plugin may only be re-prepared once its processor is off the audio
+            // thread: finish_restart runs when the retire comes back.
+            if let Some(index) = self.plugin_manager.begin_restart(&id) {
+                self.queue_audio_msg(AudioThreadMessage::RemoveProcessor { index });
+            }
+        }
+    }
+
+    /// Re-prepares a plugin whose processor was retired for a restart and puts a fresh
+    /// processor into its slot.
+    fn finish_restart(&mut self, id: &str) {
+        let Some(index) = self.plugin_manager.rt_index_of(id) else {
+            return;
+        };
+        let sr = self.current_sample_rate;
+        let bs = 4096usize.max(self.current_block_size) as i32;
+        let ch = self.current_channels as i32;
+
+        let processor = {
+            let Some(instance) = self.plugin_manager.get_mut(id) else {
+                return;
+            };
+            let _activity = crash_guard::track(CrashPhase::Loading, &instance.path, &instance.name);
+            if let Err(e) = instance.prepare_processing(sr, bs, ch) {
+                log::error!("Restart of {} failed: {}", instance.name, e);
+            }
+            instance.create_processor()
+        };
+
+        match processor {
+            Some(processor) => self.queue_add_processor(id, index, processor),
+            None => log::error!("Restart of {}: no processor", id),
+        }
+        self.send_plugin_restarted(id);
+    }
+
+    fn send_plugin_restarted(&self, id: &str) {
+        let latency_samples = self
+            .plugin_manager
+            .get(id)
+            .map(|instance| instance.latency_samples())
+            .unwrap_or(0);
+        let total_chain_latency_samples = self
+            .plugin_manager
+            .total_latency_samples(self.global_bypass)
+            .saturating_add(self.noise_reduction_latency_samples());
+        self.send_event(EngineEvent::PluginRestarted {
+            id: id.to_string(),
+            latency_samples,
+            total_chain_latency_samples,
+        });
+    }
+
+    /// Puts `processor` into slot `index` with the plugin's gain, bypass and mute state.
+    fn queue_add_processor(&mut self, id: &str, index: u8, processor: VstProcessor) {
+        let initial_gain = *self.plugin_manager.gains.get(id).unwrap_or(&1.0);
+        self.queue_audio_msg(AudioThreadMessage::AddProcessor {
+            index,
+            processor,
+            initial_gain,
+        });
+        if self.plugin_manager.bypassed.contains(id) {
+            self.queue_audio_msg(AudioThreadMessage::SetBypass {
+                index,
+                active: true,
+            });
+        }
+        if self.plugin_manager.muted.contains(id) {
+            self.queue_audio_msg(AudioThreadMessage::SetMute {
+                index,
+                active: true,
+            });
+        }
+        self.queue_audio_msg(self.make_reorder_message());
+    }
+
+    /// Rate the processing graph (and so the noise reducer) is set up for.
+    fn graph_sample_rate_hz(&self) -> u32 {
+        self.current_sample_rate.round().clamp(8_000.0, 192_000.0) as u32
+    }
+
+    fn noise_reduction_latency_samples(&self) -> u32 {
+        if self.noise_reduction_enabled {
+            RtNoiseReducer::latency_samples(self.graph_sample_rate_hz())
+        } else {
+            0
+        }
+    }
+
+    fn snapshot(&self) -> EngineSnapshot {
+        let pm = &self.plugin_manager;
+        let plugins = pm
+            .order
+            .iter()
+            .filter_map(|id| pm.get(id))
+            .map(|instance| PluginSnapshot {
+                id: instance.id.clone(),
+                path: instance.path.clone(),
+                class_id: instance.class_id.clone(),
+                name: instance.name.clone(),
+                rt_index: pm.rt_index_of(&instance.id),
+                bypassed: pm.bypassed.contains(&instance.id),
+                muted: pm.muted.contains(&instance.id),
+                gain: pm.gains.get(&instance.id).copied().unwrap_or(1.0),
+                latency_samples: instance.latency_samples(),
+                pending_init: pm.pending_init.contains(&instance.id),
+                dirty: instance.is_dirty(),
+                accepts_midi: instance.accepts_midi(),
+                midi_input: self.midi_manager.assignment(&instance.id).cloned(),
+            })
+            .collect();
+
+        EngineSnapshot {
+            plugins,
+            order: pm.order.clone(),
+            global_bypass: self.global_bypass,
+            global_mute: self.global_mute,
+            input_gain: self.input_gain,
+            output_gain: self.output_gain,
+            noise_reduction_enabled: self.noise_reduction_enabled,
+            noise_reduction_mode: self.noise_reduction_mode.clone(),
+            input_channels: (self.input_channel_l, self.input_channel_r),
+            channel_scan: self.scan_enabled,
+            transport: self.transport,
+            audio: if self.is_audio_running() {
+                self.active_audio.clone()
+            } else {
+                None
+            },
+        }
+    }
+
+    /// Same chain as `snapshot`, plus each plugin's state chunk, in a form `ApplyChain` can
+    /// replay on a fresh engine, and the ids of the plugins whose chunk it holds. Leaves the
+    /// plugins' dirty flags alone (see `Command::SessionSaved`).
+    fn session(&self) -> (SessionState, Vec<String>) {
+        let pm = &self.plugin_manager;
+        let mut ids = Vec::new();
+        let plugins = pm
+            .order
+            .iter()
+            .filter_map(|id| pm.get(id))
+            .map(|instance| {
+                let state = match instance.get_state() {
+                    Ok(state) => {
+                        ids.push(instance.id.clone());
+                        Some(state)
+                    }
+                    Err(e) => {
+                        log::warn!("Session: could not get state of {}: {}", instance.name, e);
+                        None
+                    }
+                };
+                PresetPlugin {
+                    path: instance.path.clone(),
+                    class_id: Some(instance.class_id.clone()),
+                    name: instance.name.clone(),
+                    vendor: String::new(),
+                    version: String::new(),
+                    enabled: !pm.bypassed.contains(&instance.id),
+                    muted: pm.muted.contains(&instance.id),
+                    gain: pm.gains.get(&instance.id).copied().unwrap_or(1.0),
+                    midi_input: self.midi_manager.assignment(&instance.id).cloned(),
+                    state,
+                    controller_state: instance.get_controller_state(),
+                }
+            })
+            .collect();
+
+        let session = SessionState {
+            version: SESSION_VERSION,
+            plugins,
+            input_gain: self.input_gain,
+            output_gain: self.output_gain,
+            noise_reduction_enabled: self.noise_reduction_enabled,
+            noise_reduction_mode: self.noise_reduction_mode.clone(),
+            input_channels: (self.input_channel_l, self.input_channel_r),
+            transport: self.transport,
+        };
+        (session, ids)
+    }
+
+    #[allow(deprecated)]
+    fn start_audio_impl(
+        &mut self,
+        host_name: Option<String>,
+        input_name: Option<String>,
+        output_name: Option<String>,
+        sample_rate: Option<u32>,
+        buffer_size: Option<u32>,
+        allow_fallback: bool,
+    ) -> Result<()> {
+        if self.is_audio_running() {
+            self.stop_audio();
+        }
+
+        self.send_event(EngineEvent::Log(format!(
             "Start Audio Request: Host={:?}, Input={:?}, Output={:?}, SR={:?}, Buf={:?}",
             host_name, input_name, output_name, sample_rate, buffer_size
         )));
@@ -944,6 +1559,9 @@ impl Engine {
         let host_name_str = host_name
             .as_deref()
             .ok_or_else(|| anyhow!("Host name not specified"))?;
+        if is_null_host(host_name_str) {
+            return self.start_null_audio(input_name, output_name, sample_rate, buffer_size);
+        }
         let host_id_str = match host_name_str {
             "ASIO" => cpal::HostId::Asio,
             "Wasapi" | "WASAPI" => cpal::HostId::Wasapi,
@@ -1112,142 +1730,15 @@ impl Engine {
 
         // Error Handler
         let err_fn_ipc = |err: cpal::StreamError| {
-            let msg = OutputMessage::Event(EngineEvent::Error(format!("Stream Error: {}", err)));
-            if let Ok(json) = serde_json::to_string(&msg) {
-                println!("IPC:{}", json);
-                let _ = io::stdout().flush();
-            }
+            ipc_transport::send(&OutputMessage::Event(EngineEvent::Error(format!(
+                "Stream Error: {}",
+                err
+            ))));
         };
 
-        // Init Ring Buffers
-        let ring = HeapRb::<AudioThreadMessage>::new(32);
-        let (producer, mut consumer) = ring.split();
-        self.command_tx = Some(producer);
-
-        let retire_rb = HeapRb::<RetiredProcessor>::new(32);
-        let (mut retire_prod, retire_cons) = retire_rb.split();
-        self.retire_rx = Some(retire_cons);
-
-        let level_rb = HeapRb::<MeterLevels>::new(4096);
-        let (mut level_prod, level_cons) = level_rb.split();
-        self.level_rx = Some(level_cons);
-
-        let channel_rb = HeapRb::<[f32; 32]>::new(16); // Small buffer for low-rate scan data
-        let (mut channel_prod, channel_cons) = channel_rb.split();
-        self.channel_rx = Some(channel_cons);
-
-        let audio_rb_size = (self.current_sample_rate as usize / 2) * 2;
-        let audio_rb = HeapRb::<f32>::new(audio_rb_size.max(8192));
-        let (mut audio_prod, mut audio_cons) = audio_rb.split();
-
-        // 3. Prepare Processors (Delegated to PluginManager)
-        let mut processors_vec = self.plugin_manager.prepare_for_audio_start(
-            self.current_sample_rate,
-            self.current_channels,
-            safe_max_block_size,
-        );
-
-        // Pre-compute RT state (no heap in callback)
-        let mut rt_bypassed: [bool; MAX_PLUGINS] = [false; MAX_PLUGINS];
-        for id in &self.plugin_manager.bypassed {
-            if let Some(idx) = self.plugin_manager.rt_index_of(id) {
-                rt_bypassed[idx as usize] = true;
-            }
-        }
-
-        let mut rt_muted: [bool; MAX_PLUGINS] = [false; MAX_PLUGINS];
-        for id in &self.plugin_manager.muted {
-            if let Some(idx) = self.plugin_manager.rt_index_of(id) {
-                rt_muted[idx as usize] = true;
-            }
-        }
-
-        let mut rt_gains: [Smoother; MAX_PLUGINS] = std::array::from_fn(|_| Smoother::new(1.0));
-        for (id, val) in &self.plugin_manager.gains {
-            if let Some(idx) = self.plugin_manager.rt_index_of(id) {
-                rt_gains[idx as usize] = Smoother::new(*val);
-            }
-        }
-
-        let mut rt_order: [u8; MAX_PLUGINS] = [u8::MAX; MAX_PLUGINS];
-        let mut rt_order_len: usize = 0;
-        for id in &self.plugin_manager.order {
-            if rt_order_len >= MAX_PLUGINS {
-                break;
-            }
-            if let Some(idx) = self.plugin_manager.rt_index_of(id) {
-                rt_order[rt_order_len] = idx;
-                rt_order_len += 1;
-            }
-        }
-
         let channels_len = out_stream_config.channels as usize;
-        let max_ch = channels_len.max(2);
-        let max_frames_per_callback = 4096.max(safe_max_block_size);
-
-        // RT State Setup (fixed-capacity / no resize in callback)
-        let max_len = max_frames_per_callback.saturating_mul(channels_len.max(1));
-        let mut input_buf = vec![0.0; max_len];
-
-        // Planar Buffers (fixed-capacity for callback upper bound)
-        let mut planar_buf_a: Vec<Vec<f32>> = (0..max_ch)
-            .map(|_| vec![0.0; max_frames_per_callback])
-            .collect();
-        let mut planar_buf_b: Vec<Vec<f32>> = (0..max_ch)
-            .map(|_| vec![0.0; max_frames_per_callback])
-            .collect();
-
-        let mut rt_processors: [Option<VstProcessor>; MAX_PLUGINS] = std::array::from_fn(|_| None);
-        let mut rt_active_count: usize = 0;
-        while let Some((idx, proc)) = processors_vec.pop() {
-            let slot = idx as usize;
-            if slot < MAX_PLUGINS {
-                if rt_processors[slot].is_none() {
-                    rt_processors[slot] = Some(proc);
-                    rt_active_count += 1;
-                }
-            }
-        }
-
-        let mut rt_global_mute = false;
-        let mut rt_global_bypass = self.global_bypass;
-        let mut rt_input_gain = 1.0f32;
-        let mut rt_output_gain = Smoother::new(1.0);
-        let mut rt_input_l = self.input_channel_l;
-        let mut rt_input_r = self.input_channel_r;
-        let mut rt_scan_enabled = self.scan_enabled;
-        let rt_sample_rate_hz = self.current_sample_rate.round().clamp(8_000.0, 192_000.0) as u32;
-        let mut rt_noise_reduction_enabled = self.noise_reduction_enabled;
-        let mut rt_noise_reduction_mix =
-            noise_reduction_mix_from_mode(self.noise_reduction_mode.as_str());
-        let mut rt_noise_reducer = RtNoiseReducer::new(rt_sample_rate_hz);
-
-        let frames_counter = self.frames_processed.clone();
-
-        let stats_max_jitter = Arc::new(AtomicU64::new(0));
-        let stats_glitches = Arc::new(AtomicU64::new(0));
-        self.stats_max_jitter = stats_max_jitter.clone();
-        self.stats_glitches = stats_glitches.clone();
-
-        let mut last_callback_inst = Instant::now();
-        let expected_period_micros =
-            (self.current_block_size as u64 * 1000000) / self.current_sample_rate as u64;
-
-        let mmcss_set_out = Arc::new(AtomicBool::new(false));
-        let mut pending_retire: [Option<RetiredProcessor>; MAX_PLUGINS] =
-            std::array::from_fn(|_| None);
-
-        fn remove_from_order(order: &mut [u8; MAX_PLUGINS], len: &mut usize, idx: u8) {
-            let mut write = 0usize;
-            for read in 0..*len {
-                let v = order[read];
-                if v != idx {
-                    order[write] = v;
-                    write += 1;
-                }
-            }
-            *len = write;
-        }
+        let (mut output_callback, mut audio_prod) =
+            self.build_output_callback(channels_len, safe_max_block_size);
 
         // Output Stream Setup
         let retry_host_out = host_name.clone();
@@ -1256,464 +1747,7 @@ impl Engine {
 
         let output_stream = match out_dev.build_output_stream(
             &out_stream_config,
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                // Flush any pending retire messages first (never drop VST objects in RT thread)
-                for slot in 0..MAX_PLUGINS {
-                    if let Some(retired) = pending_retire[slot].take() {
-                        match retire_prod.try_push(retired) {
-                            Ok(()) => {}
-                            Err(retired) => {
-                                pending_retire[slot] = Some(retired);
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                let now = Instant::now();
-                let delta = now.duration_since(last_callback_inst).as_micros() as u64;
-                last_callback_inst = now;
-
-                // Jitter Calc
-                if delta > 0 && expected_period_micros > 0 {
-                    let jitter = if delta > expected_period_micros {
-                        delta - expected_period_micros
-                    } else {
-                        0
-                    };
-                    let current_max = stats_max_jitter.load(Ordering::Relaxed);
-                    if jitter > current_max {
-                        stats_max_jitter.store(jitter, Ordering::Relaxed);
-                    }
-                    if jitter > (expected_period_micros / 2) {
-                        stats_glitches.fetch_add(1, Ordering::Relaxed);
-                    }
-                }
-
-                if !mmcss_set_out.load(Ordering::Relaxed) {
-                    unsafe {
-                        use windows::Win32::System::Threading::{
-                            GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_HIGHEST,
-                            THREAD_PRIORITY_TIME_CRITICAL,
-                        };
-                        let priority = if time_critical_audio_threads_enabled() {
-                            THREAD_PRIORITY_TIME_CRITICAL
-                        } else {
-                            THREAD_PRIORITY_HIGHEST
-                        };
-                        let _ = SetThreadPriority(GetCurrentThread(), priority);
-                    }
-                    mmcss_set_out.store(true, Ordering::Relaxed);
-                }
-
-                if channels_len > 0 {
-                    let _ = frames_counter
-                        .fetch_add((data.len() / channels_len) as u64, Ordering::Relaxed);
-                }
-
-                // Process Commands
-                while let Some(msg) = consumer.try_pop() {
-                    match msg {
-                        AudioThreadMessage::AddProcessor {
-                            index,
-                            processor,
-                            initial_gain,
-                        } => {
-                            let slot = index as usize;
-                            if slot < MAX_PLUGINS {
-                                if rt_processors[slot].is_none() {
-                                    rt_processors[slot] = Some(processor);
-                                    rt_active_count += 1;
-                                    rt_gains[slot] = Smoother::new_ramp(0.0, initial_gain);
-                                }
-                            }
-                        }
-                        AudioThreadMessage::RemoveProcessor { index } => {
-                            let slot = index as usize;
-                            if slot < MAX_PLUGINS {
-                                if pending_retire[slot].is_none() {
-                                    if let Some(proc) = rt_processors[slot].take() {
-                                        let retired = RetiredProcessor {
-                                            index,
-                                            processor: proc,
-                                        };
-                                        if let Err(retired) = retire_prod.try_push(retired) {
-                                            pending_retire[slot] = Some(retired);
-                                        };
-                                        rt_active_count = rt_active_count.saturating_sub(1);
-                                    }
-                                }
-                                rt_muted[slot] = false;
-                                rt_bypassed[slot] = false;
-                                rt_gains[slot] = Smoother::new(1.0);
-                                remove_from_order(&mut rt_order, &mut rt_order_len, index);
-                            }
-                        }
-                        AudioThreadMessage::ReorderProcessors { order, len } => {
-                            rt_order = order;
-                            rt_order_len = (len as usize).min(MAX_PLUGINS);
-                        }
-                        AudioThreadMessage::SetBypass { index, active } => {
-                            let slot = index as usize;
-                            if slot < MAX_PLUGINS {
-                                rt_bypassed[slot] = active;
-                            }
-                        }
-                        AudioThreadMessage::SetMute { index, active } => {
-                            let slot = index as usize;
-                            if slot < MAX_PLUGINS {
-                                rt_muted[slot] = active;
-                            }
-                        }
-                        AudioThreadMessage::SetGain { index, value } => {
-                            let slot = index as usize;
-                            if slot < MAX_PLUGINS {
-                                rt_gains[slot].set_target(value);
-                            }
-                        }
-                        AudioThreadMessage::SetGlobalMute(active) => {
-                            rt_global_mute = active;
-                        }
-                        AudioThreadMessage::SetGlobalBypass(active) => {
-                            rt_global_bypass = active;
-                        }
-                        AudioThreadMessage::SetInputGain(val) => {
-                            rt_input_gain = val;
-                        }
-                        AudioThreadMessage::SetNoiseReduction { active, mix } => {
-                            rt_noise_reduction_enabled = active;
-                            rt_noise_reduction_mix = mix.clamp(0.0, 1.0);
-                            rt_noise_reducer.reset_state();
-                        }
-                        AudioThreadMessage::SetOutputGain(val) => {
-                            rt_output_gain.set_target(val);
-                        }
-                        AudioThreadMessage::SetInputChannels(l, r) => {
-                            rt_input_l = l;
-                            rt_input_r = r;
-                        }
-                        AudioThreadMessage::SetChannelScan(enable) => {
-                            rt_scan_enabled = enable;
-                        }
-                        AudioThreadMessage::Stop => {}
-                    }
-                }
-
-                if channels_len == 0 {
-                    return;
-                }
-                let channels = channels_len;
-                let requested_frames = data.len() / channels;
-                let frames = requested_frames.min(max_frames_per_callback);
-                if frames == 0 {
-                    data.fill(0.0);
-                    return;
-                }
-                if requested_frames > frames {
-                    // Callback delivered more frames than our fixed RT capacity.
-                    // We keep RT deterministic by truncating this block and counting as a glitch.
-                    stats_glitches.fetch_add(1, Ordering::Relaxed);
-                }
-
-                // --- 1. Efficient Input Data Fetch & De-interleaving ---
-                let available = audio_cons.occupied_len();
-                let to_read = frames * channels;
-
-                let read_count = if available >= to_read {
-                    audio_cons.pop_slice(&mut input_buf[..to_read])
-                } else {
-                    audio_cons.pop_slice(&mut input_buf[..to_read.min(available)])
-                };
-
-                if read_count < to_read {
-                    input_buf[read_count..to_read].fill(0.0);
-                }
-
-                // De-interleave & Metering
-                let mut in_max_l = 0.0;
-                let mut in_max_r = 0.0;
-
-                // Channel Scanning (For UI Smart Selector)
-                let mut channel_peaks = [0.0f32; 32]; // Max 32 channels scan
-                let scan_limit = channels.min(32);
-
-                for i in 0..frames {
-                    // Manual de-interleaving and Mapping to Stereo Bus (0/1)
-                    // The internal processing is Stereo (2ch).
-                    // We map the selected Input Channels to Planar 0 and 1.
-
-                    // First de-interleave everything to planar? Or just what we need?
-                    // To support "Active Channel Scan", we should de-interleave or peek all.
-                    // For performance, let's just peek for scan and de-interleave selected for processing.
-
-                    // OPTIMIZATION: Just de-interleave ALL for now, or just the selected?
-                    // Let's stick to full de-interleave to plan_buf_a to support multi-channel plugins later if needed.
-                    for ch in 0..channels {
-                        let sample = input_buf[i * channels + ch] * rt_input_gain;
-                        planar_buf_a[ch][i] = sample;
-
-                        // Scanner Logic (only if enabled)
-                        if rt_scan_enabled && ch < scan_limit {
-                            let abs = sample.abs();
-                            if abs > channel_peaks[ch] {
-                                channel_peaks[ch] = abs;
-                            }
-                        }
-                    }
-
-                    // Input Routing & Metering:
-                    // We WANT the selected input channels (rt_input_l, rt_input_r) to appear as indices 0 and 1
-                    // for the subsequent plugin chain if the chain expects stereo.
-                    // HOWEVER, `planar_buf_a` currently holds the physical mapping (Index N = Channel N).
-                    // If we want plugins to receive "Main Input" on 0/1, we must SWAP or COPY.
-
-                    // Simple approach: Copy active inputs to a temporary "Stereo Processing Buffer"
-                    // OR just use the selected indices for Metering and passing to first plugin.
-                    // BUT: Current ping-pong logic iterates 0..channels.
-                    // If plugins process "Stereo" they usually take buf[0] and buf[1].
-
-                    // SOLUTION: The host should likely copy Selected Ch -> Ch 0, Selected Ch -> Ch 1
-                    // BEFORE processing starts.
-                    // Note: This destructively overwrites physical Ch 0/1 data in the buffer.
-                    // But that's fine, we are "Routing" inputs.
-
-                    let sample_l = if rt_input_l < channels {
-                        planar_buf_a[rt_input_l][i]
-                    } else {
-                        0.0
-                    };
-                    let sample_r = if rt_input_r < channels {
-                        planar_buf_a[rt_input_r][i]
-                    } else {
-                        0.0
-                    };
-
-                    // Overwrite 0/1 for the processing chain
-                    if channels >= 2 {
-                        planar_buf_a[0][i] = sample_l;
-                        planar_buf_a[1][i] = sample_r;
-                    }
-
-                    // Main Metering (Post-Routing)
-                    let abs_l = sample_l.abs();
-                    if abs_l > in_max_l {
-                        in_max_l = abs_l;
-                    }
-
-                    let abs_r = sample_r.abs();
-                    if abs_r > in_max_r {
-                        in_max_r = abs_r;
-                    }
-                }
-
-                if rt_noise_reduction_enabled && rt_noise_reduction_mix > 0.0 {
-                    let wet_mix = rt_noise_reduction_mix;
-                    let dry_mix = 1.0 - wet_mix;
-                    if channels >= 2 {
-                        for i in 0..frames {
-                            let dry_left = planar_buf_a[0][i];
-                            let dry_right = planar_buf_a[1][i];
-                            let (wet_left, wet_right) =
-                                rt_noise_reducer.process_sample(dry_left, dry_right);
-                            planar_buf_a[0][i] = dry_left * dry_mix + wet_left * wet_mix;
-                            planar_buf_a[1][i] = dry_right * dry_mix + wet_right * wet_mix;
-                        }
-                    } else if channels == 1 {
-                        for i in 0..frames {
-                            let dry_mono = planar_buf_a[0][i];
-                            let (wet_mono, _) =
-                                rt_noise_reducer.process_sample(dry_mono, dry_mono);
-                            planar_buf_a[0][i] = dry_mono * dry_mix + wet_mono * wet_mix;
-                        }
-                    }
-                }
-
-                // Send Channel Scan Data (throttled)
-                if rt_scan_enabled {
-                    // Simple throttling using frames_processed
-                    let current_frames = frames_counter.load(Ordering::Relaxed);
-                    // 48000Hz / 4800 = 10Hz approx.
-                    // This logic "current_frames % 4800 < frames" ensures we trigger once per ~4800-frame window.
-
-                    if current_frames % 4800 < frames as u64 {
-                        let mut peaks = [0.0f32; 32];
-                        for i in 0..scan_limit {
-                            peaks[i] = channel_peaks[i];
-                            channel_peaks[i] = 0.0; // Reset peak
-                        }
-                        // Use channel_prod captured by move closure
-                        let _ = channel_prod.try_push(peaks);
-                    }
-                }
-
-                // --- 2. Ping-Pong Processing Loop ---
-                // We toggle between using `planar_buf_a` and `planar_buf_b` as input/output
-                // Current Data is always in `current_buffer_index` (0 -> A, 1 -> B)
-
-                let mut current_source_is_a = true; // True usually implies result is in A
-
-                // Global Bypass: Skip all plugin processing (A/B comparison mode)
-                // Input remains in planar_buf_a, so current_source_is_a stays true.
-                if !rt_global_bypass && rt_active_count > 0 && rt_order_len > 0 {
-                    for i_order in 0..rt_order_len {
-                        let idx = rt_order[i_order] as usize;
-                        if idx >= MAX_PLUGINS {
-                            continue;
-                        }
-
-                        // Bypass Check
-                        if rt_bypassed[idx] {
-                            // Soft Bypass: Explicitly copy input buffer to output buffer
-                            // This ensures the processing chain continuity ("Ping-Pong" flow)
-                            // and guarantees valid data in the target buffer, resolving "Silence" issues.
-                            let (in_bufs, out_bufs) = if current_source_is_a {
-                                (&planar_buf_a, &mut planar_buf_b)
-                            } else {
-                                (&planar_buf_b, &mut planar_buf_a)
-                            };
-
-                            for ch in 0..channels {
-                                // Safety bounds check
-                                if ch < in_bufs.len() && ch < out_bufs.len() {
-                                    if in_bufs[ch].len() >= frames && out_bufs[ch].len() >= frames {
-                                        out_bufs[ch][..frames]
-                                            .copy_from_slice(&in_bufs[ch][..frames]);
-                                    }
-                                }
-                            }
-
-                            // Toggle source to maintain chain state (A -> B or B -> A)
-                            current_source_is_a = !current_source_is_a;
-                            continue;
-                        }
-
-                        // Mute Check
-                        if rt_muted[idx] {
-                            // If muted, we need to zero out the current buffer
-                            if current_source_is_a {
-                                for ch in 0..channels {
-                                    planar_buf_a[ch][..frames].fill(0.0);
-                                }
-                            } else {
-                                for ch in 0..channels {
-                                    planar_buf_b[ch][..frames].fill(0.0);
-                                }
-                            }
-                            continue;
-                        }
-
-                        // Process
-                        if let Some(proc) = rt_processors[idx].as_mut() {
-                            let (in_bufs, out_bufs) = if current_source_is_a {
-                                (&planar_buf_a, &mut planar_buf_b)
-                            } else {
-                                (&planar_buf_b, &mut planar_buf_a)
-                            };
-
-                            // Call new process_planar
-                            proc.process_planar(in_bufs, out_bufs, frames);
-
-                            // Toggle
-                            current_source_is_a = !current_source_is_a;
-
-                            // Apply Gain (Smoother)
-                            let smoother = &mut rt_gains[idx];
-                            // Optimization: Check if gain is effectively 1.0 (no change needed)
-                            if (smoother.current - 1.0).abs() > 0.0001
-                                || (smoother.target - 1.0).abs() > 0.0001
-                            {
-                                let target_buf = if current_source_is_a {
-                                    &mut planar_buf_a
-                                } else {
-                                    &mut planar_buf_b
-                                };
-
-                                let mut frame_idx = 0;
-                                while frame_idx < frames {
-                                    let gain = smoother.next();
-                                    for ch in 0..channels {
-                                        target_buf[ch][frame_idx] *= gain;
-                                    }
-                                    frame_idx += 1;
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // --- 3. Result Interleaving & Output Metering ---
-                let final_buf = if current_source_is_a {
-                    &planar_buf_a
-                } else {
-                    &planar_buf_b
-                };
-
-                if rt_global_mute {
-                    data.fill(0.0);
-                    // Zero metering too implies output is silence
-                    let _ = level_prod.try_push(MeterLevels {
-                        input: [in_max_l, in_max_r],
-                        output: [0.0, 0.0],
-                    });
-                } else {
-                    // Initialize output with silence
-                    data.fill(0.0);
-
-                    // Map processed "Main" (0/1) back to the selected physical device channels
-                    // (Symmetric Routing / Insert Logic)
-                    let target_l = rt_input_l;
-                    let target_r = rt_input_r;
-
-                    for i in 0..frames {
-                        let gain = rt_output_gain.next();
-                        let main_l = final_buf
-                            .first()
-                            .and_then(|buf| buf.get(i))
-                            .copied()
-                            .unwrap_or(0.0)
-                            * gain;
-                        let main_r = final_buf
-                            .get(1)
-                            .and_then(|buf| buf.get(i))
-                            .copied()
-                            .unwrap_or(main_l)
-                            * gain;
-
-                        // Left
-                        if target_l < channels {
-                            let out_idx = i * channels + target_l;
-                            if out_idx < data.len() {
-                                data[out_idx] = main_l;
-                            }
-                        }
-                        // Right
-                        if target_r < channels {
-                            let out_idx = i * channels + target_r;
-                            if out_idx < data.len() {
-                                data[out_idx] = main_r;
-                            }
-                        }
-                    }
-
-                    // Metering: Reflect actual output level (post-master-gain)
-                    let gain_for_meter = rt_output_gain.current;
-                    let out_max_l = final_buf
-                        .first()
-                        .map(|buf| buf[..frames].iter().fold(0.0f32, |m, &x| m.max(x.abs())))
-                        .unwrap_or(0.0)
-                        * gain_for_meter;
-                    let out_max_r = final_buf
-                        .get(1)
-                        .map(|buf| buf[..frames].iter().fold(0.0f32, |m, &x| m.max(x.abs())))
-                        .unwrap_or(out_max_l)
-                        * gain_for_meter;
-
-                    let _ = level_prod.try_push(MeterLevels {
-                        input: [in_max_l, in_max_r],
-                        output: [out_max_l, out_max_r],
-                    });
-                }
-            },
+            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| output_callback(data),
             err_fn_ipc,
             None,
         ) {
@@ -1858,13 +1892,10 @@ impl Engine {
                 }
             },
             move |err| {
-                let msg = OutputMessage::Event(EngineEvent::Error(format!(
+                ipc_transport::send(&OutputMessage::Event(EngineEvent::Error(format!(
                     "Input Stream Error: {}",
                     err
-                )));
-                if let Ok(json) = serde_json::to_string(&msg) {
-                    println!("IPC:{}", json);
-                }
+                ))));
             },
             None,
         ) {
@@ -1947,15 +1978,198 @@ impl Engine {
         Ok(())
     }
 
+    /// Headless backend: runs the output callback from a timer thread (see `null_host`).
+    fn start_null_audio(
+        &mut self,
+        input_name: Option<String>,
+        output_name: Option<String>,
+        sample_rate: Option<u32>,
+        buffer_size: Option<u32>,
+    ) -> Result<()> {
+        let sample_rate = sample_rate
+            .unwrap_or(NULL_DEFAULT_SAMPLE_RATE)
+            .clamp(8_000, 192_000);
+        self.current_sample_rate = sample_rate as f64;
+        self.current_block_size = buffer_size
+            .map(|b| b as usize)
+            .unwrap_or(NULL_DEFAULT_BLOCK_SIZE)
+            .clamp(16, 8192);
+        self.current_channels = NULL_CHANNELS;
+
+        let safe_max_block_size = 4096usize.max(self.current_block_size);
+        let (output_callback, mut audio_prod) =
+            self.build_output_callback(NULL_CHANNELS, safe_max_block_size);
+
+        let stream = NullStream::start(
+            input_name.as_deref(),
+            output_name.as_deref(),
+            sample_rate,
+            self.current_block_size,
+            move |samples: &[f32]| {
+                let _ = audio_prod.push_slice(samples);
+            },
+            output_callback,
+        )?;
+        self.null_stream = Some(stream);
+
+        self.send_event(EngineEvent::Log(format!(
+            "Audio Engine Started (Null host): Sample Rate={}, Buffer Size={}, Channels={}",
+            self.current_sample_rate, self.current_block_size, self.current_channels
+        )));
+        self.send_event(EngineEvent::Started {
+            sample_rate: self.current_sample_rate as u32,
+            buffer_size: self.current_block_size as u32,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the RT ring buffers and the output callback shared by every backend
+    /// (cpal streams and the Null host). Returns the callback together with the producer
+    /// side of the input audio ring (interleaved, `channels_len` channels per frame).
+    fn build_output_callback(
+        &mut self,
+        channels_len: usize,
+        safe_max_block_size: usize,
+    ) -> (impl FnMut(&mut [f32]) + Send + 'static, AudioProducer) {
+        let audio_rb_size = (self.current_sample_rate as usize / 2) * 2;
+        let audio_rb = HeapRb::<f32>::new(audio_rb_size.max(8192));
+        let (audio_prod, mut audio_cons) = audio_rb.split();
+
+        // 3. Prepare Processors (Delegated to PluginManager)
+        let processors_vec = self.plugin_manager.prepare_for_audio_start(
+            self.current_sample_rate,
+            self.current_channels,
+            safe_max_block_size,
+        );
+
+        let max_frames_per_callback = 4096.max(safe_max_block_size);
+
+        // Pre-compute RT state (no heap in callback)
+        let chain = PluginChain::from_manager(
+            &self.plugin_manager,
+            processors_vec,
+            channels_len.max(2),
+            max_frames_per_callback,
+        );
+        let (mut graph, links) = ProcessingGraph::new(
+            GraphConfig {
+                channels: channels_len,
+                max_frames: max_frames_per_callback,
+                sample_rate_hz: self.graph_sample_rate_hz(),
+                input_channels: (self.input_channel_l, self.input_channel_r),
+                scan_enabled: self.scan_enabled,
+                global_bypass: self.global_bypass,
+                global_mute: self.global_mute,
+                input_gain: self.input_gain,
+                output_gain: self.output_gain,
+                noise_reduction_enabled: self.noise_reduction_enabled,
+                noise_reduction_mix: noise_reduction_mix_from_mode(
+                    self.noise_reduction_mode.as_str(),
+                ),
+                transport: self.transport,
+            },
+            chain,
+        );
+        self.command_tx = Some(links.commands);
+        self.retire_rx = Some(links.retired);
+        self.level_rx = Some(links.levels);
+        self.channel_rx = Some(links.channel_scan);
+
+        // RT State Setup (fixed-capacity / no resize in callback)
+        let max_len = max_frames_per_callback.saturating_mul(channels_len.max(1));
+        let mut input_buf = vec![0.0; max_len];
+
+        let frames_counter = self.frames_processed.clone();
+
+        let stats_max_jitter = Arc::new(AtomicU64::new(0));
+        let stats_glitches = Arc::new(AtomicU64::new(0));
+        self.stats_max_jitter = stats_max_jitter.clone();
+        self.stats_glitches = stats_glitches.clone();
+
+        let mut last_callback_inst = Instant::now();
+        let expected_period_micros =
+            (self.current_block_size as u64 * 1000000) / self.current_sample_rate as u64;
+
+        let mmcss_set_out = Arc::new(AtomicBool::new(false));
+
+        // Thin adapter: timing stats + thread priority + input ring, then the graph does the DSP.
+        let callback = move |data: &mut [f32]| {
+            let now = Instant::now();
+            let delta = now.duration_since(last_callback_inst).as_micros() as u64;
+            last_callback_inst = now;
+
+            // Jitter Calc
+            if delta > 0 && expected_period_micros > 0 {
+                let jitter = if delta > expected_period_micros {
+                    delta - expected_period_micros
+                } else {
+                    0
+                };
+                let current_max = stats_max_jitter.load(Ordering::Relaxed);
+                if jitter > current_max {
+                    stats_max_jitter.store(jitter, Ordering::Relaxed);
+                }
+                if jitter > (expected_period_micros / 2) {
+                    stats_glitches.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+
+            if !mmcss_set_out.load(Ordering::Relaxed) {
+                unsafe {
+                    use windows::Win32::System::Threading::{
+                        GetCurrentThread, SetThreadPriority, THREAD_PRIORITY_HIGHEST,
+                        THREAD_PRIORITY_TIME_CRITICAL,
+                    };
+                    let priority = if time_critical_audio_threads_enabled() {
+                        THREAD_PRIORITY_TIME_CRITICAL
+                    } else {
+                        THREAD_PRIORITY_HIGHEST
+                    };
+                    let _ = SetThreadPriority(GetCurrentThread(), priority);
+                }
+                mmcss_set_out.store(true, Ordering::Relaxed);
+            }
+
+            let mut to_read = 0;
+            if channels_len > 0 {
+                let requested_frames = data.len() / channels_len;
+                let _ = frames_counter.fetch_add(requested_frames as u64, Ordering::Relaxed);
+                if requested_frames > max_frames_per_callback {
+                    // Callback delivered more frames than our fixed RT capacity.
+                    // The graph truncates this block; count it as a glitch.
+                    stats_glitches.fetch_add(1, Ordering::Relaxed);
+                }
+                to_read = requested_frames.min(max_frames_per_callback) * channels_len;
+
+                let read_count = audio_cons.pop_slice(&mut input_buf[..to_read]);
+                if read_count < to_read {
+                    input_buf[read_count..to_read].fill(0.0);
+                }
+            }
+
+            graph.process(&input_buf[..to_read], data);
+        };
+
+        (callback, audio_prod)
+    }
+
+    fn is_audio_running(&self) -> bool {
+        self.output_stream.is_some() || self.null_stream.is_some()
+    }
+
     fn stop_audio(&mut self) {
         if let Some(tx) = &mut self.command_tx {
             let _ = tx.try_push(AudioThreadMessage::Stop);
         }
         self.input_stream = None;
         self.output_stream = None;
+        self.null_stream = None;
+        self.active_audio = None;
         self.command_tx = None;
         self.level_rx = None;
         self.retire_rx = None;
         self.pending_audio_msgs.clear();
+        self.plugin_manager.cancel_restarts();
     }
 }
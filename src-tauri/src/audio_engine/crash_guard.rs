@@ -0,0 +1,237 @@
+//! Crash attribution for the engine process.
+//!
+//! The host restarts a dead engine and wants to know which plugin took it down. The engine
+//! keeps track of what it is doing with plugins (loading, restoring state, processing a slot
+//! on the audio thread, running an editor) and a last-chance handler writes the culprit to
+//! the file named by `AURALYN_CRASH_MARKER` when the process faults or panics. Main-thread
+//! activity is also reported live as `EngineEvent::Activity`, so a hang, which leaves no
+//! marker, can still be attributed.
+//!
+//! The audio thread only ever touches a thread-local slot index; everything else is behind
+//! a mutex the crash handler only `try_lock`s.
+
+use std::cell::Cell;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use crate::ipc::{CrashCulprit, CrashPhase, EngineEvent, OutputMessage};
+use crate::ipc_transport;
+
+pub const CRASH_MARKER_ENV: &str = "AURALYN_CRASH_MARKER";
+
+const NO_SLOT: u8 = u8::MAX;
+
+thread_local! {
+    /// RT slot whose processor is running on this thread, or `NO_SLOT`.
+    static CURRENT_SLOT: Cell<u8> = const { Cell::new(NO_SLOT) };
+}
+
+struct GuardState {
+    /// (path, name) of the plugin in each RT slot.
+    slots: Vec<Option<(String, String)>>,
+    /// Nested main-thread work, innermost last.
+    activity: Vec<CrashCulprit>,
+    /// Open editors by plugin id, most recently opened last.
+    editors: Vec<(String, CrashCulprit)>,
+}
+
+static STATE: Mutex<GuardState> = Mutex::new(GuardState {
+    slots: Vec::new(),
+    activity: Vec::new(),
+    editors: Vec::new(),
+});
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static MARKER_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+fn lock_state() -> std::sync::MutexGuard<'static, GuardState> {
+    match STATE.lock() {
+        Ok(state) => state,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Installs the panic hook and the unhandled exception filter. Call once from the engine's
+/// event loop; without it (offline render, tests) tracking is a no-op.
+pub fn install() {
+    if let Some(path) = std::env::var_os(CRASH_MARKER_ENV) {
+        let path = PathBuf::from(path);
+        // A marker left by a previous process would blame the wrong plugin
+        let _ = std::fs::remove_file(&path);
+        let _ = MARKER_PATH.set(path);
+    }
+    ENABLED.store(true, Ordering::SeqCst);
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        write_marker();
+        previous(info);
+    }));
+
+    #[cfg(windows)]
+    unsafe {
+        use windows::Win32::System::Diagnostics::Debug::SetUnhandledExceptionFilter;
+        SetUnhandledExceptionFilter(Some(on_unhandled_exception));
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn on_unhandled_exception(
+    _info: *const windows::Win32::System::Diagnostics::Debug::EXCEPTION_POINTERS,
+) -> i32 {
+    write_marker();
+    // EXCEPTION_CONTINUE_SEARCH: let the process die as it would have anyway
+    0
+}
+
+/// Best effort: the process is already in a bad state, so any failure is ignored.
+fn write_marker() {
+    let Some(path) = MARKER_PATH.get() else {
+        return;
+    };
+    if let Some(culprit) = culprit_at_crash() {
+        if let Ok(json) = serde_json::to_vec(&culprit) {
+            let _ = std::fs::write(path, json);
+        }
+    }
+}
+
+fn culprit_at_crash() -> Option<CrashCulprit> {
+    let slot = CURRENT_SLOT.with(|s| s.get());
+    // Never block here: the crashing thread may be the one holding the lock.
+    let state = STATE.try_lock().ok()?;
+    if slot != NO_SLOT {
+        let (path, name) = state.slots.get(slot as usize)?.clone()?;
+        return Some(CrashCulprit {
+            phase: CrashPhase::Processing,
+            path,
+            name,
+        });
+    }
+    main_culprit(&state)
+}
+
+fn main_culprit(state: &GuardState) -> Option<CrashCulprit> {
+    state
+        .activity
+        .last()
+        .or_else(|| state.editors.last().map(|(_, culprit)| culprit))
+        .cloned()
+}
+
+fn notify(culprit: Option<CrashCulprit>) {
+    ipc_transport::send(&OutputMessage::Event(EngineEvent::Activity(culprit)));
+}
+
+// --- Audio thread ---
+
+/// Marks `index` as running on this thread. RT-safe (thread-local store only).
+#[inline]
+pub fn enter_slot(index: u8) {
+    CURRENT_SLOT.with(|s| s.set(index));
+}
+
+#[inline]
+pub fn leave_slot() {
+    CURRENT_SLOT.with(|s| s.set(NO_SLOT));
+}
+
+// --- Main thread ---
+
+pub fn register_slot(index: u8, path: &str, name: &str) {
+    let mut state = lock_state();
+    let slot = index as usize;
+    if state.slots.len() <= slot {
+        state.slots.resize(slot + 1, None);
+    }
+    state.slots[slot] = Some((path.to_string(), name.to_string()));
+}
+
+pub fn clear_slot(index: u8) {
+    if let Some(slot) = lock_state().slots.get_mut(index as usize) {
+        *slot = None;
+    }
+}
+
+/// Clears the activity recorded by `track` when dropped.
+pub struct ActivityGuard {
+    active: bool,
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+        let current = {
+            let mut state = lock_state();
+            state.activity.pop();
+            main_culprit(&state)
+        };
+        notify(current);
+    }
+}
+
+/// Records that the main thread is about to call into the plugin at `path` until the
+/// returned guard is dropped. `name` may be empty before the plugin is loaded; the file
+/// name is used then.
+pub fn track(phase: CrashPhase, path: &str, name: &str) -> ActivityGuard {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ActivityGuard { active: false };
+    }
+    let culprit = CrashCulprit {
+        phase,
+        path: path.to_string(),
+        name: if name.is_empty() {
+            display_name(path)
+        } else {
+            name.to_string()
+        },
+    };
+    lock_state().activity.push(culprit.clone());
+    notify(Some(culprit));
+    ActivityGuard { active: true }
+}
+
+pub fn editor_opened(id: &str, path: &str, name: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let current = {
+        let mut state = lock_state();
+        state.editors.retain(|(open, _)| open != id);
+        state.editors.push((
+            id.to_string(),
+            CrashCulprit {
+                phase: CrashPhase::Editor,
+                path: path.to_string(),
+                name: name.to_string(),
+            },
+        ));
+        main_culprit(&state)
+    };
+    notify(current);
+}
+
+pub fn editor_closed(id: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let current = {
+        let mut state = lock_state();
+        let before = state.editors.len();
+        state.editors.retain(|(open, _)| open != id);
+        if state.editors.len() == before {
+            return;
+        }
+        main_culprit(&state)
+    };
+    notify(current);
+}
+
+fn display_name(path: &str) -> String {
+    Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
@@ -0,0 +1,516 @@
+use nnnoiseless::DenoiseState;
+
+use crate::vst_host::c_api::ProcessContext;
+use crate::vst_host::instance::VstProcessor;
+
+use super::crash_guard;
+use super::plugins::{PluginManager, MAX_PLUGINS};
+
+// Smoother Implementation
+pub(crate) struct Smoother {
+    pub(crate) current: f32,
+    pub(crate) target: f32,
+    coeff: f32,
+}
+
+impl Smoother {
+    pub(crate) fn new(initial_value: f32) -> Self {
+        Self {
+            current: initial_value,
+            target: initial_value,
+            coeff: 0.005,
+        }
+    }
+
+    pub(crate) fn new_ramp(start: f32, end: f32) -> Self {
+        Self {
+            current: start,
+            target: end,
+            coeff: 0.005,
+        }
+    }
+
+    pub(crate) fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    pub(crate) fn next(&mut self) -> f32 {
+        if (self.current - self.target).abs() < 0.0001 {
+            self.current = self.target;
+        } else {
+            self.current += (self.target - self.current) * self.coeff;
+        }
+        self.current
+    }
+}
+
+const DENOISE_FRAME_SIZE: usize = DenoiseState::FRAME_SIZE;
+const DENOISE_SCALE: f32 = 32768.0;
+pub(crate) const NOISE_REDUCTION_MODE_LOW: &str = "low";
+pub(crate) const NOISE_REDUCTION_MODE_HIGH: &str = "high";
+
+pub(crate) fn normalize_noise_reduction_mode(mode: Option<&str>) -> &'static str {
+    match mode.map(|m| m.trim().to_ascii_lowercase()) {
+        Some(m) if m == NOISE_REDUCTION_MODE_HIGH => NOISE_REDUCTION_MODE_HIGH,
+        _ => NOISE_REDUCTION_MODE_LOW,
+    }
+}
+
+pub(crate) fn noise_reduction_mix_from_mode(mode: &str) -> f32 {
+    if mode == NOISE_REDUCTION_MODE_HIGH {
+        1.0
+    } else {
+        0.6
+    }
+}
+
+pub(crate) struct RtNoiseReducer {
+    states: [Box<DenoiseState<'static>>; 2],
+    input_frame_size: usize,
+    input_frames: [Vec<f32>; 2],
+    output_frames: [Vec<f32>; 2],
+    denoise_input: [[f32; DENOISE_FRAME_SIZE]; 2],
+    denoise_output: [[f32; DENOISE_FRAME_SIZE]; 2],
+    input_pos: usize,
+    output_pos: usize,
+    output_ready: usize,
+}
+
+impl RtNoiseReducer {
+    pub(crate) fn new(sample_rate_hz: u32) -> Self {
+        let frame_size = Self::frame_size(sample_rate_hz);
+        Self {
+            states: std::array::from_fn(|_| DenoiseState::new()),
+            input_frame_size: frame_size,
+            input_frames: [vec![0.0; frame_size], vec![0.0; frame_size]],
+            output_frames: [vec![0.0; frame_size], vec![0.0; frame_size]],
+            denoise_input: [[0.0; DENOISE_FRAME_SIZE]; 2],
+            denoise_output: [[0.0; DENOISE_FRAME_SIZE]; 2],
+            input_pos: 0,
+            output_pos: 0,
+            output_ready: 0,
+        }
+    }
+
+    /// 10 ms at the host rate, resampled to `DenoiseState::FRAME_SIZE` for each frame.
+    fn frame_size(sample_rate_hz: u32) -> usize {
+        ((sample_rate_hz.max(8_000) + 50) / 100) as usize
+    }
+
+    /// Delay of the wet signal in host samples: a sample comes back once its frame is full
+    /// (frame - 1), and `DenoiseState` returns the frame before the one it was given, since
+    /// it overlap-adds two-frame windows (+ frame).
+    pub(crate) fn latency_samples(sample_rate_hz: u32) -> u32 {
+        (2 * Self::frame_size(sample_rate_hz) - 1) as u32
+    }
+
+    pub(crate) fn reset_state(&mut self) {
+        self.states = std::array::from_fn(|_| DenoiseState::new());
+        self.input_pos = 0;
+        self.output_pos = 0;
+        self.output_ready = 0;
+        for ch in 0..2 {
+            self.input_frames[ch].fill(0.0);
+            self.output_frames[ch].fill(0.0);
+            self.denoise_input[ch].fill(0.0);
+            self.denoise_output[ch].fill(0.0);
+        }
+    }
+
+    fn resample_linear(input: &[f32], output: &mut [f32]) {
+        if input.is_empty() || output.is_empty() {
+            return;
+        }
+        if input.len() == 1 {
+            output.fill(input[0]);
+            return;
+        }
+        if output.len() == 1 {
+            output[0] = input[0];
+            return;
+        }
+
+        let in_last = (input.len() - 1) as f32;
+        let out_last = (output.len() - 1) as f32;
+        for (i, out) in output.iter_mut().enumerate() {
+            let pos = (i as f32) * in_last / out_last;
+            let idx0 = pos.floor() as usize;
+            let idx1 = (idx0 + 1).min(input.len() - 1);
+            let frac = pos - idx0 as f32;
+            *out = input[idx0] * (1.0 - frac) + input[idx1] * frac;
+        }
+    }
+
+    pub(crate) fn process_sample(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.input_frames[0][self.input_pos] = (left * DENOISE_SCALE).clamp(-32768.0, 32767.0);
+        self.input_frames[1][self.input_pos] = (right * DENOISE_SCALE).clamp(-32768.0, 32767.0);
+        self.input_pos += 1;
+
+        if self.input_pos >= self.input_frame_size {
+            for ch in 0..2 {
+                if self.input_frame_size == DENOISE_FRAME_SIZE {
+                    self.denoise_input[ch]
+                        .copy_from_slice(&self.input_frames[ch][..DENOISE_FRAME_SIZE]);
+                } else {
+                    Self::resample_linear(
+                        &self.input_frames[ch][..self.input_frame_size],
+                        &mut self.denoise_input[ch],
+                    );
+                }
+            }
+
+            self.states[0].process_frame(&mut self.denoise_output[0], &self.denoise_input[0]);
+            self.states[1].process_frame(&mut self.denoise_output[1], &self.denoise_input[1]);
+
+            for ch in 0..2 {
+                if self.input_frame_size == DENOISE_FRAME_SIZE {
+                    self.output_frames[ch][..DENOISE_FRAME_SIZE]
+                        .copy_from_slice(&self.denoise_output[ch]);
+                } else {
+                    Self::resample_linear(
+                        &self.denoise_output[ch],
+                        &mut self.output_frames[ch][..self.input_frame_size],
+                    );
+                }
+            }
+
+            self.input_pos = 0;
+            self.output_pos = 0;
+            self.output_ready = self.input_frame_size;
+        }
+
+        if self.output_ready == 0 {
+            return (0.0, 0.0);
+        }
+
+        let l = (self.output_frames[0][self.output_pos] / DENOISE_SCALE).clamp(-1.0, 1.0);
+        let r = (self.output_frames[1][self.output_pos] / DENOISE_SCALE).clamp(-1.0, 1.0);
+        self.output_pos += 1;
+        self.output_ready -= 1;
+        (l, r)
+    }
+
+    /// Applies the dry/wet noise reduction in place on the main (0/1) planar bus.
+    /// A mono bus is processed as dual-mono and only channel 0 is written back.
+    pub(crate) fn process_planar(
+        &mut self,
+        buffers: &mut [Vec<f32>],
+        channels: usize,
+        frames: usize,
+        mix: f32,
+    ) {
+        if mix <= 0.0 {
+            return;
+        }
+        let wet_mix = mix;
+        let dry_mix = 1.0 - wet_mix;
+        if channels >= 2 && buffers.len() >= 2 {
+            for i in 0..frames {
+                let dry_left = buffers[0][i];
+                let dry_right = buffers[1][i];
+                let (wet_left, wet_right) = self.process_sample(dry_left, dry_right);
+                buffers[0][i] = dry_left * dry_mix + wet_left * wet_mix;
+                buffers[1][i] = dry_right * dry_mix + wet_right * wet_mix;
+            }
+        } else if channels == 1 && !buffers.is_empty() {
+            for i in 0..frames {
+                let dry_mono = buffers[0][i];
+                let (wet_mono, _) = self.process_sample(dry_mono, dry_mono);
+                buffers[0][i] = dry_mono * dry_mix + wet_mono * wet_mix;
+            }
+        }
+    }
+}
+
+/// What the RT chain needs from a plugin processor. Implemented by `VstProcessor`;
+/// unit tests plug in simple DSP stand-ins.
+pub trait PlanarProcessor: Send {
+    /// `context` carries the transport state at the start of the block.
+    fn process_planar(
+        &mut self,
+        inputs: &[Vec<f32>],
+        outputs: &mut [Vec<f32>],
+        num_samples: usize,
+        context: &ProcessContext,
+    );
+
+    /// Called instead of `process_planar` for a block the processor sits out (bypass, mute).
+    fn skip_block(&mut self) {}
+}
+
+impl PlanarProcessor for VstProcessor {
+    fn process_planar(
+        &mut self,
+        inputs: &[Vec<f32>],
+        outputs: &mut [Vec<f32>],
+        num_samples: usize,
+        context: &ProcessContext,
+    ) {
+        VstProcessor::process_planar(self, inputs, outputs, num_samples, context)
+    }
+
+    fn skip_block(&mut self) {
+        VstProcessor::skip_block(self)
+    }
+}
+
+/// Fixed-capacity plugin chain used on the audio thread (and by the offline renderer).
+/// Holds the RT copy of processors / order / bypass / mute / gain and the ping-pong buffers,
+/// so no allocation happens while processing.
+pub struct PluginChain<P: PlanarProcessor = VstProcessor> {
+    processors: [Option<P>; MAX_PLUGINS],
+    active_count: usize,
+    order: [u8; MAX_PLUGINS],
+    order_len: usize,
+    bypassed: [bool; MAX_PLUGINS],
+    muted: [bool; MAX_PLUGINS],
+    gains: [Smoother; MAX_PLUGINS],
+    buf_a: Vec<Vec<f32>>,
+    buf_b: Vec<Vec<f32>>,
+}
+
+impl<P: PlanarProcessor> PluginChain<P> {
+    pub fn new(max_channels: usize, max_frames: usize) -> Self {
+        Self {
+            processors: std::array::from_fn(|_| None),
+            active_count: 0,
+            order: [u8::MAX; MAX_PLUGINS],
+            order_len: 0,
+            bypassed: [false; MAX_PLUGINS],
+            muted: [false; MAX_PLUGINS],
+            gains: std::array::from_fn(|_| Smoother::new(1.0)),
+            buf_a: (0..max_channels).map(|_| vec![0.0; max_frames]).collect(),
+            buf_b: (0..max_channels).map(|_| vec![0.0; max_frames]).collect(),
+        }
+    }
+
+    /// Planar input bus (buffer A). Fill this before calling `process`.
+    pub fn input_mut(&mut self) -> &mut [Vec<f32>] {
+        &mut self.buf_a
+    }
+
+    pub fn insert(&mut self, index: u8, processor: P, initial_gain: f32) {
+        let slot = index as usize;
+        if slot < MAX_PLUGINS && self.processors[slot].is_none() {
+            self.processors[slot] = Some(processor);
+            self.active_count += 1;
+            self.gains[slot] = Smoother::new_ramp(0.0, initial_gain);
+        }
+    }
+
+    pub fn take_processor(&mut self, index: u8) -> Option<P> {
+        let slot = index as usize;
+        if slot >= MAX_PLUGINS {
+            return None;
+        }
+        let proc = self.processors[slot].take();
+        if proc.is_some() {
+            self.active_count = self.active_count.saturating_sub(1);
+        }
+        proc
+    }
+
+    /// Resets per-slot state and drops the slot from the processing order.
+    pub fn clear_slot(&mut self, index: u8) {
+        let slot = index as usize;
+        if slot >= MAX_PLUGINS {
+            return;
+        }
+        self.muted[slot] = false;
+        self.bypassed[slot] = false;
+        self.gains[slot] = Smoother::new(1.0);
+
+        let mut write = 0usize;
+        for read in 0..self.order_len {
+            let v = self.order[read];
+            if v != index {
+                self.order[write] = v;
+                write += 1;
+            }
+        }
+        self.order_len = write;
+    }
+
+    pub fn set_order(&mut self, order: [u8; MAX_PLUGINS], len: u8) {
+        self.order = order;
+        self.order_len = (len as usize).min(MAX_PLUGINS);
+    }
+
+    pub fn set_bypass(&mut self, index: u8, active: bool) {
+        if let Some(v) = self.bypassed.get_mut(index as usize) {
+            *v = active;
+        }
+    }
+
+    pub fn set_mute(&mut self, index: u8, active: bool) {
+        if let Some(v) = self.muted.get_mut(index as usize) {
+            *v = active;
+        }
+    }
+
+    pub fn set_gain(&mut self, index: u8, value: f32) {
+        if let Some(g) = self.gains.get_mut(index as usize) {
+            g.set_target(value);
+        }
+    }
+
+    /// Runs the ping-pong chain over `frames` samples of the input bus and returns the
+    /// buffer holding the result. Global bypass leaves the input untouched.
+    pub fn process(
+        &mut self,
+        channels: usize,
+        frames: usize,
+        global_bypass: bool,
+        context: &ProcessContext,
+    ) -> &[Vec<f32>] {
+        // Current Data is always in A (true) or B (false)
+        let mut current_source_is_a = true;
+
+        if global_bypass {
+            for proc in self.processors.iter_mut().flatten() {
+                proc.skip_block();
+            }
+        } else if self.active_count > 0 && self.order_len > 0 {
+            for i_order in 0..self.order_len {
+                let idx = self.order[i_order] as usize;
+                if idx >= MAX_PLUGINS {
+                    continue;
+                }
+
+                // Bypass Check
+                if self.bypassed[idx] {
+                    if let Some(proc) = self.processors[idx].as_mut() {
+                        proc.skip_block();
+                    }
+                    // Soft Bypass: Explicitly copy input buffer to output buffer
+                    // This ensures the processing chain continuity ("Ping-Pong" flow)
+                    // and guarantees valid data in the target buffer, resolving "Silence" issues.
+                    let (in_bufs, out_bufs) = if current_source_is_a {
+                        (&self.buf_a, &mut self.buf_b)
+                    } else {
+                        (&self.buf_b, &mut self.buf_a)
+                    };
+
+                    for ch in 0..channels {
+                        // Safety bounds check
+                        if ch < in_bufs.len() && ch < out_bufs.len() {
+                            if in_bufs[ch].len() >= frames && out_bufs[ch].len() >= frames {
+                                out_bufs[ch][..frames].copy_from_slice(&in_bufs[ch][..frames]);
+                            }
+                        }
+                    }
+
+                    // Toggle source to maintain chain state (A -> B or B -> A)
+                    current_source_is_a = !current_source_is_a;
+                    continue;
+                }
+
+                // Mute Check
+                if self.muted[idx] {
+                    if let Some(proc) = self.processors[idx].as_mut() {
+                        proc.skip_block();
+                    }
+                    // If muted, we need to zero out the current buffer
+                    let bufs = if current_source_is_a {
+                        &mut self.buf_a
+                    } else {
+                        &mut self.buf_b
+                    };
+                    for buf in bufs.iter_mut().take(channels) {
+                        buf[..frames].fill(0.0);
+                    }
+                    continue;
+                }
+
+                // Process
+                if let Some(proc) = self.processors[idx].as_mut() {
+                    let (in_bufs, out_bufs) = if current_source_is_a {
+                        (&self.buf_a, &mut self.buf_b)
+                    } else {
+                        (&self.buf_b, &mut self.buf_a)
+                    };
+
+                    crash_guard::enter_slot(idx as u8);
+                    proc.process_planar(in_bufs, out_bufs, frames, context);
+                    crash_guard::leave_slot();
+
+                    // Toggle
+                    current_source_is_a = !current_source_is_a;
+
+                    // Apply Gain (Smoother)
+                    let smoother = &mut self.gains[idx];
+                    // Optimization: Check if gain is effectively 1.0 (no change needed)
+                    if (smoother.current - 1.0).abs() > 0.0001
+                        || (smoother.target - 1.0).abs() > 0.0001
+                    {
+                        let target_buf = if current_source_is_a {
+                            &mut self.buf_a
+                        } else {
+                            &mut self.buf_b
+                        };
+
+                        for frame_idx in 0..frames {
+                            let gain = smoother.next();
+                            for buf in target_buf.iter_mut().take(channels) {
+                                buf[frame_idx] *= gain;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        if current_source_is_a {
+            &self.buf_a
+        } else {
+            &self.buf_b
+        }
+    }
+}
+
+impl PluginChain<VstProcessor> {
+    /// Pre-computes the RT state from the manager (call on the main thread, never in the callback).
+    pub fn from_manager(
+        manager: &PluginManager,
+        mut processors: Vec<(u8, VstProcessor)>,
+        max_channels: usize,
+        max_frames: usize,
+    ) -> Self {
+        let mut chain = Self::new(max_channels, max_frames);
+
+        for id in &manager.bypassed {
+            if let Some(idx) = manager.rt_index_of(id) {
+                chain.bypassed[idx as usize] = true;
+            }
+        }
+        for id in &manager.muted {
+            if let Some(idx) = manager.rt_index_of(id) {
+                chain.muted[idx as usize] = true;
+            }
+        }
+        for (id, val) in &manager.gains {
+            if let Some(idx) = manager.rt_index_of(id) {
+                chain.gains[idx as usize] = Smoother::new(*val);
+            }
+        }
+        for id in &manager.order {
+            if chain.order_len >= MAX_PLUGINS {
+                break;
+            }
+            if let Some(idx) = manager.rt_index_of(id) {
+                chain.order[chain.order_len] = idx;
+                chain.order_len += 1;
+            }
+        }
+
+        while let Some((idx, proc)) = processors.pop() {
+            let slot = idx as usize;
+            if slot < MAX_PLUGINS && chain.processors[slot].is_none() {
+                chain.processors[slot] = Some(proc);
+                chain.active_count += 1;
+            }
+        }
+
+        chain
+    }
+}
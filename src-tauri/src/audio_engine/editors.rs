@@ -1,3 +1,4 @@
+use crate::ipc::CrashPhase;
 use crate::vst_host::instance::VstInstance;
 use anyhow::{anyhow, Result};
 use log;
@@ -15,6 +16,8 @@ use windows::Win32::UI::WindowsAndMessaging::{
 use winit::event_loop::EventLoopWindowTarget;
 use winit::window::{Window, WindowBuilder, WindowId};
 
+use super::crash_guard;
+
 pub struct EditorManager {
     editor_windows: HashMap<String, Window>,
     editor_children: HashMap<String, HWND>,
@@ -186,6 +189,8 @@ impl EditorManager {
                     self.editor_children.insert(id.clone(), container_hwnd);
 
                     let hwnd_ptr = container_hwnd.0 as *mut std::ffi::c_void;
+                    let _activity =
+                        crash_guard::track(CrashPhase::Editor, &instance.path, &instance.name);
                     instance.open_editor(hwnd_ptr)?
                 }
                 _ => {
@@ -225,6 +230,7 @@ impl EditorManager {
         }
 
         let win_id = win.id();
+        crash_guard::editor_opened(&id, &instance.path, &instance.name);
         self.editor_windows.insert(id.clone(), win);
         self.window_id_to_plugin.insert(win_id, id);
 
@@ -240,6 +246,7 @@ impl EditorManager {
                 }
             }
             self.editor_windows.remove(&plugin_id);
+            crash_guard::editor_closed(&plugin_id);
             Some(plugin_id)
         } else {
             None
@@ -279,6 +286,7 @@ impl EditorManager {
             self.window_id_to_plugin.remove(&win_id);
             // Window dropped here
         }
+        crash_guard::editor_closed(plugin_id);
     }
 
     pub fn get_plugin_id(&self, window_id: WindowId) -> Option<String> {
@@ -0,0 +1,666 @@
+//! Device-independent audio processing graph.
+//!
+//! Owns the RT state (plugin chain, gains, routing, noise reducer, transport) and consumes
+//! `AudioThreadMessage`s. Backends (cpal streams, Null host, offline render) are thin
+//! adapters that feed interleaved input and collect interleaved output.
+
+use ringbuf::traits::{Consumer, Producer, Split};
+use ringbuf::HeapRb;
+
+use crate::ipc::{MeterLevels, TransportSettings};
+use crate::vst_host::instance::VstProcessor;
+
+use super::dsp::{PlanarProcessor, PluginChain, RtNoiseReducer, Smoother};
+use super::plugins::MAX_PLUGINS;
+use super::transport::TransportClock;
+
+pub enum AudioThreadMessage<P = VstProcessor> {
+    AddProcessor {
+        index: u8,
+        processor: P,
+        initial_gain: f32,
+    },
+    RemoveProcessor {
+        index: u8,
+    },
+    ReorderProcessors {
+        order: [u8; MAX_PLUGINS],
+        len: u8,
+    },
+    SetBypass {
+        index: u8,
+        active: bool,
+    },
+    SetMute {
+        index: u8,
+        active: bool,
+    },
+    SetGain {
+        index: u8,
+        value: f32,
+    },
+    SetGlobalMute(bool),
+    SetGlobalBypass(bool),
+    SetInputGain(f32),
+    SetNoiseReduction {
+        active: bool,
+        mix: f32,
+    },
+    SetOutputGain(f32),
+    SetInputChannels(usize, usize), // (Left, Right)
+    SetChannelScan(bool),           // Enable/Disable background scanning
+    SetTransport(TransportSettings),
+    Stop,
+}
+
+pub struct RetiredProcessor<P = VstProcessor> {
+    pub index: u8,
+    pub processor: P,
+}
+
+pub type CmdProducer<P = VstProcessor> = <HeapRb<AudioThreadMessage<P>> as Split>::Prod;
+pub type CmdConsumer<P = VstProcessor> = <HeapRb<AudioThreadMessage<P>> as Split>::Cons;
+pub type RetireProducer<P = VstProcessor> = <HeapRb<RetiredProcessor<P>> as Split>::Prod;
+pub type RetireConsumer<P = VstProcessor> = <HeapRb<RetiredProcessor<P>> as Split>::Cons;
+pub type LevelProducer = <HeapRb<MeterLevels> as Split>::Prod;
+pub type LevelConsumer = <HeapRb<MeterLevels> as Split>::Cons;
+pub type ChannelProducer = <HeapRb<[f32; 32]> as Split>::Prod;
+pub type ChannelConsumer = <HeapRb<[f32; 32]> as Split>::Cons;
+
+/// Frames between two channel-scan snapshots (~10 Hz at 48 kHz).
+const CHANNEL_SCAN_INTERVAL_FRAMES: u64 = 4800;
+
+/// Initial RT state, computed on the main thread before the stream starts.
+pub struct GraphConfig {
+    /// Interleaved channel count of the device buffers.
+    pub channels: usize,
+    /// Upper bound of frames per `process` call (buffers are allocated once).
+    pub max_frames: usize,
+    pub sample_rate_hz: u32,
+    pub input_channels: (usize, usize),
+    pub scan_enabled: bool,
+    pub global_bypass: bool,
+    pub global_mute: bool,
+    pub input_gain: f32,
+    pub output_gain: f32,
+    pub noise_reduction_enabled: bool,
+    pub noise_reduction_mix: f32,
+    pub transport: TransportSettings,
+}
+
+/// Main-thread side of the graph's ring buffers.
+pub struct GraphLinks<P = VstProcessor> {
+    pub commands: CmdProducer<P>,
+    pub retired: RetireConsumer<P>,
+    pub levels: LevelConsumer,
+    pub channel_scan: ChannelConsumer,
+}
+
+pub struct ProcessingGraph<P: PlanarProcessor = VstProcessor> {
+    chain: PluginChain<P>,
+    commands: CmdConsumer<P>,
+    retired: RetireProducer<P>,
+    pending_retire: [Option<RetiredProcessor<P>>; MAX_PLUGINS],
+    levels: LevelProducer,
+    channel_scan: ChannelProducer,
+
+    channels: usize,
+    max_frames: usize,
+    frames_processed: u64,
+
+    global_mute: bool,
+    global_bypass: bool,
+    input_gain: f32,
+    output_gain: Smoother,
+    input_l: usize,
+    input_r: usize,
+    scan_enabled: bool,
+    noise_reduction_enabled: bool,
+    noise_reduction_mix: f32,
+    noise_reducer: RtNoiseReducer,
+    transport: TransportClock,
+}
+
+impl<P: PlanarProcessor> ProcessingGraph<P> {
+    /// Builds the graph and its ring buffers. Call on the main thread (allocates).
+    pub fn new(config: GraphConfig, chain: PluginChain<P>) -> (Self, GraphLinks<P>) {
+        let (cmd_prod, cmd_cons) = HeapRb::<AudioThreadMessage<P>>::new(32).split();
+        let (retire_prod, retire_cons) = HeapRb::<RetiredProcessor<P>>::new(32).split();
+        let (level_prod, level_cons) = HeapRb::<MeterLevels>::new(4096).split();
+        // Small buffer for low-rate scan data
+        let (channel_prod, channel_cons) = HeapRb::<[f32; 32]>::new(16).split();
+
+        let graph = Self {
+            chain,
+            commands: cmd_cons,
+            retired: retire_prod,
+            pending_retire: std::array::from_fn(|_| None),
+            levels: level_prod,
+            channel_scan: channel_prod,
+            channels: config.channels,
+            max_frames: config.max_frames,
+            frames_processed: 0,
+            global_mute: config.global_mute,
+            global_bypass: config.global_bypass,
+            input_gain: config.input_gain,
+            output_gain: Smoother::new(config.output_gain),
+            input_l: config.input_channels.0,
+            input_r: config.input_channels.1,
+            scan_enabled: config.scan_enabled,
+            noise_reduction_enabled: config.noise_reduction_enabled,
+            noise_reduction_mix: config.noise_reduction_mix.clamp(0.0, 1.0),
+            noise_reducer: RtNoiseReducer::new(config.sample_rate_hz),
+            transport: TransportClock::new(config.transport, config.sample_rate_hz as f64),
+        };
+
+        let links = GraphLinks {
+            commands: cmd_prod,
+            retired: retire_cons,
+            levels: level_cons,
+            channel_scan: channel_cons,
+        };
+
+        (graph, links)
+    }
+
+    pub fn max_frames(&self) -> usize {
+        self.max_frames
+    }
+
+    pub fn handle_message(&mut self, msg: AudioThreadMessage<P>) {
+        match msg {
+            AudioThreadMessage::AddProcessor {
+                index,
+                processor,
+                initial_gain,
+            } => {
+                self.chain.insert(index, processor, initial_gain);
+            }
+            AudioThreadMessage::RemoveProcessor { index } => {
+                let slot = index as usize;
+                if slot < MAX_PLUGINS {
+                    if self.pending_retire[slot].is_none() {
+                        if let Some(proc) = self.chain.take_processor(index) {
+                            let retired = RetiredProcessor {
+                                index,
+                                processor: proc,
+                            };
+                            if let Err(retired) = self.retired.try_push(retired) {
+                                self.pending_retire[slot] = Some(retired);
+                            }
+                        }
+                    }
+                    self.chain.clear_slot(index);
+                }
+            }
+            AudioThreadMessage::ReorderProcessors { order, len } => {
+                self.chain.set_order(order, len);
+            }
+            AudioThreadMessage::SetBypass { index, active } => {
+                self.chain.set_bypass(index, active);
+            }
+            AudioThreadMessage::SetMute { index, active } => {
+                self.chain.set_mute(index, active);
+            }
+            AudioThreadMessage::SetGain { index, value } => {
+                self.chain.set_gain(index, value);
+            }
+            AudioThreadMessage::SetGlobalMute(active) => {
+                self.global_mute = active;
+            }
+            AudioThreadMessage::SetGlobalBypass(active) => {
+                self.global_bypass = active;
+            }
+            AudioThreadMessage::SetInputGain(val) => {
+                self.input_gain = val;
+            }
+            AudioThreadMessage::SetNoiseReduction { active, mix } => {
+                self.noise_reduction_enabled = active;
+                self.noise_reduction_mix = mix.clamp(0.0, 1.0);
+                self.noise_reducer.reset_state();
+            }
+            AudioThreadMessage::SetOutputGain(val) => {
+                self.output_gain.set_target(val);
+            }
+            AudioThreadMessage::SetInputChannels(l, r) => {
+                self.input_l = l;
+                self.input_r = r;
+            }
+            AudioThreadMessage::SetChannelScan(enable) => {
+                self.scan_enabled = enable;
+            }
+            AudioThreadMessage::SetTransport(settings) => {
+                self.transport.set_settings(settings);
+            }
+            AudioThreadMessage::Stop => {}
+        }
+    }
+
+    /// Retries retire messages that did not fit last time (never drop VST objects in RT thread).
+    fn flush_pending_retire(&mut self) {
+        for slot in 0..MAX_PLUGINS {
+            if let Some(retired) = self.pending_retire[slot].take() {
+                if let Err(retired) = self.retired.try_push(retired) {
+                    self.pending_retire[slot] = Some(retired);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Processes one device block. `input` and `output` are interleaved with `channels`
+    /// channels; missing input samples are treated as silence. Blocks larger than
+    /// `max_frames` are truncated (the tail of `output` is silenced).
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) {
+        self.flush_pending_retire();
+        while let Some(msg) = self.commands.try_pop() {
+            self.handle_message(msg);
+        }
+
+        let channels = self.channels;
+        if channels == 0 {
+            return;
+        }
+        let requested_frames = output.len() / channels;
+        self.frames_processed += requested_frames as u64;
+        let frames = requested_frames.min(self.max_frames);
+        if frames == 0 {
+            output.fill(0.0);
+            return;
+        }
+
+        // --- 1. De-interleave, Input Routing & Metering ---
+        let mut in_max_l = 0.0f32;
+        let mut in_max_r = 0.0f32;
+
+        // Channel Scanning (For UI Smart Selector)
+        let mut channel_peaks = [0.0f32; 32]; // Max 32 channels scan
+        let scan_limit = channels.min(32);
+
+        let input_l = self.input_l;
+        let input_r = self.input_r;
+        let planar = self.chain.input_mut();
+        for i in 0..frames {
+            for ch in 0..channels {
+                let sample = input.get(i * channels + ch).copied().unwrap_or(0.0) * self.input_gain;
+                if let Some(buf) = planar.get_mut(ch) {
+                    buf[i] = sample;
+                }
+
+                // Scanner Logic (only if enabled)
+                if self.scan_enabled && ch < scan_limit {
+                    let abs = sample.abs();
+                    if abs > channel_peaks[ch] {
+                        channel_peaks[ch] = abs;
+                    }
+                }
+            }
+
+            // The chain always sees the selected input pair on the main bus (0/1).
+            // This destructively overwrites physical Ch 0/1 — we are "routing" inputs.
+            let sample_l = if input_l < channels {
+                planar[input_l][i]
+            } else {
+                0.0
+            };
+            let sample_r = if input_r < channels {
+                planar[input_r][i]
+            } else {
+                0.0
+            };
+
+            if channels >= 2 {
+                planar[0][i] = sample_l;
+                planar[1][i] = sample_r;
+            }
+
+            // Main Metering (Post-Routing)
+            in_max_l = in_max_l.max(sample_l.abs());
+            in_max_r = in_max_r.max(sample_r.abs());
+        }
+
+        if self.noise_reduction_enabled {
+            self.noise_reducer
+                .process_planar(planar, channels, frames, self.noise_reduction_mix);
+        }
+
+        // Send Channel Scan Data (throttled, once per ~4800-frame window)
+        if self.scan_enabled && self.frames_processed % CHANNEL_SCAN_INTERVAL_FRAMES < frames as u64
+        {
+            let mut peaks = [0.0f32; 32];
+            peaks[..scan_limit].copy_from_slice(&channel_peaks[..scan_limit]);
+            let _ = self.channel_scan.try_push(peaks);
+        }
+
+        // --- 2. Ping-Pong Processing Loop ---
+        // Global Bypass: Skip all plugin processing (A/B comparison mode)
+        let context = self.transport.context();
+        self.transport.advance(frames);
+        let final_buf = self
+            .chain
+            .process(channels, frames, self.global_bypass, &context);
+
+        // --- 3. Result Interleaving & Output Metering ---
+        output.fill(0.0);
+
+        if self.global_mute {
+            // Zero metering too implies output is silence
+            let _ = self.levels.try_push(MeterLevels {
+                input: [in_max_l, in_max_r],
+                output: [0.0, 0.0],
+            });
+            return;
+        }
+
+        // Map processed "Main" (0/1) back to the selected physical device channels
+        // (Symmetric Routing / Insert Logic)
+        for i in 0..frames {
+            let gain = self.output_gain.next();
+            let main_l = final_buf
+                .first()
+                .and_then(|buf| buf.get(i))
+                .copied()
+                .unwrap_or(0.0)
+                * gain;
+            let main_r = final_buf
+                .get(1)
+                .and_then(|buf| buf.get(i))
+                .copied()
+                .unwrap_or(main_l)
+                * gain;
+
+            if input_l < channels {
+                if let Some(out) = output.get_mut(i * channels + input_l) {
+                    *out = main_l;
+                }
+            }
+            if input_r < channels {
+                if let Some(out) = output.get_mut(i * channels + input_r) {
+                    *out = main_r;
+                }
+            }
+        }
+
+        // Metering: Reflect actual output level (post-master-gain)
+        let gain_for_meter = self.output_gain.current;
+        let out_max_l = final_buf
+            .first()
+            .map(|buf| buf[..frames].iter().fold(0.0f32, |m, &x| m.max(x.abs())))
+            .unwrap_or(0.0)
+            * gain_for_meter;
+        let out_max_r = final_buf
+            .get(1)
+            .map(|buf| buf[..frames].iter().fold(0.0f32, |m, &x| m.max(x.abs())))
+            .unwrap_or(out_max_l)
+            * gain_for_meter;
+
+        let _ = self.levels.try_push(MeterLevels {
+            input: [in_max_l, in_max_r],
+            output: [out_max_l, out_max_r],
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vst_host::c_api::{ProcessContext, K_PLAYING};
+    use ringbuf::traits::Consumer;
+    use std::sync::{Arc, Mutex};
+
+    /// Minimal stand-in for a VST processor: `out = in * scale + offset`.
+    struct TestProc {
+        scale: f32,
+        offset: f32,
+    }
+
+    impl PlanarProcessor for TestProc {
+        fn process_planar(
+            &mut self,
+            inputs: &[Vec<f32>],
+            outputs: &mut [Vec<f32>],
+            num_samples: usize,
+            _context: &ProcessContext,
+        ) {
+            for (inp, out) in inputs.iter().zip(outputs.iter_mut()) {
+                for i in 0..num_samples {
+                    out[i] = inp[i] * self.scale + self.offset;
+                }
+            }
+        }
+    }
+
+    const FRAMES: usize = 256;
+
+    fn config(channels: usize) -> GraphConfig {
+        GraphConfig {
+            channels,
+            max_frames: FRAMES,
+            sample_rate_hz: 48_000,
+            input_channels: (0, 1),
+            scan_enabled: false,
+            global_bypass: false,
+            global_mute: false,
+            input_gain: 1.0,
+            output_gain: 1.0,
+            noise_reduction_enabled: false,
+            noise_reduction_mix: 0.0,
+            transport: TransportSettings::default(),
+        }
+    }
+
+    fn graph_with(
+        channels: usize,
+        procs: Vec<TestProc>,
+    ) -> (ProcessingGraph<TestProc>, GraphLinks<TestProc>) {
+        let (mut graph, links) =
+            ProcessingGraph::new(config(channels), PluginChain::new(2.max(channels), FRAMES));
+        let len = procs.len();
+        let mut order = [u8::MAX; MAX_PLUGINS];
+        for (i, proc) in procs.into_iter().enumerate() {
+            graph.handle_message(AudioThreadMessage::AddProcessor {
+                index: i as u8,
+                processor: proc,
+                initial_gain: 1.0,
+            });
+            order[i] = i as u8;
+        }
+        graph.handle_message(AudioThreadMessage::ReorderProcessors {
+            order,
+            len: len as u8,
+        });
+        (graph, links)
+    }
+
+    /// Runs enough constant-input blocks for the gain ramps to settle and returns the
+    /// last output frame.
+    fn settle(graph: &mut ProcessingGraph<TestProc>, channels: usize, value: f32) -> Vec<f32> {
+        let input = vec![value; FRAMES * channels];
+        let mut output = vec![0.0; FRAMES * channels];
+        for _ in 0..16 {
+            graph.process(&input, &mut output);
+        }
+        output[(FRAMES - 1) * channels..].to_vec()
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-3,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn empty_chain_passes_input_through() {
+        let (mut graph, _links) = graph_with(2, Vec::new());
+        let out = settle(&mut graph, 2, 0.5);
+        assert_close(out[0], 0.5);
+        assert_close(out[1], 0.5);
+    }
+
+    #[test]
+    fn bypass_skips_processor() {
+        let (mut graph, _links) = graph_with(
+            2,
+            vec![TestProc {
+                scale: 2.0,
+                offset: 0.0,
+            }],
+        );
+        assert_close(settle(&mut graph, 2, 0.25)[0], 0.5);
+
+        graph.handle_message(AudioThreadMessage::SetBypass {
+            index: 0,
+            active: true,
+        });
+        assert_close(settle(&mut graph, 2, 0.25)[0], 0.25);
+    }
+
+    #[test]
+    fn mute_silences_chain() {
+        let (mut graph, mut links) = graph_with(
+            2,
+            vec![TestProc {
+                scale: 1.0,
+                offset: 0.0,
+            }],
+        );
+        let _ = links.commands.try_push(AudioThreadMessage::SetMute {
+            index: 0,
+            active: true,
+        });
+        let out = settle(&mut graph, 2, 0.5);
+        assert_close(out[0], 0.0);
+        assert_close(out[1], 0.0);
+    }
+
+    #[test]
+    fn reorder_changes_processing_order() {
+        let procs = vec![
+            TestProc {
+                scale: 1.0,
+                offset: 0.1,
+            },
+            TestProc {
+                scale: 2.0,
+                offset: 0.0,
+            },
+        ];
+        let (mut graph, mut links) = graph_with(2, procs);
+        // (x + 0.1) * 2
+        assert_close(settle(&mut graph, 2, 0.2)[0], 0.6);
+
+        let mut order = [u8::MAX; MAX_PLUGINS];
+        order[0] = 1;
+        order[1] = 0;
+        let _ = links
+            .commands
+            .try_push(AudioThreadMessage::ReorderProcessors { order, len: 2 });
+        // x * 2 + 0.1
+        assert_close(settle(&mut graph, 2, 0.2)[0], 0.5);
+    }
+
+    #[test]
+    fn input_routing_maps_selected_pair_to_same_outputs() {
+        let (mut graph, _links) = graph_with(4, Vec::new());
+        graph.handle_message(AudioThreadMessage::SetInputChannels(2, 3));
+
+        // Per-channel distinct input: ch n = 0.1 * (n + 1)
+        let mut input = vec![0.0; FRAMES * 4];
+        for frame in input.chunks_mut(4) {
+            for (ch, s) in frame.iter_mut().enumerate() {
+                *s = 0.1 * (ch as f32 + 1.0);
+            }
+        }
+        let mut output = vec![0.0; FRAMES * 4];
+        graph.process(&input, &mut output);
+
+        let last = &output[(FRAMES - 1) * 4..];
+        assert_close(last[0], 0.0);
+        assert_close(last[1], 0.0);
+        assert_close(last[2], 0.3);
+        assert_close(last[3], 0.4);
+    }
+
+    #[test]
+    fn global_mute_and_remove_retire_processor() {
+        let (mut graph, mut links) = graph_with(
+            2,
+            vec![TestProc {
+                scale: 1.0,
+                offset: 0.0,
+            }],
+        );
+        graph.handle_message(AudioThreadMessage::SetGlobalMute(true));
+        assert_close(settle(&mut graph, 2, 0.5)[0], 0.0);
+        graph.handle_message(AudioThreadMessage::SetGlobalMute(false));
+
+        let _ = links
+            .commands
+            .try_push(AudioThreadMessage::RemoveProcessor { index: 0 });
+        assert_close(settle(&mut graph, 2, 0.5)[0], 0.5);
+
+        let retired = links
+            .retired
+            .try_pop()
+            .expect("processor should be retired");
+        assert_eq!(retired.index, 0);
+    }
+
+    /// Records the transport position and playing flag of every block it sees.
+    struct ClockProc(Arc<Mutex<Vec<(i64, bool)>>>);
+
+    impl PlanarProcessor for ClockProc {
+        fn process_planar(
+            &mut self,
+            _inputs: &[Vec<f32>],
+            _outputs: &mut [Vec<f32>],
+            _num_samples: usize,
+            context: &ProcessContext,
+        ) {
+            let playing = context.state & K_PLAYING != 0;
+            self.0
+                .lock()
+                .unwrap()
+                .push((context.project_time_samples, playing));
+        }
+    }
+
+    #[test]
+    fn processors_see_transport_advance() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (mut graph, _links) = ProcessingGraph::new(config(2), PluginChain::new(2, FRAMES));
+        graph.handle_message(AudioThreadMessage::AddProcessor {
+            index: 0,
+            processor: ClockProc(seen.clone()),
+            initial_gain: 1.0,
+        });
+        let mut order = [u8::MAX; MAX_PLUGINS];
+        order[0] = 0;
+        graph.handle_message(AudioThreadMessage::ReorderProcessors { order, len: 1 });
+
+        let input = vec![0.0; FRAMES * 2];
+        let mut output = vec![0.0; FRAMES * 2];
+        graph.process(&input, &mut output);
+        graph.process(&input, &mut output);
+        graph.handle_message(AudioThreadMessage::SetTransport(TransportSettings {
+            playing: false,
+            ..TransportSettings::default()
+        }));
+        graph.process(&input, &mut output);
+        graph.process(&input, &mut output);
+
+        let frames = FRAMES as i64;
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                (0, true),
+                (frames, true),
+                (2 * frames, false),
+                (2 * frames, false)
+            ]
+        );
+    }
+}
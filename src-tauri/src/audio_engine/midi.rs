@@ -0,0 +1,276 @@
+//! MIDI input ports routed to plugins (`Command::SetMidiInput`).
+//!
+//! One backend connection is kept per port in use. Its callback runs on the backend's
+//! thread and hands each message to the `MidiInlet` of every plugin listening on that port
+//! and channel; the processors pick them up at their next block. On Linux and macOS there is
+//! also `VIRTUAL_PORT_NAME`, a port of our own (an ALSA sequencer port on Linux) that other
+//! applications can send to, e.g. `aconnect` or `aplaymidi` for testing.
+//!
+//! Ports used by controller mappings (`crate::midi_map`) are "control ports": their messages
+//! are also forwarded to the engine's main thread, which drains them with `control_messages`.
+
+use anyhow::{anyhow, Result};
+use midir::{Ignore, MidiInput, MidiInputConnection};
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::ipc::MidiAssignment;
+use crate::vst_host::events::{MidiInlet, MidiMessage};
+
+const CLIENT_NAME: &str = "Auralyn";
+pub const VIRTUAL_PORT_NAME: &str = "Auralyn MIDI In";
+
+/// Message from a control port, with the port's name.
+pub type ControlMessage = (String, MidiMessage);
+
+/// Who gets the messages of one port.
+#[derive(Default)]
+struct PortListeners {
+    /// Plugins: channel filter (0-15, `None` = all) and where to deliver.
+    plugins: Vec<(Option<u8>, Arc<MidiInlet>)>,
+    /// Set while the port is a control port.
+    control: Option<Sender<ControlMessage>>,
+}
+
+type Listeners = Arc<Mutex<PortListeners>>;
+
+struct Route {
+    assignment: MidiAssignment,
+    inlet: Arc<MidiInlet>,
+}
+
+struct PortConnection {
+    // Closes the port when dropped
+    _connection: MidiInputConnection<()>,
+    listeners: Listeners,
+}
+
+pub struct MidiManager {
+    // Plugin id -> its assignment
+    routes: HashMap<String, Route>,
+    // Port name -> open connection
+    connections: HashMap<String, PortConnection>,
+    control_ports: HashSet<String>,
+    control_tx: Sender<ControlMessage>,
+    control_rx: Receiver<ControlMessage>,
+}
+
+impl MidiManager {
+    pub fn new() -> Self {
+        let (control_tx, control_rx) = mpsc::channel();
+        Self {
+            routes: HashMap::new(),
+            connections: HashMap::new(),
+            control_ports: HashSet::new(),
+            control_tx,
+            control_rx,
+        }
+    }
+
+    /// Input ports that can be assigned, the virtual port (where supported) first.
+    pub fn list_ports() -> Result<Vec<String>> {
+        let input = MidiInput::new(CLIENT_NAME).map_err(|e| anyhow!("MIDI unavailable: {}", e))?;
+        let mut names = Vec::new();
+        if cfg!(unix) {
+            names.push(VIRTUAL_PORT_NAME.to_string());
+        }
+        for port in input.ports() {
+            match input.port_name(&port) {
+                // Our own virtual port is listed by the backend too once it is open
+                Ok(name) if name.contains(VIRTUAL_PORT_NAME) => {}
+                Ok(name) => names.push(name),
+                Err(e) => log::warn!("MIDI: could not get port name: {}", e),
+            }
+        }
+        Ok(names)
+    }
+
+    pub fn assignment(&self, id: &str) -> Option<&MidiAssignment> {
+        self.routes.get(id).map(|route| &route.assignment)
+    }
+
+    /// Routes MIDI to the plugin `id`, replacing its previous assignment; `None` disconnects
+    /// it. The port is opened on first use and closed when nobody listens on it any more.
+    pub fn assign(
+        &mut self,
+        id: &str,
+        inlet: Arc<MidiInlet>,
+        assignment: Option<MidiAssignment>,
+    ) -> Result<()> {
+        match assignment {
+            Some(assignment) => {
+                if assignment.channel.is_some_and(|c| !(1..=16).contains(&c)) {
+                    return Err(anyhow!("MIDI channel must be between 1 and 16"));
+                }
+                if !self.connections.contains_key(&assignment.port) {
+                    let connection = open_port(&assignment.port)?;
+                    self.connections.insert(assignment.port.clone(), connection);
+                }
+                self.routes
+                    .insert(id.to_string(), Route { assignment, inlet });
+            }
+            None => {
+                self.routes.remove(id);
+            }
+        }
+        self.sync();
+        Ok(())
+    }
+
+    /// Disconnects an unloaded plugin.
+    pub fn unassign(&mut self, id: &str) {
+        if self.routes.remove(id).is_some() {
+            self.sync();
+        }
+    }
+
+    /// Replaces the set of control ports. A port that cannot be opened (e.g. the controller
+    /// is unplugged) is logged and skipped; its mappings stay idle.
+    pub fn set_control_ports(&mut self, ports: HashSet<String>) {
+        for port in &ports {
+            if self.connections.contains_key(port) {
+                continue;
+            }
+            match open_port(port) {
+                Ok(connection) => {
+                    self.connections.insert(port.clone(), connection);
+                }
+                Err(e) => log::warn!("MIDI control port unavailable: {}", e),
+            }
+        }
+        self.control_ports = ports;
+        self.sync();
+    }
+
+    /// Messages that arrived on control ports since the last call.
+    pub fn control_messages(&self) -> impl Iterator<Item = ControlMessage> + '_ {
+        self.control_rx.try_iter()
+    }
+
+    /// Hands each connection its current listeners and closes the unused ones.
+    fn sync(&mut self) {
+        let routes = &self.routes;
+        let control_ports = &self.control_ports;
+        let control_tx = &self.control_tx;
+        self.connections.retain(|port, connection| {
+            let plugins: Vec<_> = routes
+                .values()
+                .filter(|route| &route.assignment.port == port)
+                .map(|route| {
+                    let channel = route.assignment.channel.map(|c| c - 1);
+                    (channel, route.inlet.clone())
+                })
+                .collect();
+            let control = control_ports.contains(port).then(|| control_tx.clone());
+            if plugins.is_empty() && control.is_none() {
+                return false;
+            }
+            *lock(&connection.listeners) = PortListeners { plugins, control };
+            true
+        });
+    }
+}
+
+fn lock(listeners: &Listeners) -> MutexGuard<'_, PortListeners> {
+    match listeners.lock() {
+        Ok(listeners) => listeners,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Runs on the backend's thread for every incoming message.
+fn deliver(listeners: &Listeners, port: &str, bytes: &[u8]) {
+    let Some(msg) = MidiMessage::new(bytes) else {
+        return;
+    };
+    let channel = msg.channel();
+    let listeners = lock(listeners);
+    if let Some(control) = &listeners.control {
+        // Only fails once the engine is shutting down
+        let _ = control.send((port.to_string(), msg));
+    }
+    for (filter, inlet) in listeners.plugins.iter() {
+        // SysEx has no channel and goes to everyone on the port
+        if filter.is_none() || channel.is_none() || *filter == channel {
+            // Dropped if the processor is not draining its queue (audio stopped, bypassed)
+            inlet.push(msg);
+        }
+    }
+}
+
+fn open_port(port_name: &str) -> Result<PortConnection> {
+    let mut input = MidiInput::new(CLIENT_NAME).map_err(|e| anyhow!("MIDI unavailable: {}", e))?;
+    // Keep SysEx; clock and active sensing are of no use to plugins
+    input.ignore(Ignore::TimeAndActiveSense);
+
+    let listeners: Listeners = Arc::new(Mutex::new(PortListeners::default()));
+    let callback = {
+        let listeners = listeners.clone();
+        let port = port_name.to_string();
+        move |_timestamp: u64, bytes: &[u8], _: &mut ()| deliver(&listeners, &port, bytes)
+    };
+
+    #[cfg(unix)]
+    if port_name == VIRTUAL_PORT_NAME {
+        use midir::os::unix::VirtualInput;
+        let connection = input
+            .create_virtual(VIRTUAL_PORT_NAME, callback, ())
+            .map_err(|e| anyhow!("Failed to create MIDI port {}: {}", port_name, e))?;
+        return Ok(PortConnection {
+            _connection: connection,
+            listeners,
+        });
+    }
+
+    let port = input
+        .ports()
+        .into_iter()
+        .find(|port| input.port_name(port).is_ok_and(|name| name == port_name))
+        .ok_or_else(|| anyhow!("MIDI input not found: {}", port_name))?;
+    let connection = input
+        .connect(&port, "Auralyn In", callback, ())
+        .map_err(|e| anyhow!("Failed to open MIDI input {}: {}", port_name, e))?;
+    Ok(PortConnection {
+        _connection: connection,
+        listeners,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vst_host::events::midi_event_queue;
+    use ringbuf::traits::Consumer;
+
+    #[test]
+    fn delivers_by_channel() {
+        let (omni_tx, mut omni_rx) = midi_event_queue();
+        let (ch2_tx, mut ch2_rx) = midi_event_queue();
+        let (control_tx, control_rx) = mpsc::channel();
+        let listeners: Listeners = Arc::new(Mutex::new(PortListeners {
+            plugins: vec![
+                (None, Arc::new(MidiInlet::new(omni_tx))),
+                (Some(1), Arc::new(MidiInlet::new(ch2_tx))),
+            ],
+            control: Some(control_tx),
+        }));
+
+        deliver(&listeners, "Keys", &[0x90, 60, 100]); // Channel 1
+        deliver(&listeners, "Keys", &[0x91, 62, 100]); // Channel 2
+        deliver(&listeners, "Keys", &[0xF0, 0x7D, 0xF7]); // SysEx
+        deliver(&listeners, "Keys", &[0xF8]); // Clock, filtered
+
+        let drain = |rx: &mut crate::vst_host::events::MidiEventConsumer| {
+            std::iter::from_fn(|| rx.try_pop())
+                .map(|msg| msg.bytes()[0])
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(drain(&mut omni_rx), vec![0x90, 0x91, 0xF0]);
+        assert_eq!(drain(&mut ch2_rx), vec![0x91, 0xF0]);
+        // Control ports see everything, regardless of channel
+        let control: Vec<_> = control_rx.try_iter().collect();
+        assert_eq!(control.len(), 3);
+        assert!(control.iter().all(|(port, _)| port == "Keys"));
+    }
+}
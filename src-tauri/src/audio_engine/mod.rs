@@ -1,5 +1,12 @@
 pub mod core;
+pub mod crash_guard;
 pub mod devices;
+pub mod dsp;
 pub mod editors;
+pub mod graph;
+pub mod midi;
+pub mod null_host;
 pub mod plugins;
+pub mod render;
 pub mod resampling;
+pub mod transport;
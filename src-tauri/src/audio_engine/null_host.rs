@@ -0,0 +1,244 @@
+//! Headless "Null" audio host.
+//!
+//! Drives the real output callback from a timer thread at the requested sample rate and
+//! block size, so the full `Command` protocol can run on machines without a sound card
+//! (integration tests, build machines).
+//!
+//! Device names select the signal:
+//! - input: `None` / `"Silence"` -> silence, `"Sine"` / `"Sine:<hz>"` -> -12 dBFS sine,
+//!   `"<path>.wav"` -> the file, looped
+//! - output: `None` / `"Discard"` -> thrown away, `"<path>.wav"` -> kept in memory and
+//!   written to that file when the stream stops
+
+use anyhow::{anyhow, Context, Result};
+use log;
+use std::path::PathBuf;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+pub const NULL_HOST_NAME: &str = "Null";
+pub const NULL_DEFAULT_SAMPLE_RATE: u32 = 48_000;
+pub const NULL_DEFAULT_BLOCK_SIZE: usize = 512;
+pub const NULL_CHANNELS: usize = 2;
+
+/// Upper bound for the in-memory capture (10 minutes @ 48 kHz stereo).
+const MAX_CAPTURE_SAMPLES: usize = 48_000 * 60 * 10 * NULL_CHANNELS;
+const SINE_AMPLITUDE: f32 = 0.25; // -12 dBFS
+
+pub fn is_null_host(name: &str) -> bool {
+    name.eq_ignore_ascii_case(NULL_HOST_NAME)
+}
+
+enum NullInput {
+    Silence,
+    Sine { phase: f32, step: f32 },
+    File { samples: Vec<f32>, pos: usize },
+}
+
+impl NullInput {
+    fn from_name(name: Option<&str>, sample_rate: u32) -> Result<Self> {
+        let Some(name) = name.map(str::trim).filter(|n| !n.is_empty()) else {
+            return Ok(Self::Silence);
+        };
+
+        if name.eq_ignore_ascii_case("silence") {
+            return Ok(Self::Silence);
+        }
+
+        let lower = name.to_ascii_lowercase();
+        if let Some(rest) = lower.strip_prefix("sine") {
+            let freq = rest
+                .trim_start_matches(':')
+                .trim()
+                .trim_end_matches("hz")
+                .trim()
+                .parse::<f32>()
+                .unwrap_or(440.0);
+            return Ok(Self::Sine {
+                phase: 0.0,
+                step: freq / sample_rate as f32,
+            });
+        }
+
+        if lower.ends_with(".wav") {
+            return Ok(Self::File {
+                samples: read_wav_stereo(name)?,
+                pos: 0,
+            });
+        }
+
+        Err(anyhow!("Unknown Null input: {}", name))
+    }
+
+    /// Fills `buf` with interleaved frames of `channels` channels.
+    fn fill(&mut self, buf: &mut [f32], channels: usize) {
+        match self {
+            Self::Silence => buf.fill(0.0),
+            Self::Sine { phase, step } => {
+                for frame in buf.chunks_mut(channels) {
+                    let v = (*phase * std::f32::consts::TAU).sin() * SINE_AMPLITUDE;
+                    frame.fill(v);
+                    *phase = (*phase + *step).fract();
+                }
+            }
+            Self::File { samples, pos } => {
+                if samples.is_empty() {
+                    buf.fill(0.0);
+                    return;
+                }
+                for frame in buf.chunks_mut(channels) {
+                    let left = samples[*pos];
+                    let right = samples[*pos + 1];
+                    for (ch, out) in frame.iter_mut().enumerate() {
+                        *out = if ch % 2 == 0 { left } else { right };
+                    }
+                    *pos = (*pos + NULL_CHANNELS) % samples.len();
+                }
+            }
+        }
+    }
+}
+
+/// Reads a WAV file as interleaved stereo f32 (mono is duplicated, extra channels dropped).
+fn read_wav_stereo(path: &str) -> Result<Vec<f32>> {
+    let mut reader =
+        hound::WavReader::open(path).with_context(|| format!("Failed to open {}", path))?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+    let interleaved: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1i64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|v| v as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let mut stereo = Vec::with_capacity(interleaved.len() / channels * NULL_CHANNELS);
+    for frame in interleaved.chunks_exact(channels) {
+        let left = frame[0];
+        let right = if channels > 1 { frame[1] } else { left };
+        stereo.push(left);
+        stereo.push(right);
+    }
+    Ok(stereo)
+}
+
+pub struct NullStream {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<Vec<f32>>>,
+    capture_path: Option<PathBuf>,
+    sample_rate: u32,
+}
+
+impl NullStream {
+    /// Starts the clock thread. Every period it generates one block of input, hands it to
+    /// `push_input` (interleaved, `NULL_CHANNELS` wide) and then runs `render` on an output
+    /// block of the same size.
+    pub fn start(
+        input_name: Option<&str>,
+        output_name: Option<&str>,
+        sample_rate: u32,
+        block_size: usize,
+        mut push_input: impl FnMut(&[f32]) + Send + 'static,
+        mut render: impl FnMut(&mut [f32]) + Send + 'static,
+    ) -> Result<Self> {
+        let mut input = NullInput::from_name(input_name, sample_rate)?;
+        let capture_path = output_name
+            .map(str::trim)
+            .filter(|n| n.to_ascii_lowercase().ends_with(".wav"))
+            .map(PathBuf::from);
+        let capture = capture_path.is_some();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = stop.clone();
+        let block_size = block_size.max(1);
+        let period = Duration::from_secs_f64(block_size as f64 / sample_rate.max(1) as f64);
+
+        let thread = thread::Builder::new()
+            .name("auralyn-null-audio".to_string())
+            .spawn(move || {
+                let mut in_buf = vec![0.0f32; block_size * NULL_CHANNELS];
+                let mut out_buf = vec![0.0f32; block_size * NULL_CHANNELS];
+                let mut captured = Vec::new();
+                let mut next_tick = Instant::now();
+
+                while !stop_flag.load(Ordering::Relaxed) {
+                    input.fill(&mut in_buf, NULL_CHANNELS);
+                    push_input(&in_buf);
+                    render(&mut out_buf);
+
+                    if capture && captured.len() + out_buf.len() <= MAX_CAPTURE_SAMPLES {
+                        captured.extend_from_slice(&out_buf);
+                    }
+
+                    next_tick += period;
+                    let now = Instant::now();
+                    if next_tick > now {
+                        thread::sleep(next_tick - now);
+                    } else if now - next_tick > period * 8 {
+                        // Fell far behind (debugger, suspended VM): resync instead of bursting.
+                        next_tick = now;
+                    }
+                }
+
+                captured
+            })
+            .context("Failed to spawn Null audio thread")?;
+
+        log::info!(
+            "[NullHost] Started: {} Hz, {} frames, capture={:?}",
+            sample_rate,
+            block_size,
+            capture_path
+        );
+
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+            capture_path,
+            sample_rate,
+        })
+    }
+
+    fn write_capture(&self, samples: &[f32]) -> Result<()> {
+        let Some(path) = &self.capture_path else {
+            return Ok(());
+        };
+        let spec = hound::WavSpec {
+            channels: NULL_CHANNELS as u16,
+            sample_rate: self.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec)?;
+        for &s in samples {
+            writer.write_sample(s)?;
+        }
+        writer.finalize()?;
+        Ok(())
+    }
+}
+
+impl Drop for NullStream {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+        match thread.join() {
+            Ok(captured) => {
+                if let Err(e) = self.write_capture(&captured) {
+                    log::error!("[NullHost] Failed to write capture: {}", e);
+                }
+            }
+            Err(_) => log::error!("[NullHost] Audio thread panicked"),
+        }
+    }
+}
@@ -3,8 +3,11 @@ use std::collections::{HashMap, HashSet};
 use anyhow::{anyhow, Result};
 use log;
 
+use crate::ipc::CrashPhase;
 use crate::vst_host::instance::{VstInstance, VstProcessor};
 
+use super::crash_guard;
+
 pub const MAX_PLUGINS: usize = 32;
 
 fn burned_library_key(path: &str) -> String {
@@ -18,9 +21,32 @@ fn burned_library_key(path: &str) -> String {
     }
 }
 
+/// Pairs each wanted `(path, class_id)` with a loaded `(id, path, class_id)`, keeping chain
+/// order. A loaded plugin is handed out at most once, so a chain that lists the same plugin
+/// twice still gets two instances. A wanted `class_id` of `None` matches any class.
+fn match_loaded_plugins(
+    loaded: &[(&str, &str, &str)],
+    wanted: &[(&str, Option<&str>)],
+) -> Vec<Option<String>> {
+    let mut taken = vec![false; loaded.len()];
+    wanted
+        .iter()
+        .map(|(path, class_id)| {
+            let path_key = burned_library_key(path);
+            let found = loaded.iter().enumerate().position(|(i, (_, p, c))| {
+                !taken[i]
+                    && burned_library_key(p) == path_key
+                    && class_id.is_none_or(|want| want.eq_ignore_ascii_case(c))
+            })?;
+            taken[found] = true;
+            Some(loaded[found].0.to_string())
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::burned_library_key;
+    use super::{burned_library_key, match_loaded_plugins};
 
     #[test]
     fn burned_library_key_is_stable() {
@@ -37,6 +63,39 @@ mod tests {
         let lower = burned_library_key(r"c:\vst3\plugin.vst3");
         assert_eq!(upper, lower);
     }
+
+    #[test]
+    fn match_loaded_plugins_reuses_each_instance_once() {
+        let loaded = [
+            ("comp-1", r"C:\VST3\Comp.vst3", "AA"),
+            ("eq-1", r"C:\VST3\EQ.vst3", "BB"),
+            ("comp-2", r"C:\VST3\Comp.vst3", "AA"),
+        ];
+        let wanted = [
+            (r"C:\VST3\EQ.vst3", None),
+            (r"C:\VST3\Comp.vst3", Some("aa")),
+            (r"C:\VST3\Comp.vst3", None),
+            (r"C:\VST3\Comp.vst3", None),
+            (r"C:\VST3\Gate.vst3", None),
+        ];
+        assert_eq!(
+            match_loaded_plugins(&loaded, &wanted),
+            vec![
+                Some("eq-1".to_string()),
+                Some("comp-1".to_string()),
+                Some("comp-2".to_string()),
+                None,
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn match_loaded_plugins_requires_same_class() {
+        let loaded = [("multi-1", r"C:\VST3\Multi.vst3", "AA")];
+        let wanted = [(r"C:\VST3\Multi.vst3", Some("BB"))];
+        assert_eq!(match_loaded_plugins(&loaded, &wanted), vec![None]);
+    }
 }
 
 pub struct PluginManager {
@@ -50,6 +109,9 @@ pub struct PluginManager {
 
     // Deferred drop (unload) handling: instance stays alive until RT confirms processor retired
     pub pending_drop_by_index: HashMap<u8, VstInstance>,
+    // Restart (restartComponent) handling: processors being retired so the plugin can be
+    // re-prepared and get a fresh one, by RT index
+    restarting: HashMap<u8, String>,
 
     // UI State
     pub muted: HashSet<String>,
@@ -70,6 +132,7 @@ impl PluginManager {
             rt_index_by_id: HashMap::new(),
             id_by_rt_index: vec![None; MAX_PLUGINS],
             pending_drop_by_index: HashMap::new(),
+            restarting: HashMap::new(),
             muted: HashSet::new(),
             bypassed: HashSet::new(),
             gains: HashMap::new(),
@@ -115,20 +178,25 @@ impl PluginManager {
         if idx_usize < self.id_by_rt_index.len() {
             self.id_by_rt_index[idx_usize] = None;
         }
+        crash_guard::clear_slot(idx);
     }
 
     pub fn load_plugin(
         &mut self,
         path: &str,
+        class_id: Option<&str>,
         sample_rate: f64,
         block_size: usize,
         channels: usize,
         engine_running: bool, // If true, we try to prepare processing immediately
     ) -> Result<(String, String, u8, Option<VstProcessor>)> {
-        let mut instance = VstInstance::load(path)?;
+        // Covers module load, instantiation and activation below
+        let _activity = crash_guard::track(CrashPhase::Loading, path, "");
+        let mut instance = VstInstance::load(path, class_id)?;
         let id = instance.id.clone();
         let name = instance.name.clone();
         let rt_index = self.alloc_rt_index(&id)?;
+        crash_guard::register_slot(rt_index, path, &name);
 
         // Deferred Logic
         if instance.needs_deferred_connection() {
@@ -218,6 +286,18 @@ impl PluginManager {
                 self.rt_index_by_id.remove(&id);
             }
         }
+        crash_guard::clear_slot(index);
+    }
+
+    /// For each wanted `(path, class_id)`, the id of a loaded plugin that can be reused as is.
+    pub fn find_reusable(&self, wanted: &[(&str, Option<&str>)]) -> Vec<Option<String>> {
+        let loaded: Vec<(&str, &str, &str)> = self
+            .order
+            .iter()
+            .filter_map(|id| self.plugins.get(id))
+            .map(|p| (p.id.as_str(), p.path.as_str(), p.class_id.as_str()))
+            .collect();
+        match_loaded_plugins(&loaded, wanted)
     }
 
     pub fn get_mut(&mut self, id: &str) -> Option<&mut VstInstance> {
@@ -276,8 +356,32 @@ impl PluginManager {
         processors
     }
 
-    pub fn on_processor_retired(&mut self, index: u8) {
+    /// Marks the processor of `id` as being taken off the audio thread for a restart.
+    /// Returns its RT index, or `None` if it has none or a restart is already under way.
+    pub fn begin_restart(&mut self, id: &str) -> Option<u8> {
+        let index = self.rt_index_of(id)?;
+        if self.restarting.contains_key(&index) {
+            return None;
+        }
+        self.restarting.insert(index, id.to_string());
+        Some(index)
+    }
+
+    /// Forgets restarts in flight; the audio thread that would retire them is gone.
+    pub fn cancel_restarts(&mut self) {
+        self.restarting.clear();
+    }
+
+    /// Returns the id of the plugin if the processor was retired for a restart and the
+    /// plugin still needs a new one. Otherwise the plugin was unloaded and is dropped now.
+    pub fn on_processor_retired(&mut self, index: u8) -> Option<String> {
+        if let Some(id) = self.restarting.remove(&index) {
+            if self.plugins.contains_key(&id) {
+                return Some(id);
+            }
+        }
         self.finalize_unload(index);
+        None
     }
 
     pub fn runtime_stats(&self) -> (u32, u32, u32) {
@@ -0,0 +1,487 @@
+//! Offline render mode (`audio_engine --render in.wav out.wav --preset name`).
+//!
+//! Streams a WAV file through the same chain as the live engine (input gain -> noise reduction
+//! -> plugin chain -> output gain) without opening any cpal device. Used to A/B presets on
+//! recorded takes and to run the DSP in CI.
+
+use anyhow::{anyhow, Context, Result};
+use log;
+use std::path::{Path, PathBuf};
+
+use crate::ipc::TransportSettings;
+use crate::vst_host::presets::{self, Preset};
+use crate::vst_host::VstProcessor;
+
+use super::dsp::{
+    noise_reduction_mix_from_mode, normalize_noise_reduction_mode, PluginChain, RtNoiseReducer,
+};
+use super::graph::{GraphConfig, ProcessingGraph};
+use super::plugins::PluginManager;
+use super::transport;
+
+/// Internal processing bus is stereo, same as the live engine's main (0/1) bus.
+const RENDER_CHANNELS: usize = 2;
+const DEFAULT_RENDER_BLOCK_SIZE: usize = 512;
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// Preset name (looked up in `<config_dir>/presets`) or a path to a preset JSON file.
+    pub preset: Option<String>,
+    pub config_dir: Option<PathBuf>,
+    pub block_size: usize,
+    pub input_gain: f32,
+    pub output_gain: f32,
+    /// `Some("low" | "high")` enables the noise reducer with that mode.
+    pub noise_reduction: Option<String>,
+    /// Transport reported to plugins (tempo-synced effects).
+    pub transport: TransportSettings,
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderSummary {
+    pub frames: u64,
+    pub sample_rate: u32,
+    pub plugin_count: usize,
+    pub latency_samples: u32,
+}
+
+impl RenderOptions {
+    /// Parses the arguments following `--render`:
+    /// `<in.wav> <out.wav> [--preset <name|file.json>] [--config-dir <dir>] [--block-size <n>]
+    ///  [--input-gain <x>] [--output-gain <x>] [--noise-reduction <low|high>] [--tempo <bpm>]`
+    pub fn from_args(args: &[String]) -> Result<Self> {
+        let mut iter = args.iter();
+        let input = iter
+            .next()
+            .ok_or_else(|| anyhow!("--render requires <in.wav> <out.wav>"))?;
+        let output = iter
+            .next()
+            .ok_or_else(|| anyhow!("--render requires <in.wav> <out.wav>"))?;
+
+        let mut opts = Self {
+            input: PathBuf::from(input),
+            output: PathBuf::from(output),
+            preset: None,
+            config_dir: None,
+            block_size: DEFAULT_RENDER_BLOCK_SIZE,
+            input_gain: 1.0,
+            output_gain: 1.0,
+            noise_reduction: None,
+            transport: TransportSettings::default(),
+        };
+
+        while let Some(flag) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Missing value for {}", flag))
+            };
+            match flag.as_str() {
+                "--preset" => opts.preset = Some(value()?),
+                "--config-dir" => opts.config_dir = Some(PathBuf::from(value()?)),
+                "--block-size" => {
+                    opts.block_size = value()?
+                        .parse::<usize>()
+                        .context("Invalid --block-size")?
+                        .clamp(16, 8192)
+                }
+                "--input-gain" => {
+                    opts.input_gain = value()?.parse::<f32>().context("Invalid --input-gain")?
+                }
+                "--output-gain" => {
+                    opts.output_gain = value()?.parse::<f32>().context("Invalid --output-gain")?
+                }
+                "--noise-reduction" => opts.noise_reduction = Some(value()?),
+                "--tempo" => {
+                    opts.transport.tempo = value()?.parse::<f64>().context("Invalid --tempo")?;
+                    transport::validate(&opts.transport)?;
+                }
+                other => return Err(anyhow!("Unknown render option: {}", other)),
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Same directory Tauri resolves as `app_config_dir()` for the host app.
+pub fn default_config_dir() -> Option<PathBuf> {
+    std::env::var_os("APPDATA")
+        .map(|appdata| PathBuf::from(appdata).join("com.kuro7983.auralynhost"))
+}
+
+fn resolve_preset(name_or_path: &str, config_dir: Option<&Path>) -> Result<Preset> {
+    let as_path = Path::new(name_or_path);
+    if as_path.extension().map_or(false, |ext| ext == "json") && as_path.is_file() {
+        let content = std::fs::read_to_string(as_path)
+            .with_context(|| format!("Failed to read preset file {:?}", as_path))?;
+        return serde_json::from_str(&content)
+            .with_context(|| format!("Invalid preset file {:?}", as_path));
+    }
+
+    let config_dir = config_dir
+        .map(Path::to_path_buf)
+        .or_else(default_config_dir)
+        .ok_or_else(|| anyhow!("Config directory not found (use --config-dir)"))?;
+    presets::load_preset(&config_dir, name_or_path).map_err(|e| anyhow!(e))
+}
+
+/// Loads every plugin of the preset into `manager`, prepared for offline processing,
+/// and returns the processors keyed by RT index.
+fn load_preset_chain(
+    manager: &mut PluginManager,
+    preset: &Preset,
+    sample_rate: f64,
+    block_size: usize,
+) -> Result<Vec<(u8, VstProcessor)>> {
+    let mut processors = Vec::new();
+
+    for entry in &preset.plugins {
+        let (id, name, index, processor) = manager
+            .load_plugin(
+                &entry.path,
+                entry.class_id.as_deref(),
+                sample_rate,
+                block_size,
+                RENDER_CHANNELS,
+                true,
+            )
+            .with_context(|| format!("Failed to load plugin {}", entry.path))?;
+
+        let processor = if manager.pending_init.contains(&id) {
+            // Deferred plugins: same order as the live engine (Activate -> Connect)
+            manager.pending_init.retain(|x| x != &id);
+            let instance = manager
+                .get_mut(&id)
+                .ok_or_else(|| anyhow!("Plugin vanished during load: {}", id))?;
+            if let Err(e) =
+                instance.prepare_processing(sample_rate, block_size as i32, RENDER_CHANNELS as i32)
+            {
+                log::warn!("[Render] Deferred activation failed for {}: {}", name, e);
+            }
+            let processor = instance.create_processor();
+            if let Err(e) = instance.finalize_connection() {
+                log::warn!("[Render] Deferred connection failed for {}: {}", name, e);
+            }
+            processor
+        } else {
+            processor
+        };
+
+        if let Some(state) = &entry.state {
+            if let Some(instance) = manager.get(&id) {
+                if let Err(e) = instance.set_state(state, entry.controller_state.as_deref()) {
+                    log::warn!("[Render] Failed to restore state for {}: {}", name, e);
+                }
+            }
+        }
+
+        if !entry.enabled {
+            manager.bypassed.insert(id.clone());
+        }
+        if entry.muted {
+            manager.muted.insert(id.clone());
+        }
+        manager.gains.insert(id.clone(), entry.gain);
+
+        match processor {
+            Some(proc) => processors.push((index, proc)),
+            None => log::warn!("[Render] {} has no processor, it will be skipped", name),
+        }
+        log::info!("[Render] Loaded {} ({})", name, id);
+    }
+
+    Ok(processors)
+}
+
+pub fn render_file(opts: &RenderOptions) -> Result<RenderSummary> {
+    let mut reader = hound::WavReader::open(&opts.input)
+        .with_context(|| format!("Failed to open input WAV {:?}", opts.input))?;
+    if reader.spec().channels == 0 {
+        return Err(anyhow!("Input WAV has no channels"));
+    }
+    let sample_rate = reader.spec().sample_rate;
+    let total_frames = reader.duration() as u64;
+
+    let mut manager = PluginManager::new();
+    let processors = match &opts.preset {
+        Some(preset_name) => {
+            let preset = resolve_preset(preset_name, opts.config_dir.as_deref())?;
+            log::info!(
+                "[Render] Preset '{}' ({} plugins)",
+                preset.name,
+                preset.plugins.len()
+            );
+            load_preset_chain(&mut manager, &preset, sample_rate as f64, opts.block_size)?
+        }
+        None => Vec::new(),
+    };
+
+    run_render(&mut reader, opts, manager, processors, total_frames)
+}
+
+fn run_render<R: std::io::Read>(
+    reader: &mut hound::WavReader<R>,
+    opts: &RenderOptions,
+    manager: PluginManager,
+    processors: Vec<(u8, VstProcessor)>,
+    total_frames: u64,
+) -> Result<RenderSummary> {
+    let spec = reader.spec();
+    let in_channels = spec.channels as usize;
+    let sample_rate = spec.sample_rate;
+    let block_size = opts.block_size.max(1);
+
+    let noise_mix = opts
+        .noise_reduction
+        .as_deref()
+        .map(|mode| noise_reduction_mix_from_mode(normalize_noise_reduction_mode(Some(mode))));
+
+    // Same graph as the live output callback, driven block by block from the file.
+    let graph_rate = sample_rate.clamp(8_000, 192_000);
+    let chain = PluginChain::from_manager(&manager, processors, RENDER_CHANNELS, block_size);
+    let (mut graph, _links) = ProcessingGraph::new(
+        GraphConfig {
+            channels: RENDER_CHANNELS,
+            max_frames: block_size,
+            sample_rate_hz: graph_rate,
+            input_channels: (0, 1),
+            scan_enabled: false,
+            global_bypass: false,
+            global_mute: false,
+            input_gain: opts.input_gain,
+            output_gain: opts.output_gain,
+            noise_reduction_enabled: noise_mix.is_some(),
+            noise_reduction_mix: noise_mix.unwrap_or(0.0),
+            transport: opts.transport,
+        },
+        chain,
+    );
+
+    // Latency compensation: drop the chain's delay from the head and flush it from the tail,
+    // so the rendered file lines up sample-for-sample with the input. The dry share that
+    // `low` noise reduction blends in is not delayed (live neither), so it stays early.
+    let noise_latency = if noise_mix.is_some() {
+        RtNoiseReducer::latency_samples(graph_rate) as u64
+    } else {
+        0
+    };
+    let latency = manager.total_latency_samples(false) as u64 + noise_latency;
+
+    let out_spec = hound::WavSpec {
+        channels: RENDER_CHANNELS as u16,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(&opts.output, out_spec)
+        .with_context(|| format!("Failed to create output WAV {:?}", opts.output))?;
+
+    let int_scale = 1.0 / (1i64 << (spec.bits_per_sample.max(1) - 1)) as f32;
+    let mut samples: Box<dyn Iterator<Item = hound::Result<f32>> + '_> = match spec.sample_format {
+        hound::SampleFormat::Float => Box::new(reader.samples::<f32>()),
+        hound::SampleFormat::Int => Box::new(
+            reader
+                .samples::<i32>()
+                .map(move |s| s.map(|v| v as f32 * int_scale)),
+        ),
+    };
+
+    let mut frame_buf = vec![0.0f32; in_channels];
+    let mut in_block = vec![0.0f32; block_size * RENDER_CHANNELS];
+    let mut out_block = vec![0.0f32; block_size * RENDER_CHANNELS];
+    let mut frames_read: u64 = 0;
+    let mut frames_written: u64 = 0;
+    let mut frames_to_skip = latency;
+    let frames_to_flush = total_frames + latency;
+
+    while frames_read < frames_to_flush {
+        let frames = (frames_to_flush - frames_read).min(block_size as u64) as usize;
+
+        // Interleave to the stereo bus (mono is duplicated, extra channels are ignored)
+        for frame in in_block[..frames * RENDER_CHANNELS].chunks_exact_mut(RENDER_CHANNELS) {
+            if frames_read < total_frames {
+                for sample in frame_buf.iter_mut() {
+                    *sample = samples
+                        .next()
+                        .transpose()
+                        .context("Failed to read input WAV")?
+                        .unwrap_or(0.0);
+                }
+            } else {
+                frame_buf.fill(0.0);
+            }
+            frame[0] = frame_buf[0];
+            frame[1] = if in_channels > 1 {
+                frame_buf[1]
+            } else {
+                frame_buf[0]
+            };
+            frames_read += 1;
+        }
+
+        let out = &mut out_block[..frames * RENDER_CHANNELS];
+        graph.process(&in_block[..frames * RENDER_CHANNELS], out);
+
+        for frame in out.chunks_exact(RENDER_CHANNELS) {
+            if frames_to_skip > 0 {
+                frames_to_skip -= 1;
+                continue;
+            }
+            if frames_written >= total_frames {
+                break;
+            }
+            writer.write_sample(frame[0])?;
+            writer.write_sample(frame[1])?;
+            frames_written += 1;
+        }
+    }
+
+    writer.finalize().context("Failed to finalize output WAV")?;
+
+    let plugin_count = manager.plugins.len();
+    // Processors must be released before their instances (same as the live retire path).
+    drop(graph);
+    drop(manager);
+
+    Ok(RenderSummary {
+        frames: frames_written,
+        sample_rate,
+        plugin_count,
+        latency_samples: latency.min(u32::MAX as u64) as u32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestDir;
+
+    const SAMPLE_RATE: u32 = 48_000;
+
+    fn write_float_wav(path: &Path, channels: u16, samples: &[f32]) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn read_output(path: &Path) -> Vec<f32> {
+        let mut reader = hound::WavReader::open(path).unwrap();
+        assert_eq!(reader.spec().channels, RENDER_CHANNELS as u16);
+        assert_eq!(reader.spec().sample_rate, SAMPLE_RATE);
+        reader.samples::<f32>().map(Result::unwrap).collect()
+    }
+
+    fn options(dir: &TestDir, extra: &[&str]) -> RenderOptions {
+        let mut args = vec![
+            dir.join("in.wav").to_string_lossy().into_owned(),
+            dir.join("out.wav").to_string_lossy().into_owned(),
+        ];
+        args.extend(extra.iter().map(|s| s.to_string()));
+        RenderOptions::from_args(&args).unwrap()
+    }
+
+    /// A stereo ramp with different left and right channels.
+    fn stereo_input(frames: usize) -> Vec<f32> {
+        (0..frames)
+            .flat_map(|i| {
+                let left = (i % 200) as f32 / 200.0 - 0.5;
+                [left, -left * 0.5]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_chain_renders_input_unchanged() {
+        let dir = TestDir::new("render-passthrough");
+        // Not a multiple of the block size, so the last block is partial
+        let input = stereo_input(1000);
+        write_float_wav(&dir.join("in.wav"), 2, &input);
+
+        let summary = render_file(&options(&dir, &["--block-size", "256"])).unwrap();
+        assert_eq!(summary.frames, 1000);
+        assert_eq!(summary.sample_rate, SAMPLE_RATE);
+        assert_eq!(summary.plugin_count, 0);
+        assert_eq!(summary.latency_samples, 0);
+        assert_eq!(read_output(&dir.join("out.wav")), input);
+    }
+
+    #[test]
+    fn gains_apply_and_mono_input_is_duplicated() {
+        let dir = TestDir::new("render-gain");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(dir.join("in.wav"), spec).unwrap();
+        let input: Vec<i16> = (0..600).map(|i| (i * 50 - 15_000) as i16).collect();
+        for &sample in &input {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let opts = options(&dir, &["--input-gain", "2", "--output-gain", "0.25"]);
+        assert_eq!(render_file(&opts).unwrap().frames, 600);
+
+        let output = read_output(&dir.join("out.wav"));
+        assert_eq!(output.len(), 600 * RENDER_CHANNELS);
+        for (frame, &sample) in output.chunks_exact(RENDER_CHANNELS).zip(&input) {
+            let expected = sample as f32 / 32768.0 * 0.5;
+            assert!((frame[0] - expected).abs() < 1e-6);
+            assert_eq!(frame[0], frame[1]);
+        }
+    }
+
+    #[test]
+    fn chain_latency_is_trimmed_and_the_tail_flushed() {
+        let dir = TestDir::new("render-latency");
+        let frames = 8000;
+        let click = 3000;
+        let mut input = vec![0.0f32; frames * 2];
+        input[click * 2] = 0.5;
+        input[click * 2 + 1] = 0.5;
+        write_float_wav(&dir.join("in.wav"), 2, &input);
+
+        let opts = options(&dir, &["--block-size", "128", "--noise-reduction", "high"]);
+        let summary = render_file(&opts).unwrap();
+        assert_eq!(
+            summary.latency_samples,
+            RtNoiseReducer::latency_samples(SAMPLE_RATE)
+        );
+        // Same length as the input: the delayed head is dropped and the tail rendered
+        assert_eq!(summary.frames, frames as u64);
+
+        let output = read_output(&dir.join("out.wav"));
+        assert_eq!(output.len(), input.len());
+        assert!(output.iter().all(|s| s.is_finite() && s.abs() <= 1.0));
+        // The denoised click comes out where it went in
+        let peak = output
+            .chunks_exact(RENDER_CHANNELS)
+            .map(|frame| frame[0].abs())
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        assert_eq!(peak.0, click);
+        assert!(peak.1 > 0.0);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(RenderOptions::from_args(&args(&["in.wav"])).is_err());
+        assert!(RenderOptions::from_args(&args(&["in.wav", "out.wav", "--gain", "2"])).is_err());
+        assert!(RenderOptions::from_args(&args(&["in.wav", "out.wav", "--tempo"])).is_err());
+    }
+}
@@ -0,0 +1,172 @@
+//! Transport clock behind the `ProcessContext` handed to plugins.
+//!
+//! There is no timeline to play back: while playing, the project position just keeps
+//! advancing from where the stream started, which is all tempo-synced effects and LFOs
+//! need. Tempo, time signature and the playing state come from the host
+//! (`Command::SetTransport`).
+
+use std::sync::OnceLock;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+
+use crate::ipc::TransportSettings;
+use crate::vst_host::c_api::{
+    ProcessContext, K_BAR_POSITION_VALID, K_CONT_TIME_VALID, K_PLAYING, K_PROJECT_TIME_MUSIC_VALID,
+    K_SYSTEM_TIME_VALID, K_TEMPO_VALID, K_TIME_SIG_VALID,
+};
+
+pub const MIN_TEMPO: f64 = 20.0;
+pub const MAX_TEMPO: f64 = 999.0;
+
+/// Rejects settings plugins could not make sense of.
+pub fn validate(settings: &TransportSettings) -> Result<()> {
+    if !(MIN_TEMPO..=MAX_TEMPO).contains(&settings.tempo) {
+        return Err(anyhow!(
+            "Tempo must be between {} and {} BPM",
+            MIN_TEMPO,
+            MAX_TEMPO
+        ));
+    }
+    if !(1..=32).contains(&settings.time_sig_numerator) {
+        return Err(anyhow!("Time signature numerator must be between 1 and 32"));
+    }
+    let denominator = settings.time_sig_denominator;
+    if !denominator.is_power_of_two() || denominator > 32 {
+        return Err(anyhow!(
+            "Time signature denominator must be 1, 2, 4, 8, 16 or 32"
+        ));
+    }
+    Ok(())
+}
+
+/// Reference point of `ProcessContext::system_time`, shared by every stream of the process.
+fn system_time_ns() -> i64 {
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as i64
+}
+
+/// RT side: owned by the processing graph and advanced once per block.
+pub struct TransportClock {
+    settings: TransportSettings,
+    sample_rate: f64,
+    project_samples: i64,
+    continuous_samples: i64,
+    // Advanced per block at the tempo of that block, so tempo changes do not jump
+    project_quarters: f64,
+}
+
+impl TransportClock {
+    pub fn new(settings: TransportSettings, sample_rate: f64) -> Self {
+        // Initialise the epoch off the audio thread
+        let _ = system_time_ns();
+        Self {
+            settings,
+            sample_rate,
+            project_samples: 0,
+            continuous_samples: 0,
+            project_quarters: 0.0,
+        }
+    }
+
+    pub fn set_settings(&mut self, settings: TransportSettings) {
+        self.settings = settings;
+    }
+
+    /// Context for the block starting at the current position. RT-safe.
+    pub fn context(&self) -> ProcessContext {
+        let s = &self.settings;
+        let bar_length = s.time_sig_numerator as f64 * 4.0 / s.time_sig_denominator as f64;
+        let bar_position = (self.project_quarters / bar_length).floor() * bar_length;
+
+        let mut state = K_SYSTEM_TIME_VALID
+            | K_CONT_TIME_VALID
+            | K_PROJECT_TIME_MUSIC_VALID
+            | K_BAR_POSITION_VALID
+            | K_TEMPO_VALID
+            | K_TIME_SIG_VALID;
+        if s.playing {
+            state |= K_PLAYING;
+        }
+
+        ProcessContext {
+            state,
+            sample_rate: self.sample_rate,
+            project_time_samples: self.project_samples,
+            system_time: system_time_ns(),
+            continous_time_samples: self.continuous_samples,
+            project_time_music: self.project_quarters,
+            bar_position_music: bar_position,
+            tempo: s.tempo,
+            time_sig_numerator: s.time_sig_numerator as i32,
+            time_sig_denominator: s.time_sig_denominator as i32,
+            ..Default::default()
+        }
+    }
+
+    /// Moves past a processed block. The project position only moves while playing.
+    pub fn advance(&mut self, frames: usize) {
+        self.continuous_samples += frames as i64;
+        if !self.settings.playing || self.sample_rate <= 0.0 {
+            return;
+        }
+        self.project_samples += frames as i64;
+        self.project_quarters += frames as f64 / self.sample_rate * self.settings.tempo / 60.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(tempo: f64, numerator: u32, denominator: u32) -> TransportSettings {
+        TransportSettings {
+            tempo,
+            time_sig_numerator: numerator,
+            time_sig_denominator: denominator,
+            playing: true,
+        }
+    }
+
+    #[test]
+    fn advances_musical_position_with_tempo() {
+        let mut clock = TransportClock::new(settings(120.0, 3, 4), 48_000.0);
+        clock.advance(48_000); // 1 s at 120 BPM = 2 quarters
+        let ctx = clock.context();
+        assert_eq!(ctx.project_time_samples, 48_000);
+        assert!((ctx.project_time_music - 2.0).abs() < 1e-9);
+        assert_eq!(ctx.bar_position_music, 0.0);
+        assert_ne!(ctx.state & K_PLAYING, 0);
+
+        // Tempo changes apply from the next block on, without a jump
+        clock.set_settings(settings(60.0, 3, 4));
+        clock.advance(96_000); // 2 s at 60 BPM = 2 quarters
+        let ctx = clock.context();
+        assert!((ctx.project_time_music - 4.0).abs() < 1e-9);
+        assert_eq!(ctx.bar_position_music, 3.0);
+        assert_eq!(ctx.tempo, 60.0);
+        assert_eq!((ctx.time_sig_numerator, ctx.time_sig_denominator), (3, 4));
+    }
+
+    #[test]
+    fn stopped_transport_holds_project_time() {
+        let mut stopped = settings(120.0, 4, 4);
+        stopped.playing = false;
+        let mut clock = TransportClock::new(stopped, 48_000.0);
+        clock.advance(512);
+        let ctx = clock.context();
+        assert_eq!(ctx.project_time_samples, 0);
+        assert_eq!(ctx.continous_time_samples, 512);
+        assert_eq!(ctx.state & K_PLAYING, 0);
+    }
+
+    #[test]
+    fn rejects_unusable_settings() {
+        assert!(validate(&TransportSettings::default()).is_ok());
+        assert!(validate(&settings(0.0, 4, 4)).is_err());
+        assert!(validate(&settings(f64::NAN, 4, 4)).is_err());
+        assert!(validate(&settings(120.0, 0, 4)).is_err());
+        assert!(validate(&settings(120.0, 7, 6)).is_err());
+        assert!(validate(&settings(120.0, 7, 8)).is_ok());
+    }
+}
@@ -251,11 +251,51 @@ fn main() {
         return;
     }
 
+    // Offline render: --render in.wav out.wav [--preset name] (no audio device involved)
+    if let Some(pos) = args.iter().position(|a| a == "--render") {
+        std::process::exit(run_render(&args[pos + 1..]));
+    }
+
     // Normal startup
     let engine = Engine::new();
     engine.run_loop();
 }
 
+fn run_render(args: &[String]) -> i32 {
+    use vst_host_lib::audio_engine::render::{render_file, RenderOptions};
+
+    let opts = match RenderOptions::from_args(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!(
+                "Usage: audio_engine --render <in.wav> <out.wav> [--preset <name|file.json>] \
+                 [--config-dir <dir>] [--block-size <n>] [--input-gain <x>] [--output-gain <x>] \
+                 [--noise-reduction <low|high>]"
+            );
+            return 2;
+        }
+    };
+
+    match render_file(&opts) {
+        Ok(summary) => {
+            log::info!(
+                "[Render] Done: {} frames @ {} Hz, {} plugins, latency compensated {} samples -> {:?}",
+                summary.frames,
+                summary.sample_rate,
+                summary.plugin_count,
+                summary.latency_samples,
+                opts.output
+            );
+            0
+        }
+        Err(e) => {
+            log::error!("[Render] Failed: {:#}", e);
+            1
+        }
+    }
+}
+
 // Function effectively similar to asio_diag but integrated
 fn scan_devices() {
     use cpal::traits::{DeviceTrait, HostTrait};
@@ -3,16 +3,25 @@ use serde::Serialize;
 use std::env;
 use std::ffi::{c_void, CStr};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use vst_host_lib::vst_host::c_api::{IPluginFactoryVtbl, PFactoryInfo};
+use vst_host_lib::vst_host::capabilities::{self, PROBE_MARKER};
+use vst_host_lib::vst_host::classes::{factory_classes, parse_class_id, PluginClass};
+use vst_host_lib::vst_host::instance::HostContext;
 
 // We define the function pointer type locally since it's not in c_api.rs
 type GetPluginFactory = unsafe extern "C" fn() -> *mut c_void;
 
+/// Longest one class may take to probe. If it takes longer, the module is reported as it
+/// stands, with that class and the ones after it unprobed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
 unsafe fn get_vtbl<T>(ptr: *mut c_void) -> &'static T {
     &**(ptr as *mut *mut T)
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 struct ScanResult {
     path: String,
     name: String,
@@ -20,10 +29,13 @@ struct ScanResult {
     version: String,
     success: bool,
     error: Option<String>,
+    /// Every class of the module; the host picks the audio effects.
+    classes: Vec<PluginClass>,
 }
 
 fn main() {
     // Prevent OS-level crash/error dialogs that can freeze scanning indefinitely.
+    #[cfg(windows)]
     unsafe {
         use windows::Win32::System::Diagnostics::Debug::{
             SetErrorMode, SEM_FAILCRITICALERRORS, SEM_NOOPENFILEERRORBOX, SEM_NOGPFAULTERRORBOX,
@@ -41,24 +53,25 @@ fn main() {
     let path = PathBuf::from(path_str);
 
     if !path.exists() {
-        print_json_and_exit(ScanResult {
+        print_json_and_exit(&ScanResult {
             path: path_str.clone(),
             name: "".to_string(),
             vendor: "".to_string(),
             version: "".to_string(),
             success: false,
             error: Some("File not found".to_string()),
+            classes: Vec::new(),
         });
     }
 
     // Attempt to load
     let result = unsafe { load_plugin_info(&path) };
     
-    print_json_and_exit(result);
+    print_json_and_exit(&result);
 }
 
-fn print_json_and_exit(result: ScanResult) -> ! {
-    let json = serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string());
+fn print_json_and_exit(result: &ScanResult) -> ! {
+    let json = serde_json::to_string(result).unwrap_or_else(|_| "{}".to_string());
     println!("{}", json);
     if result.success {
         std::process::exit(0);
@@ -80,6 +93,7 @@ unsafe fn load_plugin_info(path: &PathBuf) -> ScanResult {
             version: "".to_string(),
             success: false,
             error: Some(format!("Failed to load library: {}", e)),
+            classes: Vec::new(),
         },
     };
 
@@ -93,6 +107,7 @@ unsafe fn load_plugin_info(path: &PathBuf) -> ScanResult {
             version: "".to_string(),
             success: false,
             error: Some(format!("GetPluginFactory not found: {}", e)),
+            classes: Vec::new(),
         },
     };
 
@@ -105,6 +120,7 @@ unsafe fn load_plugin_info(path: &PathBuf) -> ScanResult {
             version: "".to_string(),
             success: false,
             error: Some("GetPluginFactory returned null".to_string()),
+            classes: Vec::new(),
         };
     }
 
@@ -120,22 +136,92 @@ unsafe fn load_plugin_info(path: &PathBuf) -> ScanResult {
         }
     }
 
-    // Release factory
-    (factory_vtbl.base.release)(factory_ptr);
+    let classes = factory_classes(factory_ptr);
 
-    // Name is usually derived from filename for VST3 (factory classes have names but often multiple)
-    // For scanner MVP, we use filename as name, similar to existing implementation
+    // Module-level name; each class carries its own
     let name = path
         .file_stem()
         .map(|s| s.to_string_lossy().to_string())
         .unwrap_or_else(|| "Unknown".to_string());
+    let version = classes
+        .iter()
+        .map(|c| c.version.as_str())
+        .find(|v| !v.is_empty())
+        .unwrap_or("0.0.0")
+        .to_string();
+
+    let result = probe_effects(
+        factory_ptr,
+        ScanResult {
+            path: path_str,
+            name,
+            vendor,
+            version,
+            success: true,
+            error: None,
+            classes,
+        },
+    );
 
-    ScanResult {
-        path: path_str,
-        name,
-        vendor,
-        version: "0.0.0".to_string(), // TODO: Extract version if possible
-        success: true,
-        error: None,
+    // Release factory
+    (factory_vtbl.base.release)(factory_ptr);
+    result
+}
+
+/// The result as probed so far, and when probing the current class started.
+struct Probing {
+    result: ScanResult,
+    class_started: Option<Instant>,
+}
+
+/// Instantiates each effect once to see what it supports. A plugin that crashes here would
+/// crash the engine too, so the host blacklists it like any other scanner crash. One that
+/// takes too long is left unprobed: a watchdog prints the result as it stands and exits.
+unsafe fn probe_effects(factory: *mut c_void, result: ScanResult) -> ScanResult {
+    let probing = Arc::new(Mutex::new(Probing {
+        result,
+        class_started: None,
+    }));
+    let watchdog = probing.clone();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(Duration::from_millis(100));
+        let probing = watchdog.lock().unwrap();
+        if probing
+            .class_started
+            .is_some_and(|started| started.elapsed() >= PROBE_TIMEOUT)
+        {
+            eprintln!("Probing timed out after {:?}", PROBE_TIMEOUT);
+            print_json_and_exit(&probing.result);
+        }
+    });
+
+    let host = HostContext::new("Auralyn");
+    let count = probing.lock().unwrap().result.classes.len();
+    for index in 0..count {
+        let cid = {
+            let mut probing = probing.lock().unwrap();
+            let class = &probing.result.classes[index];
+            if !class.is_audio_effect() {
+                continue;
+            }
+            let Some(cid) = parse_class_id(&class.class_id) else {
+                continue;
+            };
+            // Tells the host the scanner is still making progress
+            eprintln!("{} {}", PROBE_MARKER, class.name);
+            probing.class_started = Some(Instant::now());
+            cid
+        };
+        let caps = capabilities::probe(factory, &cid, host.as_ptr());
+
+        let mut probing = probing.lock().unwrap();
+        probing.class_started = None;
+        let class = &mut probing.result.classes[index];
+        match caps {
+            Ok(caps) => class.capabilities = Some(caps),
+            Err(e) => eprintln!("Could not probe {}: {}", class.name, e),
+        }
     }
+    let probing = probing.lock().unwrap();
+    probing.result.clone()
 }
@@ -0,0 +1,43 @@
+//! File helpers shared by the config and state files the host keeps on disk.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Writes `contents` to `path` atomically: a crash mid-write leaves the previous file intact.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = temp_path(path);
+    {
+        let mut file =
+            fs::File::create(&tmp).with_context(|| format!("Failed to create {:?}", tmp))?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    // Replaces the old file in one step (MoveFileEx with REPLACE_EXISTING on Windows)
+    fs::rename(&tmp, path).with_context(|| format!("Failed to replace {:?}", path))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestDir;
+
+    #[test]
+    fn replaces_the_file_without_leaving_the_temp_behind() {
+        let dir = TestDir::new("fs-util-atomic");
+        let path = dir.join("config.json");
+
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert!(!temp_path(&path).exists());
+    }
+}
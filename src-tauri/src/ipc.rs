@@ -1,8 +1,40 @@
 use serde::{Deserialize, Serialize};
 
+use crate::midi_map::{MidiMapping, MidiTarget};
+use crate::session::SessionState;
+use crate::vst_host::params::PluginParameter;
+use crate::vst_host::presets::PresetPlugin;
+use crate::vst_host::programs::ProgramList;
+
+/// Wire protocol revision. Bump whenever `Command`, `Response`, `EngineEvent` or the
+/// framing change in a way an older peer cannot parse.
+pub const PROTOCOL_VERSION: u32 = 18;
+
+/// Correlation id chosen by the host for each request and echoed back in the reply.
+pub type RequestId = u64;
+
+/// A command as written to the engine's stdin. The engine also accepts a bare `Command`
+/// (no envelope) for manual testing; its reply then carries `id: null`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Request {
+    pub id: RequestId,
+    pub command: Command,
+}
+
+/// A response tagged with the id of the request it answers.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Reply {
+    pub id: Option<RequestId>,
+    pub response: Response,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", content = "payload")]
 pub enum Command {
+    /// First request after spawning the engine; answered with `Response::Version`.
+    Hello {
+        protocol_version: u32,
+    },
     GetDevices,
     Start {
         host: String,
@@ -14,6 +46,9 @@ pub enum Command {
     Stop,
     LoadPlugin {
         path: String,
+        /// Audio module class to instantiate (`VstPlugin::class_id`); `None` takes the first.
+        #[serde(default)]
+        class_id: Option<String>,
     },
     UnloadPlugin {
         id: String,
@@ -21,6 +56,12 @@ pub enum Command {
     ReorderPlugins {
         order: Vec<String>,
     },
+    /// Replaces the whole rack with `plugins` (in order). Instances already loaded from the
+    /// same path/class are reused; on any failure the previous chain is left untouched.
+    /// Answered with `Response::ChainApplied`.
+    ApplyChain {
+        plugins: Vec<PresetPlugin>,
+    },
     OpenEditor {
         id: String,
     },
@@ -59,7 +100,18 @@ pub enum Command {
     SetChannelScan {
         active: bool,
     },
+    /// Tempo, time signature and playing state reported to plugins.
+    SetTransport(TransportSettings),
     GetRuntimeStats,
+    /// Everything the engine is currently running, for UI resync after a reload/restart.
+    GetSnapshot,
+    /// Chain (with plugin state chunks) and mixer settings, for the host's session autosave.
+    GetSession,
+    /// The host wrote the session from `Response::Session` to disk; clears the dirty flag of
+    /// these plugins.
+    SessionSaved {
+        ids: Vec<String>,
+    },
     // Parameter Automation
     GetPluginState {
         id: String,
@@ -67,12 +119,62 @@ pub enum Command {
     SetPluginState {
         id: String,
         state: String, // Base64 chunk
+        #[serde(default)]
+        controller_state: Option<String>,
+    },
+    /// Writes the plugin's state to a Steinberg `.vstpreset` file.
+    ExportVstPreset {
+        id: String,
+        path: String,
+    },
+    /// Loads a `.vstpreset` file; fails if it was made for another plugin class.
+    ImportVstPreset {
+        id: String,
+        path: String,
+    },
+    /// Answered with `Response::Parameters`.
+    GetParameters {
+        id: String,
+    },
+    /// Sets the edit controller and the processor (from the next audio block). Answered
+    /// with `Response::ParameterValue`.
+    SetParameter {
+        id: String,
+        param_id: u32,
+        value: f64, // Normalized (0.0 to 1.0)
+    },
+    /// Factory program lists (`IUnitInfo`). Answered with `Response::Programs`.
+    GetPrograms {
+        id: String,
+    },
+    /// Selects a program through its list's program-change parameter. Answered with
+    /// `Response::ParameterValue` for that parameter.
+    SelectProgram {
+        id: String,
+        list_id: i32,
+        program_index: usize,
+    },
+    /// Answered with `Response::MidiPorts`.
+    GetMidiPorts,
+    /// Routes a MIDI input port to a plugin, replacing its previous assignment; `None`
+    /// disconnects it.
+    SetMidiInput {
+        id: String,
+        input: Option<MidiAssignment>,
+    },
+    /// Replaces the MIDI controller mappings (see `crate::midi_map`).
+    SetMidiMappings(Vec<MidiMapping>),
+    /// Binds the next CC or note on any MIDI port to `target`; `None` cancels. The new
+    /// mapping is reported with `EngineEvent::MidiLearned`.
+    MidiLearn {
+        target: Option<MidiTarget>,
     },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", content = "payload")]
 pub enum Response {
+    Version(EngineVersion),
     Devices(Vec<DeviceInfo>),
     Success,
     Started {
@@ -103,11 +205,142 @@ pub enum Response {
         noise_reduction_active: bool,
         noise_reduction_mode: String,
     },
+    Snapshot(EngineSnapshot),
+    /// `ids` are the plugins whose state chunk is in `session`, for `Command::SessionSaved`.
+    Session {
+        session: SessionState,
+        ids: Vec<String>,
+    },
+    /// One entry per requested plugin, in chain order.
+    ChainApplied(Vec<AppliedPlugin>),
     // ... existing code ...
     PluginState {
         id: String,
         state: String,
+        /// Edit controller state, if the plugin keeps one apart from `state`.
+        #[serde(default)]
+        controller_state: Option<String>,
+    },
+    Parameters {
+        id: String,
+        parameters: Vec<PluginParameter>,
+    },
+    /// Value the controller reports after `SetParameter`, with the plugin's text for it.
+    ParameterValue {
+        id: String,
+        param_id: u32,
+        value: f64,
+        display: String,
     },
+    Programs {
+        id: String,
+        lists: Vec<ProgramList>,
+    },
+    /// Names accepted by `MidiAssignment::port`.
+    MidiPorts(Vec<String>),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EngineVersion {
+    pub protocol_version: u32,
+    /// Crate version the engine binary was built from.
+    pub engine_version: String,
+    pub capabilities: EngineCapabilities,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EngineCapabilities {
+    /// Audio hosts accepted by `Command::Start`.
+    pub hosts: Vec<String>,
+    pub noise_reduction_modes: Vec<String>,
+    pub max_plugins: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PluginSnapshot {
+    pub id: String,
+    pub path: String,
+    /// Audio module class the instance was created from.
+    pub class_id: String,
+    pub name: String,
+    /// Slot in the audio thread's processor table.
+    pub rt_index: Option<u8>,
+    pub bypassed: bool,
+    pub muted: bool,
+    pub gain: f32,
+    pub latency_samples: u32,
+    /// Loaded but waiting for deferred activation (not processing audio yet).
+    pub pending_init: bool,
+    /// The plugin reported unsaved changes since its state was last saved (`SessionSaved`).
+    pub dirty: bool,
+    /// The plugin has an event input, so `SetMidiInput` can route MIDI to it.
+    pub accepts_midi: bool,
+    pub midi_input: Option<MidiAssignment>,
+}
+
+/// MIDI input port (as listed by `GetMidiPorts`) feeding a plugin.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MidiAssignment {
+    pub port: String,
+    pub channel: Option<u8>, // 1-16; None = all channels
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AppliedPlugin {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    /// `true` if an already loaded instance was kept instead of loading a new one.
+    pub reused: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AudioConfigSnapshot {
+    pub host: String,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub sample_rate: u32,
+    pub buffer_size: u32,
+    pub channels: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EngineSnapshot {
+    /// Loaded plugins in processing order.
+    pub plugins: Vec<PluginSnapshot>,
+    pub order: Vec<String>,
+    pub global_bypass: bool,
+    pub global_mute: bool,
+    pub input_gain: f32,
+    pub output_gain: f32,
+    pub noise_reduction_enabled: bool,
+    pub noise_reduction_mode: String,
+    pub input_channels: (usize, usize),
+    pub channel_scan: bool,
+    pub transport: TransportSettings,
+    /// `None` while audio is stopped.
+    pub audio: Option<AudioConfigSnapshot>,
+}
+
+/// Musical transport the engine reports to plugins in every block's `ProcessContext`.
+/// There is no timeline: while `playing`, the project position simply keeps advancing.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct TransportSettings {
+    pub tempo: f64, // BPM
+    pub time_sig_numerator: u32,
+    pub time_sig_denominator: u32,
+    pub playing: bool,
+}
+
+impl Default for TransportSettings {
+    fn default() -> Self {
+        Self {
+            tempo: 120.0,
+            time_sig_numerator: 4,
+            time_sig_denominator: 4,
+            playing: true,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -116,6 +349,30 @@ pub struct MeterLevels {
     pub output: [f32; 2],
 }
 
+/// What the engine was doing with a plugin when it died.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CrashPhase {
+    /// Loading, activating or connecting the plugin.
+    Loading,
+    /// Restoring a state chunk.
+    State,
+    /// Inside the plugin's process call on the audio thread.
+    Processing,
+    /// Running the plugin's editor window.
+    Editor,
+}
+
+/// Plugin blamed for an engine crash or hang. Written to the crash marker by the engine's
+/// last-chance handler and reported live through `EngineEvent::Activity`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashCulprit {
+    pub phase: CrashPhase,
+    pub path: String,
+    pub name: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "type", content = "payload")]
 pub enum EngineEvent {
@@ -125,7 +382,44 @@ pub enum EngineEvent {
     LevelMeter(MeterLevels),
     // Channel Activity Scan (Up to 32 chans)
     ChannelLevels(Vec<f32>),
-    Started { sample_rate: u32, buffer_size: u32 },
+    Started {
+        sample_rate: u32,
+        buffer_size: u32,
+    },
+    /// Sent once a second from the engine's event loop; the host treats silence as a hang.
+    Heartbeat,
+    /// Plugin the main thread is currently busy with (`None` when idle).
+    Activity(Option<CrashCulprit>),
+    /// A parameter was changed in the plugin's own editor. Coalesced by the engine: at most
+    /// one event per parameter every 50 ms, carrying the latest value.
+    ParameterChanged {
+        id: String,
+        param_id: u32,
+        value: f64,
+        display: String,
+    },
+    /// The plugin reloaded its parameter values or titles (`restartComponent`); refetch them
+    /// with `GetParameters`.
+    ParametersChanged {
+        id: String,
+    },
+    /// The plugin changed its latency or I/O setup (`restartComponent`) and was re-prepared
+    /// with a fresh processor. Latencies are as reported afterwards.
+    PluginRestarted {
+        id: String,
+        latency_samples: u32,
+        total_chain_latency_samples: u32,
+    },
+    /// Learn mode bound a control. The engine already uses the mapping; the host saves it.
+    MidiLearned(MidiMapping),
+    /// A MIDI mapping changed a setting, as the command it ran. At most one per target each
+    /// time the engine drains its control ports.
+    MidiApplied(Command),
+    /// A MIDI mapping wants the global mute on or off (`None` = toggle). The host owns that
+    /// state together with the Alt+M hotkey and the tray, so it makes the change.
+    GlobalMuteRequested {
+        active: Option<bool>,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -141,6 +435,479 @@ pub struct DeviceInfo {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "kind", content = "data")]
 pub enum OutputMessage {
-    Response(Response),
+    Response(Reply),
     Event(EngineEvent),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc_transport::{read_message, write_message, Encoding};
+    use crate::midi_map::{MidiCurve, MidiMode, MidiTrigger, MidiTriggerKind, PluginRef};
+    use std::collections::HashSet;
+    use std::io::Cursor;
+
+    // No wildcard arms: a new variant fails to compile here until it gets a sample below.
+    fn command_name(cmd: &Command) -> &'static str {
+        match cmd {
+            Command::Hello { .. } => "Hello",
+            Command::GetDevices => "GetDevices",
+            Command::Start { .. } => "Start",
+            Command::Stop => "Stop",
+            Command::LoadPlugin { .. } => "LoadPlugin",
+            Command::UnloadPlugin { .. } => "UnloadPlugin",
+            Command::ReorderPlugins { .. } => "ReorderPlugins",
+            Command::ApplyChain { .. } => "ApplyChain",
+            Command::OpenEditor { .. } => "OpenEditor",
+            Command::SetBypass { .. } => "SetBypass",
+            Command::SetMute { .. } => "SetMute",
+            Command::SetGain { .. } => "SetGain",
+            Command::SetGlobalMute { .. } => "SetGlobalMute",
+            Command::SetInputGain { .. } => "SetInputGain",
+            Command::SetNoiseReduction { .. } => "SetNoiseReduction",
+            Command::SetOutputGain { .. } => "SetOutputGain",
+            Command::SetGlobalBypass { .. } => "SetGlobalBypass",
+            Command::SetInputChannels { .. } => "SetInputChannels",
+            Command::SetChannelScan { .. } => "SetChannelScan",
+            Command::SetTransport(_) => "SetTransport",
+            Command::GetRuntimeStats => "GetRuntimeStats",
+            Command::GetSnapshot => "GetSnapshot",
+            Command::GetSession => "GetSession",
+            Command::SessionSaved { .. } => "SessionSaved",
+            Command::GetPluginState { .. } => "GetPluginState",
+            Command::SetPluginState { .. } => "SetPluginState",
+            Command::ExportVstPreset { .. } => "ExportVstPreset",
+            Command::ImportVstPreset { .. } => "ImportVstPreset",
+            Command::GetParameters { .. } => "GetParameters",
+            Command::SetParameter { .. } => "SetParameter",
+            Command::GetPrograms { .. } => "GetPrograms",
+            Command::SelectProgram { .. } => "SelectProgram",
+            Command::GetMidiPorts => "GetMidiPorts",
+            Command::SetMidiInput { .. } => "SetMidiInput",
+            Command::SetMidiMappings(_) => "SetMidiMappings",
+            Command::MidiLearn { .. } => "MidiLearn",
+        }
+    }
+
+    fn response_name(resp: &Response) -> &'static str {
+        match resp {
+            Response::Version(_) => "Version",
+            Response::Devices(_) => "Devices",
+            Response::Success => "Success",
+            Response::Started { .. } => "Started",
+            Response::Error(_) => "Error",
+            Response::PluginLoaded { .. } => "PluginLoaded",
+            Response::RuntimeStats { .. } => "RuntimeStats",
+            Response::Snapshot(_) => "Snapshot",
+            Response::Session { .. } => "Session",
+            Response::ChainApplied(_) => "ChainApplied",
+            Response::PluginState { .. } => "PluginState",
+            Response::Parameters { .. } => "Parameters",
+            Response::ParameterValue { .. } => "ParameterValue",
+            Response::Programs { .. } => "Programs",
+            Response::MidiPorts(_) => "MidiPorts",
+        }
+    }
+
+    fn event_name(event: &EngineEvent) -> &'static str {
+        match event {
+            EngineEvent::Log(_) => "Log",
+            EngineEvent::Error(_) => "Error",
+            EngineEvent::LevelMeter(_) => "LevelMeter",
+            EngineEvent::ChannelLevels(_) => "ChannelLevels",
+            EngineEvent::Started { .. } => "Started",
+            EngineEvent::Heartbeat => "Heartbeat",
+            EngineEvent::Activity(_) => "Activity",
+            EngineEvent::ParameterChanged { .. } => "ParameterChanged",
+            EngineEvent::ParametersChanged { .. } => "ParametersChanged",
+            EngineEvent::PluginRestarted { .. } => "PluginRestarted",
+            EngineEvent::MidiLearned(_) => "MidiLearned",
+            EngineEvent::MidiApplied(_) => "MidiApplied",
+            EngineEvent::GlobalMuteRequested { .. } => "GlobalMuteRequested",
+        }
+    }
+
+    const PATH: &str = r"C:\VST3\Comp.vst3";
+    const CLASS_ID: &str = "00112233445566778899AABBCCDDEEFF";
+
+    fn assignment() -> MidiAssignment {
+        MidiAssignment {
+            port: "Auralyn MIDI In".to_string(),
+            channel: Some(10),
+        }
+    }
+
+    fn preset_plugin() -> PresetPlugin {
+        PresetPlugin {
+            path: PATH.to_string(),
+            class_id: Some(CLASS_ID.to_string()),
+            name: "Comp".to_string(),
+            vendor: "Vendor".to_string(),
+            version: "1.0.0".to_string(),
+            enabled: true,
+            muted: false,
+            gain: 0.5,
+            state: Some("AAEC".to_string()),
+            controller_state: Some("BQY=".to_string()),
+            midi_input: Some(assignment()),
+        }
+    }
+
+    fn mapping() -> MidiMapping {
+        MidiMapping {
+            trigger: MidiTrigger {
+                port: "nanoKONTROL".to_string(),
+                channel: 1,
+                kind: MidiTriggerKind::Cc,
+                number: 7,
+            },
+            target: MidiTarget::Parameter {
+                plugin: PluginRef {
+                    path: PATH.to_string(),
+                    class_id: Some(CLASS_ID.to_string()),
+                    occurrence: 1,
+                },
+                param_id: 42,
+            },
+            min: 0.25,
+            max: 0.75,
+            curve: MidiCurve::Logarithmic,
+            mode: MidiMode::Toggle,
+        }
+    }
+
+    fn commands() -> Vec<Command> {
+        let id = || "plugin-1".to_string();
+        vec![
+            Command::Hello {
+                protocol_version: PROTOCOL_VERSION,
+            },
+            Command::GetDevices,
+            Command::Start {
+                host: "ASIO".to_string(),
+                input: Some("In".to_string()),
+                output: None,
+                buffer_size: Some(256),
+                sample_rate: Some(48_000),
+            },
+            Command::Stop,
+            Command::LoadPlugin {
+                path: PATH.to_string(),
+                class_id: Some(CLASS_ID.to_string()),
+            },
+            Command::UnloadPlugin { id: id() },
+            Command::ReorderPlugins {
+                order: vec![id(), "plugin-2".to_string()],
+            },
+            Command::ApplyChain {
+                plugins: vec![preset_plugin()],
+            },
+            Command::OpenEditor { id: id() },
+            Command::SetBypass {
+                id: id(),
+                active: true,
+            },
+            Command::SetMute {
+                id: id(),
+                active: false,
+            },
+            Command::SetGain {
+                id: id(),
+                value: 0.75,
+            },
+            Command::SetGlobalMute { active: true },
+            Command::SetInputGain { value: 1.5 },
+            Command::SetNoiseReduction {
+                active: true,
+                mode: Some("high".to_string()),
+            },
+            Command::SetOutputGain { value: 0.25 },
+            Command::SetGlobalBypass { active: true },
+            Command::SetInputChannels { left: 2, right: 3 },
+            Command::SetChannelScan { active: true },
+            Command::SetTransport(TransportSettings {
+                tempo: 92.5,
+                time_sig_numerator: 6,
+                time_sig_denominator: 8,
+                playing: false,
+            }),
+            Command::GetRuntimeStats,
+            Command::GetSnapshot,
+            Command::GetSession,
+            Command::SessionSaved { ids: vec![id()] },
+            Command::GetPluginState { id: id() },
+            Command::SetPluginState {
+                id: id(),
+                state: "AAEC".to_string(),
+                controller_state: Some("BQY=".to_string()),
+            },
+            Command::ExportVstPreset {
+                id: id(),
+                path: "out.vstpreset".to_string(),
+            },
+            Command::ImportVstPreset {
+                id: id(),
+                path: "in.vstpreset".to_string(),
+            },
+            Command::GetParameters { id: id() },
+            Command::SetParameter {
+                id: id(),
+                param_id: 42,
+                value: 0.125,
+            },
+            Command::GetPrograms { id: id() },
+            Command::SelectProgram {
+                id: id(),
+                list_id: 7,
+                program_index: 3,
+            },
+            Command::GetMidiPorts,
+            Command::SetMidiInput {
+                id: id(),
+                input: Some(assignment()),
+            },
+            Command::SetMidiMappings(vec![mapping()]),
+            Command::MidiLearn {
+                target: Some(MidiTarget::Mute {
+                    plugin: PluginRef {
+                        path: PATH.to_string(),
+                        class_id: None,
+                        occurrence: 0,
+                    },
+                }),
+            },
+        ]
+    }
+
+    fn responses() -> Vec<Response> {
+        let id = || "plugin-1".to_string();
+        vec![
+            Response::Version(EngineVersion {
+                protocol_version: PROTOCOL_VERSION,
+                engine_version: "1.2.3".to_string(),
+                capabilities: EngineCapabilities {
+                    hosts: vec!["ASIO".to_string(), "WASAPI".to_string()],
+                    noise_reduction_modes: vec!["low".to_string(), "high".to_string()],
+                    max_plugins: 32,
+                },
+            }),
+            Response::Devices(vec![DeviceInfo {
+                name: "Speakers".to_string(),
+                host: "WASAPI".to_string(),
+                is_input: false,
+                buffer_size_range: Some((64, 2048)),
+                channels: 2,
+                is_default: true,
+            }]),
+            Response::Success,
+            Response::Started {
+                sample_rate: 48_000,
+                buffer_size: 256,
+            },
+            Response::Error("boom".to_string()),
+            Response::PluginLoaded {
+                id: id(),
+                name: "Comp".to_string(),
+                vendor: "Vendor".to_string(),
+            },
+            Response::RuntimeStats {
+                active_plugin_count: 2,
+                enabled_plugin_count: 1,
+                pending_unload_count: 0,
+                burned_library_count: 1,
+                global_bypass: false,
+                max_jitter_us: 120,
+                glitch_count: 3,
+                total_plugin_latency_samples: 64,
+                total_plugin_latency_ms: 1.5,
+                noise_reduction_latency_samples: 480,
+                noise_reduction_latency_ms: 10.0,
+                total_chain_latency_samples: 544,
+                total_chain_latency_ms: 11.5,
+                noise_reduction_enabled: true,
+                noise_reduction_active: true,
+                noise_reduction_mode: "low".to_string(),
+            },
+            Response::Snapshot(EngineSnapshot {
+                plugins: vec![PluginSnapshot {
+                    id: id(),
+                    path: PATH.to_string(),
+                    class_id: CLASS_ID.to_string(),
+                    name: "Comp".to_string(),
+                    rt_index: Some(0),
+                    bypassed: false,
+                    muted: true,
+                    gain: 0.5,
+                    latency_samples: 64,
+                    pending_init: false,
+                    dirty: true,
+                    accepts_midi: true,
+                    midi_input: Some(assignment()),
+                }],
+                order: vec![id()],
+                global_bypass: false,
+                global_mute: true,
+                input_gain: 1.5,
+                output_gain: 0.5,
+                noise_reduction_enabled: true,
+                noise_reduction_mode: "high".to_string(),
+                input_channels: (2, 3),
+                channel_scan: false,
+                transport: TransportSettings::default(),
+                audio: Some(AudioConfigSnapshot {
+                    host: "ASIO".to_string(),
+                    input: Some("In".to_string()),
+                    output: Some("Out".to_string()),
+                    sample_rate: 48_000,
+                    buffer_size: 256,
+                    channels: 2,
+                }),
+            }),
+            Response::Session {
+                session: SessionState {
+                    version: 1,
+                    plugins: vec![preset_plugin()],
+                    input_gain: 1.0,
+                    output_gain: 0.75,
+                    noise_reduction_enabled: false,
+                    noise_reduction_mode: "low".to_string(),
+                    input_channels: (0, 1),
+                    transport: TransportSettings::default(),
+                },
+                ids: vec![id()],
+            },
+            Response::ChainApplied(vec![AppliedPlugin {
+                id: id(),
+                name: "Comp".to_string(),
+                path: PATH.to_string(),
+                reused: true,
+            }]),
+            Response::PluginState {
+                id: id(),
+                state: "AAEC".to_string(),
+                controller_state: None,
+            },
+            Response::Parameters {
+                id: id(),
+                parameters: vec![PluginParameter {
+                    id: 42,
+                    title: "Threshold".to_string(),
+                    short_title: "Thr".to_string(),
+                    units: "dB".to_string(),
+                    step_count: 0,
+                    default_value: 0.5,
+                    value: 0.25,
+                    display: "-12.0".to_string(),
+                    unit_id: 0,
+                    flags: 1,
+                }],
+            },
+            Response::ParameterValue {
+                id: id(),
+                param_id: 42,
+                value: 0.25,
+                display: "-12.0".to_string(),
+            },
+            Response::Programs {
+                id: id(),
+                lists: vec![ProgramList {
+                    id: 7,
+                    name: "Factory".to_string(),
+                    programs: vec!["Init".to_string(), "Vocal".to_string()],
+                    param_id: Some(99),
+                    current: Some(1),
+                }],
+            },
+            Response::MidiPorts(vec!["Auralyn MIDI In".to_string()]),
+        ]
+    }
+
+    fn events() -> Vec<EngineEvent> {
+        let id = || "plugin-1".to_string();
+        vec![
+            EngineEvent::Log("hello".to_string()),
+            EngineEvent::Error("boom".to_string()),
+            EngineEvent::LevelMeter(MeterLevels {
+                input: [0.5, 0.25],
+                output: [0.125, 1.0],
+            }),
+            EngineEvent::ChannelLevels(vec![0.5; 4]),
+            EngineEvent::Started {
+                sample_rate: 44_100,
+                buffer_size: 512,
+            },
+            EngineEvent::Heartbeat,
+            EngineEvent::Activity(Some(CrashCulprit {
+                phase: CrashPhase::State,
+                path: PATH.to_string(),
+                name: "Comp".to_string(),
+            })),
+            EngineEvent::ParameterChanged {
+                id: id(),
+                param_id: 42,
+                value: 0.75,
+                display: "-3.0".to_string(),
+            },
+            EngineEvent::ParametersChanged { id: id() },
+            EngineEvent::PluginRestarted {
+                id: id(),
+                latency_samples: 128,
+                total_chain_latency_samples: 608,
+            },
+            EngineEvent::MidiLearned(mapping()),
+            EngineEvent::MidiApplied(Command::SetBypass {
+                id: id(),
+                active: true,
+            }),
+            EngineEvent::GlobalMuteRequested { active: None },
+        ]
+    }
+
+    fn assert_distinct(names: impl IntoIterator<Item = &'static str>, count: usize) {
+        let names: HashSet<_> = names.into_iter().collect();
+        assert_eq!(names.len(), count, "duplicate sample");
+    }
+
+    /// Sends `msg` the way the engine does and checks it decodes to the same JSON.
+    fn assert_output_round_trips(msg: OutputMessage) {
+        let mut buf = Vec::new();
+        write_message(&mut buf, &msg, Encoding::for_message(&msg)).unwrap();
+        let decoded = read_message(&mut Cursor::new(buf)).unwrap().unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&msg).unwrap()
+        );
+    }
+
+    #[test]
+    fn every_message_round_trips() {
+        let commands = commands();
+        assert_distinct(commands.iter().map(command_name), commands.len());
+        for (id, command) in commands.into_iter().enumerate() {
+            // Written to the engine's stdin as one JSON line
+            let request = Request {
+                id: id as RequestId,
+                command,
+            };
+            let line = serde_json::to_string(&request).unwrap();
+            let decoded: Request = serde_json::from_str(&line).unwrap();
+            assert_eq!(decoded.id, request.id);
+            assert_eq!(serde_json::to_string(&decoded).unwrap(), line);
+        }
+
+        let responses = responses();
+        assert_distinct(responses.iter().map(response_name), responses.len());
+        for (id, response) in responses.into_iter().enumerate() {
+            assert_output_round_trips(OutputMessage::Response(Reply {
+                id: Some(id as RequestId),
+                response,
+            }));
+        }
+
+        let events = events();
+        assert_distinct(events.iter().map(event_name), events.len());
+        for event in events {
+            assert_output_round_trips(OutputMessage::Event(event));
+        }
+    }
+}
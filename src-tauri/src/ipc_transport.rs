@@ -0,0 +1,327 @@
+//! Engine -> host message channel.
+//!
+//! The host listens on a loopback socket and passes its address and a one-off token to the
+//! sidecar through the environment. The engine connects, sends the token as its first frame
+//! and from then on writes every `OutputMessage` there. Stdout is left to plugins, which print
+//! whatever they like (often without a trailing newline).
+//!
+//! Frame layout: `[len: u32 LE][encoding: u8][payload]`, where `len` counts the encoding byte
+//! and the payload. Replies and rare events are JSON; meters, which arrive at ~60 Hz, are
+//! MessagePack.
+//!
+//! Without the environment (manual runs, integration tests) the engine falls back to the old
+//! `IPC:{json}` stdout lines.
+
+use anyhow::{anyhow, Context, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::ipc::{EngineEvent, OutputMessage};
+
+pub const IPC_ADDR_ENV: &str = "AURALYN_IPC_ADDR";
+pub const IPC_TOKEN_ENV: &str = "AURALYN_IPC_TOKEN";
+
+/// Upper bound for one frame; anything larger means the stream is out of sync.
+const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Encoding {
+    Json = 0,
+    MessagePack = 1,
+}
+
+impl Encoding {
+    fn from_byte(b: u8) -> Result<Self> {
+        match b {
+            0 => Ok(Self::Json),
+            1 => Ok(Self::MessagePack),
+            other => Err(anyhow!("Unknown frame encoding: {}", other)),
+        }
+    }
+
+    /// High-rate events go binary, everything else stays human-readable.
+    pub fn for_message(msg: &OutputMessage) -> Self {
+        match msg {
+            OutputMessage::Event(EngineEvent::LevelMeter(_))
+            | OutputMessage::Event(EngineEvent::ChannelLevels(_)) => Self::MessagePack,
+            _ => Self::Json,
+        }
+    }
+}
+
+fn write_raw_frame(w: &mut impl Write, encoding: Encoding, payload: &[u8]) -> io::Result<()> {
+    let len = (payload.len() + 1) as u32;
+    let mut frame = Vec::with_capacity(payload.len() + 5);
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.push(encoding as u8);
+    frame.extend_from_slice(payload);
+    // Single write so frames from different threads never interleave
+    w.write_all(&frame)?;
+    w.flush()
+}
+
+/// Returns `None` on a clean EOF between frames. An error means the stream is out of sync
+/// and has to be dropped.
+pub fn read_raw_frame(r: &mut impl Read) -> Result<Option<(Encoding, Vec<u8>)>> {
+    let mut len_buf = [0u8; 4];
+    match r.read_exact(&mut len_buf) {
+        Ok(()) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes(len_buf) as usize;
+    if len == 0 || len > MAX_FRAME_LEN {
+        return Err(anyhow!("Invalid frame length: {}", len));
+    }
+
+    let mut frame = vec![0u8; len];
+    r.read_exact(&mut frame).context("Truncated frame")?;
+    let payload = frame.split_off(1);
+    Ok(Some((Encoding::from_byte(frame[0])?, payload)))
+}
+
+pub fn write_message(w: &mut impl Write, msg: &OutputMessage, encoding: Encoding) -> Result<()> {
+    let payload = match encoding {
+        Encoding::Json => serde_json::to_vec(msg)?,
+        // Named (map) encoding: the enums are adjacently tagged and need field names
+        Encoding::MessagePack => rmp_serde::to_vec_named(msg)?,
+    };
+    write_raw_frame(w, encoding, &payload)?;
+    Ok(())
+}
+
+/// Decodes the payload of one frame. Failing here (a message this build does not know,
+/// say) leaves the stream in sync, so the reader can skip the frame and go on.
+pub fn decode_message(encoding: Encoding, payload: &[u8]) -> Result<OutputMessage> {
+    Ok(match encoding {
+        Encoding::Json => serde_json::from_slice(payload)?,
+        Encoding::MessagePack => rmp_serde::from_slice(payload)?,
+    })
+}
+
+pub fn read_message(r: &mut impl Read) -> Result<Option<OutputMessage>> {
+    let Some((encoding, payload)) = read_raw_frame(r)? else {
+        return Ok(None);
+    };
+    decode_message(encoding, &payload).map(Some)
+}
+
+// --- Host side ---
+
+/// Listening end created before the engine is spawned.
+pub struct HostChannel {
+    listener: TcpListener,
+    addr: SocketAddr,
+    token: String,
+}
+
+impl HostChannel {
+    pub fn bind() -> Result<Self> {
+        let listener =
+            TcpListener::bind(("127.0.0.1", 0)).context("Failed to bind IPC listener")?;
+        let addr = listener.local_addr()?;
+        Ok(Self {
+            listener,
+            addr,
+            token: new_token(),
+        })
+    }
+
+    /// Environment the engine needs to connect back.
+    pub fn env(&self) -> [(&'static str, String); 2] {
+        [
+            (IPC_ADDR_ENV, self.addr.to_string()),
+            (IPC_TOKEN_ENV, self.token.clone()),
+        ]
+    }
+
+    /// Waits for the engine to connect and prove it is the process we spawned.
+    pub fn accept(self, timeout: Duration) -> Result<TcpStream> {
+        self.listener.set_nonblocking(true)?;
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match self.listener.accept() {
+                Ok((mut stream, peer)) => {
+                    stream.set_nonblocking(false)?;
+                    stream.set_read_timeout(Some(
+                        deadline
+                            .saturating_duration_since(Instant::now())
+                            .max(Duration::from_millis(100)),
+                    ))?;
+                    match read_raw_frame(&mut stream) {
+                        Ok(Some((_, token))) if token == self.token.as_bytes() => {
+                            stream.set_read_timeout(None)?;
+                            let _ = stream.set_nodelay(true);
+                            return Ok(stream);
+                        }
+                        // Some other local process poking the port: ignore it and keep waiting
+                        _ => log::warn!("Rejected IPC connection from {}", peer),
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(anyhow!("IPC accept failed: {}", e)),
+            }
+
+            if Instant::now() >= deadline {
+                return Err(anyhow!("Audio engine did not open the IPC channel"));
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+fn new_token() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default(),
+    );
+    hasher.write_u32(std::process::id());
+    let a = hasher.finish();
+    let b = RandomState::new().build_hasher().finish();
+    format!("{:016x}{:016x}", a, b)
+}
+
+// --- Engine side ---
+
+static ENGINE_CHANNEL: OnceLock<Mutex<TcpStream>> = OnceLock::new();
+
+/// Connects to the host if it passed a channel. Call once at startup; returns false when
+/// running stand-alone (stdout fallback).
+pub fn connect_from_env() -> Result<bool> {
+    let (Ok(addr), Ok(token)) = (std::env::var(IPC_ADDR_ENV), std::env::var(IPC_TOKEN_ENV)) else {
+        return Ok(false);
+    };
+
+    let mut stream = TcpStream::connect(addr.as_str())
+        .with_context(|| format!("Failed to connect IPC channel {}", addr))?;
+    let _ = stream.set_nodelay(true);
+    write_raw_frame(&mut stream, Encoding::Json, token.as_bytes())?;
+
+    ENGINE_CHANNEL
+        .set(Mutex::new(stream))
+        .map_err(|_| anyhow!("IPC channel already connected"))?;
+    Ok(true)
+}
+
+/// Sends a message to the host. Safe to call from any non-RT thread.
+pub fn send(msg: &OutputMessage) {
+    if let Some(channel) = ENGINE_CHANNEL.get() {
+        let mut stream = match channel.lock() {
+            Ok(stream) => stream,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        if let Err(e) = write_message(&mut *stream, msg, Encoding::for_message(msg)) {
+            log::error!("IPC send failed: {}", e);
+        }
+        return;
+    }
+
+    match serde_json::to_string(msg) {
+        Ok(json) => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            let _ = writeln!(out, "IPC:{}", json);
+            let _ = out.flush();
+        }
+        Err(e) => log::error!("JSON Serialize Error: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::{MeterLevels, Reply, Response};
+    use std::io::Cursor;
+
+    #[test]
+    fn frames_round_trip_in_both_encodings() {
+        let meter = OutputMessage::Event(EngineEvent::LevelMeter(MeterLevels {
+            input: [0.5, 0.25],
+            output: [0.125, 1.0],
+        }));
+        let reply = OutputMessage::Response(Reply {
+            id: Some(7),
+            response: Response::Error("boom".to_string()),
+        });
+        assert_eq!(Encoding::for_message(&meter), Encoding::MessagePack);
+        assert_eq!(Encoding::for_message(&reply), Encoding::Json);
+
+        let mut buf = Vec::new();
+        write_message(&mut buf, &meter, Encoding::for_message(&meter)).unwrap();
+        write_message(&mut buf, &reply, Encoding::for_message(&reply)).unwrap();
+
+        let mut cursor = Cursor::new(buf);
+        match read_message(&mut cursor).unwrap() {
+            Some(OutputMessage::Event(EngineEvent::LevelMeter(levels))) => {
+                assert_eq!(levels.input, [0.5, 0.25]);
+                assert_eq!(levels.output, [0.125, 1.0]);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match read_message(&mut cursor).unwrap() {
+            Some(OutputMessage::Response(Reply {
+                id: Some(7),
+                response: Response::Error(e),
+            })) => assert_eq!(e, "boom"),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(read_message(&mut cursor).unwrap().is_none());
+    }
+
+    #[test]
+    fn rejects_corrupt_length() {
+        let mut cursor = Cursor::new(vec![0xff, 0xff, 0xff, 0xff, 0]);
+        assert!(read_message(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn undecodable_payload_keeps_the_stream_in_sync() {
+        let meter = OutputMessage::Event(EngineEvent::ChannelLevels(vec![0.5]));
+        // An event a newer engine might send
+        let unknown = br#"{"kind":"Event","data":{"type":"FromTheFuture"}}"#;
+        let mut buf = Vec::new();
+        write_raw_frame(&mut buf, Encoding::Json, unknown).unwrap();
+        write_message(&mut buf, &meter, Encoding::for_message(&meter)).unwrap();
+
+        let mut cursor = Cursor::new(buf);
+        let (encoding, payload) = read_raw_frame(&mut cursor).unwrap().unwrap();
+        assert!(decode_message(encoding, &payload).is_err());
+        match read_message(&mut cursor).unwrap() {
+            Some(OutputMessage::Event(EngineEvent::ChannelLevels(levels))) => {
+                assert_eq!(levels, vec![0.5])
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn host_accepts_engine_with_token() {
+        let channel = HostChannel::bind().unwrap();
+        let [(_, addr), (_, token)] = channel.env();
+
+        let engine = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            write_raw_frame(&mut stream, Encoding::Json, token.as_bytes()).unwrap();
+            let msg = OutputMessage::Event(EngineEvent::ChannelLevels(vec![0.1; 4]));
+            write_message(&mut stream, &msg, Encoding::for_message(&msg)).unwrap();
+        });
+
+        let mut stream = channel.accept(Duration::from_secs(5)).unwrap();
+        match read_message(&mut stream).unwrap() {
+            Some(OutputMessage::Event(EngineEvent::ChannelLevels(levels))) => {
+                assert_eq!(levels.len(), 4)
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        engine.join().unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::sync::{mpsc, Arc, Mutex};
 use tauri::{AppHandle, Emitter, Manager, State, WindowEvent};
 use tauri::menu::{MenuBuilder, MenuItemBuilder, PredefinedMenuItem};
@@ -7,10 +8,21 @@ use tauri_plugin_global_shortcut::{Code, GlobalShortcutExt, Modifiers, Shortcut,
 pub mod audio;
 pub mod audio_engine;
 pub mod autostart;
+pub mod fs_util;
 pub mod ipc;
+pub mod ipc_transport;
+pub mod midi_map;
 pub mod obs;
+pub mod session;
+pub mod supervisor;
+#[cfg(test)]
+mod test_util;
 pub mod vst_host;
+use crate::midi_map::{MidiMapping, MidiTarget};
+use crate::vst_host::blacklist::{Blacklist, BlacklistEntry};
 use crate::vst_host::presets::{self, Preset, PresetPlugin};
+use crate::vst_host::scanner::{ScanControl, ScanFileResult};
+use crate::vst_host::search_paths::{self, SearchPath};
 
 #[tauri::command]
 fn get_autostart_status() -> Result<autostart::AutostartStatus, String> {
@@ -69,15 +81,29 @@ fn set_engine_tuning_config(
 }
 
 #[tauri::command]
-fn get_engine_runtime_stats(
+async fn get_engine_runtime_stats(
     state: State<'_, audio::AudioState>,
 ) -> Result<audio::EngineRuntimeStats, String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.get_engine_runtime_stats().map_err(|e| e.to_string())
+    state
+        .get_engine_runtime_stats()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn discard_session(state: State<'_, audio::AudioState>) -> Result<(), String> {
+    state.discard_session().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_engine_snapshot(
+    state: State<'_, audio::AudioState>,
+) -> Result<Option<ipc::EngineSnapshot>, String> {
+    state.get_engine_snapshot().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn start_audio(
+async fn start_audio(
     state: State<'_, audio::AudioState>,
     input: Option<String>,
     output: Option<String>,
@@ -89,57 +115,77 @@ fn start_audio(
         "start_audio IPC Args: host={:?}, input={:?}, buffer={:?}, rate={:?}",
         host, input, buffer_size, sample_rate
     );
-    let mut host_instance = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host_instance
+    state
         .start(host, input, output, buffer_size, sample_rate)
+        .await
         .map_err(|e| audio::localize_audio_error(e.to_string()))
 }
 
 #[tauri::command]
-fn stop_audio(state: State<'_, audio::AudioState>) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.stop();
-    Ok(())
+async fn stop_audio(state: State<'_, audio::AudioState>) -> Result<(), String> {
+    state.stop().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn load_plugin(state: State<'_, audio::AudioState>, path: String) -> Result<String, String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.load_plugin(&path).map_err(|e| e.to_string())
+async fn load_plugin(
+    state: State<'_, audio::AudioState>,
+    path: String,
+    class_id: Option<String>,
+) -> Result<String, String> {
+    state
+        .load_plugin(path, class_id)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn remove_plugin(state: State<'_, audio::AudioState>, id: String) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.remove_plugin(&id).map_err(|e| e.to_string())
+async fn remove_plugin(state: State<'_, audio::AudioState>, id: String) -> Result<(), String> {
+    state.remove_plugin(id).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn reorder_plugins(state: State<'_, audio::AudioState>, order: Vec<String>) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.reorder_plugins(order).map_err(|e| e.to_string())
+async fn reorder_plugins(
+    state: State<'_, audio::AudioState>,
+    order: Vec<String>,
+) -> Result<(), String> {
+    state
+        .reorder_plugins(order)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_bypass(state: State<'_, audio::AudioState>, id: String, active: bool) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_bypass(&id, active).map_err(|e| e.to_string())
+async fn set_bypass(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    active: bool,
+) -> Result<(), String> {
+    state
+        .set_bypass(id, active)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_mute(state: State<'_, audio::AudioState>, id: String, active: bool) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_mute(&id, active).map_err(|e| e.to_string())
+async fn set_mute(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    active: bool,
+) -> Result<(), String> {
+    state.set_mute(id, active).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_gain(state: State<'_, audio::AudioState>, id: String, value: f32) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_gain(&id, value).map_err(|e| e.to_string())
+async fn set_gain(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    value: f32,
+) -> Result<(), String> {
+    state.set_gain(id, value).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn restart_audio_engine(
+async fn restart_audio_engine(
     state: State<'_, audio::AudioState>,
     input: Option<String>,
     output: Option<String>,
@@ -151,37 +197,202 @@ fn restart_audio_engine(
         "restart_audio_engine IPC Args: host={:?}, input={:?}, buffer={:?}, rate={:?}",
         host, input, buffer_size, sample_rate
     );
-    let mut audio_host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    audio_host.kill_engine();
-    // Short delay to ensure process is dead? Usually synchronous kill is fine on Windows.
-    // Re-start
-    audio_host
-        .start(host, input, output, buffer_size, sample_rate)
+    state
+        .restart(host, input, output, buffer_size, sample_rate)
+        .await
         .map_err(|e| audio::localize_audio_error(e.to_string()))
 }
 
 
 #[tauri::command]
-async fn scan_plugins(app: tauri::AppHandle) -> Result<Vec<vst_host::VstPlugin>, String> {
+async fn scan_plugins(
+    app: tauri::AppHandle,
+    scan: State<'_, Arc<ScanControl>>,
+    force_rescan: Option<bool>,
+) -> Result<Vec<vst_host::VstPlugin>, String> {
     let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
     if !config_dir.exists() {
         std::fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
     }
-    Ok(vst_host::scan_system_vst3(&config_dir))
+    let guard = scan
+        .begin()
+        .ok_or_else(|| "A plugin scan is already running".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        vst_host::scan_system_vst3(
+            &config_dir,
+            force_rescan.unwrap_or(false),
+            &guard,
+            |progress| {
+                let _ = app.emit("scan-progress", progress);
+            },
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "cancelled".to_string())
+}
+
+#[tauri::command]
+fn cancel_plugin_scan(scan: State<'_, Arc<ScanControl>>) {
+    scan.cancel();
+}
+
+#[tauri::command]
+async fn get_vst_search_paths(app: AppHandle) -> Result<Vec<SearchPath>, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    let user_paths = search_paths::load_user_paths(&config_dir).map_err(|e| e.to_string())?;
+    Ok(search_paths::search_paths(&user_paths))
+}
+
+#[tauri::command]
+async fn add_vst_search_path(app: AppHandle) -> Result<Vec<SearchPath>, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    let folder = rfd::FileDialog::new()
+        .set_title("VST3 プラグインのフォルダを追加")
+        .pick_folder()
+        .ok_or_else(|| "cancelled".to_string())?;
+
+    let mut user_paths = search_paths::load_user_paths(&config_dir).map_err(|e| e.to_string())?;
+    let folder = folder.to_string_lossy().into_owned();
+    if !user_paths.contains(&folder) {
+        user_paths.push(folder);
+        search_paths::save_user_paths(&config_dir, &user_paths).map_err(|e| e.to_string())?;
+    }
+    Ok(search_paths::search_paths(&user_paths))
 }
 
 #[tauri::command]
-async fn clear_blacklist(app: AppHandle) -> Result<(), String> {
+async fn remove_vst_search_path(app: AppHandle, path: String) -> Result<Vec<SearchPath>, String> {
     let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
-    let mut blacklist = vst_host::blacklist::Blacklist::new(&config_dir);
+    let mut user_paths = search_paths::load_user_paths(&config_dir).map_err(|e| e.to_string())?;
+    user_paths.retain(|p| p != &path);
+    search_paths::save_user_paths(&config_dir, &user_paths).map_err(|e| e.to_string())?;
+    Ok(search_paths::search_paths(&user_paths))
+}
+
+#[tauri::command]
+async fn get_blacklist(app: AppHandle) -> Result<BTreeMap<String, BlacklistEntry>, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(Blacklist::new(&config_dir).entries().clone())
+}
+
+#[tauri::command]
+async fn remove_from_blacklist(
+    app: AppHandle,
+    scan: State<'_, Arc<ScanControl>>,
+    path: String,
+) -> Result<(), String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    // A running scan saves its own copy of the list when done and would undo this
+    let _guard = scan
+        .begin()
+        .ok_or_else(|| "A plugin scan is already running".to_string())?;
+    Blacklist::new(&config_dir).remove(&path);
+    Ok(())
+}
+
+#[tauri::command]
+async fn retry_blacklisted_plugin(
+    app: AppHandle,
+    scan: State<'_, Arc<ScanControl>>,
+    path: String,
+) -> Result<ScanFileResult, String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    let guard = scan
+        .begin()
+        .ok_or_else(|| "A plugin scan is already running".to_string())?;
+    tauri::async_runtime::spawn_blocking(move || {
+        vst_host::scanner::retry_blacklisted(&config_dir, &path, &guard)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn clear_blacklist(app: AppHandle, scan: State<'_, Arc<ScanControl>>) -> Result<(), String> {
+    let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    let _guard = scan
+        .begin()
+        .ok_or_else(|| "A plugin scan is already running".to_string())?;
+    let mut blacklist = Blacklist::new(&config_dir);
     blacklist.clear();
     Ok(())
 }
 
 #[tauri::command]
-fn open_editor(state: State<'_, audio::AudioState>, id: String) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.open_editor(&id).map_err(|e| e.to_string())
+async fn apply_chain(
+    state: State<'_, audio::AudioState>,
+    plugins: Vec<PresetPlugin>,
+) -> Result<Vec<ipc::AppliedPlugin>, String> {
+    state.apply_chain(plugins).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn open_editor(state: State<'_, audio::AudioState>, id: String) -> Result<(), String> {
+    state.open_editor(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_plugin_parameters(
+    state: State<'_, audio::AudioState>,
+    id: String,
+) -> Result<Vec<vst_host::params::PluginParameter>, String> {
+    state.get_parameters(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_plugin_parameter(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    param_id: u32,
+    value: f64,
+) -> Result<audio::ParameterValue, String> {
+    state
+        .set_parameter(id, param_id, value)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_plugin_state(
+    state: State<'_, audio::AudioState>,
+    id: String,
+) -> Result<audio::PluginStateData, String> {
+    state.get_plugin_state(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_plugin_state(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    data: audio::PluginStateData,
+) -> Result<(), String> {
+    state
+        .set_plugin_state(id, data)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_plugin_programs(
+    state: State<'_, audio::AudioState>,
+    id: String,
+) -> Result<Vec<vst_host::programs::ProgramList>, String> {
+    state.get_programs(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn select_plugin_program(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    list_id: i32,
+    program_index: usize,
+) -> Result<audio::ParameterValue, String> {
+    state
+        .select_program(id, list_id, program_index)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -260,61 +471,170 @@ async fn import_preset(app: AppHandle) -> Result<String, String> {
 }
 
 #[tauri::command]
-fn toggle_global_mute(state: State<'_, audio::AudioState>) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.toggle_global_mute().map_err(|e| e.to_string())
+async fn export_vst_preset(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    name: String,
+) -> Result<(), String> {
+    let path = rfd::FileDialog::new()
+        .set_file_name(format!("{}.vstpreset", name))
+        .add_filter("VST3 Preset", &["vstpreset"])
+        .save_file();
+
+    match path {
+        Some(p) => state
+            .export_vstpreset(id, p.to_string_lossy().into_owned())
+            .await
+            .map_err(|e| e.to_string()),
+        None => Err("cancelled".to_string()),
+    }
 }
 
 #[tauri::command]
-fn set_global_mute(state: State<'_, audio::AudioState>, active: bool) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_global_mute(active).map_err(|e| e.to_string())
+async fn import_vst_preset(state: State<'_, audio::AudioState>, id: String) -> Result<(), String> {
+    let path = rfd::FileDialog::new()
+        .add_filter("VST3 Preset", &["vstpreset"])
+        .pick_file();
+
+    match path {
+        Some(p) => state
+            .import_vstpreset(id, p.to_string_lossy().into_owned())
+            .await
+            .map_err(|e| e.to_string()),
+        None => Err("cancelled".to_string()),
+    }
 }
 
 #[tauri::command]
-fn set_input_gain(state: State<'_, audio::AudioState>, value: f32) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_input_gain(value).map_err(|e| e.to_string())
+async fn toggle_global_mute(state: State<'_, audio::AudioState>) -> Result<(), String> {
+    state.toggle_global_mute().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_global_mute(state: State<'_, audio::AudioState>, active: bool) -> Result<(), String> {
+    state
+        .set_global_mute(active)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_midi_mappings(app: AppHandle) -> Result<Vec<MidiMapping>, String> {
+    let path = audio::midi_mappings_path(&app).map_err(|e| e.to_string())?;
+    midi_map::load_mappings(&path).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_noise_reduction(
+async fn set_midi_mappings(
+    app: AppHandle,
+    state: State<'_, audio::AudioState>,
+    mappings: Vec<MidiMapping>,
+) -> Result<(), String> {
+    let path = audio::midi_mappings_path(&app).map_err(|e| e.to_string())?;
+    state
+        .set_midi_mappings(path, mappings)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn start_midi_learn(
+    state: State<'_, audio::AudioState>,
+    target: MidiTarget,
+) -> Result<(), String> {
+    state
+        .midi_learn(Some(target))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn cancel_midi_learn(state: State<'_, audio::AudioState>) -> Result<(), String> {
+    state.midi_learn(None).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_input_gain(state: State<'_, audio::AudioState>, value: f32) -> Result<(), String> {
+    state.set_input_gain(value).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_noise_reduction(
     state: State<'_, audio::AudioState>,
     active: bool,
     mode: Option<String>,
 ) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_noise_reduction(active, mode)
+    state
+        .set_noise_reduction(active, mode)
+        .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_output_gain(state: State<'_, audio::AudioState>, value: f32) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_output_gain(value).map_err(|e| e.to_string())
+async fn set_output_gain(state: State<'_, audio::AudioState>, value: f32) -> Result<(), String> {
+    state
+        .set_output_gain(value)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_global_bypass(state: State<'_, audio::AudioState>, active: bool) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_global_bypass(active).map_err(|e| e.to_string())
+async fn set_global_bypass(
+    state: State<'_, audio::AudioState>,
+    active: bool,
+) -> Result<(), String> {
+    state
+        .set_global_bypass(active)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_input_channels(
+async fn set_input_channels(
     state: State<'_, audio::AudioState>,
     left: usize,
     right: usize,
 ) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_input_channels(left, right)
+    state
+        .set_input_channels(left, right)
+        .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-fn set_channel_scan(state: State<'_, audio::AudioState>, active: bool) -> Result<(), String> {
-    let mut host = state.0.lock().map_err(|_| "Failed to lock audio state")?;
-    host.set_channel_scan(active).map_err(|e| e.to_string())
+async fn set_channel_scan(state: State<'_, audio::AudioState>, active: bool) -> Result<(), String> {
+    state
+        .set_channel_scan(active)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_transport(
+    state: State<'_, audio::AudioState>,
+    transport: ipc::TransportSettings,
+) -> Result<(), String> {
+    state
+        .set_transport(transport)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_midi_ports(state: State<'_, audio::AudioState>) -> Result<Vec<String>, String> {
+    state.get_midi_ports().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_midi_input(
+    state: State<'_, audio::AudioState>,
+    id: String,
+    input: Option<ipc::MidiAssignment>,
+) -> Result<(), String> {
+    state
+        .set_midi_input(id, input)
+        .await
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -373,11 +693,8 @@ pub fn run() {
                     if event.state == ShortcutState::Pressed {
                         if shortcut.matches(Modifiers::ALT, Code::KeyM) {
                             log::info!("Global Mute Hotkey Pressed");
-                            if let Some(state) = app.try_state::<audio::AudioState>() {
-                                if let Ok(mut host) = state.0.lock() {
-                                    let _ = host.toggle_global_mute();
-                                }
-                            }
+                            // Same path as the tray item and MIDI mappings (`GlobalMuteRequested`)
+                            audio::spawn_global_mute(app, None);
                         }
                     }
                 })
@@ -385,6 +702,7 @@ pub fn run() {
         )
         .manage(audio_state)
         .manage(obs_state)
+        .manage(Arc::new(ScanControl::default()))
         .setup(|app| {
             let state = app.state::<audio::AudioState>();
 
@@ -428,16 +746,31 @@ pub fn run() {
                             let _ = window.set_focus();
                         }
                     }
-                    "mute_toggle" => {
-                        if let Some(audio_state) = app.try_state::<audio::AudioState>() {
-                            if let Ok(mut host) = audio_state.0.lock() {
-                                let _ = host.toggle_global_mute();
-                            }
-                        }
-                    }
+                    "mute_toggle" => audio::spawn_global_mute(app, None),
                     "quit" => {
-                        // Actually quit the application
-                        app.exit(0);
+                        // Last session autosave before the engine is killed with us. Off the
+                        // menu thread, so a slow engine doesn't freeze the window and tray.
+                        let audio_state = app
+                            .try_state::<audio::AudioState>()
+                            .map(|state| state.inner().clone());
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Some(audio_state) = audio_state {
+                                let save = tokio::time::timeout(
+                                    audio::SESSION_QUIT_SAVE_TIMEOUT,
+                                    audio_state.save_session(),
+                                );
+                                match save.await {
+                                    Ok(Ok(_)) => {}
+                                    Ok(Err(e)) => {
+                                        log::warn!("Failed to save session on quit: {}", e)
+                                    }
+                                    Err(_) => log::warn!("Session save on quit timed out"),
+                                }
+                            }
+                            // Actually quit the application
+                            app.exit(0);
+                        });
                     }
                     _ => {}
                 })
@@ -468,12 +801,27 @@ pub fn run() {
             }
 
             // Warmup Audio Engine (Spawn Sidecar in Background)
-            let host_clone = state.0.clone();
+            let warmup_state = state.inner().clone();
+            warmup_state.begin_warmup();
             std::thread::spawn(move || {
-                if let Ok(mut host) = host_clone.lock() {
-                    log::info!("Warming up Audio Engine...");
-                    if let Err(e) = host.warmup() {
-                        log::error!("Failed to warmup audio engine: {}", e);
+                log::info!("Warming up Audio Engine...");
+                if let Err(e) = warmup_state.warmup() {
+                    log::error!("Failed to warmup audio engine: {}", e);
+                }
+            });
+
+            // Respawns the engine (with its device config and chain) if it crashes or hangs
+            supervisor::spawn(state.inner().clone(), app.handle().clone());
+
+            // Session autosave: the engine's chain survives webview reloads and restarts
+            let autosave_state = state.inner().clone();
+            tauri::async_runtime::spawn(async move {
+                let mut ticker = tokio::time::interval(audio::SESSION_AUTOSAVE_INTERVAL);
+                ticker.tick().await; // The first tick completes immediately
+                loop {
+                    ticker.tick().await;
+                    if let Err(e) = autosave_state.save_session().await {
+                        log::warn!("Session autosave failed: {}", e);
                     }
                 }
             });
@@ -495,17 +843,33 @@ pub fn run() {
             get_engine_tuning_config,
             set_engine_tuning_config,
             get_engine_runtime_stats,
+            get_engine_snapshot,
+            discard_session,
             scan_plugins,
+            cancel_plugin_scan,
+            get_vst_search_paths,
+            add_vst_search_path,
+            remove_vst_search_path,
+            get_blacklist,
+            remove_from_blacklist,
+            retry_blacklisted_plugin,
             clear_blacklist,
             start_audio,
             stop_audio,
             load_plugin,
             remove_plugin,
             reorder_plugins,
+            apply_chain,
             set_bypass,
             set_mute,
             set_gain,
             open_editor,
+            get_plugin_parameters,
+            set_plugin_parameter,
+            get_plugin_state,
+            set_plugin_state,
+            get_plugin_programs,
+            select_plugin_program,
             restart_audio_engine,
             list_presets,
             save_preset,
@@ -513,8 +877,14 @@ pub fn run() {
             delete_preset,
             export_preset,
             import_preset,
+            export_vst_preset,
+            import_vst_preset,
             toggle_global_mute,
             set_global_mute,
+            get_midi_mappings,
+            set_midi_mappings,
+            start_midi_learn,
+            cancel_midi_learn,
             set_input_gain,
             set_noise_reduction,
             set_output_gain,
@@ -526,6 +896,9 @@ pub fn run() {
             set_autostart_enabled,
             set_input_channels,
             set_channel_scan,
+            set_transport,
+            get_midi_ports,
+            set_midi_input,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,591 @@
+//! MIDI controller mappings ("MIDI learn").
+//!
+//! A mapping binds a CC or note on one port and channel to a host action: a mixer setting, a
+//! plugin's gain/bypass/mute or one of its parameters. The host keeps them in
+//! `midi_mappings.json` in the config dir and hands them to the engine
+//! (`Command::SetMidiMappings`), whose `MidiMapper` turns incoming messages into actions.
+//! Learn mode (`Command::MidiLearn`) binds the next CC or note that arrives to a target.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fs_util::write_atomic;
+use crate::vst_host::events::MidiMessage;
+
+pub const MIDI_MAPPINGS_FILE: &str = "midi_mappings.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiTriggerKind {
+    Cc,
+    Note,
+}
+
+/// The control a mapping listens to.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MidiTrigger {
+    /// Port name as listed by `GetMidiPorts`.
+    pub port: String,
+    /// 1-16
+    pub channel: u8,
+    pub kind: MidiTriggerKind,
+    /// Controller or note number, 0-127.
+    pub number: u8,
+}
+
+/// Plugin a mapping controls. Runtime ids change with every load, so it is named by its path
+/// and class and which instance of that plugin it is in chain order (0 = the first).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct PluginRef {
+    pub path: String,
+    /// Audio module class within the binary (see `VstPlugin::class_id`); `None` matches
+    /// any class, as in mappings saved before modules could hold several.
+    #[serde(default)]
+    pub class_id: Option<String>,
+    #[serde(default)]
+    pub occurrence: usize,
+}
+
+/// What a mapping controls; mirrors the `Command` it ends up sending.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", content = "payload")]
+pub enum MidiTarget {
+    Gain { plugin: PluginRef },
+    Bypass { plugin: PluginRef },
+    Mute { plugin: PluginRef },
+    Parameter { plugin: PluginRef, param_id: u32 },
+    GlobalMute,
+    InputGain,
+    OutputGain,
+    NoiseReduction,
+}
+
+impl MidiTarget {
+    /// On/off targets; the others take a value between the mapping's `min` and `max`.
+    pub fn is_switch(&self) -> bool {
+        matches!(
+            self,
+            Self::Bypass { .. } | Self::Mute { .. } | Self::GlobalMute | Self::NoiseReduction
+        )
+    }
+
+    /// Range a freshly learned mapping gets: gains up to +6 dB (the UI's sliders),
+    /// parameters their full normalized range.
+    fn default_range(&self) -> (f64, f64) {
+        match self {
+            Self::Gain { .. } | Self::InputGain | Self::OutputGain => (0.0, 2.0),
+            _ => (0.0, 1.0),
+        }
+    }
+}
+
+/// Shapes a CC value before it is scaled to `min..max`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiCurve {
+    #[default]
+    Linear,
+    /// Squared: finer steps at the low end.
+    Exponential,
+    /// Square root: finer steps at the high end.
+    Logarithmic,
+}
+
+impl MidiCurve {
+    fn apply(self, x: f64) -> f64 {
+        match self {
+            Self::Linear => x,
+            Self::Exponential => x * x,
+            Self::Logarithmic => x.sqrt(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MidiMode {
+    /// Follows the control: a CC value (or note velocity) scaled to `min..max`. Switch
+    /// targets are on while the control is at least half way up (or the note is held).
+    #[default]
+    Continuous,
+    /// Each press (note on, or a CC going from below 64 to 64 or above) flips the target;
+    /// value targets alternate between `max` and `min`.
+    Toggle,
+    /// On (or `max`) while held, off (or `min`) when released.
+    Momentary,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MidiMapping {
+    pub trigger: MidiTrigger,
+    pub target: MidiTarget,
+    /// Value at the bottom of the control's travel. May be above `max` to invert it.
+    #[serde(default)]
+    pub min: f64,
+    #[serde(default = "default_max")]
+    pub max: f64,
+    #[serde(default)]
+    pub curve: MidiCurve,
+    #[serde(default)]
+    pub mode: MidiMode,
+}
+
+fn default_max() -> f64 {
+    1.0
+}
+
+impl MidiMapping {
+    /// Defaults for a learned control: buttons and pads toggle, knobs and faders follow.
+    fn learned(trigger: MidiTrigger, target: MidiTarget) -> Self {
+        let (min, max) = target.default_range();
+        let mode = if target.is_switch() || trigger.kind == MidiTriggerKind::Note {
+            MidiMode::Toggle
+        } else {
+            MidiMode::Continuous
+        };
+        Self {
+            trigger,
+            target,
+            min,
+            max,
+            curve: MidiCurve::Linear,
+            mode,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if !(1..=16).contains(&self.trigger.channel) {
+            return Err(anyhow!("MIDI channel must be between 1 and 16"));
+        }
+        if self.trigger.number > 127 {
+            return Err(anyhow!("MIDI controller/note number must be 0-127"));
+        }
+        if !self.min.is_finite() || !self.max.is_finite() {
+            return Err(anyhow!("MIDI mapping range must be finite"));
+        }
+        Ok(())
+    }
+
+    fn scale(&self, x: f64) -> f64 {
+        self.min + self.curve.apply(x) * (self.max - self.min)
+    }
+}
+
+/// Adds `mapping`, dropping any older one for the same target so a target has one control.
+pub fn upsert(mappings: &mut Vec<MidiMapping>, mapping: MidiMapping) {
+    mappings.retain(|m| m.target != mapping.target);
+    mappings.push(mapping);
+}
+
+/// What a mapping asks of its target after a message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MidiAction {
+    /// New value for a value target.
+    Set(f64),
+    /// Switch target on or off.
+    Switch(bool),
+    /// Flip a switch target.
+    Toggle,
+}
+
+/// A CC or note message, as far as mappings are concerned.
+struct ControlInput {
+    channel: u8,
+    kind: MidiTriggerKind,
+    number: u8,
+    value: u8,
+}
+
+impl ControlInput {
+    fn parse(msg: &MidiMessage) -> Option<Self> {
+        let bytes = msg.bytes();
+        if bytes.len() < 3 {
+            return None;
+        }
+        let (kind, value) = match bytes[0] & 0xF0 {
+            0xB0 => (MidiTriggerKind::Cc, bytes[2]),
+            0x90 => (MidiTriggerKind::Note, bytes[2]),
+            0x80 => (MidiTriggerKind::Note, 0),
+            _ => return None,
+        };
+        Some(Self {
+            channel: (bytes[0] & 0x0F) + 1,
+            kind,
+            number: bytes[1],
+            value,
+        })
+    }
+
+    fn pressed(&self) -> bool {
+        match self.kind {
+            MidiTriggerKind::Cc => self.value >= 64,
+            MidiTriggerKind::Note => self.value > 0,
+        }
+    }
+
+    fn matches(&self, port: &str, trigger: &MidiTrigger) -> bool {
+        trigger.port == port
+            && trigger.channel == self.channel
+            && trigger.kind == self.kind
+            && trigger.number == self.number
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct ControlState {
+    held: bool,
+    // Toggle mode on a value target: currently at `max`
+    toggled: bool,
+}
+
+/// Engine-side evaluation of the mappings, including learn mode.
+pub struct MidiMapper {
+    mappings: Vec<MidiMapping>,
+    state: Vec<ControlState>,
+    learning: Option<MidiTarget>,
+}
+
+impl MidiMapper {
+    pub fn new() -> Self {
+        Self {
+            mappings: Vec::new(),
+            state: Vec::new(),
+            learning: None,
+        }
+    }
+
+    pub fn set_mappings(&mut self, mappings: Vec<MidiMapping>) {
+        self.state = vec![ControlState::default(); mappings.len()];
+        self.mappings = mappings;
+    }
+
+    /// Ports the mappings listen on.
+    pub fn ports(&self) -> HashSet<String> {
+        self.mappings
+            .iter()
+            .map(|m| m.trigger.port.clone())
+            .collect()
+    }
+
+    /// Binds the next CC or note to `target`; `None` cancels.
+    pub fn learn(&mut self, target: Option<MidiTarget>) {
+        self.learning = target;
+    }
+
+    pub fn is_learning(&self) -> bool {
+        self.learning.is_some()
+    }
+
+    /// In learn mode, turns the first CC or note into a mapping for the learn target and adds
+    /// it (replacing the target's previous mapping). `None` if not learning or `msg` is not a
+    /// CC or note.
+    pub fn try_learn(&mut self, port: &str, msg: &MidiMessage) -> Option<MidiMapping> {
+        self.learning.as_ref()?;
+        let input = ControlInput::parse(msg)?;
+        // A note off cannot start a binding; its note on came before learn mode did
+        if input.kind == MidiTriggerKind::Note && !input.pressed() {
+            return None;
+        }
+        let target = self.learning.take()?;
+        let trigger = MidiTrigger {
+            port: port.to_string(),
+            channel: input.channel,
+            kind: input.kind,
+            number: input.number,
+        };
+        let mapping = MidiMapping::learned(trigger, target);
+        let mut mappings = std::mem::take(&mut self.mappings);
+        upsert(&mut mappings, mapping.clone());
+        self.set_mappings(mappings);
+        // The control is down right now; its release must not count as a second press
+        if let Some(state) = self.state.last_mut() {
+            state.held = input.pressed();
+        }
+        Some(mapping)
+    }
+
+    /// Actions for every mapping `msg` triggers.
+    pub fn handle(&mut self, port: &str, msg: &MidiMessage) -> Vec<(MidiTarget, MidiAction)> {
+        let Some(input) = ControlInput::parse(msg) else {
+            return Vec::new();
+        };
+        let pressed = input.pressed();
+        let mut actions = Vec::new();
+
+        for (mapping, state) in self.mappings.iter().zip(self.state.iter_mut()) {
+            if !input.matches(port, &mapping.trigger) {
+                continue;
+            }
+            let was_held = state.held;
+            state.held = pressed;
+            let switch = mapping.target.is_switch();
+
+            let action = match mapping.mode {
+                MidiMode::Continuous if switch => Some(MidiAction::Switch(pressed)),
+                // Note offs carry no position to follow
+                MidiMode::Continuous if input.kind == MidiTriggerKind::Note && !pressed => None,
+                MidiMode::Continuous => {
+                    Some(MidiAction::Set(mapping.scale(input.value as f64 / 127.0)))
+                }
+                MidiMode::Toggle if !pressed || was_held => None,
+                MidiMode::Toggle if switch => Some(MidiAction::Toggle),
+                MidiMode::Toggle => {
+                    state.toggled = !state.toggled;
+                    Some(MidiAction::Set(if state.toggled {
+                        mapping.max
+                    } else {
+                        mapping.min
+                    }))
+                }
+                MidiMode::Momentary if pressed == was_held => None,
+                MidiMode::Momentary if switch => Some(MidiAction::Switch(pressed)),
+                MidiMode::Momentary => Some(MidiAction::Set(if pressed {
+                    mapping.max
+                } else {
+                    mapping.min
+                })),
+            };
+            if let Some(action) = action {
+                actions.push((mapping.target.clone(), action));
+            }
+        }
+        actions
+    }
+}
+
+pub fn mappings_path(config_dir: &Path) -> PathBuf {
+    config_dir.join(MIDI_MAPPINGS_FILE)
+}
+
+/// Empty if the file does not exist yet.
+pub fn load_mappings(path: &Path) -> Result<Vec<MidiMapping>> {
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+    serde_json::from_slice(&json).with_context(|| format!("Invalid MIDI mapping file {:?}", path))
+}
+
+pub fn save_mappings(path: &Path, mappings: &[MidiMapping]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {:?}", dir))?;
+    }
+    let json = serde_json::to_vec_pretty(mappings)?;
+    write_atomic(path, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestDir;
+
+    fn msg(bytes: &[u8]) -> MidiMessage {
+        MidiMessage::new(bytes).unwrap()
+    }
+
+    fn mapping(kind: MidiTriggerKind, target: MidiTarget, mode: MidiMode) -> MidiMapping {
+        MidiMapping {
+            trigger: MidiTrigger {
+                port: "nanoKONTROL".to_string(),
+                channel: 1,
+                kind,
+                number: 7,
+            },
+            target,
+            min: 0.0,
+            max: 2.0,
+            curve: MidiCurve::Linear,
+            mode,
+        }
+    }
+
+    #[test]
+    fn continuous_cc_scales_with_curve() {
+        let mut gain = mapping(
+            MidiTriggerKind::Cc,
+            MidiTarget::InputGain,
+            MidiMode::Continuous,
+        );
+        gain.curve = MidiCurve::Exponential;
+        let mut mapper = MidiMapper::new();
+        mapper.set_mappings(vec![gain]);
+
+        let actions = mapper.handle("nanoKONTROL", &msg(&[0xB0, 7, 127]));
+        assert_eq!(actions, vec![(MidiTarget::InputGain, MidiAction::Set(2.0))]);
+        let actions = mapper.handle("nanoKONTROL", &msg(&[0xB0, 7, 0]));
+        assert_eq!(actions, vec![(MidiTarget::InputGain, MidiAction::Set(0.0))]);
+        let Some((_, MidiAction::Set(mid))) =
+            mapper.handle("nanoKONTROL", &msg(&[0xB0, 7, 64])).pop()
+        else {
+            panic!("expected a value");
+        };
+        assert!((mid - 2.0 * (64.0f64 / 127.0).powi(2)).abs() < 1e-9);
+
+        // Other channel, controller or port
+        assert!(mapper
+            .handle("nanoKONTROL", &msg(&[0xB1, 7, 10]))
+            .is_empty());
+        assert!(mapper
+            .handle("nanoKONTROL", &msg(&[0xB0, 8, 10]))
+            .is_empty());
+        assert!(mapper.handle("Other", &msg(&[0xB0, 7, 10])).is_empty());
+    }
+
+    #[test]
+    fn toggle_flips_once_per_press() {
+        let mut mapper = MidiMapper::new();
+        mapper.set_mappings(vec![
+            mapping(
+                MidiTriggerKind::Cc,
+                MidiTarget::GlobalMute,
+                MidiMode::Toggle,
+            ),
+            mapping(
+                MidiTriggerKind::Note,
+                MidiTarget::OutputGain,
+                MidiMode::Toggle,
+            ),
+        ]);
+
+        // A button sending 127/0, with a repeated 127 in between
+        let mut flips = Vec::new();
+        for value in [127, 127, 0, 127, 0] {
+            flips.extend(mapper.handle("nanoKONTROL", &msg(&[0xB0, 7, value])));
+        }
+        assert_eq!(
+            flips,
+            vec![
+                (MidiTarget::GlobalMute, MidiAction::Toggle),
+                (MidiTarget::GlobalMute, MidiAction::Toggle),
+            ]
+        );
+
+        // Value targets alternate between max and min; note offs (both forms) do nothing
+        let mut values = Vec::new();
+        for bytes in [[0x90, 7, 100], [0x80, 7, 0], [0x90, 7, 1], [0x90, 7, 0]] {
+            values.extend(mapper.handle("nanoKONTROL", &msg(&bytes)));
+        }
+        assert_eq!(
+            values,
+            vec![
+                (MidiTarget::OutputGain, MidiAction::Set(2.0)),
+                (MidiTarget::OutputGain, MidiAction::Set(0.0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn momentary_follows_press_and_release() {
+        let plugin = PluginRef {
+            path: "C:/VST3/Comp.vst3".to_string(),
+            class_id: None,
+            occurrence: 0,
+        };
+        let bypass = MidiTarget::Bypass {
+            plugin: plugin.clone(),
+        };
+        let mut mapper = MidiMapper::new();
+        mapper.set_mappings(vec![mapping(
+            MidiTriggerKind::Note,
+            bypass.clone(),
+            MidiMode::Momentary,
+        )]);
+
+        let mut actions = Vec::new();
+        for bytes in [[0x90, 7, 90], [0x90, 7, 90], [0x80, 7, 64]] {
+            actions.extend(mapper.handle("nanoKONTROL", &msg(&bytes)));
+        }
+        assert_eq!(
+            actions,
+            vec![
+                (bypass.clone(), MidiAction::Switch(true)),
+                (bypass, MidiAction::Switch(false)),
+            ]
+        );
+    }
+
+    #[test]
+    fn learn_binds_next_control_and_replaces_target() {
+        let mut mapper = MidiMapper::new();
+        mapper.set_mappings(vec![mapping(
+            MidiTriggerKind::Cc,
+            MidiTarget::GlobalMute,
+            MidiMode::Toggle,
+        )]);
+        assert!(mapper
+            .try_learn("nanoKONTROL", &msg(&[0xB0, 1, 127]))
+            .is_none());
+
+        mapper.learn(Some(MidiTarget::GlobalMute));
+        // Note offs and non-control messages are skipped
+        assert!(mapper.try_learn("Pads", &msg(&[0x82, 36, 0])).is_none());
+        assert!(mapper.try_learn("Pads", &msg(&[0xE2, 0, 64])).is_none());
+        let learned = mapper.try_learn("Pads", &msg(&[0x92, 36, 100])).unwrap();
+        assert!(!mapper.is_learning());
+        assert_eq!(
+            learned.trigger,
+            MidiTrigger {
+                port: "Pads".to_string(),
+                channel: 3,
+                kind: MidiTriggerKind::Note,
+                number: 36,
+            }
+        );
+        assert_eq!(learned.mode, MidiMode::Toggle);
+        assert_eq!(mapper.ports(), HashSet::from(["Pads".to_string()]));
+
+        // Releasing the pad used for learning does not toggle; the next press does
+        assert!(mapper.handle("Pads", &msg(&[0x82, 36, 0])).is_empty());
+        assert_eq!(
+            mapper.handle("Pads", &msg(&[0x92, 36, 100])),
+            vec![(MidiTarget::GlobalMute, MidiAction::Toggle)]
+        );
+
+        // Knobs learned on a value target follow the control over the target's range
+        mapper.learn(Some(MidiTarget::InputGain));
+        let learned = mapper.try_learn("Pads", &msg(&[0xB0, 20, 3])).unwrap();
+        assert_eq!(learned.mode, MidiMode::Continuous);
+        assert_eq!((learned.min, learned.max), (0.0, 2.0));
+    }
+
+    #[test]
+    fn mappings_round_trip_with_defaults() {
+        let dir = TestDir::new("midi-map");
+        let path = mappings_path(&dir);
+        assert!(load_mappings(&path).unwrap().is_empty());
+
+        let mut param = mapping(
+            MidiTriggerKind::Cc,
+            MidiTarget::Parameter {
+                plugin: PluginRef {
+                    path: "C:/VST3/EQ.vst3".to_string(),
+                    class_id: Some("0123456789ABCDEF0123456789ABCDEF".to_string()),
+                    occurrence: 1,
+                },
+                param_id: 42,
+            },
+            MidiMode::Continuous,
+        );
+        param.curve = MidiCurve::Logarithmic;
+        save_mappings(&path, std::slice::from_ref(&param)).unwrap();
+        assert_eq!(load_mappings(&path).unwrap(), vec![param]);
+
+        // Hand-written files may leave out everything but the trigger and target
+        let minimal = r#"[{
+            "trigger": { "port": "X", "channel": 16, "kind": "note", "number": 60 },
+            "target": { "type": "Mute", "payload": { "plugin": { "path": "a.vst3" } } }
+        }]"#;
+        let parsed: Vec<MidiMapping> = serde_json::from_str(minimal).unwrap();
+        assert_eq!((parsed[0].min, parsed[0].max), (0.0, 1.0));
+        assert_eq!(parsed[0].mode, MidiMode::Continuous);
+        assert!(parsed[0].validate().is_ok());
+        let MidiTarget::Mute { plugin } = &parsed[0].target else {
+            panic!("not a mute target");
+        };
+        assert_eq!((plugin.class_id.as_deref(), plugin.occurrence), (None, 0));
+    }
+}
@@ -0,0 +1,166 @@
+//! Engine session autosave.
+//!
+//! The host periodically asks the engine for its full state (`Command::GetSession`) and keeps
+//! it in `session.json` next to the last audio config. When a fresh engine process starts
+//! audio, the host replays the file (`ApplyChain` plus the mixer settings), so the chain
+//! survives webview reloads, engine crashes and app restarts.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::fs_util::write_atomic;
+use crate::ipc::TransportSettings;
+use crate::vst_host::presets::PresetPlugin;
+
+pub const SESSION_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionState {
+    #[serde(default)]
+    pub version: u32,
+    /// Plugin chain in processing order, with each plugin's state chunk.
+    pub plugins: Vec<PresetPlugin>,
+    pub input_gain: f32,
+    pub output_gain: f32,
+    pub noise_reduction_enabled: bool,
+    pub noise_reduction_mode: String,
+    pub input_channels: (usize, usize),
+    #[serde(default)]
+    pub transport: TransportSettings,
+}
+
+pub fn save_session(path: &Path, session: &SessionState) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create session directory {:?}", dir))?;
+    }
+
+    let json = serde_json::to_vec_pretty(session)?;
+    write_atomic(path, &json)
+}
+
+/// Returns `None` if there is no session yet.
+pub fn load_session(path: &Path) -> Result<Option<SessionState>> {
+    let json = match fs::read(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+    let session = serde_json::from_slice(&json)
+        .with_context(|| format!("Invalid session file {:?}", path))?;
+    Ok(Some(session))
+}
+
+/// Moves a session that could not be restored out of the way (kept as `*.failed.json` for
+/// support), so the next engine start does not trip over it again.
+pub fn set_aside(path: &Path) -> Result<PathBuf> {
+    let failed = path.with_extension("failed.json");
+    fs::rename(path, &failed).with_context(|| format!("Failed to move {:?}", path))?;
+    Ok(failed)
+}
+
+pub fn discard(path: &Path) -> Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e).with_context(|| format!("Failed to delete {:?}", path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ipc::MidiAssignment;
+    use crate::test_util::TestDir;
+
+    fn sample() -> SessionState {
+        SessionState {
+            version: SESSION_VERSION,
+            plugins: vec![PresetPlugin {
+                path: r"C:\VST3\Comp.vst3".to_string(),
+                class_id: Some("00112233445566778899AABBCCDDEEFF".to_string()),
+                name: "Comp".to_string(),
+                vendor: String::new(),
+                version: String::new(),
+                enabled: false,
+                muted: true,
+                gain: 0.5,
+                state: Some("AAEC".to_string()),
+                controller_state: Some("BQY=".to_string()),
+                midi_input: Some(MidiAssignment {
+                    port: "Auralyn MIDI In".to_string(),
+                    channel: Some(1),
+                }),
+            }],
+            input_gain: 1.5,
+            output_gain: 0.8,
+            noise_reduction_enabled: true,
+            noise_reduction_mode: "high".to_string(),
+            input_channels: (2, 3),
+            transport: TransportSettings {
+                tempo: 92.5,
+                time_sig_numerator: 6,
+                time_sig_denominator: 8,
+                playing: true,
+            },
+        }
+    }
+
+    #[test]
+    fn save_and_load_round_trip() {
+        let dir = TestDir::new("session-round-trip");
+        let path = dir.join("session.json");
+        assert!(load_session(&path).unwrap().is_none());
+
+        save_session(&path, &sample()).unwrap();
+        // Overwrite an existing file
+        save_session(&path, &sample()).unwrap();
+        assert!(!dir.join("session.json.tmp").exists());
+
+        let loaded = load_session(&path).unwrap().unwrap();
+        assert_eq!(loaded.version, SESSION_VERSION);
+        assert_eq!(loaded.input_channels, (2, 3));
+        assert_eq!(loaded.plugins.len(), 1);
+        assert!(loaded.plugins[0].muted);
+        assert_eq!(loaded.plugins[0].state.as_deref(), Some("AAEC"));
+        assert_eq!(loaded.plugins[0].controller_state.as_deref(), Some("BQY="));
+        assert_eq!(loaded.plugins[0].midi_input, sample().plugins[0].midi_input);
+        assert_eq!(loaded.transport, sample().transport);
+    }
+
+    #[test]
+    fn session_without_transport_gets_default() {
+        let mut json = serde_json::to_value(sample()).unwrap();
+        json.as_object_mut().unwrap().remove("transport");
+        let loaded: SessionState = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.transport, TransportSettings::default());
+    }
+
+    #[test]
+    fn plugin_without_controller_state_still_loads() {
+        let mut json = serde_json::to_value(sample()).unwrap();
+        json["plugins"][0]
+            .as_object_mut()
+            .unwrap()
+            .remove("controller_state");
+        let loaded: SessionState = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.plugins[0].state.as_deref(), Some("AAEC"));
+        assert!(loaded.plugins[0].controller_state.is_none());
+    }
+
+    #[test]
+    fn corrupt_session_can_be_set_aside() {
+        let dir = TestDir::new("session-corrupt");
+        let path = dir.join("session.json");
+        fs::write(&path, b"{ not json").unwrap();
+
+        assert!(load_session(&path).is_err());
+        let failed = set_aside(&path).unwrap();
+        assert!(failed.exists());
+        assert!(load_session(&path).unwrap().is_none());
+
+        discard(&path).unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+//! Engine supervisor.
+//!
+//! Watches the audio engine sidecar for an unexpected exit or a missed heartbeat and brings
+//! it back: respawn with backoff, restart audio with the last device config and replay the
+//! autosaved session. The engine reports which plugin it was loading, processing or editing
+//! when it died (see `audio_engine::crash_guard`); a plugin that takes the engine down
+//! repeatedly is quarantined, i.e. restored bypassed, restored bypassed without its saved
+//! state if it crashes reading that state back, or left out entirely if it crashes while
+//! loading.
+//!
+//! UI events:
+//! - `engine-crashed` (`EngineCrashReport`) for every failure,
+//! - `engine-restarted` (`EngineRestarted`) once the engine is back,
+//! - `engine-restart-failed` (message) if the restart itself failed,
+//! - `audio-error` (legacy crash dialog) when the supervisor gives up.
+
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use crate::audio::{AudioConfig, AudioState};
+use crate::ipc::{CrashCulprit, CrashPhase};
+use crate::vst_host::presets::PresetPlugin;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const BACKOFF_MAX: Duration = Duration::from_secs(10);
+/// More crashes than this within `RESTART_WINDOW` and the supervisor gives up.
+const MAX_RESTARTS: usize = 5;
+const RESTART_WINDOW: Duration = Duration::from_secs(120);
+/// Crashes blamed on the same plugin before it is quarantined.
+const QUARANTINE_AFTER: u32 = 2;
+
+/// Why an engine process went away.
+#[derive(Debug, Clone)]
+pub struct EngineFailure {
+    pub reason: String,
+    pub culprit: Option<CrashCulprit>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum QuarantineAction {
+    /// Restored, but bypassed.
+    Bypass,
+    /// Restored bypassed and with its saved state dropped (it crashes restoring that state).
+    ResetState,
+    /// Left out of the restored chain (it crashes before it could be bypassed).
+    Remove,
+}
+
+fn plugin_key(path: &str) -> String {
+    if cfg!(windows) {
+        path.replace('/', "\\").to_ascii_lowercase()
+    } else {
+        path.to_string()
+    }
+}
+
+/// Plugins the supervisor keeps out of automatic restores, by path.
+#[derive(Default)]
+pub struct Quarantine(HashMap<String, QuarantineAction>);
+
+impl Quarantine {
+    pub fn insert(&mut self, path: &str, action: QuarantineAction) {
+        self.0.insert(plugin_key(path), action);
+    }
+
+    /// Bypasses, resets or drops quarantined entries of a chain about to be restored. Returns
+    /// the plugins it touched.
+    pub fn apply(&self, plugins: &mut Vec<PresetPlugin>) -> Vec<(String, QuarantineAction)> {
+        let mut touched = Vec::new();
+        plugins.retain_mut(|plugin| match self.0.get(&plugin_key(&plugin.path)) {
+            Some(QuarantineAction::Remove) => {
+                touched.push((plugin.name.clone(), QuarantineAction::Remove));
+                false
+            }
+            Some(QuarantineAction::Bypass) => {
+                if plugin.enabled {
+                    plugin.enabled = false;
+                    touched.push((plugin.name.clone(), QuarantineAction::Bypass));
+                }
+                true
+            }
+            Some(QuarantineAction::ResetState) => {
+                // Bypassing alone would still hand it the chunk it crashed on
+                if plugin.enabled || plugin.state.is_some() || plugin.controller_state.is_some() {
+                    plugin.enabled = false;
+                    plugin.state = None;
+                    plugin.controller_state = None;
+                    touched.push((plugin.name.clone(), QuarantineAction::ResetState));
+                }
+                true
+            }
+            None => true,
+        });
+        touched
+    }
+}
+
+/// What to do about one failure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrashVerdict {
+    /// Failures within the restart window, this one included.
+    pub attempt: u32,
+    /// `None` once the engine crashed too often to keep restarting it.
+    pub restart_in: Option<Duration>,
+    pub quarantine: Option<QuarantineAction>,
+}
+
+/// Restart budget and per-plugin crash counts.
+#[derive(Default)]
+pub struct CrashTracker {
+    recent: VecDeque<Instant>,
+    crashes_by_plugin: HashMap<String, u32>,
+}
+
+impl CrashTracker {
+    pub fn record(&mut self, failure: &EngineFailure, now: Instant) -> CrashVerdict {
+        while self
+            .recent
+            .front()
+            .is_some_and(|&t| now.duration_since(t) > RESTART_WINDOW)
+        {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(now);
+        let attempt = self.recent.len();
+
+        let restart_in = (attempt <= MAX_RESTARTS).then(|| {
+            BACKOFF_BASE
+                .saturating_mul(1u32 << (attempt - 1).min(16))
+                .min(BACKOFF_MAX)
+        });
+
+        let quarantine = failure.culprit.as_ref().and_then(|culprit| {
+            let count = self
+                .crashes_by_plugin
+                .entry(plugin_key(&culprit.path))
+                .or_insert(0);
+            *count += 1;
+            (*count >= QUARANTINE_AFTER).then_some(match culprit.phase {
+                CrashPhase::Loading => QuarantineAction::Remove,
+                CrashPhase::State => QuarantineAction::ResetState,
+                CrashPhase::Processing | CrashPhase::Editor => QuarantineAction::Bypass,
+            })
+        });
+
+        CrashVerdict {
+            attempt: attempt as u32,
+            restart_in,
+            quarantine,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineCrashReport {
+    pub reason: String,
+    pub culprit: Option<CrashCulprit>,
+    pub quarantine: Option<QuarantineAction>,
+    pub attempt: u32,
+    /// `None` if the supervisor gave up.
+    pub restart_in_ms: Option<u64>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EngineRestarted {
+    pub attempt: u32,
+    /// Running device config, `None` if audio was not running before the crash.
+    pub audio: Option<AudioConfig>,
+}
+
+/// Starts the supervisor thread. It runs for the lifetime of the app.
+pub fn spawn(state: AudioState, app: AppHandle) {
+    let result = thread::Builder::new()
+        .name("engine-supervisor".to_string())
+        .spawn(move || {
+            let mut tracker = CrashTracker::default();
+            loop {
+                thread::sleep(POLL_INTERVAL);
+                let failure = match state.0.lock() {
+                    Ok(mut host) => host.check_engine_health(),
+                    Err(_) => None,
+                };
+                if let Some(failure) = failure {
+                    handle_failure(&state, &app, &mut tracker, failure);
+                }
+            }
+        });
+    if let Err(e) = result {
+        log::error!("Failed to start engine supervisor: {}", e);
+    }
+}
+
+fn handle_failure(
+    state: &AudioState,
+    app: &AppHandle,
+    tracker: &mut CrashTracker,
+    failure: EngineFailure,
+) {
+    let verdict = tracker.record(&failure, Instant::now());
+    match &failure.culprit {
+        Some(culprit) => log::error!(
+            "Audio Engine failed ({}) while {:?} {} ({})",
+            failure.reason,
+            culprit.phase,
+            culprit.name,
+            culprit.path
+        ),
+        None => log::error!("Audio Engine failed ({})", failure.reason),
+    }
+
+    if let (Some(culprit), Some(action)) = (&failure.culprit, verdict.quarantine) {
+        log::warn!("Quarantining {} ({:?})", culprit.name, action);
+        if let Ok(mut host) = state.0.lock() {
+            host.quarantine_plugin(&culprit.path, action);
+        }
+    }
+
+    let _ = app.emit(
+        "engine-crashed",
+        EngineCrashReport {
+            reason: failure.reason.clone(),
+            culprit: failure.culprit.clone(),
+            quarantine: verdict.quarantine,
+            attempt: verdict.attempt,
+            restart_in_ms: verdict.restart_in.map(|d| d.as_millis() as u64),
+        },
+    );
+
+    let Some(delay) = verdict.restart_in else {
+        log::error!(
+            "Audio Engine crashed {} times within {:?}; not restarting it again",
+            verdict.attempt,
+            RESTART_WINDOW
+        );
+        if let Ok(mut host) = state.0.lock() {
+            host.cancel_restart();
+        }
+        let _ = app.emit("audio-error", "Audio Engine Process Exited (Crash?)");
+        return;
+    };
+
+    thread::sleep(delay);
+    match state.restart_after_crash() {
+        Ok(audio) => {
+            log::info!("Audio Engine restarted (attempt {})", verdict.attempt);
+            let _ = app.emit(
+                "engine-restarted",
+                EngineRestarted {
+                    attempt: verdict.attempt,
+                    audio,
+                },
+            );
+        }
+        Err(e) => {
+            log::error!("Audio Engine restart failed: {:#}", e);
+            let _ = app.emit("engine-restart-failed", format!("{:#}", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(culprit: Option<(CrashPhase, &str)>) -> EngineFailure {
+        EngineFailure {
+            reason: "exit code: 0xc0000005".to_string(),
+            culprit: culprit.map(|(phase, path)| CrashCulprit {
+                phase,
+                path: path.to_string(),
+                name: "Comp".to_string(),
+            }),
+        }
+    }
+
+    fn entry(path: &str) -> PresetPlugin {
+        PresetPlugin {
+            path: path.to_string(),
+            class_id: None,
+            name: path.to_string(),
+            vendor: String::new(),
+            version: String::new(),
+            enabled: true,
+            muted: false,
+            gain: 1.0,
+            state: None,
+            controller_state: None,
+            midi_input: None,
+        }
+    }
+
+    #[test]
+    fn backs_off_and_gives_up_within_the_window() {
+        let mut tracker = CrashTracker::default();
+        let start = Instant::now();
+
+        let delays: Vec<_> = (0..MAX_RESTARTS as u64)
+            .map(|i| {
+                tracker
+                    .record(&failure(None), start + Duration::from_secs(i))
+                    .restart_in
+            })
+            .collect();
+        assert_eq!(delays[0], Some(BACKOFF_BASE));
+        assert_eq!(delays[1], Some(BACKOFF_BASE * 2));
+        assert!(delays.iter().all(|d| d.is_some_and(|d| d <= BACKOFF_MAX)));
+
+        let verdict = tracker.record(&failure(None), start + Duration::from_secs(10));
+        assert_eq!(verdict.attempt, MAX_RESTARTS as u32 + 1);
+        assert_eq!(verdict.restart_in, None);
+
+        // Old crashes age out of the window
+        let later = start + RESTART_WINDOW + Duration::from_secs(30);
+        let verdict = tracker.record(&failure(None), later);
+        assert_eq!(verdict.attempt, 1);
+        assert_eq!(verdict.restart_in, Some(BACKOFF_BASE));
+    }
+
+    #[test]
+    fn quarantines_repeat_offenders_by_phase() {
+        let mut tracker = CrashTracker::default();
+        let now = Instant::now();
+
+        let first = tracker.record(&failure(Some((CrashPhase::Processing, "A.vst3"))), now);
+        assert_eq!(first.quarantine, None);
+        let other = tracker.record(&failure(Some((CrashPhase::Processing, "B.vst3"))), now);
+        assert_eq!(other.quarantine, None);
+        let second = tracker.record(&failure(Some((CrashPhase::Editor, "A.vst3"))), now);
+        assert_eq!(second.quarantine, Some(QuarantineAction::Bypass));
+
+        tracker.record(&failure(Some((CrashPhase::Loading, "C.vst3"))), now);
+        let loading = tracker.record(&failure(Some((CrashPhase::Loading, "C.vst3"))), now);
+        assert_eq!(loading.quarantine, Some(QuarantineAction::Remove));
+
+        tracker.record(&failure(Some((CrashPhase::State, "D.vst3"))), now);
+        let state = tracker.record(&failure(Some((CrashPhase::State, "D.vst3"))), now);
+        assert_eq!(state.quarantine, Some(QuarantineAction::ResetState));
+    }
+
+    #[test]
+    fn quarantine_bypasses_or_drops_restored_plugins() {
+        let mut quarantine = Quarantine::default();
+        quarantine.insert("A.vst3", QuarantineAction::Bypass);
+        quarantine.insert("C.vst3", QuarantineAction::Remove);
+
+        let mut chain = vec![entry("A.vst3"), entry("B.vst3"), entry("C.vst3")];
+        let touched = quarantine.apply(&mut chain);
+
+        assert_eq!(
+            touched,
+            vec![
+                ("A.vst3".to_string(), QuarantineAction::Bypass),
+                ("C.vst3".to_string(), QuarantineAction::Remove),
+            ]
+        );
+        assert_eq!(chain.len(), 2);
+        assert!(!chain[0].enabled);
+        assert!(chain[1].enabled);
+    }
+
+    #[test]
+    fn state_culprits_are_restored_without_their_state() {
+        let mut tracker = CrashTracker::default();
+        let now = Instant::now();
+        let mut quarantine = Quarantine::default();
+        for _ in 0..QUARANTINE_AFTER {
+            let failure = failure(Some((CrashPhase::State, "A.vst3")));
+            if let Some(action) = tracker.record(&failure, now).quarantine {
+                quarantine.insert("A.vst3", action);
+            }
+        }
+
+        let mut saved = entry("A.vst3");
+        saved.state = Some("AAEC".to_string());
+        saved.controller_state = Some("AwQF".to_string());
+        let mut chain = vec![saved, entry("B.vst3")];
+        let touched = quarantine.apply(&mut chain);
+
+        assert_eq!(
+            touched,
+            vec![("A.vst3".to_string(), QuarantineAction::ResetState)]
+        );
+        assert_eq!(chain.len(), 2);
+        assert!(!chain[0].enabled);
+        assert_eq!(chain[0].state, None);
+        assert_eq!(chain[0].controller_state, None);
+        assert!(chain[1].enabled);
+    }
+}
@@ -1,68 +1,251 @@
+//! Plugins the scanner will not load again.
+//!
+//! A binary goes on the list when `plugin_scanner` crashes on it, hangs or prints garbage.
+//! Each entry records why and when, and the file's modification time: once the binary
+//! changes (an update, a reinstall) it is scanned again, and taken off the list if it passes.
+
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::fs_util::write_atomic;
+use crate::vst_host::scan_cache::FileStamp;
+
+pub const BLACKLIST_FILE: &str = "vst_blacklist.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlacklistReason {
+    /// The scanner exited with an error or was killed by the plugin.
+    Crash,
+    Timeout,
+    /// The scanner finished but its output was not a scan result.
+    BadOutput,
+    /// Migrated from the old list, which kept no reason.
+    Unknown,
+}
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlacklistEntry {
+    pub reason: BlacklistReason,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Last failure, milliseconds since the Unix epoch.
+    pub blacklisted_at: u64,
+    /// Modification time of the binary at the last failure, in milliseconds.
+    #[serde(default)]
+    pub modified: Option<u64>,
+    pub failures: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredBlacklist {
+    Current {
+        entries: BTreeMap<String, BlacklistEntry>,
+    },
+    /// `{"paths": [...]}`, written before entries had details.
+    Legacy { paths: HashSet<String> },
+}
+
+#[derive(Debug, Serialize, Default)]
 pub struct Blacklist {
-    pub paths: HashSet<String>,
+    entries: BTreeMap<String, BlacklistEntry>,
     #[serde(skip)]
     file_path: PathBuf,
 }
 
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn modified_ms(path: &str) -> Option<u64> {
+    FileStamp::of(Path::new(path))
+        .ok()
+        .map(|stamp| stamp.modified)
+}
+
 impl Blacklist {
-    pub fn new(config_dir: &PathBuf) -> Self {
-        let file_path = config_dir.join("vst_blacklist.json");
-        let mut list = if file_path.exists() {
+    pub fn new(config_dir: &Path) -> Self {
+        let file_path = config_dir.join(BLACKLIST_FILE);
+        let mut list = Blacklist::default();
+        let mut migrated = false;
+        if file_path.exists() {
             match fs::read_to_string(&file_path) {
-                Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
-                    log::error!("Failed to parse blacklist: {}", e);
-                    Blacklist::default()
-                }),
-                Err(e) => {
-                    log::error!("Failed to read blacklist: {}", e);
-                    Blacklist::default()
-                }
+                Ok(s) => match serde_json::from_str(&s) {
+                    Ok(StoredBlacklist::Current { entries }) => list.entries = entries,
+                    Ok(StoredBlacklist::Legacy { paths }) => {
+                        // Kept until the binary changes, as before
+                        let now = now_ms();
+                        for path in paths {
+                            let entry = BlacklistEntry {
+                                reason: BlacklistReason::Unknown,
+                                exit_code: None,
+                                error: None,
+                                blacklisted_at: now,
+                                modified: modified_ms(&path),
+                                failures: 1,
+                            };
+                            list.entries.insert(path, entry);
+                        }
+                        migrated = true;
+                    }
+                    Err(e) => log::error!("Failed to parse blacklist: {}", e),
+                },
+                Err(e) => log::error!("Failed to read blacklist: {}", e),
             }
-        } else {
-            Blacklist::default()
-        };
+        }
         list.file_path = file_path;
+        if migrated {
+            log::info!("Migrated {} blacklist entries", list.entries.len());
+            list.save();
+        }
         list
     }
 
     pub fn save(&self) {
         match serde_json::to_string_pretty(self) {
             Ok(s) => {
-                if let Err(e) = fs::write(&self.file_path, s) {
-                    log::error!("Failed to save blacklist: {}", e);
+                if let Err(e) = write_atomic(&self.file_path, s.as_bytes()) {
+                    log::error!("Failed to save blacklist: {:#}", e);
                 }
             }
             Err(e) => log::error!("Failed to serialize blacklist: {}", e),
         }
     }
 
-    pub fn add(&mut self, path: &str) {
-        if self.paths.insert(path.to_string()) {
-            log::warn!("Blacklisting plugin: {}", path);
-            self.save();
-        }
+    /// Records a failure of `path`, counting it if the binary was already listed.
+    pub fn add(
+        &mut self,
+        path: &str,
+        reason: BlacklistReason,
+        exit_code: Option<i32>,
+        error: Option<String>,
+    ) {
+        let failures = self.entries.get(path).map_or(0, |e| e.failures) + 1;
+        log::warn!(
+            "Blacklisting plugin: {} ({:?}, failure {})",
+            path,
+            reason,
+            failures
+        );
+        self.entries.insert(
+            path.to_string(),
+            BlacklistEntry {
+                reason,
+                exit_code,
+                error,
+                blacklisted_at: now_ms(),
+                modified: modified_ms(path),
+                failures,
+            },
+        );
+        self.save();
     }
 
-    pub fn remove(&mut self, path: &str) {
-        if self.paths.remove(path) {
+    pub fn remove(&mut self, path: &str) -> bool {
+        if self.entries.remove(path).is_some() {
             log::info!("Removing plugin from blacklist: {}", path);
             self.save();
+            true
+        } else {
+            false
         }
     }
 
     pub fn contains(&self, path: &str) -> bool {
-        self.paths.contains(path)
+        self.entries.contains_key(path)
+    }
+
+    /// Whether the scanner should skip `path`: it is listed and the binary has not changed
+    /// since it last failed.
+    pub fn is_blocked(&self, path: &str) -> bool {
+        self.entries
+            .get(path)
+            .is_some_and(|entry| entry.modified == modified_ms(path))
+    }
+
+    pub fn get(&self, path: &str) -> Option<&BlacklistEntry> {
+        self.entries.get(path)
+    }
+
+    /// Entries by plugin path.
+    pub fn entries(&self) -> &BTreeMap<String, BlacklistEntry> {
+        &self.entries
     }
 
     pub fn clear(&mut self) {
-        self.paths.clear();
+        self.entries.clear();
         self.save();
         log::info!("Blacklist cleared.");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestDir;
+    use std::time::Duration;
+
+    #[test]
+    fn old_path_list_migrates() {
+        let dir = TestDir::new("blacklist-migrate");
+        let binary = dir.join("Crashy.vst3").to_string_lossy().into_owned();
+        fs::write(&binary, b"module").unwrap();
+        let legacy = serde_json::json!({ "paths": [binary] });
+        fs::write(dir.join(BLACKLIST_FILE), legacy.to_string()).unwrap();
+
+        let list = Blacklist::new(&dir);
+        let entry = list.get(&binary).unwrap();
+        assert_eq!(entry.reason, BlacklistReason::Unknown);
+        assert_eq!(entry.failures, 1);
+        assert!(list.is_blocked(&binary));
+
+        // Saved in the new format
+        let saved = fs::read_to_string(dir.join(BLACKLIST_FILE)).unwrap();
+        assert!(saved.contains("\"entries\""));
+        assert_eq!(Blacklist::new(&dir).entries(), list.entries());
+    }
+
+    #[test]
+    fn changed_binaries_are_retested() {
+        let dir = TestDir::new("blacklist-retest");
+        let binary = dir.join("Hangs.vst3");
+        let path = binary.to_string_lossy().into_owned();
+        fs::write(&binary, b"module").unwrap();
+
+        let mut list = Blacklist::new(&dir);
+        list.add(
+            &path,
+            BlacklistReason::Timeout,
+            None,
+            Some("Timed out".into()),
+        );
+        list.add(&path, BlacklistReason::Crash, Some(-1073741819), None);
+        let entry = list.get(&path).unwrap();
+        assert_eq!(entry.reason, BlacklistReason::Crash);
+        assert_eq!(entry.exit_code, Some(-1073741819));
+        assert_eq!(entry.failures, 2);
+        assert!(list.is_blocked(&path));
+
+        // Updated plugin
+        let file = fs::File::options().write(true).open(&binary).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+        let list = Blacklist::new(&dir);
+        assert!(list.contains(&path));
+        assert!(!list.is_blocked(&path));
+
+        let mut list = list;
+        assert!(list.remove(&path));
+        assert!(!list.remove(&path));
+        assert!(Blacklist::new(&dir).entries().is_empty());
+    }
+}
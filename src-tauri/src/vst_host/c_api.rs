@@ -4,6 +4,9 @@ pub type TUID = [u8; 16];
 pub type TResult = i32;
 
 pub const K_RESULT_OK: TResult = 0;
+pub const K_RESULT_FALSE: TResult = 1;
+pub const K_NO_INTERFACE: TResult = -2147467262;
+pub const K_INVALID_ARGUMENT: TResult = -2147467261;
 
 pub const IID_IUNKNOWN: TUID = [
     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xC0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
@@ -145,6 +148,55 @@ pub struct IPluginFactoryVtbl {
     ) -> TResult,
 }
 
+/// Category of the classes that implement `IComponent` (`kVstAudioEffectClass`).
+pub const K_VST_AUDIO_EFFECT_CLASS: &str = "Audio Module Class";
+
+/// `PClassInfo2`, from `IPluginFactory2::getClassInfo2`.
+#[repr(C)]
+pub struct PClassInfo2 {
+    pub cid: TUID,
+    pub cardinality: i32,
+    pub category: [c_char; 32],
+    pub name: [c_char; 64],
+    pub class_flags: u32,
+    /// '|'-separated, e.g. "Fx|Dynamics"
+    pub sub_categories: [c_char; 128],
+    pub vendor: [c_char; 64],
+    pub version: [c_char; 64],
+    pub sdk_version: [c_char; 64],
+}
+
+/// `PClassInfoW`, from `IPluginFactory3::getClassInfoUnicode`: `PClassInfo2` with UTF-16
+/// name, vendor and versions.
+#[repr(C)]
+pub struct PClassInfoW {
+    pub cid: TUID,
+    pub cardinality: i32,
+    pub category: [c_char; 32],
+    pub name: [u16; 64],
+    pub class_flags: u32,
+    pub sub_categories: [c_char; 128],
+    pub vendor: [u16; 64],
+    pub version: [u16; 64],
+    pub sdk_version: [u16; 64],
+}
+
+#[repr(C)]
+pub struct IPluginFactory2Vtbl {
+    pub base: IPluginFactoryVtbl,
+    pub get_class_info2:
+        unsafe extern "system" fn(this: *mut c_void, index: i32, info: *mut PClassInfo2) -> TResult,
+}
+
+#[repr(C)]
+pub struct IPluginFactory3Vtbl {
+    pub base: IPluginFactory2Vtbl,
+    pub get_class_info_unicode:
+        unsafe extern "system" fn(this: *mut c_void, index: i32, info: *mut PClassInfoW) -> TResult,
+    pub set_host_context:
+        unsafe extern "system" fn(this: *mut c_void, context: *mut c_void) -> TResult,
+}
+
 #[repr(C)]
 pub struct IComponentVtbl {
     pub base: FUnknownVtbl,
@@ -232,6 +284,7 @@ pub struct ProcessData {
 
 pub const K_REALTIME: i32 = 0;
 pub const K_SAMPLE_32: i32 = 0;
+pub const K_SAMPLE_64: i32 = 1;
 
 #[repr(C)]
 pub struct ProcessSetup {
@@ -241,6 +294,52 @@ pub struct ProcessSetup {
     pub sample_rate: f64,
 }
 
+// ProcessContext::state flags (StatesAndFlags, subset we fill)
+pub const K_PLAYING: u32 = 1 << 1;
+pub const K_SYSTEM_TIME_VALID: u32 = 1 << 8;
+pub const K_PROJECT_TIME_MUSIC_VALID: u32 = 1 << 9;
+pub const K_TEMPO_VALID: u32 = 1 << 10;
+pub const K_BAR_POSITION_VALID: u32 = 1 << 11;
+pub const K_TIME_SIG_VALID: u32 = 1 << 13;
+pub const K_CONT_TIME_VALID: u32 = 1 << 17;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Chord {
+    pub key_note: u8,
+    pub root_note: u8,
+    pub chord_mask: i16,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FrameRate {
+    pub frames_per_second: u32,
+    pub flags: u32,
+}
+
+/// Transport and timing info for one process call (`ProcessData::process_context`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessContext {
+    pub state: u32,
+    pub sample_rate: f64,
+    pub project_time_samples: i64,
+    pub system_time: i64, // Nanoseconds
+    pub continous_time_samples: i64,
+    pub project_time_music: f64, // Quarter notes
+    pub bar_position_music: f64,
+    pub cycle_start_music: f64,
+    pub cycle_end_music: f64,
+    pub tempo: f64, // BPM
+    pub time_sig_numerator: i32,
+    pub time_sig_denominator: i32,
+    pub chord: Chord,
+    pub smpte_offset_subframes: i32,
+    pub frame_rate: FrameRate,
+    pub samples_to_next_clock: i32,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct ViewRect {
@@ -323,6 +422,13 @@ pub struct IComponentHandlerVtbl {
     pub restart_component: unsafe extern "system" fn(this: *mut c_void, flags: i32) -> TResult,
 }
 
+// RestartFlags passed to IComponentHandler::restartComponent (subset we act on)
+pub const K_RELOAD_COMPONENT: i32 = 1 << 0;
+pub const K_IO_CHANGED: i32 = 1 << 1;
+pub const K_PARAM_VALUES_CHANGED: i32 = 1 << 2;
+pub const K_LATENCY_CHANGED: i32 = 1 << 3;
+pub const K_PARAM_TITLES_CHANGED: i32 = 1 << 4;
+
 #[repr(C)]
 pub struct ITimerHandlerVtbl {
     pub base: FUnknownVtbl,
@@ -396,6 +502,172 @@ pub struct IEditControllerVtbl {
         unsafe extern "system" fn(this: *mut c_void, name: *const c_char) -> *mut c_void, // returns IPlugView*
 }
 
+pub type String128 = [u16; 128];
+
+#[repr(C)]
+pub struct ParameterInfo {
+    pub id: u32,
+    pub title: String128,
+    pub short_title: String128,
+    pub units: String128,
+    pub step_count: i32,
+    pub default_normalized_value: f64,
+    pub unit_id: i32,
+    pub flags: i32,
+}
+
+#[repr(C)]
+pub struct IParameterChangesVtbl {
+    pub base: FUnknownVtbl,
+    pub get_parameter_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    // Returns IParamValueQueue*
+    pub get_parameter_data: unsafe extern "system" fn(this: *mut c_void, index: i32) -> *mut c_void,
+    pub add_parameter_data: unsafe extern "system" fn(
+        this: *mut c_void,
+        id: *const u32,
+        index: *mut i32,
+    ) -> *mut c_void,
+}
+
+#[repr(C)]
+pub struct IParamValueQueueVtbl {
+    pub base: FUnknownVtbl,
+    pub get_parameter_id: unsafe extern "system" fn(this: *mut c_void) -> u32,
+    pub get_point_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    pub get_point: unsafe extern "system" fn(
+        this: *mut c_void,
+        index: i32,
+        sample_offset: *mut i32,
+        value: *mut f64,
+    ) -> TResult,
+    pub add_point: unsafe extern "system" fn(
+        this: *mut c_void,
+        sample_offset: i32,
+        value: f64,
+        index: *mut i32,
+    ) -> TResult,
+}
+
+// MediaTypes / BusDirections
+pub const K_AUDIO: i32 = 0;
+pub const K_EVENT: i32 = 1;
+pub const K_INPUT: i32 = 0;
+pub const K_OUTPUT: i32 = 1;
+// BusTypes
+pub const K_MAIN: i32 = 0;
+pub const K_AUX: i32 = 1;
+
+// SpeakerArrangement
+pub const K_SPEAKER_STEREO: u64 = 0b11; // kSpeakerL | kSpeakerR
+pub const K_SPEAKER_MONO: u64 = 1 << 19; // kSpeakerM
+
+#[repr(C)]
+pub struct BusInfo {
+    pub media_type: i32,
+    pub direction: i32,
+    pub channel_count: i32,
+    pub name: String128,
+    pub bus_type: i32,
+    pub flags: u32,
+}
+
+// Event::EventTypes
+pub const K_NOTE_ON_EVENT: u16 = 0;
+pub const K_NOTE_OFF_EVENT: u16 = 1;
+pub const K_DATA_EVENT: u16 = 2;
+pub const K_POLY_PRESSURE_EVENT: u16 = 3;
+// Event::EventFlags
+pub const K_IS_LIVE: u16 = 1 << 0;
+// DataEvent::DataTypes
+pub const K_MIDI_SYS_EX: u32 = 0;
+
+// ControllerNumbers beyond the 128 MIDI CCs (IMidiMapping)
+pub const K_AFTER_TOUCH: i16 = 128;
+pub const K_PITCH_BEND: i16 = 129;
+pub const K_CTRL_PROGRAM_CHANGE: i16 = 130;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct NoteOnEvent {
+    pub channel: i16,
+    pub pitch: i16,
+    pub tuning: f32,
+    pub velocity: f32,
+    pub length: i32,
+    pub note_id: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct NoteOffEvent {
+    pub channel: i16,
+    pub pitch: i16,
+    pub velocity: f32,
+    pub note_id: i32,
+    pub tuning: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DataEvent {
+    pub size: u32,
+    pub type_: u32,
+    pub bytes: *const u8,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct PolyPressureEvent {
+    pub channel: i16,
+    pub pitch: i16,
+    pub pressure: f32,
+    pub note_id: i32,
+}
+
+/// Only the event types the host sends; `_size` pads the union to the SDK's largest member
+/// (NoteExpressionTextEvent).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union EventPayload {
+    pub note_on: NoteOnEvent,
+    pub note_off: NoteOffEvent,
+    pub data: DataEvent,
+    pub poly_pressure: PolyPressureEvent,
+    pub _size: [u64; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Event {
+    pub bus_index: i32,
+    pub sample_offset: i32,
+    pub ppq_position: f64,
+    pub flags: u16,
+    pub type_: u16,
+    pub payload: EventPayload,
+}
+
+#[repr(C)]
+pub struct IEventListVtbl {
+    pub base: FUnknownVtbl,
+    pub get_event_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    pub get_event:
+        unsafe extern "system" fn(this: *mut c_void, index: i32, event: *mut Event) -> TResult,
+    pub add_event: unsafe extern "system" fn(this: *mut c_void, event: *mut Event) -> TResult,
+}
+
+#[repr(C)]
+pub struct IMidiMappingVtbl {
+    pub base: FUnknownVtbl,
+    pub get_midi_controller_assignment: unsafe extern "system" fn(
+        this: *mut c_void,
+        bus_index: i32,
+        channel: i16,
+        midi_controller_number: i16,
+        id: *mut u32,
+    ) -> TResult,
+}
+
 #[repr(C)]
 pub struct IUnitHandlerVtbl {
     pub base: FUnknownVtbl,
@@ -405,6 +677,79 @@ pub struct IUnitHandlerVtbl {
         unsafe extern "system" fn(this: *mut c_void, list_id: i32, program_index: i32) -> TResult,
 }
 
+/// `kNoProgramListId`: the unit has no program list.
+pub const K_NO_PROGRAM_LIST_ID: i32 = -1;
+
+#[repr(C)]
+pub struct UnitInfo {
+    pub id: i32,
+    pub parent_unit_id: i32,
+    pub name: String128,
+    pub program_list_id: i32,
+}
+
+#[repr(C)]
+pub struct ProgramListInfo {
+    pub id: i32,
+    pub name: String128,
+    pub program_count: i32,
+}
+
+#[repr(C)]
+pub struct IUnitInfoVtbl {
+    pub base: FUnknownVtbl,
+    pub get_unit_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    pub get_unit_info: unsafe extern "system" fn(
+        this: *mut c_void,
+        unit_index: i32,
+        info: *mut UnitInfo,
+    ) -> TResult,
+    pub get_program_list_count: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    pub get_program_list_info: unsafe extern "system" fn(
+        this: *mut c_void,
+        list_index: i32,
+        info: *mut ProgramListInfo,
+    ) -> TResult,
+    pub get_program_name: unsafe extern "system" fn(
+        this: *mut c_void,
+        list_id: i32,
+        program_index: i32,
+        name: *mut u16,
+    ) -> TResult,
+    pub get_program_info: unsafe extern "system" fn(
+        this: *mut c_void,
+        list_id: i32,
+        program_index: i32,
+        attribute_id: *const c_char,
+        attribute_value: *mut u16,
+    ) -> TResult,
+    pub has_program_pitch_names:
+        unsafe extern "system" fn(this: *mut c_void, list_id: i32, program_index: i32) -> TResult,
+    pub get_program_pitch_name: unsafe extern "system" fn(
+        this: *mut c_void,
+        list_id: i32,
+        program_index: i32,
+        midi_pitch: i16,
+        name: *mut u16,
+    ) -> TResult,
+    pub get_selected_unit: unsafe extern "system" fn(this: *mut c_void) -> i32,
+    pub select_unit: unsafe extern "system" fn(this: *mut c_void, unit_id: i32) -> TResult,
+    pub get_unit_by_bus: unsafe extern "system" fn(
+        this: *mut c_void,
+        media_type: i32,
+        direction: i32,
+        bus_index: i32,
+        channel: i32,
+        unit_id: *mut i32,
+    ) -> TResult,
+    pub set_unit_program_data: unsafe extern "system" fn(
+        this: *mut c_void,
+        list_or_unit_id: i32,
+        program_index: i32,
+        data: *mut c_void,
+    ) -> TResult,
+}
+
 #[repr(C)]
 pub struct IContextMenuVtbl {
     pub base: FUnknownVtbl,
@@ -438,3 +783,37 @@ pub struct IParameterFinderVtbl {
         result_tag: *mut u32,
     ) -> TResult,
 }
+
+/// The vtable behind a COM-style interface pointer.
+pub(crate) unsafe fn get_vtbl<T>(ptr: *mut c_void) -> &'static T {
+    &**(ptr as *mut *mut T)
+}
+
+/// `obj`'s `iid` interface, or `None` if it doesn't implement it.
+pub(crate) unsafe fn query_interface(obj: *mut c_void, iid: &TUID) -> Option<*mut c_void> {
+    let unknown = get_vtbl::<FUnknownVtbl>(obj);
+    let mut out: *mut c_void = std::ptr::null_mut();
+    let res = (unknown.query_interface)(obj, iid, &mut out);
+    (res == K_RESULT_OK && !out.is_null()).then_some(out)
+}
+
+/// A NUL-terminated UTF-16 buffer (`String128`, the wide `PClassInfoW` fields) as a string.
+pub(crate) fn utf16_string(buf: &[u16]) -> String {
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf16_lossy(&buf[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_string_stops_at_nul() {
+        let mut s: String128 = [0; 128];
+        for (dst, src) in s.iter_mut().zip("Gain".encode_utf16()) {
+            *dst = src;
+        }
+        assert_eq!(utf16_string(&s), "Gain");
+        assert_eq!(utf16_string(&[0x41; 128]).len(), 128);
+    }
+}
@@ -0,0 +1,272 @@
+//! What an effect class can do, found by instantiating it in `plugin_scanner`.
+//!
+//! The probe lists the audio buses, tries the arrangements the engine uses (one bus in, one
+//! out, stereo or mono), and reads latency, tail, parameter count, whether the controller
+//! has an editor and whether 64-bit processing is supported. The host uses this to warn
+//! about plugins that will not fit the live chain, such as mono-only or sidechain effects,
+//! before they are loaded.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use vst3::Interface;
+use vst3::Steinberg::Vst::{IAudioProcessor, IComponent, IEditController};
+
+use crate::vst_host::c_api::{
+    get_vtbl, query_interface, utf16_string, BusInfo, FUnknownVtbl, IAudioProcessorVtbl,
+    IComponentVtbl, IEditControllerVtbl, IPluginFactoryVtbl, ProcessSetup, K_AUDIO, K_AUX, K_INPUT,
+    K_OUTPUT, K_REALTIME, K_RESULT_OK, K_SAMPLE_32, K_SAMPLE_64, K_SPEAKER_MONO, K_SPEAKER_STEREO,
+    TUID,
+};
+
+/// Setup used to read latency and tail; most plugins report them once active.
+const PROBE_SAMPLE_RATE: f64 = 48000.0;
+const PROBE_BLOCK_SIZE: i32 = 512;
+
+/// Start of the line `plugin_scanner` writes to stderr as it starts probing a class, so the
+/// host can tell a module with many classes from a hung one.
+pub const PROBE_MARKER: &str = "plugin_scanner: probing";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AudioBus {
+    pub name: String,
+    /// Channels in the bus's default arrangement.
+    pub channels: i32,
+    /// Aux input buses are sidechains.
+    pub aux: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct PluginCapabilities {
+    pub inputs: Vec<AudioBus>,
+    pub outputs: Vec<AudioBus>,
+    /// Whether the plugin takes one stereo bus in and one out, as the engine sets it up.
+    pub accepts_stereo: bool,
+    /// The same with mono buses.
+    pub accepts_mono: bool,
+    pub has_sidechain: bool,
+    pub latency_samples: u32,
+    /// `u32::MAX` for an infinite tail.
+    pub tail_samples: u32,
+    pub parameter_count: i32,
+    pub has_editor: bool,
+    pub supports_64bit: bool,
+}
+
+impl PluginCapabilities {
+    /// Only works in mono: refuses stereo but takes mono, or has mono main buses only.
+    pub fn is_mono_only(&self) -> bool {
+        if self.accepts_stereo {
+            return false;
+        }
+        let mut main_buses = self
+            .inputs
+            .iter()
+            .chain(&self.outputs)
+            .filter(|bus| !bus.aux)
+            .peekable();
+        self.accepts_mono
+            || (main_buses.peek().is_some() && main_buses.all(|bus| bus.channels == 1))
+    }
+}
+
+unsafe fn release(obj: *mut c_void) {
+    (get_vtbl::<FUnknownVtbl>(obj).release)(obj);
+}
+
+unsafe fn audio_buses(component: *mut c_void, direction: i32) -> Vec<AudioBus> {
+    let vtbl = get_vtbl::<IComponentVtbl>(component);
+    let count = (vtbl.get_bus_count)(component, K_AUDIO, direction);
+    (0..count)
+        .filter_map(|index| {
+            let mut info: BusInfo = std::mem::zeroed();
+            let res = (vtbl.get_bus_info)(
+                component,
+                K_AUDIO,
+                direction,
+                index,
+                &mut info as *mut _ as *mut c_void,
+            );
+            (res == K_RESULT_OK).then(|| AudioBus {
+                name: utf16_string(&info.name),
+                channels: info.channel_count,
+                aux: info.bus_type == K_AUX,
+            })
+        })
+        .collect()
+}
+
+unsafe fn probe_processor(
+    component: *mut c_void,
+    processor: *mut c_void,
+    caps: &mut PluginCapabilities,
+) {
+    let vtbl = get_vtbl::<IAudioProcessorVtbl>(processor);
+    let accepts = |arrangement: u64| {
+        let (mut input, mut output) = (arrangement, arrangement);
+        (vtbl.set_bus_arrangements)(
+            processor,
+            &mut input as *mut _ as *mut c_void,
+            1,
+            &mut output as *mut _ as *mut c_void,
+            1,
+        ) == K_RESULT_OK
+    };
+    caps.accepts_mono = accepts(K_SPEAKER_MONO);
+    // Last, so the plugin is activated in stereo like in the engine
+    caps.accepts_stereo = accepts(K_SPEAKER_STEREO);
+    caps.supports_64bit = (vtbl.can_process_sample_size)(processor, K_SAMPLE_64) == K_RESULT_OK;
+
+    let mut setup = ProcessSetup {
+        process_mode: K_REALTIME,
+        symbolic_sample_size: K_SAMPLE_32,
+        max_samples_per_block: PROBE_BLOCK_SIZE,
+        sample_rate: PROBE_SAMPLE_RATE,
+    };
+    let comp_vtbl = get_vtbl::<IComponentVtbl>(component);
+    let active = (vtbl.setup_processing)(processor, &mut setup as *mut _ as *mut c_void)
+        == K_RESULT_OK
+        && (comp_vtbl.set_active)(component, 1) == K_RESULT_OK;
+    caps.latency_samples = (vtbl.get_latency_samples)(processor);
+    caps.tail_samples = (vtbl.get_tail_samples)(processor);
+    if active {
+        (comp_vtbl.set_active)(component, 0);
+    }
+}
+
+unsafe fn probe_view(controller: *mut c_void, caps: &mut PluginCapabilities) {
+    let vtbl = get_vtbl::<IEditControllerVtbl>(controller);
+    caps.parameter_count = (vtbl.get_parameter_count)(controller).max(0);
+    let mut view = (vtbl.create_view)(controller, c"editor".as_ptr());
+    if view.is_null() {
+        // As in `open_editor`, some plugins only answer a null name
+        view = (vtbl.create_view)(controller, std::ptr::null());
+    }
+    caps.has_editor = !view.is_null();
+    if !view.is_null() {
+        release(view);
+    }
+}
+
+unsafe fn probe_controller(
+    factory: *mut c_void,
+    component: *mut c_void,
+    host_context: *mut c_void,
+    caps: &mut PluginCapabilities,
+) {
+    // Single-component plugins implement the controller on the component itself
+    if let Some(controller) = query_interface(component, &IEditController::IID) {
+        probe_view(controller, caps);
+        release(controller);
+        return;
+    }
+
+    let mut controller_cid: TUID = [0; 16];
+    let comp_vtbl = get_vtbl::<IComponentVtbl>(component);
+    if (comp_vtbl.get_controller_class_id)(component, &mut controller_cid) != K_RESULT_OK {
+        return;
+    }
+    let factory_vtbl = get_vtbl::<IPluginFactoryVtbl>(factory);
+    let mut controller: *mut c_void = std::ptr::null_mut();
+    let res = (factory_vtbl.create_instance)(
+        factory,
+        &controller_cid,
+        &IEditController::IID,
+        &mut controller,
+    );
+    if res != K_RESULT_OK || controller.is_null() {
+        return;
+    }
+    let vtbl = get_vtbl::<IEditControllerVtbl>(controller);
+    if (vtbl.initialize)(controller, host_context) == K_RESULT_OK {
+        probe_view(controller, caps);
+        (vtbl.terminate)(controller);
+    }
+    release(controller);
+}
+
+/// Instantiates class `cid` of `factory` and reads its capabilities.
+///
+/// # Safety
+/// `factory` must point to a live `IPluginFactory` and `host_context` to an
+/// `IHostApplication`. The plugin code runs in this process; call it only where a crash is
+/// contained, i.e. in `plugin_scanner`.
+pub unsafe fn probe(
+    factory: *mut c_void,
+    cid: &TUID,
+    host_context: *mut c_void,
+) -> Result<PluginCapabilities> {
+    let factory_vtbl = get_vtbl::<IPluginFactoryVtbl>(factory);
+    let mut component: *mut c_void = std::ptr::null_mut();
+    let res = (factory_vtbl.create_instance)(factory, cid, &IComponent::IID, &mut component);
+    if res != K_RESULT_OK || component.is_null() {
+        return Err(anyhow!("Failed to create the component ({})", res));
+    }
+    let comp_vtbl = get_vtbl::<IComponentVtbl>(component);
+    let res = (comp_vtbl.initialize)(component, host_context);
+    if res != K_RESULT_OK {
+        release(component);
+        return Err(anyhow!("Failed to initialize the component ({})", res));
+    }
+
+    let mut caps = PluginCapabilities {
+        inputs: audio_buses(component, K_INPUT),
+        outputs: audio_buses(component, K_OUTPUT),
+        ..Default::default()
+    };
+    caps.has_sidechain = caps.inputs.iter().any(|bus| bus.aux);
+    if let Some(processor) = query_interface(component, &IAudioProcessor::IID) {
+        probe_processor(component, processor, &mut caps);
+        release(processor);
+    }
+    probe_controller(factory, component, host_context, &mut caps);
+
+    (comp_vtbl.terminate)(component);
+    release(component);
+    Ok(caps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bus(channels: i32, aux: bool) -> AudioBus {
+        AudioBus {
+            name: String::new(),
+            channels,
+            aux,
+        }
+    }
+
+    #[test]
+    fn mono_only_plugins_are_recognised() {
+        let stereo = PluginCapabilities {
+            inputs: vec![bus(2, false), bus(1, true)],
+            outputs: vec![bus(2, false)],
+            accepts_stereo: true,
+            accepts_mono: true,
+            ..Default::default()
+        };
+        assert!(!stereo.is_mono_only());
+
+        let refuses_stereo = PluginCapabilities {
+            accepts_stereo: false,
+            ..stereo.clone()
+        };
+        assert!(refuses_stereo.is_mono_only());
+
+        // Answers neither arrangement, but its main buses say mono
+        let mono_buses = PluginCapabilities {
+            inputs: vec![bus(1, false), bus(2, true)],
+            outputs: vec![bus(1, false)],
+            ..Default::default()
+        };
+        assert!(mono_buses.is_mono_only());
+        let silent = PluginCapabilities {
+            accepts_mono: false,
+            ..refuses_stereo
+        };
+        assert!(!silent.is_mono_only());
+        assert!(!PluginCapabilities::default().is_mono_only());
+    }
+}
@@ -0,0 +1,222 @@
+//! The classes a VST3 module exports.
+//!
+//! One `.vst3` can hold several effects (a vendor's suite, a shell plugin). `IPluginFactory2`
+//! and `IPluginFactory3` describe each class with sub-categories ("Fx|Dynamics"), vendor and
+//! versions; a plain `IPluginFactory` only gives the category and name.
+
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, c_void};
+use vst3::Interface;
+use vst3::Steinberg::{IPluginFactory2, IPluginFactory3};
+
+use crate::vst_host::c_api::{
+    get_vtbl, query_interface, utf16_string, FUnknownVtbl, IPluginFactory2Vtbl,
+    IPluginFactory3Vtbl, IPluginFactoryVtbl, PClassInfo, PClassInfo2, PClassInfoW, K_RESULT_OK,
+    K_VST_AUDIO_EFFECT_CLASS, TUID,
+};
+use crate::vst_host::capabilities::PluginCapabilities;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PluginClass {
+    /// `format_class_id` of the class UID.
+    pub class_id: String,
+    pub name: String,
+    /// Factory category, e.g. "Audio Module Class".
+    pub category: String,
+    /// As the SDK writes them, e.g. "Fx|Dynamics". Empty for `IPluginFactory` classes.
+    #[serde(default)]
+    pub sub_categories: String,
+    #[serde(default)]
+    pub vendor: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub sdk_version: String,
+    /// Filled in by `plugin_scanner` for audio effects it could instantiate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<PluginCapabilities>,
+}
+
+impl PluginClass {
+    /// Whether the class is an effect we can put in the chain. Instruments and the like say
+    /// so in their sub-categories; classes that have none are taken to be effects.
+    pub fn is_audio_effect(&self) -> bool {
+        self.category == K_VST_AUDIO_EFFECT_CLASS
+            && (self.sub_categories.is_empty()
+                || split_sub_categories(&self.sub_categories).any(|c| c.eq_ignore_ascii_case("Fx")))
+    }
+}
+
+pub fn split_sub_categories(s: &str) -> impl Iterator<Item = &str> {
+    s.split('|').map(str::trim).filter(|c| !c.is_empty())
+}
+
+/// Formats a class ID as its 16 bytes in memory order (32 upper-case hex digits), the form
+/// used for `class_id` throughout the host. Not the SDK's `FUID::toString`, which differs on
+/// Windows (see `vstpreset::class_id_to_string`).
+pub fn format_class_id(cid: &TUID) -> String {
+    cid.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// The reverse of `format_class_id`.
+pub fn parse_class_id(s: &str) -> Option<TUID> {
+    if s.len() != 32 || !s.is_ascii() {
+        return None;
+    }
+    let mut cid: TUID = [0; 16];
+    for (i, byte) in cid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(cid)
+}
+
+fn c_string(buf: &[c_char]) -> String {
+    let bytes: Vec<u8> = buf
+        .iter()
+        .take_while(|&&c| c != 0)
+        .map(|&c| c as u8)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+unsafe fn class_info_unicode(factory3: *mut c_void, index: i32) -> Option<PluginClass> {
+    let vtbl = get_vtbl::<IPluginFactory3Vtbl>(factory3);
+    let mut info: PClassInfoW = std::mem::zeroed();
+    if (vtbl.get_class_info_unicode)(factory3, index, &mut info) != K_RESULT_OK {
+        return None;
+    }
+    Some(PluginClass {
+        class_id: format_class_id(&info.cid),
+        name: utf16_string(&info.name),
+        category: c_string(&info.category),
+        sub_categories: c_string(&info.sub_categories),
+        vendor: utf16_string(&info.vendor),
+        version: utf16_string(&info.version),
+        sdk_version: utf16_string(&info.sdk_version),
+        capabilities: None,
+    })
+}
+
+unsafe fn class_info2(factory2: *mut c_void, index: i32) -> Option<PluginClass> {
+    let vtbl = get_vtbl::<IPluginFactory2Vtbl>(factory2);
+    let mut info: PClassInfo2 = std::mem::zeroed();
+    if (vtbl.get_class_info2)(factory2, index, &mut info) != K_RESULT_OK {
+        return None;
+    }
+    Some(PluginClass {
+        class_id: format_class_id(&info.cid),
+        name: c_string(&info.name),
+        category: c_string(&info.category),
+        sub_categories: c_string(&info.sub_categories),
+        vendor: c_string(&info.vendor),
+        version: c_string(&info.version),
+        sdk_version: c_string(&info.sdk_version),
+        capabilities: None,
+    })
+}
+
+unsafe fn class_info(factory: *mut c_void, index: i32) -> Option<PluginClass> {
+    let vtbl = get_vtbl::<IPluginFactoryVtbl>(factory);
+    let mut info: PClassInfo = std::mem::zeroed();
+    if (vtbl.get_class_info)(factory, index, &mut info) != K_RESULT_OK {
+        return None;
+    }
+    Some(PluginClass {
+        class_id: format_class_id(&info.cid),
+        name: c_string(&info.name),
+        category: c_string(&info.category),
+        sub_categories: String::new(),
+        vendor: String::new(),
+        version: String::new(),
+        sdk_version: String::new(),
+        capabilities: None,
+    })
+}
+
+/// Every class `factory` exports, described by the newest factory interface it implements.
+///
+/// # Safety
+/// `factory` must point to a live `IPluginFactory`.
+pub unsafe fn factory_classes(factory: *mut c_void) -> Vec<PluginClass> {
+    let factory3 = query_interface(factory, &IPluginFactory3::IID);
+    let factory2 = match factory3 {
+        Some(_) => None,
+        None => query_interface(factory, &IPluginFactory2::IID),
+    };
+
+    let vtbl = get_vtbl::<IPluginFactoryVtbl>(factory);
+    let count = (vtbl.count_classes)(factory);
+    let mut classes = Vec::with_capacity(count.max(0) as usize);
+    for index in 0..count {
+        let class = factory3
+            .and_then(|f| class_info_unicode(f, index))
+            .or_else(|| factory2.and_then(|f| class_info2(f, index)))
+            .or_else(|| class_info(factory, index));
+        classes.extend(class);
+    }
+
+    for extended in [factory3, factory2].into_iter().flatten() {
+        (get_vtbl::<FUnknownVtbl>(extended).release)(extended);
+    }
+    classes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(category: &str, sub_categories: &str) -> PluginClass {
+        PluginClass {
+            class_id: format_class_id(&[0xAB; 16]),
+            name: "Comp".to_string(),
+            category: category.to_string(),
+            sub_categories: sub_categories.to_string(),
+            vendor: String::new(),
+            version: String::new(),
+            sdk_version: String::new(),
+            capabilities: None,
+        }
+    }
+
+    #[test]
+    fn only_audio_effects_are_offered() {
+        assert!(class(K_VST_AUDIO_EFFECT_CLASS, "Fx|Dynamics").is_audio_effect());
+        assert!(class(K_VST_AUDIO_EFFECT_CLASS, "Analyzer | fx").is_audio_effect());
+        assert!(class(K_VST_AUDIO_EFFECT_CLASS, "").is_audio_effect());
+        assert!(!class(K_VST_AUDIO_EFFECT_CLASS, "Instrument|Synth").is_audio_effect());
+        assert!(!class("Component Controller Class", "").is_audio_effect());
+
+        assert_eq!(
+            split_sub_categories("Fx||EQ ").collect::<Vec<_>>(),
+            vec!["Fx", "EQ"]
+        );
+    }
+
+    #[test]
+    fn strings_stop_at_nul() {
+        let mut buf = [0 as c_char; 8];
+        for (dst, src) in buf.iter_mut().zip(b"Fx|EQ") {
+            *dst = *src as c_char;
+        }
+        assert_eq!(c_string(&buf), "Fx|EQ");
+        assert_eq!(c_string(&[b'A' as c_char; 4]), "AAAA");
+
+        let mut wide = [0u16; 8];
+        for (dst, src) in wide.iter_mut().zip("Comp".encode_utf16()) {
+            *dst = src;
+        }
+        assert_eq!(utf16_string(&wide), "Comp");
+        assert_eq!(
+            class(K_VST_AUDIO_EFFECT_CLASS, "").class_id,
+            "AB".repeat(16)
+        );
+    }
+
+    #[test]
+    fn class_ids_parse_back() {
+        let cid: TUID = *b"VSTAGainvst3\x00\x01\x02\x03";
+        assert_eq!(parse_class_id(&format_class_id(&cid)), Some(cid));
+        assert_eq!(parse_class_id(&"ab".repeat(16)), Some([0xAB; 16]));
+        assert_eq!(parse_class_id("not a class id"), None);
+    }
+}
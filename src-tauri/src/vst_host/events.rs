@@ -0,0 +1,564 @@
+//! MIDI input for plugins.
+//!
+//! Messages arrive on the MIDI backend's thread and reach the processor through a
+//! per-instance queue (`MidiInlet`). Right before each `process` call the audio thread turns
+//! everything queued since the last block into a preallocated `IEventList`: notes, poly
+//! pressure and SysEx become events, while controllers, channel pressure, pitch bend and
+//! program changes go through the plugin's `IMidiMapping` into parameter changes, which is
+//! how VST3 expects them. There are no timestamps: every event lands at the start of the
+//! block.
+
+use ringbuf::traits::{Producer, Split};
+use ringbuf::HeapRb;
+use std::ffi::c_void;
+use std::sync::Mutex;
+use vst3::Interface;
+
+use crate::vst_host::c_api::{
+    DataEvent, Event, EventPayload, FUnknownVtbl, IEventListVtbl, NoteOffEvent, NoteOnEvent,
+    PolyPressureEvent, TResult, K_AFTER_TOUCH, K_CTRL_PROGRAM_CHANGE, K_DATA_EVENT,
+    K_INVALID_ARGUMENT, K_IS_LIVE, K_MIDI_SYS_EX, K_NOTE_OFF_EVENT, K_NOTE_ON_EVENT,
+    K_NO_INTERFACE, K_PITCH_BEND, K_POLY_PRESSURE_EVENT, K_RESULT_FALSE, K_RESULT_OK, TUID,
+};
+
+/// Messages that can be queued between two process calls, and events per block.
+pub const MIDI_QUEUE_CAPACITY: usize = 256;
+/// Longest SysEx message (including F0/F7) passed on; longer ones are dropped.
+pub const MAX_SYSEX_LEN: usize = 64;
+/// The 128 CCs plus `K_AFTER_TOUCH`, `K_PITCH_BEND` and `K_CTRL_PROGRAM_CHANGE`.
+const NUM_CONTROLLERS: usize = 131;
+
+/// One complete MIDI message, stored inline so queueing it never allocates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MidiMessage {
+    len: u8,
+    bytes: [u8; MAX_SYSEX_LEN],
+}
+
+impl MidiMessage {
+    /// `None` for incomplete messages, SysEx longer than `MAX_SYSEX_LEN` and system messages
+    /// plugins have no use for (clock, active sensing, ...).
+    pub fn new(bytes: &[u8]) -> Option<Self> {
+        let status = *bytes.first()?;
+        if !(0x80..=0xF0).contains(&status) || bytes.len() > MAX_SYSEX_LEN {
+            return None;
+        }
+        let len = match status & 0xF0 {
+            0xC0 | 0xD0 => 2,
+            0xF0 => bytes.len(),
+            _ => 3,
+        };
+        if bytes.len() < len {
+            return None;
+        }
+        let mut msg = Self {
+            len: len as u8,
+            bytes: [0; MAX_SYSEX_LEN],
+        };
+        msg.bytes[..len].copy_from_slice(&bytes[..len]);
+        Some(msg)
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len as usize]
+    }
+
+    /// 0-15, or `None` for SysEx.
+    pub fn channel(&self) -> Option<u8> {
+        let status = self.bytes[0];
+        (status < 0xF0).then_some(status & 0x0F)
+    }
+}
+
+pub type MidiEventProducer = <HeapRb<MidiMessage> as Split>::Prod;
+pub type MidiEventConsumer = <HeapRb<MidiMessage> as Split>::Cons;
+
+/// MIDI messages from the input connection to one processor.
+pub fn midi_event_queue() -> (MidiEventProducer, MidiEventConsumer) {
+    HeapRb::<MidiMessage>::new(MIDI_QUEUE_CAPACITY).split()
+}
+
+/// Where MIDI input for one plugin instance goes. Shared by the instance, which swaps in a
+/// fresh queue whenever it creates a processor, and the MIDI input routing to it.
+pub struct MidiInlet {
+    tx: Mutex<MidiEventProducer>,
+}
+
+impl MidiInlet {
+    pub fn new(tx: MidiEventProducer) -> Self {
+        Self { tx: Mutex::new(tx) }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MidiEventProducer> {
+        // Pushed to from the MIDI backend's thread; a panic there must not wedge the instance
+        match self.tx.lock() {
+            Ok(tx) => tx,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Queues `msg` for the processor's next block. `false` if the queue is full.
+    pub fn push(&self, msg: MidiMessage) -> bool {
+        self.lock().try_push(msg).is_ok()
+    }
+
+    pub fn replace(&self, tx: MidiEventProducer) {
+        *self.lock() = tx;
+    }
+}
+
+/// A message VST3 delivers as a parameter change rather than an event.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MidiControl {
+    pub channel: u8,
+    /// MIDI CC number, `K_AFTER_TOUCH`, `K_PITCH_BEND` or `K_CTRL_PROGRAM_CHANGE`.
+    pub controller: i16,
+    pub value: u16,
+    /// Largest `value` of this kind of message (127, or 16383 for pitch bend).
+    pub max: u16,
+}
+
+/// Parameter a controller is bound to, and its step count (for program changes).
+#[derive(Clone, Copy)]
+struct MappedParam {
+    id: u32,
+    step_count: i32,
+}
+
+/// The plugin's `IMidiMapping` for its first event bus, looked up once per processor so the
+/// audio thread never calls into the controller.
+pub struct MidiControllerMap {
+    params: Box<[[Option<MappedParam>; NUM_CONTROLLERS]; 16]>,
+}
+
+impl MidiControllerMap {
+    pub fn empty() -> Self {
+        Self {
+            params: Box::new([[None; NUM_CONTROLLERS]; 16]),
+        }
+    }
+
+    /// `lookup(channel, controller)` returns the bound parameter id and its step count.
+    pub fn build(mut lookup: impl FnMut(i16, i16) -> Option<(u32, i32)>) -> Self {
+        let mut map = Self::empty();
+        for (channel, params) in map.params.iter_mut().enumerate() {
+            for (controller, param) in params.iter_mut().enumerate() {
+                *param = lookup(channel as i16, controller as i16)
+                    .map(|(id, step_count)| MappedParam { id, step_count });
+            }
+        }
+        map
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.params.iter().flatten().all(Option::is_none)
+    }
+
+    /// Parameter id and normalized value for `control`, if the plugin maps it. Program
+    /// changes select the n-th step of a stepped parameter. RT-safe.
+    pub fn to_param(&self, control: &MidiControl) -> Option<(u32, f64)> {
+        let param = self
+            .params
+            .get(control.channel as usize)?
+            .get(usize::try_from(control.controller).ok()?)?
+            .as_ref()?;
+        let value = if control.controller == K_CTRL_PROGRAM_CHANGE && param.step_count > 0 {
+            control.value.min(param.step_count as u16) as f64 / param.step_count as f64
+        } else {
+            control.value as f64 / control.max as f64
+        };
+        Some((param.id, value))
+    }
+}
+
+// --- IEventList ---
+
+/// `ProcessData::input_events` of one processor. Boxed, so the pointer handed to the plugin
+/// stays valid when the processor moves to the audio thread.
+#[repr(C)]
+pub struct EventList {
+    vtbl: *const IEventListVtbl,
+    /// Preallocated to `MIDI_QUEUE_CAPACITY`; only the first `count` are in use.
+    events: Vec<Event>,
+    /// Storage `DataEvent::bytes` points into; only the first `sysex_count` are in use.
+    sysex: Vec<[u8; MAX_SYSEX_LEN]>,
+    count: usize,
+    sysex_count: usize,
+}
+
+impl EventList {
+    pub fn new() -> Box<Self> {
+        Box::new(Self {
+            vtbl: &EVENT_LIST_VTBL,
+            events: vec![empty_event(); MIDI_QUEUE_CAPACITY],
+            sysex: vec![[0; MAX_SYSEX_LEN]; MIDI_QUEUE_CAPACITY],
+            count: 0,
+            sysex_count: 0,
+        })
+    }
+
+    /// RT-safe.
+    pub fn clear(&mut self) {
+        self.count = 0;
+        self.sysex_count = 0;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Drops all but the note offs, keeping one per channel and pitch, so notes that were
+    /// playing when a plugin got bypassed still end once it processes again. RT-safe.
+    pub fn retain_note_offs(&mut self) {
+        let note = |event: &Event| unsafe {
+            (event.payload.note_off.channel, event.payload.note_off.pitch)
+        };
+        let mut kept = 0;
+        for i in 0..self.count {
+            let event = self.events[i];
+            if event.type_ == K_NOTE_OFF_EVENT
+                && !self.events[..kept].iter().any(|e| note(e) == note(&event))
+            {
+                self.events[kept] = event;
+                kept += 1;
+            }
+        }
+        self.count = kept;
+        // Only data events use the sysex storage
+        self.sysex_count = 0;
+    }
+
+    pub fn as_ptr(&mut self) -> *mut c_void {
+        self as *mut Self as *mut c_void
+    }
+
+    fn push(&mut self, event: Event) -> bool {
+        if self.count == self.events.len() {
+            return false;
+        }
+        self.events[self.count] = event;
+        self.count += 1;
+        true
+    }
+
+    /// Adds `msg` as an event at the start of the block, or returns it as a `MidiControl`
+    /// if VST3 delivers it as a parameter change. Messages beyond capacity are dropped.
+    /// RT-safe.
+    pub fn add_midi(&mut self, msg: &MidiMessage) -> Option<MidiControl> {
+        let bytes = msg.bytes();
+        let status = bytes[0];
+        let channel = status & 0x0F;
+        let control = |controller: i16, value: u16, max: u16| {
+            Some(MidiControl {
+                channel,
+                controller,
+                value,
+                max,
+            })
+        };
+
+        let mut event = empty_event();
+        match status & 0xF0 {
+            0x90 if bytes[2] > 0 => {
+                event.type_ = K_NOTE_ON_EVENT;
+                event.payload = EventPayload {
+                    note_on: NoteOnEvent {
+                        channel: channel as i16,
+                        pitch: bytes[1] as i16,
+                        velocity: bytes[2] as f32 / 127.0,
+                        note_id: -1,
+                        ..Default::default()
+                    },
+                };
+            }
+            // Note on with velocity 0 is a note off
+            0x80 | 0x90 => {
+                event.type_ = K_NOTE_OFF_EVENT;
+                event.payload = EventPayload {
+                    note_off: NoteOffEvent {
+                        channel: channel as i16,
+                        pitch: bytes[1] as i16,
+                        velocity: bytes[2] as f32 / 127.0,
+                        note_id: -1,
+                        ..Default::default()
+                    },
+                };
+            }
+            0xA0 => {
+                event.type_ = K_POLY_PRESSURE_EVENT;
+                event.payload = EventPayload {
+                    poly_pressure: PolyPressureEvent {
+                        channel: channel as i16,
+                        pitch: bytes[1] as i16,
+                        pressure: bytes[2] as f32 / 127.0,
+                        note_id: -1,
+                    },
+                };
+            }
+            0xB0 => return control(bytes[1] as i16, bytes[2] as u16, 127),
+            0xC0 => return control(K_CTRL_PROGRAM_CHANGE, bytes[1] as u16, 127),
+            0xD0 => return control(K_AFTER_TOUCH, bytes[1] as u16, 127),
+            0xE0 => {
+                let bend = (bytes[2] as u16) << 7 | bytes[1] as u16;
+                return control(K_PITCH_BEND, bend, 16383);
+            }
+            _ => {
+                // SysEx (MidiMessage::new lets nothing else through)
+                if self.count == self.events.len() || self.sysex_count == self.sysex.len() {
+                    return None;
+                }
+                let storage = &mut self.sysex[self.sysex_count];
+                storage[..bytes.len()].copy_from_slice(bytes);
+                self.sysex_count += 1;
+                event.type_ = K_DATA_EVENT;
+                event.payload = EventPayload {
+                    data: DataEvent {
+                        size: bytes.len() as u32,
+                        type_: K_MIDI_SYS_EX,
+                        bytes: storage.as_ptr(),
+                    },
+                };
+            }
+        }
+        self.push(event);
+        None
+    }
+}
+
+fn empty_event() -> Event {
+    Event {
+        bus_index: 0,
+        sample_offset: 0,
+        ppq_position: 0.0,
+        flags: K_IS_LIVE,
+        type_: 0,
+        payload: EventPayload { _size: [0; 3] },
+    }
+}
+
+unsafe extern "system" fn events_query_interface(
+    this: *mut c_void,
+    iid: *const TUID,
+    obj: *mut *mut c_void,
+) -> TResult {
+    if obj.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    let iid = *iid;
+    let i_u = vst3::Steinberg::FUnknown::IID;
+    let i_e = vst3::Steinberg::Vst::IEventList::IID;
+    if iid == i_u || iid == i_e {
+        *obj = this;
+        return K_RESULT_OK;
+    }
+    *obj = std::ptr::null_mut();
+    K_NO_INTERFACE
+}
+
+// Owned by the processor for its whole lifetime; plugins must not keep it past `process`.
+unsafe extern "system" fn events_add_ref(_this: *mut c_void) -> u32 {
+    1
+}
+unsafe extern "system" fn events_release(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "system" fn events_get_event_count(this: *mut c_void) -> i32 {
+    (*(this as *const EventList)).count as i32
+}
+
+unsafe extern "system" fn events_get_event(
+    this: *mut c_void,
+    index: i32,
+    event: *mut Event,
+) -> TResult {
+    let list = &*(this as *const EventList);
+    if event.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    if index < 0 || index as usize >= list.count {
+        return K_RESULT_FALSE;
+    }
+    *event = list.events[index as usize];
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn events_add_event(this: *mut c_void, event: *mut Event) -> TResult {
+    if event.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    let list = &mut *(this as *mut EventList);
+    if list.push(*event) {
+        K_RESULT_OK
+    } else {
+        K_RESULT_FALSE
+    }
+}
+
+static EVENT_LIST_VTBL: IEventListVtbl = IEventListVtbl {
+    base: FUnknownVtbl {
+        query_interface: events_query_interface,
+        add_ref: events_add_ref,
+        release: events_release,
+    },
+    get_event_count: events_get_event_count,
+    get_event: events_get_event,
+    add_event: events_add_event,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(bytes: &[u8]) -> MidiMessage {
+        MidiMessage::new(bytes).expect("valid message")
+    }
+
+    /// Reads the events back through the COM vtable, the way a plugin would.
+    fn read_back(list: &mut EventList) -> Vec<Event> {
+        let this = list.as_ptr();
+        let mut events = Vec::new();
+        unsafe {
+            let count = (EVENT_LIST_VTBL.get_event_count)(this);
+            for i in 0..count {
+                let mut event = empty_event();
+                assert_eq!(
+                    (EVENT_LIST_VTBL.get_event)(this, i, &mut event),
+                    K_RESULT_OK
+                );
+                events.push(event);
+            }
+            let mut past_end = empty_event();
+            assert_eq!(
+                (EVENT_LIST_VTBL.get_event)(this, count, &mut past_end),
+                K_RESULT_FALSE
+            );
+        }
+        events
+    }
+
+    #[test]
+    fn event_layout_matches_sdk() {
+        assert_eq!(std::mem::size_of::<Event>(), 48);
+        assert_eq!(std::mem::offset_of!(Event, payload), 24);
+        assert_eq!(std::mem::size_of::<NoteOnEvent>(), 20);
+    }
+
+    #[test]
+    fn notes_become_events() {
+        let mut list = EventList::new();
+        assert!(list.add_midi(&msg(&[0x91, 60, 127])).is_none());
+        assert!(list.add_midi(&msg(&[0x91, 60, 0])).is_none());
+        assert!(list.add_midi(&msg(&[0x82, 64, 64])).is_none());
+        assert!(list.add_midi(&msg(&[0xA3, 67, 127])).is_none());
+
+        let events = read_back(&mut list);
+        assert_eq!(events.len(), 4);
+        assert!(events
+            .iter()
+            .all(|e| e.sample_offset == 0 && e.flags == K_IS_LIVE));
+        unsafe {
+            assert_eq!(events[0].type_, K_NOTE_ON_EVENT);
+            let on = events[0].payload.note_on;
+            assert_eq!((on.channel, on.pitch, on.velocity), (1, 60, 1.0));
+            assert_eq!(events[1].type_, K_NOTE_OFF_EVENT);
+            assert_eq!(events[1].payload.note_off.pitch, 60);
+            assert_eq!(events[2].type_, K_NOTE_OFF_EVENT);
+            assert_eq!(events[2].payload.note_off.channel, 2);
+            assert_eq!(events[3].type_, K_POLY_PRESSURE_EVENT);
+            assert_eq!(events[3].payload.poly_pressure.pressure, 1.0);
+        }
+
+        list.clear();
+        assert!(list.is_empty());
+        assert!(read_back(&mut list).is_empty());
+    }
+
+    #[test]
+    fn skipped_blocks_keep_note_offs() {
+        let mut list = EventList::new();
+        for bytes in [
+            &[0x90, 60, 100][..],
+            &[0x80, 60, 0],
+            &[0xA0, 60, 20],
+            &[0x91, 60, 0],
+            &[0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7],
+        ] {
+            assert!(list.add_midi(&msg(bytes)).is_none());
+        }
+        list.retain_note_offs();
+        // A second skipped block releasing the same note again
+        assert!(list.add_midi(&msg(&[0x80, 60, 0])).is_none());
+        list.retain_note_offs();
+
+        let events = read_back(&mut list);
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.type_ == K_NOTE_OFF_EVENT));
+        unsafe {
+            assert_eq!(events[0].payload.note_off.channel, 0);
+            assert_eq!(events[1].payload.note_off.channel, 1);
+        }
+    }
+
+    #[test]
+    fn sysex_becomes_data_event() {
+        let mut list = EventList::new();
+        let sysex = [0xF0, 0x7E, 0x7F, 0x06, 0x01, 0xF7];
+        assert!(list.add_midi(&msg(&sysex)).is_none());
+
+        let events = read_back(&mut list);
+        assert_eq!(events[0].type_, K_DATA_EVENT);
+        unsafe {
+            let data = events[0].payload.data;
+            assert_eq!(data.type_, K_MIDI_SYS_EX);
+            let bytes = std::slice::from_raw_parts(data.bytes, data.size as usize);
+            assert_eq!(bytes, sysex);
+        }
+    }
+
+    #[test]
+    fn controllers_map_to_parameters() {
+        let mut list = EventList::new();
+        let cc = list.add_midi(&msg(&[0xB0, 7, 127])).unwrap();
+        let bend = list.add_midi(&msg(&[0xE0, 0x7F, 0x7F])).unwrap();
+        let program = list.add_midi(&msg(&[0xC0, 3])).unwrap();
+        let pressure = list.add_midi(&msg(&[0xD5, 64])).unwrap();
+        assert!(list.is_empty());
+        assert_eq!(pressure.channel, 5);
+        assert_eq!(pressure.controller, K_AFTER_TOUCH);
+
+        let map = MidiControllerMap::build(|channel, controller| match (channel, controller) {
+            (0, 7) => Some((100, 0)),
+            (0, K_PITCH_BEND) => Some((101, 0)),
+            (0, K_CTRL_PROGRAM_CHANGE) => Some((102, 4)),
+            _ => None,
+        });
+        assert!(!map.is_empty());
+        assert_eq!(map.to_param(&cc), Some((100, 1.0)));
+        assert_eq!(map.to_param(&bend), Some((101, 1.0)));
+        assert_eq!(map.to_param(&program), Some((102, 0.75)));
+        assert_eq!(map.to_param(&pressure), None);
+        assert!(MidiControllerMap::empty().is_empty());
+    }
+
+    #[test]
+    fn filters_unusable_messages() {
+        assert!(MidiMessage::new(&[]).is_none());
+        assert!(MidiMessage::new(&[0xF8]).is_none()); // Clock
+        assert!(MidiMessage::new(&[0xFE]).is_none()); // Active sensing
+        assert!(MidiMessage::new(&[0x40, 0x40]).is_none()); // Running status
+        assert!(MidiMessage::new(&[0x90, 60]).is_none());
+        assert!(MidiMessage::new(&[0xF0; MAX_SYSEX_LEN + 1]).is_none());
+        assert_eq!(msg(&[0x9F, 60, 100]).channel(), Some(15));
+        assert_eq!(msg(&[0xF0, 0xF7]).channel(), None);
+    }
+
+    #[test]
+    fn drops_events_past_capacity() {
+        let mut list = EventList::new();
+        for _ in 0..MIDI_QUEUE_CAPACITY + 1 {
+            list.add_midi(&msg(&[0x90, 60, 100]));
+        }
+        assert_eq!(read_back(&mut list).len(), MIDI_QUEUE_CAPACITY);
+    }
+}
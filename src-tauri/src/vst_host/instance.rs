@@ -1,5 +1,6 @@
 use anyhow::{anyhow, Context, Result};
 use libloading::{Library, Symbol};
+use ringbuf::traits::{Consumer, Producer};
 use std::ffi::{c_char, c_void};
 use std::path::Path;
 use std::path::PathBuf;
@@ -27,15 +28,25 @@ use windows::Win32::UI::WindowsAndMessaging::{
 };
 
 use crate::vst_host::c_api::{
-    AudioBusBuffers, FUnknownVtbl, IAudioProcessorVtbl, IBStreamVtbl, IComponentHandler2Vtbl,
-    IComponentVtbl, IConnectionPointVtbl, IEditControllerVtbl, IHostApplicationVtbl,
-    IPlugFrameVtbl, IPlugViewVtbl, IPluginFactoryVtbl, ITimerHandlerVtbl, PClassInfo, ProcessData,
-    TResult, ViewRect, K_REALTIME, K_RESULT_OK, K_SAMPLE_32, TUID,
+    get_vtbl, utf16_string, AudioBusBuffers, FUnknownVtbl, IAudioProcessorVtbl, IBStreamVtbl,
+    IComponentHandler2Vtbl, IComponentVtbl, IConnectionPointVtbl, IEditControllerVtbl,
+    IHostApplicationVtbl, IMidiMappingVtbl, IPlugFrameVtbl, IPlugViewVtbl, IPluginFactoryVtbl,
+    ITimerHandlerVtbl, IUnitInfoVtbl, PClassInfo, ParameterInfo, ProcessContext, ProcessData,
+    ProgramListInfo, String128, TResult, UnitInfo, ViewRect, K_CTRL_PROGRAM_CHANGE, K_EVENT,
+    K_INPUT, K_INVALID_ARGUMENT, K_IO_CHANGED, K_LATENCY_CHANGED, K_NO_INTERFACE,
+    K_NO_PROGRAM_LIST_ID, K_OUTPUT, K_REALTIME, K_RESULT_FALSE, K_RESULT_OK, K_SAMPLE_32, TUID,
 };
-
-const K_NO_INTERFACE: TResult = -2147467262;
-const K_INVALID_ARGUMENT: TResult = -2147467261;
-const K_RESULT_FALSE: TResult = 1;
+use crate::vst_host::classes::{format_class_id, parse_class_id};
+use crate::vst_host::events::{
+    midi_event_queue, EventList, MidiControllerMap, MidiEventConsumer, MidiInlet,
+};
+use crate::vst_host::params::{
+    param_change_queue, ParamChangeConsumer, ParamChangeProducer, ParameterChanges, PluginParameter,
+};
+use crate::vst_host::programs::{
+    normalized_to_program, program_param, program_to_normalized, ProgramList,
+};
+use crate::vst_host::vstpreset::{self, VstPreset};
 
 fn vst_trace_enabled() -> bool {
     static ON: OnceLock<bool> = OnceLock::new();
@@ -679,12 +690,6 @@ fn find_primary_plugin_child_hwnd(container: HWND) -> Option<HWND> {
     }
 }
 
-// Constants for IIDs (Little Endian bytes from VST3 SDK)
-// Helper to cast
-unsafe fn get_vtbl<T>(ptr: *mut c_void) -> &'static T {
-    &**(ptr as *mut *mut T)
-}
-
 // Type alias for the entry point
 type GetPluginFactory = unsafe extern "C" fn() -> *mut c_void;
 
@@ -1852,10 +1857,12 @@ fn find_all_plugin_child_hwnds(parent: HWND) -> Vec<HWND> {
 pub struct MockHostApplication {
     pub vtbl: *const IHostApplicationVtbl,
     pub name_u16: [u16; HOST_NAME_MAX_U16],
+    pub handler: *mut c_void, // Owning instance's component handler (owned hosts only)
 }
 static mut GLOBAL_MOCK_HOST_APP: MockHostApplication = MockHostApplication {
     vtbl: std::ptr::null(),
     name_u16: [0u16; HOST_NAME_MAX_U16],
+    handler: std::ptr::null_mut(),
 };
 
 fn get_mock_host_app_ptr() -> *mut c_void {
@@ -1887,6 +1894,15 @@ unsafe extern "system" fn host_app_query_interface(
         return K_RESULT_OK;
     }
 
+    // Edits must reach the instance that made them, not the shared mock handler
+    let handler = unsafe { (*(this as *mut MockHostApplication)).handler };
+    if !handler.is_null() && is_component_handler_iid(&iid_slice) {
+        unsafe {
+            *obj = handler;
+        }
+        return K_RESULT_OK;
+    }
+
     unsafe { host_query_interface(this, iid, obj) }
 }
 
@@ -1901,10 +1917,11 @@ static HOST_APP_OWNED_VTBL: IHostApplicationVtbl = IHostApplicationVtbl {
     create_host_attribute: host_create_host_attribute,
 };
 
-fn new_mock_host_app_ptr(name: &str) -> *mut c_void {
+fn new_mock_host_app_ptr(name: &str, handler: *mut c_void) -> *mut c_void {
     let obj = Box::new(MockHostApplication {
         vtbl: &HOST_APP_OWNED_VTBL,
         name_u16: encode_host_name_u16(name),
+        handler,
     });
     Box::into_raw(obj) as *mut c_void
 }
@@ -1926,8 +1943,8 @@ unsafe fn drop_owned_host_app_ptr(ptr: *mut c_void) {
 
 struct HostAppGuard(*mut c_void);
 impl HostAppGuard {
-    fn new(name: &str) -> Self {
-        Self(new_mock_host_app_ptr(name))
+    fn new(name: &str, handler: *mut c_void) -> Self {
+        Self(new_mock_host_app_ptr(name, handler))
     }
 
     fn as_ptr(&self) -> *mut c_void {
@@ -1947,6 +1964,20 @@ impl Drop for HostAppGuard {
     }
 }
 
+/// Host context (`IHostApplication`) for instantiating a plugin outside `VstInstance`, as
+/// the scanner does to probe it. Has no component handler.
+pub struct HostContext(HostAppGuard);
+
+impl HostContext {
+    pub fn new(name: &str) -> Self {
+        Self(HostAppGuard::new(name, std::ptr::null_mut()))
+    }
+
+    pub fn as_ptr(&self) -> *mut c_void {
+        self.0.as_ptr()
+    }
+}
+
 // --- Mock Plug Frame ---
 unsafe extern "system" fn host_resize_view(
     _this: *mut c_void,
@@ -2077,6 +2108,106 @@ fn get_mock_handler_ptr() -> *mut c_void {
     }
 }
 
+fn is_component_handler_iid(iid: &TUID) -> bool {
+    *iid == crate::vst_host::c_api::IID_ICOMPONENTHANDLER
+        || *iid == crate::vst_host::c_api::IID_ICOMPONENTHANDLER2
+        || *iid == crate::vst_host::c_api::IID_ICOMPONENTHANDLER2_BE
+}
+
+// --- Per-instance Component Handler ---
+
+/// `IComponentHandler2` given to each plugin's controller. Edits made in the plugin's own
+/// editor are forwarded to its processor and collected for `EngineEvent::ParameterChanged`;
+/// `restartComponent` flags are kept until the engine picks them up.
+/// Owned by the `VstInstance`; plugin reference counting is ignored like for the mocks.
+#[repr(C)]
+pub struct InstanceComponentHandler {
+    vtbl: *const IComponentHandler2Vtbl,
+    state: Mutex<HandlerState>,
+}
+
+struct HandlerState {
+    // Replaced whenever the instance creates a new processor
+    param_tx: ParamChangeProducer,
+    // Latest value of each parameter edited since the last take_edits, in edit order
+    edits: Vec<(u32, f64)>,
+    dirty: bool,
+    // RestartFlags requested since the last take_restart_flags
+    restart_flags: i32,
+}
+
+impl InstanceComponentHandler {
+    fn new(param_tx: ParamChangeProducer) -> Box<Self> {
+        Box::new(Self {
+            vtbl: &INSTANCE_HANDLER_VTBL,
+            state: Mutex::new(HandlerState {
+                param_tx,
+                edits: Vec::new(),
+                dirty: false,
+                restart_flags: 0,
+            }),
+        })
+    }
+
+    fn as_ptr(&self) -> *mut c_void {
+        self as *const Self as *mut c_void
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HandlerState> {
+        // Plugins may call in from any thread; a panic there must not wedge the instance
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    /// Queues a change for the processor's next block.
+    fn queue_for_processor(&self, id: u32, value: f64) -> bool {
+        self.lock().param_tx.try_push((id, value)).is_ok()
+    }
+}
+
+unsafe extern "system" fn instance_perform_edit(this: *mut c_void, id: u32, value: f64) -> i32 {
+    let handler = &*(this as *const InstanceComponentHandler);
+    let mut state = handler.lock();
+    if state.param_tx.try_push((id, value)).is_err() {
+        eprintln!("Warning: parameter queue full, dropping edit of {}", id);
+    }
+    match state.edits.iter_mut().find(|(param_id, _)| *param_id == id) {
+        Some(edit) => edit.1 = value,
+        None => state.edits.push((id, value)),
+    }
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn instance_restart_component(this: *mut c_void, flags: i32) -> i32 {
+    let handler = &*(this as *const InstanceComponentHandler);
+    handler.lock().restart_flags |= flags;
+    K_RESULT_OK
+}
+
+unsafe extern "system" fn instance_set_dirty(this: *mut c_void, dirty: i32) -> i32 {
+    let handler = &*(this as *const InstanceComponentHandler);
+    handler.lock().dirty = dirty != 0;
+    K_RESULT_OK
+}
+
+static INSTANCE_HANDLER_VTBL: IComponentHandler2Vtbl = IComponentHandler2Vtbl {
+    base: FUnknownVtbl {
+        query_interface: handler_query_interface,
+        add_ref: host_add_ref,
+        release: host_release,
+    },
+    begin_edit: mock_begin_edit,
+    perform_edit: instance_perform_edit,
+    end_edit: mock_end_edit,
+    restart_component: instance_restart_component,
+    set_dirty: instance_set_dirty,
+    request_open_editor: mock_request_open_editor,
+    start_group_edit: mock_start_group_edit,
+    finish_group_edit: mock_finish_group_edit,
+};
+
 // --- Mock Connection Point ---
 unsafe extern "system" fn connection_connect(_this: *mut c_void, _other: *mut c_void) -> i32 {
     println!("IConnectionPoint::connect called");
@@ -2142,10 +2273,7 @@ unsafe extern "system" fn handler_query_interface(
     }
 
     // Check IComponentHandler / IComponentHandler2
-    if iid_slice == crate::vst_host::c_api::IID_ICOMPONENTHANDLER
-        || iid_slice == crate::vst_host::c_api::IID_ICOMPONENTHANDLER2
-        || iid_slice == crate::vst_host::c_api::IID_ICOMPONENTHANDLER2_BE
-    {
+    if is_component_handler_iid(&iid_slice) {
         println!("MockComponentHandler::query_interface -> IComponentHandler/2 (BE/LE) matched!");
         *obj = _this;
         return K_RESULT_OK;
@@ -2279,9 +2407,23 @@ pub struct VstInstance {
     editor_env: Option<EditorEnvGuard>,
     channels: usize,         // Stored from prepare_processing for create_processor
     max_block_size: usize,   // Stored from prepare_processing for create_processor
+    activated: bool,         // Component active and processing (prepare_processing)
     host_app: *mut c_void,   // IHostApplication context (per-plugin quirks)
     pub path: String,        // Stored for CWD switching during editor open
+    pub class_id: String,    // Hex CID of the instantiated audio module class
     module_hmodule: HMODULE, // Plugin DLL module handle (for UI/resource quirks)
+    // Receives the plugin's edits; also holds the processor's parameter queue
+    handler: Box<InstanceComponentHandler>,
+    // The first processor's end of that queue. Waits here until the first create_processor,
+    // so changes made before audio starts are not lost.
+    param_rx: Option<ParamChangeConsumer>,
+    // MIDI input for the processor; same hand-over as the parameter queue
+    midi_inlet: Arc<MidiInlet>,
+    midi_rx: Option<MidiEventConsumer>,
+    // Latency and main bus arrangements right after the last prepare_processing, to tell
+    // restart requests that change something from those that do not
+    prepared_latency: u32,
+    prepared_arrangements: (u64, u64),
 }
 
 unsafe impl Send for VstInstance {}
@@ -2300,6 +2442,16 @@ pub struct VstProcessor {
 
     active_flag: Arc<AtomicBool>, // Kill switch
 
+    // Host parameter changes, drained into param_changes once per block
+    param_rx: ParamChangeConsumer,
+    param_changes: Box<ParameterChanges>,
+    // MIDI input, drained into events (and mapped controllers into param_changes)
+    midi_rx: MidiEventConsumer,
+    midi_controllers: MidiControllerMap,
+    events: Box<EventList>,
+    // Transport of the block being processed (process_planar)
+    process_context: ProcessContext,
+
     // Safety constants
     max_block_size: usize,
     _num_channels: usize,
@@ -2308,7 +2460,9 @@ pub struct VstProcessor {
 unsafe impl Send for VstProcessor {}
 
 impl VstInstance {
-    pub fn load(path: &str) -> Result<Self> {
+    /// Loads `path` and instantiates its audio module class `class_id` (see
+    /// `format_class_id`), or the first one that instantiates if `None`.
+    pub fn load(path: &str, class_id: Option<&str>) -> Result<Self> {
         let path_obj = Path::new(path);
         let plugin_name = path_obj.file_stem().unwrap().to_string_lossy().to_string();
         let is_insight2 = plugin_name.contains("Insight 2");
@@ -2412,6 +2566,12 @@ impl VstInstance {
                     let class_name = read_cstr(&class_info.name);
                     let category_lower = category.to_lowercase();
 
+                    if let Some(wanted) = class_id {
+                        if !format_class_id(&class_info.cid).eq_ignore_ascii_case(wanted) {
+                            continue;
+                        }
+                    }
+
                     println!("Found class: '{}', Category: '{}'", class_name, category);
 
                     // VST3 "Audio Module Class"
@@ -2514,10 +2674,20 @@ impl VstInstance {
             }
 
             if component_ptr.is_null() {
+                if let Some(wanted) = class_id {
+                    return Err(anyhow!(
+                        "Class {} not found in {} or failed to instantiate",
+                        wanted,
+                        path
+                    ));
+                }
                 return Err(anyhow!(
                     "No valid Audio Module class found or failed to instantiate"
                 ));
             }
+            // The loop breaks right after a successful instantiation, so `class_info` still
+            // describes the class we created.
+            let class_id = format_class_id(&class_info.cid);
 
             // Initialize
             let component_vtbl = get_vtbl::<IComponentVtbl>(component_ptr);
@@ -2540,7 +2710,10 @@ impl VstInstance {
             } else {
                 "Auralyn".to_string()
             };
-            let host_app = HostAppGuard::new(&host_name);
+            let (param_tx, param_rx) = param_change_queue();
+            let handler = InstanceComponentHandler::new(param_tx);
+            let (midi_tx, midi_rx) = midi_event_queue();
+            let host_app = HostAppGuard::new(&host_name, handler.as_ptr());
             let host_ctx = host_app.as_ptr();
 
             // [Resource Fix] Relative paths / helper DLLs compatibility
@@ -2635,10 +2808,10 @@ impl VstInstance {
                                 println!("Controller initialized.");
 
                                 // Essential: Set Component Handler
-                                let handler = get_mock_handler_ptr();
-                                println!("Setting Component Handler: {:p}", handler);
+                                let handler_ptr = handler.as_ptr();
+                                println!("Setting Component Handler: {:p}", handler_ptr);
                                 let handler_res =
-                                    (ctrl_vtbl.set_component_handler)(raw_ctrl_ptr, handler);
+                                    (ctrl_vtbl.set_component_handler)(raw_ctrl_ptr, handler_ptr);
                                 println!("  -> Result: {}", handler_res);
 
                                 // Synchronize State
@@ -2761,9 +2934,10 @@ impl VstInstance {
                             if init_res == K_RESULT_OK {
                                 controller_already_initialized = true;
 
-                                let handler = get_mock_handler_ptr();
-                                let handler_res =
-                                    (ctrl_vtbl.set_component_handler)(raw_ctrl_ptr, handler);
+                                let handler_res = (ctrl_vtbl.set_component_handler)(
+                                    raw_ctrl_ptr,
+                                    handler.as_ptr(),
+                                );
                                 println!(
                                     "[Quirk] OTT: set_component_handler returned: {}",
                                     handler_res
@@ -2880,8 +3054,8 @@ impl VstInstance {
                             println!("[Quirk] OTT: Ignoring Controller initialize failure (same_object=true). Treating as success.");
                         }
                     }
-                    let handler = get_mock_handler_ptr();
-                    let handler_res = (ctrl_vtbl.set_component_handler)(controller_ptr, handler);
+                    let handler_res =
+                        (ctrl_vtbl.set_component_handler)(controller_ptr, handler.as_ptr());
                     if handler_res != K_RESULT_OK {
                         eprintln!(
                             "Warning: set_component_handler (from Component) failed: {}",
@@ -2935,9 +3109,17 @@ impl VstInstance {
                 editor_env: None,
                 channels: 2,
                 max_block_size: 0,
+                activated: false,
                 host_app: host_app.into_raw(),
                 path: path.to_string(),
+                class_id,
                 module_hmodule,
+                handler,
+                param_rx: Some(param_rx),
+                midi_inlet: Arc::new(MidiInlet::new(midi_tx)),
+                midi_rx: Some(midi_rx),
+                prepared_latency: 0,
+                prepared_arrangements: (0, 0),
             })
         } // Close unsafe
     } // Close load
@@ -2970,10 +3152,24 @@ impl VstInstance {
     }
 
     // Create a processor handle to be moved to audio thread
-    pub fn create_processor(&self) -> Option<VstProcessor> {
+    pub fn create_processor(&mut self) -> Option<VstProcessor> {
         if self.processor.is_null() {
             return None;
         }
+        // A previous processor (if any) is being replaced: give the new one a fresh queue
+        let param_rx = self.param_rx.take().unwrap_or_else(|| {
+            let (tx, rx) = param_change_queue();
+            self.handler.lock().param_tx = tx;
+            rx
+        });
+        let mut midi_rx = self.midi_rx.take().unwrap_or_else(|| {
+            let (tx, rx) = midi_event_queue();
+            self.midi_inlet.replace(tx);
+            rx
+        });
+        // Notes played while no processor was running are stale by now
+        while midi_rx.try_pop().is_some() {}
+        let midi_controllers = self.midi_controller_map();
         unsafe {
             let vtbl = get_vtbl::<IAudioProcessorVtbl>(self.processor);
             (vtbl.base.add_ref)(self.processor);
@@ -3000,6 +3196,12 @@ impl VstInstance {
             bus_inputs: Vec::with_capacity(2),
             bus_outputs: Vec::with_capacity(2),
             active_flag: self.active_flag.clone(),
+            param_rx,
+            param_changes: ParameterChanges::new(),
+            midi_rx,
+            midi_controllers,
+            events: EventList::new(),
+            process_context: ProcessContext::default(),
             max_block_size: cap,
             _num_channels: channels,
         })
@@ -3019,6 +3221,14 @@ impl VstInstance {
             let proc_vtbl = get_vtbl::<IAudioProcessorVtbl>(self.processor);
             let comp_vtbl = get_vtbl::<IComponentVtbl>(self.component);
 
+            // 0. Setup and bus arrangements may only change while inactive (audio restart,
+            // restartComponent). The caller has already taken our processor off the audio thread.
+            if self.activated {
+                let _ = (proc_vtbl.set_processing)(self.processor, 0);
+                let _ = (comp_vtbl.set_active)(self.component, 0);
+                self.activated = false;
+            }
+
             // 1. Setup Processing
             let mut setup = crate::vst_host::c_api::ProcessSetup {
                 process_mode: crate::vst_host::c_api::K_REALTIME,
@@ -3065,6 +3275,11 @@ impl VstInstance {
                 eprintln!("Warning: set_bus_arrangements failed");
             }
 
+            // Event buses start inactive; the first one takes our MIDI input
+            if self.accepts_midi() {
+                let _ = (comp_vtbl.activate_bus)(self.component, K_EVENT, K_INPUT, 0, 1);
+            }
+
             // 3. Activate Component
             if (comp_vtbl.set_active)(self.component, 1) != K_RESULT_OK {
                 // 1 = true
@@ -3073,6 +3288,7 @@ impl VstInstance {
 
             // 4. Set Processing Active
             let _ = (proc_vtbl.set_processing)(self.processor, 1);
+            self.activated = true;
 
             // eprintln!(
             //     "Plugin processing prepared: {}Hz, Block={}, Ch={}",
@@ -3083,9 +3299,100 @@ impl VstInstance {
             self.channels = plugin_channels as usize;
             self.max_block_size = block_size.max(0) as usize;
         }
+
+        // Plugins often announce latency or bus changes from setupProcessing/setActive. That
+        // is the setup just made, not a request to restart.
+        self.handler.lock().restart_flags &= !(K_LATENCY_CHANGED | K_IO_CHANGED);
+        self.prepared_latency = self.latency_samples();
+        self.prepared_arrangements = self.main_bus_arrangements();
         Ok(())
     }
 
+    /// Whether a `restartComponent` request with `flags` needs the plugin re-prepared: its
+    /// latency or main bus arrangement differs from the last `prepare_processing`.
+    pub fn needs_restart(&self, flags: i32) -> bool {
+        (flags & K_LATENCY_CHANGED != 0 && self.latency_samples() != self.prepared_latency)
+            || (flags & K_IO_CHANGED != 0
+                && self.main_bus_arrangements() != self.prepared_arrangements)
+    }
+
+    /// Speaker arrangements of the main input and output bus.
+    fn main_bus_arrangements(&self) -> (u64, u64) {
+        let (mut input, mut output) = (0u64, 0u64);
+        if self.processor.is_null() {
+            return (input, output);
+        }
+        unsafe {
+            let vtbl = get_vtbl::<IAudioProcessorVtbl>(self.processor);
+            let get = |dir: i32, arr: &mut u64| {
+                (vtbl.get_bus_arrangement)(self.processor, dir, 0, arr as *mut _ as *mut c_void)
+            };
+            get(K_INPUT, &mut input);
+            get(K_OUTPUT, &mut output);
+        }
+        (input, output)
+    }
+
+    /// Whether the plugin has an event input bus to take MIDI on.
+    pub fn accepts_midi(&self) -> bool {
+        if self.component.is_null() {
+            return false;
+        }
+        unsafe {
+            let vtbl = get_vtbl::<IComponentVtbl>(self.component);
+            (vtbl.get_bus_count)(self.component, K_EVENT, K_INPUT) > 0
+        }
+    }
+
+    /// Where MIDI input for this plugin goes. Stays valid when the processor is replaced.
+    pub fn midi_inlet(&self) -> Arc<MidiInlet> {
+        self.midi_inlet.clone()
+    }
+
+    /// The controller's `IMidiMapping` for the first event bus, if it implements one.
+    fn midi_controller_map(&self) -> MidiControllerMap {
+        if self.controller.is_null() || !self.accepts_midi() {
+            return MidiControllerMap::empty();
+        }
+        unsafe {
+            let iid = vst3::Steinberg::Vst::IMidiMapping::IID;
+            let mut mapping: *mut c_void = std::ptr::null_mut();
+            let unknown = get_vtbl::<FUnknownVtbl>(self.controller);
+            let res = (unknown.query_interface)(
+                self.controller,
+                &iid as *const _ as *const TUID,
+                &mut mapping,
+            );
+            if res != K_RESULT_OK || mapping.is_null() {
+                return MidiControllerMap::empty();
+            }
+            let vtbl = get_vtbl::<IMidiMappingVtbl>(mapping);
+            // Program changes need the step count of their parameter; only looked up if mapped
+            let mut step_counts: Option<HashMap<u32, i32>> = None;
+            let map = MidiControllerMap::build(|channel, controller| {
+                let mut id = 0u32;
+                if (vtbl.get_midi_controller_assignment)(mapping, 0, channel, controller, &mut id)
+                    != K_RESULT_OK
+                {
+                    return None;
+                }
+                let step_count = if controller == K_CTRL_PROGRAM_CHANGE {
+                    let counts = step_counts.get_or_insert_with(|| {
+                        self.parameters()
+                            .map(|params| params.iter().map(|p| (p.id, p.step_count)).collect())
+                            .unwrap_or_default()
+                    });
+                    counts.get(&id).copied().unwrap_or(0)
+                } else {
+                    0
+                };
+                Some((id, step_count))
+            });
+            (vtbl.base.release)(mapping);
+            map
+        }
+    }
+
     pub fn latency_samples(&self) -> u32 {
         unsafe {
             if self.processor.is_null() {
@@ -3637,6 +3944,38 @@ impl VstInstance {
         }
     }
     pub fn get_state(&self) -> Result<String> {
+        let data = self.component_state()?;
+
+        // Encode to Base64
+        use base64::{engine::general_purpose, Engine as _};
+        let b64 = general_purpose::STANDARD.encode(&data);
+        Ok(b64)
+    }
+
+    /// Base64 of the edit controller's own state, if it keeps any.
+    pub fn get_controller_state(&self) -> Option<String> {
+        use base64::{engine::general_purpose, Engine as _};
+        self.controller_state()
+            .map(|data| general_purpose::STANDARD.encode(data))
+    }
+
+    /// Restores a component state and, if given, the controller state saved next to it.
+    /// Presets from before controller state was kept only have the former.
+    pub fn set_state(&self, state_b64: &str, controller_b64: Option<&str>) -> Result<()> {
+        // Decode Base64
+        use base64::{engine::general_purpose, Engine as _};
+        let data = general_purpose::STANDARD
+            .decode(state_b64)
+            .context("mvn failed to decode state base64")?;
+        let controller = controller_b64
+            .map(|b64| general_purpose::STANDARD.decode(b64))
+            .transpose()
+            .context("Failed to decode controller state base64")?;
+
+        self.apply_state(data, controller)
+    }
+
+    fn component_state(&self) -> Result<Vec<u8>> {
         if self.component.is_null() {
             return Err(anyhow!("Component is null"));
         }
@@ -3649,28 +3988,37 @@ impl VstInstance {
             if res != K_RESULT_OK {
                 return Err(anyhow!("Failed to get state from component: {}", res));
             }
+            Ok(std::mem::take(&mut stream.data))
+        }
+    }
 
-            // Encode to Base64
-            use base64::{engine::general_purpose, Engine as _};
-            let b64 = general_purpose::STANDARD.encode(&stream.data);
-            Ok(b64)
+    /// The edit controller's own state (`IEditController::getState`): view settings and the
+    /// like that are not part of the component state. `None` if there is no controller or it
+    /// keeps nothing.
+    fn controller_state(&self) -> Option<Vec<u8>> {
+        if self.controller.is_null() {
+            return None;
+        }
+        unsafe {
+            let ctrl_vtbl = get_vtbl::<IEditControllerVtbl>(self.controller);
+            let mut stream = MemoryStream::new();
+            let stream_ptr = &mut stream as *mut MemoryStream as *mut c_void;
+            let res = (ctrl_vtbl.get_state)(self.controller, stream_ptr);
+            (res == K_RESULT_OK && !stream.data.is_empty())
+                .then(|| std::mem::take(&mut stream.data))
         }
     }
 
-    pub fn set_state(&self, state_b64: &str) -> Result<()> {
+    /// Loads a component state, syncs the controller to it and then applies the controller's
+    /// own state, if given.
+    fn apply_state(&self, component: Vec<u8>, controller: Option<Vec<u8>>) -> Result<()> {
         if self.component.is_null() {
             return Err(anyhow!("Component is null"));
         }
 
-        // Decode Base64
-        use base64::{engine::general_purpose, Engine as _};
-        let data = general_purpose::STANDARD
-            .decode(state_b64)
-            .context("mvn failed to decode state base64")?;
-
         unsafe {
             let mut stream = MemoryStream::new();
-            stream.data = data;
+            stream.data = component;
             let stream_ptr = &mut stream as *mut MemoryStream as *mut c_void;
 
             let component_vtbl = get_vtbl::<IComponentVtbl>(self.component);
@@ -3690,13 +4038,287 @@ impl VstInstance {
                 } else {
                     println!("Controller state synchronized.");
                 }
+
+                if let Some(controller) = controller {
+                    let mut ctrl_stream = MemoryStream::new();
+                    ctrl_stream.data = controller;
+                    let ctrl_stream_ptr = &mut ctrl_stream as *mut MemoryStream as *mut c_void;
+                    let res = (ctrl_vtbl.set_state)(self.controller, ctrl_stream_ptr);
+                    if res != K_RESULT_OK {
+                        eprintln!("Warning: Failed to set controller state: {}", res);
+                    }
+                }
             }
         }
         Ok(())
     }
+
+    /// Writes the current state as a `.vstpreset` file.
+    pub fn export_vstpreset(&self, path: &Path) -> Result<()> {
+        let preset = VstPreset {
+            class_id: self.class_tuid()?,
+            component: self.component_state()?,
+            controller: self.controller_state(),
+        };
+        vstpreset::save(path, &preset)
+    }
+
+    /// Loads a `.vstpreset` file made for this plugin class.
+    pub fn import_vstpreset(&self, path: &Path) -> Result<()> {
+        let preset = vstpreset::load(path)?;
+        if preset.class_id != self.class_tuid()? {
+            // Spelled like the class ids everywhere else in the app, not like the file does
+            return Err(anyhow!(
+                "Preset is for another plugin (class {}, {} is {})",
+                format_class_id(&preset.class_id),
+                self.name,
+                self.class_id
+            ));
+        }
+        self.apply_state(preset.component, preset.controller)
+    }
+
+    fn class_tuid(&self) -> Result<TUID> {
+        parse_class_id(&self.class_id).ok_or_else(|| anyhow!("Unknown class id for {}", self.name))
+    }
+
+    pub fn parameters(&self) -> Result<Vec<PluginParameter>> {
+        if self.controller.is_null() {
+            return Err(anyhow!("Plugin has no edit controller"));
+        }
+        unsafe {
+            let ctrl_vtbl = get_vtbl::<IEditControllerVtbl>(self.controller);
+            let count = (ctrl_vtbl.get_parameter_count)(self.controller).max(0);
+            let mut params = Vec::with_capacity(count as usize);
+            for index in 0..count {
+                let mut info: ParameterInfo = std::mem::zeroed();
+                let res = (ctrl_vtbl.get_parameter_info)(
+                    self.controller,
+                    index,
+                    &mut info as *mut _ as *mut c_void,
+                );
+                if res != K_RESULT_OK {
+                    eprintln!("Warning: get_parameter_info({}) failed: {}", index, res);
+                    continue;
+                }
+                let value = (ctrl_vtbl.get_param_normalized)(self.controller, info.id);
+                params.push(PluginParameter {
+                    id: info.id,
+                    title: utf16_string(&info.title),
+                    short_title: utf16_string(&info.short_title),
+                    units: utf16_string(&info.units),
+                    step_count: info.step_count,
+                    default_value: info.default_normalized_value,
+                    value,
+                    display: self.param_display(info.id, value),
+                    unit_id: info.unit_id,
+                    flags: info.flags,
+                });
+            }
+            Ok(params)
+        }
+    }
+
+    /// Parameters edited in the plugin's own editor since the last call, with their latest
+    /// normalized value.
+    pub fn take_param_edits(&self) -> Vec<(u32, f64)> {
+        std::mem::take(&mut self.handler.lock().edits)
+    }
+
+    /// `restartComponent` flags the plugin raised since the last call (0 if none).
+    pub fn take_restart_flags(&self) -> i32 {
+        std::mem::take(&mut self.handler.lock().restart_flags)
+    }
+
+    /// Whether the plugin reported unsaved changes (`IComponentHandler2::setDirty`).
+    pub fn is_dirty(&self) -> bool {
+        self.handler.lock().dirty
+    }
+
+    /// Like `is_dirty`, and clears the flag.
+    pub fn take_dirty(&self) -> bool {
+        std::mem::take(&mut self.handler.lock().dirty)
+    }
+
+    /// The plugin's text for `value` (empty if it has none).
+    pub fn param_display(&self, param_id: u32, value: f64) -> String {
+        if self.controller.is_null() {
+            return String::new();
+        }
+        unsafe {
+            let ctrl_vtbl = get_vtbl::<IEditControllerVtbl>(self.controller);
+            let mut text: String128 = [0; 128];
+            let res = (ctrl_vtbl.get_param_string_by_value)(
+                self.controller,
+                param_id,
+                value,
+                text.as_mut_ptr() as *mut c_void,
+            );
+            if res == K_RESULT_OK {
+                utf16_string(&text)
+            } else {
+                String::new()
+            }
+        }
+    }
+
+    /// Sets a parameter on the controller and queues it for the processor. Returns the
+    /// normalized value the controller ended up with.
+    pub fn set_parameter(&mut self, param_id: u32, value: f64) -> Result<f64> {
+        if self.controller.is_null() {
+            return Err(anyhow!("Plugin has no edit controller"));
+        }
+        let value = value.clamp(0.0, 1.0);
+        unsafe {
+            let ctrl_vtbl = get_vtbl::<IEditControllerVtbl>(self.controller);
+            let res = (ctrl_vtbl.set_param_normalized)(self.controller, param_id, value);
+            if res != K_RESULT_OK {
+                return Err(anyhow!("Failed to set parameter {}: {}", param_id, res));
+            }
+            if !self.handler.queue_for_processor(param_id, value) {
+                eprintln!(
+                    "Warning: parameter queue of {} is full, dropping change to {}",
+                    self.name, param_id
+                );
+            }
+            Ok((ctrl_vtbl.get_param_normalized)(self.controller, param_id))
+        }
+    }
+
+    /// Factory program lists from the controller's `IUnitInfo` (empty if it has none).
+    pub fn programs(&self) -> Result<Vec<ProgramList>> {
+        let params = self.parameters()?;
+        Ok(self.program_lists(&params))
+    }
+
+    /// Selects program `program_index` of list `list_id` through the list's program-change
+    /// parameter. Returns that parameter and the normalized value it ended up with.
+    pub fn select_program(&mut self, list_id: i32, program_index: usize) -> Result<(u32, f64)> {
+        let params = self.parameters()?;
+        let list = self
+            .program_lists(&params)
+            .into_iter()
+            .find(|list| list.id == list_id)
+            .ok_or_else(|| anyhow!("Program list {} not found", list_id))?;
+        if program_index >= list.programs.len() {
+            return Err(anyhow!(
+                "{} has {} programs, no program {}",
+                list.name,
+                list.programs.len(),
+                program_index
+            ));
+        }
+        let param = list
+            .param_id
+            .and_then(|id| params.iter().find(|p| p.id == id))
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} has no program-change parameter for {}",
+                    self.name,
+                    list.name
+                )
+            })?;
+        let value = self.set_parameter(
+            param.id,
+            program_to_normalized(program_index, param.step_count),
+        )?;
+        Ok((param.id, value))
+    }
+
+    fn program_lists(&self, params: &[PluginParameter]) -> Vec<ProgramList> {
+        if self.controller.is_null() {
+            return Vec::new();
+        }
+        unsafe {
+            let iid = vst3::Steinberg::Vst::IUnitInfo::IID;
+            let mut unit_info: *mut c_void = std::ptr::null_mut();
+            let unknown = get_vtbl::<FUnknownVtbl>(self.controller);
+            let res = (unknown.query_interface)(
+                self.controller,
+                &iid as *const _ as *const TUID,
+                &mut unit_info,
+            );
+            if res != K_RESULT_OK || unit_info.is_null() {
+                return Vec::new();
+            }
+            let vtbl = get_vtbl::<IUnitInfoVtbl>(unit_info);
+
+            // (unit id, program list id) of the units that have a list
+            let mut units = Vec::new();
+            for index in 0..(vtbl.get_unit_count)(unit_info).max(0) {
+                let mut info: UnitInfo = std::mem::zeroed();
+                if (vtbl.get_unit_info)(unit_info, index, &mut info) == K_RESULT_OK
+                    && info.program_list_id != K_NO_PROGRAM_LIST_ID
+                {
+                    units.push((info.id, info.program_list_id));
+                }
+            }
+
+            let list_count = (vtbl.get_program_list_count)(unit_info).max(0);
+            let mut lists = Vec::with_capacity(list_count as usize);
+            for index in 0..list_count {
+                let mut info: ProgramListInfo = std::mem::zeroed();
+                if (vtbl.get_program_list_info)(unit_info, index, &mut info) != K_RESULT_OK {
+                    continue;
+                }
+                let programs = (0..info.program_count.max(0))
+                    .map(|program| {
+                        let mut name: String128 = [0; 128];
+                        let res =
+                            (vtbl.get_program_name)(unit_info, info.id, program, name.as_mut_ptr());
+                        if res == K_RESULT_OK {
+                            utf16_string(&name)
+                        } else {
+                            format!("Program {}", program + 1)
+                        }
+                    })
+                    .collect();
+                let param = program_param(info.id, list_count as usize, &units, params);
+                lists.push(ProgramList {
+                    id: info.id,
+                    name: utf16_string(&info.name),
+                    programs,
+                    param_id: param.map(|p| p.id),
+                    current: param.map(|p| normalized_to_program(p.value, p.step_count)),
+                });
+            }
+            (vtbl.base.release)(unit_info);
+            lists
+        }
+    }
 }
 
 impl VstProcessor {
+    /// Moves queued parameter changes into `param_changes`, where they stay until the next
+    /// block that reaches the plugin. RT-safe.
+    fn collect_param_changes(&mut self) {
+        while let Some((id, value)) = self.param_rx.try_pop() {
+            self.param_changes.set(id, value);
+        }
+    }
+
+    /// Moves queued MIDI into `events`, and controllers the plugin maps into
+    /// `param_changes`. RT-safe.
+    fn collect_midi(&mut self) {
+        while let Some(msg) = self.midi_rx.try_pop() {
+            if let Some(control) = self.events.add_midi(&msg) {
+                if let Some((id, value)) = self.midi_controllers.to_param(&control) {
+                    self.param_changes.set(id, value);
+                }
+            }
+        }
+    }
+
+    /// Called instead of a process call while the plugin is bypassed or muted, so changes
+    /// keep collapsing into the latest value rather than piling up in the queue. Notes
+    /// started meanwhile are dropped, but note offs wait for the next process call so no
+    /// note hangs; mapped controllers are kept like parameter changes.
+    pub fn skip_block(&mut self) {
+        self.collect_param_changes();
+        self.collect_midi();
+        self.events.retain_note_offs();
+    }
+
     pub fn process(
         &mut self,
         input_buffer: &[f32],
@@ -3798,6 +4420,8 @@ impl VstProcessor {
                 channel_buffers64: std::ptr::null_mut(),
             });
 
+            self.collect_param_changes();
+            self.collect_midi();
             let mut data = ProcessData {
                 process_mode: K_REALTIME,
                 symbolic_sample_size: K_SAMPLE_32,
@@ -3806,15 +4430,17 @@ impl VstProcessor {
                 num_outputs: 1,
                 inputs: self.bus_inputs.as_mut_ptr(),
                 outputs: self.bus_outputs.as_mut_ptr(),
-                input_events: std::ptr::null_mut(),
+                input_events: self.events.as_ptr(),
                 output_events: std::ptr::null_mut(),
-                input_param_changes: std::ptr::null_mut(),
+                input_param_changes: self.param_changes.as_ptr(),
                 output_param_changes: std::ptr::null_mut(),
                 process_context: std::ptr::null_mut(),
             };
 
             // 4. プラグイン処理実行
             let res = (vtbl.process)(self.ptr, &mut data as *mut _ as *mut c_void);
+            self.param_changes.clear();
+            self.events.clear();
 
             // 5. 出力データのインターリーブ (Interleave Output)
             if res == K_RESULT_OK {
@@ -3847,6 +4473,7 @@ impl VstProcessor {
         inputs: &[Vec<f32>],
         outputs: &mut [Vec<f32>],
         num_samples: usize,
+        context: &ProcessContext,
     ) {
         unsafe {
             // KILL SWITCH check
@@ -3922,6 +4549,10 @@ impl VstProcessor {
                 channel_buffers64: std::ptr::null_mut(),
             });
 
+            self.collect_param_changes();
+            self.collect_midi();
+            // Our own copy: the plugin gets a mutable pointer
+            self.process_context = *context;
             let mut data = ProcessData {
                 process_mode: K_REALTIME,
                 symbolic_sample_size: K_SAMPLE_32,
@@ -3930,14 +4561,16 @@ impl VstProcessor {
                 num_outputs: 1,
                 inputs: self.bus_inputs.as_mut_ptr(),
                 outputs: self.bus_outputs.as_mut_ptr(),
-                input_events: std::ptr::null_mut(),
+                input_events: self.events.as_ptr(),
                 output_events: std::ptr::null_mut(),
-                input_param_changes: std::ptr::null_mut(),
+                input_param_changes: self.param_changes.as_ptr(),
                 output_param_changes: std::ptr::null_mut(),
-                process_context: std::ptr::null_mut(),
+                process_context: &mut self.process_context as *mut _ as *mut c_void,
             };
 
             let res = (vtbl.process)(self.ptr, &mut data as *mut _ as *mut c_void);
+            self.param_changes.clear();
+            self.events.clear();
 
             // 5. Clean up unused channels
             // (process_planar implies we write directly to outputs, but if outputs has more channels
@@ -4021,3 +4654,237 @@ impl Drop for VstInstance {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vst_host::c_api::IComponentHandlerVtbl;
+
+    /// Component and processor of a plugin that, like many real ones, works out its latency
+    /// in `setActive` and announces it with `restartComponent(kLatencyChanged)`.
+    #[repr(C)]
+    struct FakePart<V: 'static> {
+        vtbl: &'static V,
+        plugin: *const FakePlugin,
+    }
+
+    struct FakePlugin {
+        handler: *mut c_void,
+        latency: AtomicU32,
+        // What setActive(true) sets the latency to
+        active_latency: AtomicU32,
+    }
+
+    unsafe fn plugin<'a>(this: *mut c_void) -> &'a FakePlugin {
+        &*(*(this as *const FakePart<FUnknownVtbl>)).plugin
+    }
+
+    unsafe extern "system" fn fake_query_interface(
+        _this: *mut c_void,
+        _iid: *const TUID,
+        _obj: *mut *mut c_void,
+    ) -> TResult {
+        -1
+    }
+    unsafe extern "system" fn fake_ref_count(_this: *mut c_void) -> u32 {
+        1
+    }
+    unsafe extern "system" fn fake_ok(_this: *mut c_void) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_set_state(_this: *mut c_void, _state: *mut c_void) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_initialize(_this: *mut c_void, _ctx: *mut c_void) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_controller_class_id(
+        _this: *mut c_void,
+        _cid: *mut TUID,
+    ) -> TResult {
+        -1
+    }
+    unsafe extern "system" fn fake_set_io_mode(_this: *mut c_void, _mode: i32) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_bus_count(_this: *mut c_void, _type: i32, _dir: i32) -> i32 {
+        0
+    }
+    unsafe extern "system" fn fake_bus_info(
+        _this: *mut c_void,
+        _type: i32,
+        _dir: i32,
+        _index: i32,
+        _info: *mut c_void,
+    ) -> TResult {
+        -1
+    }
+    unsafe extern "system" fn fake_routing_info(
+        _this: *mut c_void,
+        _in_info: *mut c_void,
+        _out_info: *mut c_void,
+    ) -> TResult {
+        -1
+    }
+    unsafe extern "system" fn fake_activate_bus(
+        _this: *mut c_void,
+        _type: i32,
+        _dir: i32,
+        _index: i32,
+        _state: i32,
+    ) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_set_active(this: *mut c_void, state: i32) -> TResult {
+        let plugin = plugin(this);
+        if state != 0 {
+            let latency = plugin.active_latency.load(Ordering::SeqCst);
+            plugin.latency.store(latency, Ordering::SeqCst);
+            let handler = get_vtbl::<IComponentHandlerVtbl>(plugin.handler);
+            (handler.restart_component)(plugin.handler, K_LATENCY_CHANGED);
+        }
+        K_RESULT_OK
+    }
+
+    unsafe extern "system" fn fake_set_arrangements(
+        _this: *mut c_void,
+        _inputs: *mut c_void,
+        _num_ins: i32,
+        _outputs: *mut c_void,
+        _num_outs: i32,
+    ) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_get_arrangement(
+        _this: *mut c_void,
+        _dir: i32,
+        _index: i32,
+        arr: *mut c_void,
+    ) -> TResult {
+        *(arr as *mut u64) = 3;
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_sample_size(_this: *mut c_void, _size: i32) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_latency(this: *mut c_void) -> u32 {
+        plugin(this).latency.load(Ordering::SeqCst)
+    }
+    unsafe extern "system" fn fake_tail(_this: *mut c_void) -> u32 {
+        0
+    }
+    unsafe extern "system" fn fake_setup(_this: *mut c_void, _setup: *mut c_void) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_set_processing(_this: *mut c_void, _state: i32) -> TResult {
+        K_RESULT_OK
+    }
+    unsafe extern "system" fn fake_process(_this: *mut c_void, _data: *mut c_void) -> TResult {
+        K_RESULT_OK
+    }
+
+    const FAKE_UNKNOWN: FUnknownVtbl = FUnknownVtbl {
+        query_interface: fake_query_interface,
+        add_ref: fake_ref_count,
+        release: fake_ref_count,
+    };
+    static FAKE_COMPONENT_VTBL: IComponentVtbl = IComponentVtbl {
+        base: FAKE_UNKNOWN,
+        initialize: fake_initialize,
+        terminate: fake_ok,
+        get_controller_class_id: fake_controller_class_id,
+        set_io_mode: fake_set_io_mode,
+        get_bus_count: fake_bus_count,
+        get_bus_info: fake_bus_info,
+        get_routing_info: fake_routing_info,
+        activate_bus: fake_activate_bus,
+        set_active: fake_set_active,
+        set_state: fake_set_state,
+        get_state: fake_set_state,
+    };
+    static FAKE_PROCESSOR_VTBL: IAudioProcessorVtbl = IAudioProcessorVtbl {
+        base: FAKE_UNKNOWN,
+        set_bus_arrangements: fake_set_arrangements,
+        get_bus_arrangement: fake_get_arrangement,
+        can_process_sample_size: fake_sample_size,
+        get_latency_samples: fake_latency,
+        setup_processing: fake_setup,
+        set_processing: fake_set_processing,
+        process: fake_process,
+        get_tail_samples: fake_tail,
+    };
+
+    /// An instance around the fake parts; the library is the test binary itself.
+    fn fake_instance(
+        component: &FakePart<IComponentVtbl>,
+        processor: &FakePart<IAudioProcessorVtbl>,
+        handler: Box<InstanceComponentHandler>,
+        param_rx: ParamChangeConsumer,
+    ) -> VstInstance {
+        let (midi_tx, midi_rx) = midi_event_queue();
+        let library = libloading::os::windows::Library::this().unwrap();
+        VstInstance {
+            id: "fake".to_string(),
+            name: "Fake".to_string(),
+            _library: Arc::new(library.into()),
+            component: component as *const _ as *mut c_void,
+            processor: processor as *const _ as *mut c_void,
+            controller: std::ptr::null_mut(),
+            active_view: std::ptr::null_mut(),
+            active_flag: Arc::new(AtomicBool::new(true)),
+            editor_env: None,
+            channels: 2,
+            max_block_size: 0,
+            activated: false,
+            host_app: std::ptr::null_mut(),
+            path: String::new(),
+            class_id: String::new(),
+            module_hmodule: HMODULE(std::ptr::null_mut()),
+            handler,
+            param_rx: Some(param_rx),
+            midi_inlet: Arc::new(MidiInlet::new(midi_tx)),
+            midi_rx: Some(midi_rx),
+            prepared_latency: 0,
+            prepared_arrangements: (0, 0),
+        }
+    }
+
+    #[test]
+    fn latency_announced_while_preparing_is_not_a_restart() {
+        let (param_tx, param_rx) = param_change_queue();
+        let handler = InstanceComponentHandler::new(param_tx);
+        let plugin = FakePlugin {
+            handler: handler.as_ptr(),
+            latency: AtomicU32::new(0),
+            active_latency: AtomicU32::new(64),
+        };
+        let component = FakePart {
+            vtbl: &FAKE_COMPONENT_VTBL,
+            plugin: &plugin,
+        };
+        let processor = FakePart {
+            vtbl: &FAKE_PROCESSOR_VTBL,
+            plugin: &plugin,
+        };
+        let mut instance = fake_instance(&component, &processor, handler, param_rx);
+
+        // The first activation raises kLatencyChanged, and so does every restart after it
+        for _ in 0..2 {
+            instance.prepare_processing(48000.0, 512, 2).unwrap();
+            assert_eq!(instance.latency_samples(), 64);
+            assert_eq!(instance.take_restart_flags(), 0);
+        }
+
+        // A request that changes nothing is answered without re-preparing
+        assert!(!instance.needs_restart(K_LATENCY_CHANGED | K_IO_CHANGED));
+
+        // One that does is acted on, once
+        plugin.active_latency.store(128, Ordering::SeqCst);
+        plugin.latency.store(128, Ordering::SeqCst);
+        assert!(instance.needs_restart(K_LATENCY_CHANGED));
+        assert!(!instance.needs_restart(K_IO_CHANGED));
+        instance.prepare_processing(48000.0, 512, 2).unwrap();
+        assert_eq!(instance.take_restart_flags(), 0);
+        assert!(!instance.needs_restart(K_LATENCY_CHANGED));
+    }
+}
@@ -1,7 +1,15 @@
 pub mod c_api;
+pub mod capabilities;
+pub mod classes;
 pub mod instance;
 pub mod scanner;
 pub mod presets;
+pub mod params;
+pub mod programs;
+pub mod scan_cache;
+pub mod search_paths;
+pub mod vstpreset;
+pub mod events;
 pub mod blacklist;
 
 pub use instance::VstInstance;
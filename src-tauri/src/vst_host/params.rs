@@ -0,0 +1,348 @@
+//! Plugin parameters: enumeration for the generic UI and host-side parameter changes.
+//!
+//! A `SetParameter` updates the edit controller on the main thread and queues the same value
+//! for the processor. The audio thread drains that queue into a preallocated
+//! `IParameterChanges` right before each `process` call, so delivering a change neither
+//! allocates nor locks.
+
+use ringbuf::traits::Split;
+use ringbuf::HeapRb;
+use serde::{Deserialize, Serialize};
+use std::ffi::c_void;
+use vst3::Interface;
+
+use crate::vst_host::c_api::{
+    FUnknownVtbl, IParamValueQueueVtbl, IParameterChangesVtbl, TResult, K_INVALID_ARGUMENT,
+    K_NO_INTERFACE, K_RESULT_FALSE, K_RESULT_OK, TUID,
+};
+
+// ParameterInfo::ParameterFlags
+pub const PARAM_CAN_AUTOMATE: i32 = 1 << 0;
+pub const PARAM_IS_READ_ONLY: i32 = 1 << 1;
+pub const PARAM_IS_WRAP_AROUND: i32 = 1 << 2;
+pub const PARAM_IS_LIST: i32 = 1 << 3;
+pub const PARAM_IS_HIDDEN: i32 = 1 << 4;
+pub const PARAM_IS_PROGRAM_CHANGE: i32 = 1 << 15;
+pub const PARAM_IS_BYPASS: i32 = 1 << 16;
+
+/// Changes that can be queued between two process calls, and the number of distinct
+/// parameters one block can carry.
+pub const PARAM_QUEUE_CAPACITY: usize = 256;
+
+/// Host-visible description of one parameter (`Response::Parameters`).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PluginParameter {
+    pub id: u32,
+    pub title: String,
+    pub short_title: String,
+    pub units: String,
+    /// 0 = continuous, 1 = toggle, n = n + 1 discrete values.
+    pub step_count: i32,
+    pub default_value: f64,
+    /// Current normalized value (0.0 - 1.0).
+    pub value: f64,
+    /// The plugin's own rendering of `value`, e.g. "-6.0 dB".
+    pub display: String,
+    pub unit_id: i32,
+    /// `PARAM_*` bits.
+    pub flags: i32,
+}
+
+pub type ParamChangeProducer = <HeapRb<(u32, f64)> as Split>::Prod;
+pub type ParamChangeConsumer = <HeapRb<(u32, f64)> as Split>::Cons;
+
+/// (parameter id, normalized value) from the main thread to one processor.
+pub fn param_change_queue() -> (ParamChangeProducer, ParamChangeConsumer) {
+    HeapRb::<(u32, f64)>::new(PARAM_QUEUE_CAPACITY).split()
+}
+
+// --- IParamValueQueue ---
+
+/// Changes of one parameter within a block. The host only ever sends the latest value at
+/// the start of the block, so a queue holds a single point.
+#[repr(C)]
+struct ParamValueQueue {
+    vtbl: *const IParamValueQueueVtbl,
+    id: u32,
+    /// (sample offset, normalized value)
+    point: Option<(i32, f64)>,
+}
+
+unsafe extern "system" fn queue_query_interface(
+    this: *mut c_void,
+    iid: *const TUID,
+    obj: *mut *mut c_void,
+) -> TResult {
+    if obj.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    let iid = *iid;
+    let i_u = vst3::Steinberg::FUnknown::IID;
+    let i_q = vst3::Steinberg::Vst::IParamValueQueue::IID;
+    if iid == i_u || iid == i_q {
+        *obj = this;
+        return K_RESULT_OK;
+    }
+    *obj = std::ptr::null_mut();
+    K_NO_INTERFACE
+}
+
+// Owned by the processor for its whole lifetime; plugins must not keep them past `process`.
+unsafe extern "system" fn queue_add_ref(_this: *mut c_void) -> u32 {
+    1
+}
+unsafe extern "system" fn queue_release(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "system" fn queue_get_parameter_id(this: *mut c_void) -> u32 {
+    (*(this as *const ParamValueQueue)).id
+}
+
+unsafe extern "system" fn queue_get_point_count(this: *mut c_void) -> i32 {
+    (*(this as *const ParamValueQueue)).point.is_some() as i32
+}
+
+unsafe extern "system" fn queue_get_point(
+    this: *mut c_void,
+    index: i32,
+    sample_offset: *mut i32,
+    value: *mut f64,
+) -> TResult {
+    let queue = &*(this as *const ParamValueQueue);
+    match queue.point {
+        Some((offset, point_value)) if index == 0 => {
+            if !sample_offset.is_null() {
+                *sample_offset = offset;
+            }
+            if !value.is_null() {
+                *value = point_value;
+            }
+            K_RESULT_OK
+        }
+        _ => K_RESULT_FALSE,
+    }
+}
+
+unsafe extern "system" fn queue_add_point(
+    this: *mut c_void,
+    sample_offset: i32,
+    value: f64,
+    index: *mut i32,
+) -> TResult {
+    let queue = &mut *(this as *mut ParamValueQueue);
+    if queue
+        .point
+        .is_some_and(|(offset, _)| offset != sample_offset)
+    {
+        return K_RESULT_FALSE;
+    }
+    queue.point = Some((sample_offset, value));
+    if !index.is_null() {
+        *index = 0;
+    }
+    K_RESULT_OK
+}
+
+static PARAM_VALUE_QUEUE_VTBL: IParamValueQueueVtbl = IParamValueQueueVtbl {
+    base: FUnknownVtbl {
+        query_interface: queue_query_interface,
+        add_ref: queue_add_ref,
+        release: queue_release,
+    },
+    get_parameter_id: queue_get_parameter_id,
+    get_point_count: queue_get_point_count,
+    get_point: queue_get_point,
+    add_point: queue_add_point,
+};
+
+// --- IParameterChanges ---
+
+/// `ProcessData::input_param_changes` of one processor. Boxed, so the pointer handed to the
+/// plugin stays valid when the processor moves to the audio thread.
+#[repr(C)]
+pub struct ParameterChanges {
+    vtbl: *const IParameterChangesVtbl,
+    /// Preallocated to `PARAM_QUEUE_CAPACITY`; only the first `count` are in use.
+    queues: Vec<ParamValueQueue>,
+    count: usize,
+}
+
+impl ParameterChanges {
+    pub fn new() -> Box<Self> {
+        let queues = (0..PARAM_QUEUE_CAPACITY)
+            .map(|_| ParamValueQueue {
+                vtbl: &PARAM_VALUE_QUEUE_VTBL,
+                id: 0,
+                point: None,
+            })
+            .collect();
+        Box::new(Self {
+            vtbl: &PARAMETER_CHANGES_VTBL,
+            queues,
+            count: 0,
+        })
+    }
+
+    /// RT-safe.
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    /// Index of the queue for `id`, claiming a free one if needed. RT-safe.
+    fn queue_index(&mut self, id: u32) -> Option<usize> {
+        if let Some(index) = self.queues[..self.count].iter().position(|q| q.id == id) {
+            return Some(index);
+        }
+        if self.count == self.queues.len() {
+            return None;
+        }
+        let index = self.count;
+        self.queues[index].id = id;
+        self.queues[index].point = None;
+        self.count += 1;
+        Some(index)
+    }
+
+    /// Sets `id` to `value` from the start of the block; a later change to the same
+    /// parameter in the same block replaces the earlier one. RT-safe.
+    pub fn set(&mut self, id: u32, value: f64) -> bool {
+        match self.queue_index(id) {
+            Some(index) => {
+                self.queues[index].point = Some((0, value));
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    pub fn as_ptr(&mut self) -> *mut c_void {
+        self as *mut Self as *mut c_void
+    }
+}
+
+unsafe extern "system" fn changes_query_interface(
+    this: *mut c_void,
+    iid: *const TUID,
+    obj: *mut *mut c_void,
+) -> TResult {
+    if obj.is_null() {
+        return K_INVALID_ARGUMENT;
+    }
+    let iid = *iid;
+    let i_u = vst3::Steinberg::FUnknown::IID;
+    let i_c = vst3::Steinberg::Vst::IParameterChanges::IID;
+    if iid == i_u || iid == i_c {
+        *obj = this;
+        return K_RESULT_OK;
+    }
+    *obj = std::ptr::null_mut();
+    K_NO_INTERFACE
+}
+
+unsafe extern "system" fn changes_add_ref(_this: *mut c_void) -> u32 {
+    1
+}
+unsafe extern "system" fn changes_release(_this: *mut c_void) -> u32 {
+    1
+}
+
+unsafe extern "system" fn changes_get_parameter_count(this: *mut c_void) -> i32 {
+    (*(this as *const ParameterChanges)).count as i32
+}
+
+unsafe extern "system" fn changes_get_parameter_data(this: *mut c_void, index: i32) -> *mut c_void {
+    let changes = &mut *(this as *mut ParameterChanges);
+    if index < 0 || index as usize >= changes.count {
+        return std::ptr::null_mut();
+    }
+    &mut changes.queues[index as usize] as *mut ParamValueQueue as *mut c_void
+}
+
+unsafe extern "system" fn changes_add_parameter_data(
+    this: *mut c_void,
+    id: *const u32,
+    index: *mut i32,
+) -> *mut c_void {
+    if id.is_null() {
+        return std::ptr::null_mut();
+    }
+    let changes = &mut *(this as *mut ParameterChanges);
+    match changes.queue_index(*id) {
+        Some(queue) => {
+            if !index.is_null() {
+                *index = queue as i32;
+            }
+            &mut changes.queues[queue] as *mut ParamValueQueue as *mut c_void
+        }
+        None => std::ptr::null_mut(),
+    }
+}
+
+static PARAMETER_CHANGES_VTBL: IParameterChangesVtbl = IParameterChangesVtbl {
+    base: FUnknownVtbl {
+        query_interface: changes_query_interface,
+        add_ref: changes_add_ref,
+        release: changes_release,
+    },
+    get_parameter_count: changes_get_parameter_count,
+    get_parameter_data: changes_get_parameter_data,
+    add_parameter_data: changes_add_parameter_data,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads the changes back through the COM vtables, the way a plugin would.
+    fn read_back(changes: &mut ParameterChanges) -> Vec<(u32, i32, f64)> {
+        let this = changes.as_ptr();
+        let mut points = Vec::new();
+        unsafe {
+            let count = (PARAMETER_CHANGES_VTBL.get_parameter_count)(this);
+            for i in 0..count {
+                let queue = (PARAMETER_CHANGES_VTBL.get_parameter_data)(this, i);
+                assert!(!queue.is_null());
+                let id = (PARAM_VALUE_QUEUE_VTBL.get_parameter_id)(queue);
+                for p in 0..(PARAM_VALUE_QUEUE_VTBL.get_point_count)(queue) {
+                    let (mut offset, mut value) = (-1, -1.0);
+                    let res = (PARAM_VALUE_QUEUE_VTBL.get_point)(queue, p, &mut offset, &mut value);
+                    assert_eq!(res, K_RESULT_OK);
+                    points.push((id, offset, value));
+                }
+            }
+            assert!((PARAMETER_CHANGES_VTBL.get_parameter_data)(this, count).is_null());
+        }
+        points
+    }
+
+    #[test]
+    fn latest_change_per_parameter_wins() {
+        let mut changes = ParameterChanges::new();
+        assert!(changes.is_empty());
+
+        assert!(changes.set(7, 0.25));
+        assert!(changes.set(3, 1.0));
+        assert!(changes.set(7, 0.5));
+        assert_eq!(read_back(&mut changes), vec![(7, 0, 0.5), (3, 0, 1.0)]);
+
+        changes.clear();
+        assert!(changes.is_empty());
+        assert!(read_back(&mut changes).is_empty());
+    }
+
+    #[test]
+    fn runs_out_of_queues_only_past_capacity() {
+        let mut changes = ParameterChanges::new();
+        for id in 0..PARAM_QUEUE_CAPACITY as u32 {
+            assert!(changes.set(id, 0.0));
+        }
+        assert!(!changes.set(u32::MAX, 0.0));
+        // Known parameters can still be updated
+        assert!(changes.set(0, 1.0));
+    }
+}
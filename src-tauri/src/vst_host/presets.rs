@@ -2,9 +2,14 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::ipc::MidiAssignment;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PresetPlugin {
     pub path: String,
+    /// Audio module class inside the bundle; `None` means "whichever class the host picks".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class_id: Option<String>,
     pub name: String,
     pub vendor: String,
     pub version: String,
@@ -12,9 +17,15 @@ pub struct PresetPlugin {
     pub muted: bool,
     pub gain: f32,
     pub state: Option<String>, // Base64
+    /// Edit controller state (Base64), restored after `state`. Missing in older presets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller_state: Option<String>,
+    /// Restored by `ApplyChain`; `None` leaves a reused instance's assignment alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub midi_input: Option<MidiAssignment>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Preset {
     pub name: String,
     pub plugins: Vec<PresetPlugin>,
@@ -0,0 +1,118 @@
+//! Factory programs from the controller's `IUnitInfo`.
+//!
+//! Plugins that ship their presets as programs publish them as program lists, each used by
+//! one or more units. A unit's program-change parameter (`PARAM_IS_PROGRAM_CHANGE`) selects
+//! a program of its list, so `SelectProgram` goes through `set_parameter` like any other
+//! edit and reaches the processor the same way.
+
+use serde::{Deserialize, Serialize};
+
+use crate::vst_host::params::{PluginParameter, PARAM_IS_PROGRAM_CHANGE};
+
+/// One program list (`Response::Programs`).
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ProgramList {
+    pub id: i32,
+    pub name: String,
+    pub programs: Vec<String>,
+    /// Program-change parameter that selects from this list. `None` if the plugin offers
+    /// none, in which case the list can only be shown.
+    pub param_id: Option<u32>,
+    /// Program the parameter currently points at.
+    pub current: Option<usize>,
+}
+
+/// The program-change parameter of a unit using list `list_id`. `units` holds
+/// (unit id, program list id) for every unit. Plugins without unit info usually have a
+/// single list and put its parameter in the root unit, so with one list and no unit claiming
+/// it any program-change parameter will do.
+pub fn program_param<'a>(
+    list_id: i32,
+    list_count: usize,
+    units: &[(i32, i32)],
+    params: &'a [PluginParameter],
+) -> Option<&'a PluginParameter> {
+    let mut program_params = params
+        .iter()
+        .filter(|p| p.flags & PARAM_IS_PROGRAM_CHANGE != 0);
+    let mut owners = units
+        .iter()
+        .filter(|(_, list)| *list == list_id)
+        .map(|(unit, _)| *unit)
+        .peekable();
+    if owners.peek().is_none() {
+        return if list_count == 1 {
+            program_params.next()
+        } else {
+            None
+        };
+    }
+    let owners: Vec<i32> = owners.collect();
+    program_params.find(|p| owners.contains(&p.unit_id))
+}
+
+/// Normalized value of a program-change parameter with `step_count` steps selecting
+/// program `index`.
+pub fn program_to_normalized(index: usize, step_count: i32) -> f64 {
+    if step_count <= 0 {
+        return 0.0;
+    }
+    index.min(step_count as usize) as f64 / step_count as f64
+}
+
+pub fn normalized_to_program(value: f64, step_count: i32) -> usize {
+    (value.clamp(0.0, 1.0) * step_count.max(0) as f64).round() as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn param(id: u32, unit_id: i32, flags: i32) -> PluginParameter {
+        PluginParameter {
+            id,
+            title: format!("Param {}", id),
+            short_title: String::new(),
+            units: String::new(),
+            step_count: 15,
+            default_value: 0.0,
+            value: 0.0,
+            display: String::new(),
+            unit_id,
+            flags,
+        }
+    }
+
+    #[test]
+    fn finds_the_program_parameter_of_the_list_unit() {
+        let params = [
+            param(1, 0, 0),
+            param(2, 0, PARAM_IS_PROGRAM_CHANGE),
+            param(3, 5, PARAM_IS_PROGRAM_CHANGE),
+        ];
+        // Root unit uses list 10, unit 5 uses list 20
+        let units = [(0, 10), (5, 20)];
+        assert_eq!(program_param(10, 2, &units, &params).map(|p| p.id), Some(2));
+        assert_eq!(program_param(20, 2, &units, &params).map(|p| p.id), Some(3));
+        assert!(program_param(30, 3, &units, &params).is_none());
+
+        // No unit info: only a lone list gets the parameter
+        assert_eq!(program_param(10, 1, &[], &params).map(|p| p.id), Some(2));
+        assert!(program_param(10, 2, &[], &params).is_none());
+        assert!(program_param(10, 1, &[], &params[..1]).is_none());
+    }
+
+    #[test]
+    fn programs_map_to_parameter_steps() {
+        assert_eq!(program_to_normalized(0, 15), 0.0);
+        assert_eq!(program_to_normalized(15, 15), 1.0);
+        assert_eq!(program_to_normalized(40, 15), 1.0);
+        assert_eq!(program_to_normalized(3, 0), 0.0);
+        for index in 0..=15 {
+            assert_eq!(
+                normalized_to_program(program_to_normalized(index, 15), 15),
+                index
+            );
+        }
+    }
+}
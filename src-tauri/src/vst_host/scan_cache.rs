@@ -0,0 +1,239 @@
+//! Scanner results kept between scans, so a rescan only launches `plugin_scanner` for
+//! binaries that are new or changed.
+//!
+//! An entry is reused while the file's size and modification time are unchanged. If those
+//! differ, the contents are hashed: a binary that was merely touched or reinstalled as is
+//! keeps its entry, anything else is scanned again.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::fs_util::write_atomic;
+use crate::vst_host::scanner::VstPlugin;
+
+pub const SCAN_CACHE_FILE: &str = "vst_scan_cache.json";
+/// Bump when `VstPlugin` changes shape; older caches are then discarded.
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Milliseconds since the Unix epoch.
+    pub modified: u64,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> io::Result<Self> {
+        let meta = fs::metadata(path)?;
+        let modified = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        Ok(Self {
+            size: meta.len(),
+            modified,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CacheEntry {
+    pub stamp: FileStamp,
+    /// FNV-1a of the file contents.
+    pub hash: u64,
+    /// Audio effects found in the module; empty if it has none.
+    pub plugins: Vec<VstPlugin>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ScanCache {
+    version: u32,
+    entries: HashMap<String, CacheEntry>,
+    #[serde(skip)]
+    file_path: PathBuf,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl ScanCache {
+    pub fn new(config_dir: &Path) -> Self {
+        let file_path = config_dir.join(SCAN_CACHE_FILE);
+        let mut cache = match fs::read_to_string(&file_path) {
+            Ok(s) => serde_json::from_str::<ScanCache>(&s)
+                .ok()
+                .filter(|c| c.version == CACHE_VERSION)
+                .unwrap_or_else(|| {
+                    log::warn!("Discarding outdated or damaged plugin scan cache");
+                    ScanCache::default()
+                }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => ScanCache::default(),
+            Err(e) => {
+                log::error!("Failed to read plugin scan cache: {}", e);
+                ScanCache::default()
+            }
+        };
+        cache.version = CACHE_VERSION;
+        cache.file_path = file_path;
+        cache
+    }
+
+    /// The cached plugins of `path` if the file has not changed since it was scanned.
+    pub fn lookup(&mut self, path: &str) -> Option<Vec<VstPlugin>> {
+        let entry = self.entries.get_mut(path)?;
+        let file = Path::new(path);
+        let stamp = FileStamp::of(file).ok()?;
+        if entry.stamp != stamp {
+            if hash_file(file).ok()? != entry.hash {
+                return None;
+            }
+            entry.stamp = stamp;
+            self.dirty = true;
+        }
+        Some(entry.plugins.clone())
+    }
+
+    pub fn insert(&mut self, path: &str, plugins: Vec<VstPlugin>) {
+        let file = Path::new(path);
+        match FileStamp::of(file).and_then(|stamp| Ok((stamp, hash_file(file)?))) {
+            Ok((stamp, hash)) => {
+                self.entries.insert(
+                    path.to_string(),
+                    CacheEntry {
+                        stamp,
+                        hash,
+                        plugins,
+                    },
+                );
+                self.dirty = true;
+            }
+            Err(e) => log::warn!("Not caching scan result of {}: {}", path, e),
+        }
+    }
+
+    /// Drops the entries of binaries that were not found by this scan.
+    pub fn retain(&mut self, found: &HashSet<String>) {
+        let before = self.entries.len();
+        self.entries.retain(|path, _| found.contains(path));
+        if self.entries.len() != before {
+            self.dirty = true;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        if !self.entries.is_empty() {
+            self.entries.clear();
+            self.dirty = true;
+        }
+    }
+
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        match serde_json::to_string(self) {
+            Ok(s) => match write_atomic(&self.file_path, s.as_bytes()) {
+                Ok(()) => self.dirty = false,
+                Err(e) => log::error!("Failed to save plugin scan cache: {:#}", e),
+            },
+            Err(e) => log::error!("Failed to serialize plugin scan cache: {}", e),
+        }
+    }
+}
+
+fn hash_file(path: &Path) -> io::Result<u64> {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut buf = [0u8; 64 * 1024];
+    let mut hash = OFFSET;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hash);
+        }
+        for &byte in &buf[..n] {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestDir;
+    use std::time::{Duration, SystemTime};
+
+    fn plugin(path: &str) -> VstPlugin {
+        VstPlugin {
+            name: "Comp".to_string(),
+            path: path.to_string(),
+            vendor: "Vendor".to_string(),
+            version: "1.0.0".to_string(),
+            class_id: "AB".repeat(16),
+            category: "Fx|Dynamics".to_string(),
+            sdk_version: "VST 3.7.9".to_string(),
+            capabilities: None,
+        }
+    }
+
+    fn touch(path: &Path, offset_secs: u64) {
+        let file = File::options().write(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() + Duration::from_secs(offset_secs))
+            .unwrap();
+    }
+
+    #[test]
+    fn reuses_unchanged_binaries_only() {
+        let dir = TestDir::new("scan-cache-reuse");
+        let binary = dir.join("Comp.vst3");
+        let path = binary.to_string_lossy().into_owned();
+        fs::write(&binary, b"module v1").unwrap();
+
+        let mut cache = ScanCache::new(&dir);
+        assert!(cache.lookup(&path).is_none());
+        cache.insert(&path, vec![plugin(&path)]);
+        cache.save();
+
+        let mut cache = ScanCache::new(&dir);
+        assert_eq!(cache.lookup(&path).unwrap()[0].class_id, "AB".repeat(16));
+
+        // Touched, same contents: still cached
+        touch(&binary, 60);
+        assert!(cache.lookup(&path).is_some());
+        assert_eq!(cache.entries[&path].stamp, FileStamp::of(&binary).unwrap());
+
+        // New build
+        fs::write(&binary, b"module v2, larger").unwrap();
+        assert!(cache.lookup(&path).is_none());
+    }
+
+    #[test]
+    fn forgets_removed_binaries() {
+        let dir = TestDir::new("scan-cache-retain");
+        let kept = dir.join("Kept.vst3").to_string_lossy().into_owned();
+        let removed = dir.join("Removed.vst3").to_string_lossy().into_owned();
+        fs::write(&kept, b"kept").unwrap();
+        fs::write(&removed, b"removed").unwrap();
+
+        let mut cache = ScanCache::new(&dir);
+        cache.insert(&kept, vec![plugin(&kept)]);
+        cache.insert(&removed, Vec::new());
+        cache.retain(&HashSet::from([kept.clone()]));
+        cache.save();
+
+        let mut cache = ScanCache::new(&dir);
+        assert!(cache.lookup(&kept).is_some());
+        assert!(!cache.entries.contains_key(&removed));
+
+        // Outdated format
+        fs::write(dir.join(SCAN_CACHE_FILE), r#"{"version":0,"entries":{}}"#).unwrap();
+        assert!(ScanCache::new(&dir).entries.is_empty());
+    }
+}
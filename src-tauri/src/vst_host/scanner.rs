@@ -1,11 +1,20 @@
-use crate::vst_host::blacklist::Blacklist;
+use crate::vst_host::blacklist::{Blacklist, BlacklistReason};
+use crate::vst_host::capabilities::{PluginCapabilities, PROBE_MARKER};
+use crate::vst_host::classes::PluginClass;
+use crate::vst_host::scan_cache::ScanCache;
+use crate::vst_host::search_paths;
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::env;
-use std::io;
+use std::env::consts::EXE_SUFFIX;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
 use std::time::{Duration, Instant};
-use walkdir::WalkDir;
 
 #[cfg(windows)]
 mod win_job {
@@ -52,12 +61,21 @@ mod win_job {
     }
 }
 
+/// One audio effect class of a scanned module; a module with several yields several.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct VstPlugin {
     pub name: String,
     pub path: String,
     pub vendor: String,
     pub version: String,
+    /// Passed back in `LoadPlugin` to instantiate this class.
+    pub class_id: String,
+    /// Sub-categories, e.g. "Fx|Dynamics".
+    pub category: String,
+    pub sdk_version: String,
+    /// Buses, latency, editor etc.; `None` if the scanner could not instantiate the class.
+    #[serde(default)]
+    pub capabilities: Option<PluginCapabilities>,
 }
 
 #[derive(Deserialize)]
@@ -68,23 +86,55 @@ struct ScanResult {
     version: String,
     success: bool,
     error: Option<String>,
+    #[serde(default)]
+    classes: Vec<PluginClass>,
+}
+
+impl ScanResult {
+    /// The module's audio effects. Class fields the factory left empty fall back to the
+    /// module's (file name, factory vendor).
+    fn into_plugins(self) -> Vec<VstPlugin> {
+        let or = |value: String, fallback: &str| {
+            if value.is_empty() {
+                fallback.to_string()
+            } else {
+                value
+            }
+        };
+        self.classes
+            .into_iter()
+            .filter(PluginClass::is_audio_effect)
+            .map(|class| VstPlugin {
+                name: or(class.name, &self.name),
+                path: self.path.clone(),
+                vendor: or(class.vendor, &self.vendor),
+                version: or(class.version, &self.version),
+                class_id: class.class_id,
+                category: class.sub_categories,
+                sdk_version: class.sdk_version,
+                capabilities: class.capabilities,
+            })
+            .collect()
+    }
 }
 
 fn get_scanner_path() -> Option<PathBuf> {
+    let file_name = format!("plugin_scanner{}", EXE_SUFFIX);
+
     // 1. Try side-by-side with executable
     if let Ok(exe_path) = env::current_exe() {
         if let Some(parent) = exe_path.parent() {
-            let direct = parent.join("plugin_scanner.exe");
+            let direct = parent.join(&file_name);
             if direct.exists() {
                 return Some(direct);
             }
 
-            let direct_bin = parent.join("bin").join("plugin_scanner.exe");
+            let direct_bin = parent.join("bin").join(&file_name);
             if direct_bin.exists() {
                 return Some(direct_bin);
             }
 
-            // Tauri externalBin naming often looks like: plugin_scanner-<target-triple>.exe
+            // Tauri externalBin naming often looks like: plugin_scanner-<target-triple>[.exe]
             if let Ok(entries) = std::fs::read_dir(parent) {
                 for entry in entries.flatten() {
                     let p = entry.path();
@@ -95,7 +145,7 @@ fn get_scanner_path() -> Option<PathBuf> {
                         continue;
                     };
                     let lower = name.to_ascii_lowercase();
-                    if lower.starts_with("plugin_scanner-") && lower.ends_with(".exe") {
+                    if lower.starts_with("plugin_scanner-") && lower.ends_with(EXE_SUFFIX) {
                         return Some(p);
                     }
                 }
@@ -111,7 +161,7 @@ fn get_scanner_path() -> Option<PathBuf> {
                         continue;
                     };
                     let lower = name.to_ascii_lowercase();
-                    if lower.starts_with("plugin_scanner-") && lower.ends_with(".exe") {
+                    if lower.starts_with("plugin_scanner-") && lower.ends_with(EXE_SUFFIX) {
                         return Some(p);
                     }
                 }
@@ -122,12 +172,12 @@ fn get_scanner_path() -> Option<PathBuf> {
     // 2. Try CWD (development fallback)
     if let Ok(cwd) = env::current_dir() {
         // Look in target/debug/
-        let debug = cwd.join("target").join("debug").join("plugin_scanner.exe");
+        let debug = cwd.join("target").join("debug").join(&file_name);
         if debug.exists() {
             return Some(debug);
         }
 
-        let cwd_bin = cwd.join("bin").join("plugin_scanner.exe");
+        let cwd_bin = cwd.join("bin").join(&file_name);
         if cwd_bin.exists() {
             return Some(cwd_bin);
         }
@@ -136,204 +186,534 @@ fn get_scanner_path() -> Option<PathBuf> {
     None
 }
 
-pub fn scan_system_vst3(config_dir: &PathBuf) -> Vec<VstPlugin> {
-    let mut plugins = Vec::new();
+/// Hang protection: how long the scanner may go without starting to probe another class.
+const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+/// Scanner processes run at once; each one loads a plugin binary.
+const MAX_SCAN_WORKERS: usize = 4;
+
+/// Lets another thread cancel the running scan, and keeps a second one from starting.
+#[derive(Default)]
+pub struct ScanControl {
+    running: AtomicBool,
+    cancelled: AtomicBool,
+}
+
+impl ScanControl {
+    /// `None` if a scan is already running; the scan counts as running until the guard drops.
+    pub fn begin(self: &Arc<Self>) -> Option<ScanGuard> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        self.cancelled.store(false, Ordering::SeqCst);
+        Some(ScanGuard(self.clone()))
+    }
+
+    pub fn cancel(&self) {
+        if self.running.load(Ordering::SeqCst) {
+            self.cancelled.store(true, Ordering::SeqCst);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+pub struct ScanGuard(Arc<ScanControl>);
+
+impl Deref for ScanGuard {
+    type Target = ScanControl;
+
+    fn deref(&self) -> &ScanControl {
+        &self.0
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        self.0.running.store(false, Ordering::SeqCst);
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanStatus {
+    /// Unchanged since the last scan; taken from the scan cache.
+    Cached,
+    Scanned,
+    /// Skipped, or added to the blacklist by this scan.
+    Blacklisted,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanFileResult {
+    pub status: ScanStatus,
+    pub plugins: Vec<VstPlugin>,
+    pub error: Option<String>,
+}
+
+/// Payload of the "scan-progress" event, sent as each binary is finished.
+#[derive(Debug, Serialize, Clone)]
+pub struct ScanProgress {
+    pub done: usize,
+    pub total: usize,
+    pub current_path: String,
+    pub result: ScanFileResult,
+}
+
+enum ScanOutcome {
+    Found(Vec<VstPlugin>),
+    Failed(String),
+    /// Crash, hang or garbage output: the binary goes on the blacklist.
+    Unsafe {
+        reason: BlacklistReason,
+        exit_code: Option<i32>,
+        error: String,
+    },
+    Cancelled,
+}
+
+/// Runs `plugin_scanner` on one binary, killing it on timeout or cancellation. Time spent
+/// probing classes does not count towards the timeout as long as each class gets done.
+fn run_scanner(scanner_path: &Path, path: &str, control: &ScanControl) -> io::Result<Output> {
+    let mut command = Command::new(scanner_path);
+    command.arg(path);
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        command.creation_flags(CREATE_NO_WINDOW);
+    }
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Best-effort: kill process tree on timeout (Windows)
+    #[cfg(windows)]
+    let _job = {
+        use std::os::windows::io::AsRawHandle;
+        use windows::Win32::Foundation::HANDLE;
+
+        let job = win_job::Job::new_kill_on_drop();
+        if let Some(ref job) = job {
+            let handle = HANDLE(child.as_raw_handle());
+            let _ = job.assign(handle);
+        }
+        job
+    };
+
+    // Both pipes are drained while the scanner runs, or a chatty plugin would block it on a
+    // full pipe and get blacklisted as hanging
+    let stdout = child.stdout.take().map(|mut stdout| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = stdout.read_to_end(&mut buf);
+            buf
+        })
+    });
+
+    // Each class the scanner starts probing restarts the hang timer
+    let (progress_tx, progress_rx) = mpsc::channel();
+    let stderr = child.stderr.take().map(|stderr| {
+        std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            for line in BufReader::new(stderr).split(b'\n').map_while(Result::ok) {
+                if line.starts_with(PROBE_MARKER.as_bytes()) {
+                    let _ = progress_tx.send(());
+                }
+                buf.extend_from_slice(&line);
+                buf.push(b'\n');
+            }
+            buf
+        })
+    });
+
+    let mut last_progress = Instant::now();
+    loop {
+        if progress_rx.try_iter().count() > 0 {
+            last_progress = Instant::now();
+        }
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                return Ok(Output {
+                    status,
+                    stdout: join_pipe(stdout),
+                    stderr: join_pipe(stderr),
+                });
+            }
+            Ok(None) => {
+                if control.is_cancelled() {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(io::Error::new(
+                        io::ErrorKind::Interrupted,
+                        "plugin scan cancelled",
+                    ));
+                }
+                if last_progress.elapsed() >= SCAN_TIMEOUT {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "plugin_scanner timeout",
+                    ));
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Output collected by one of `run_scanner`'s pipe readers; empty if the reader panicked.
+fn join_pipe(reader: Option<std::thread::JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    reader.and_then(|r| r.join().ok()).unwrap_or_default()
+}
+
+/// The scanner prints its result as the last line; plugins may print their own output
+/// before it while being probed.
+fn parse_scan_output(stdout: &str) -> serde_json::Result<ScanResult> {
+    let json = stdout
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("");
+    serde_json::from_str(json)
+}
+
+fn scan_binary(scanner_path: &Path, path: &str, control: &ScanControl) -> ScanOutcome {
+    log::info!("Scanning: {:?}", path);
+
+    let out = match run_scanner(scanner_path, path, control) {
+        Ok(out) => out,
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => return ScanOutcome::Cancelled,
+        Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+            log::warn!("Plugin scanner timed out: {:?} (>{:?})", path, SCAN_TIMEOUT);
+            return ScanOutcome::Unsafe {
+                reason: BlacklistReason::Timeout,
+                exit_code: None,
+                error: format!("Timed out after {:?}", SCAN_TIMEOUT),
+            };
+        }
+        Err(e) => {
+            log::error!("Failed to spawn scanner: {}", e);
+            return ScanOutcome::Failed(format!("Failed to spawn scanner: {}", e));
+        }
+    };
+
+    if !out.status.success() {
+        log::warn!(
+            "Plugin scanner crashed or failed: {:?} (Code: {:?})",
+            path,
+            out.status.code()
+        );
+        // Crash -> Blacklist
+        return ScanOutcome::Unsafe {
+            reason: BlacklistReason::Crash,
+            exit_code: out.status.code(),
+            error: format!("Scanner exited with {:?}", out.status.code()),
+        };
+    }
+
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    match parse_scan_output(&stdout) {
+        Ok(res) if res.success => {
+            let plugins = res.into_plugins();
+            if plugins.is_empty() {
+                log::info!("No audio effect classes in {:?}", path);
+            }
+            ScanOutcome::Found(plugins)
+        }
+        Ok(res) => {
+            log::warn!(
+                "Plugin scan failed (internal): {:?} - {:?}",
+                path,
+                res.error
+            );
+            ScanOutcome::Failed(res.error.unwrap_or_default())
+        }
+        Err(e) => {
+            log::error!("Failed to parse scanner output: {} - Output: {}", e, stdout);
+            // Bad output -> likely crash or garbage -> Blacklist (Safe Mode!)
+            ScanOutcome::Unsafe {
+                reason: BlacklistReason::BadOutput,
+                exit_code: out.status.code(),
+                error: format!("Unreadable scanner output: {}", e),
+            }
+        }
+    }
+}
+
+/// Files the outcome of scanning `path` in the blacklist and the scan cache. `None` if the
+/// scan was cancelled.
+fn record_outcome(
+    path: &str,
+    outcome: ScanOutcome,
+    blacklist: &mut Blacklist,
+    cache: &mut ScanCache,
+) -> Option<ScanFileResult> {
+    let result = match outcome {
+        ScanOutcome::Found(plugins) => {
+            blacklist.remove(path);
+            cache.insert(path, plugins.clone());
+            ScanFileResult {
+                status: ScanStatus::Scanned,
+                plugins,
+                error: None,
+            }
+        }
+        // Failed cleanly, so it is safe to try again next time
+        ScanOutcome::Failed(error) => {
+            blacklist.remove(path);
+            ScanFileResult {
+                status: ScanStatus::Failed,
+                plugins: Vec::new(),
+                error: Some(error),
+            }
+        }
+        ScanOutcome::Unsafe {
+            reason,
+            exit_code,
+            error,
+        } => {
+            blacklist.add(path, reason, exit_code, Some(error.clone()));
+            ScanFileResult {
+                status: ScanStatus::Blacklisted,
+                plugins: Vec::new(),
+                error: Some(error),
+            }
+        }
+        ScanOutcome::Cancelled => return None,
+    };
+    Some(result)
+}
+
+/// Scans the VST3 folders, running up to `MAX_SCAN_WORKERS` scanner processes at once and
+/// reporting each finished binary to `on_progress`. Binaries that have not changed since
+/// they were last scanned come from the scan cache unless `force_rescan` is set.
+///
+/// Returns `None` if the scan was cancelled through `control`; results gathered until then
+/// are still cached.
+pub fn scan_system_vst3(
+    config_dir: &PathBuf,
+    force_rescan: bool,
+    control: &ScanControl,
+    mut on_progress: impl FnMut(&ScanProgress),
+) -> Option<Vec<VstPlugin>> {
     let mut blacklist = Blacklist::new(config_dir);
+    let mut cache = ScanCache::new(config_dir);
+    if force_rescan {
+        cache.clear();
+    }
 
     let scanner_path = match get_scanner_path() {
         Some(p) => p,
         None => {
-            log::error!("Could not find plugin_scanner.exe");
-            return Vec::new();
+            log::error!("Could not find plugin_scanner");
+            return Some(Vec::new());
         }
     };
     log::info!("Using scanner binary at: {:?}", scanner_path);
 
-    // Common VST3 paths on Windows
-    let paths = vec![
-        r"C:\Program Files\Common Files\VST3",
-        r"C:\Program Files\Steinberg\VST3",
-    ];
-
-    for path_str in paths {
-        let path = Path::new(path_str);
-        if path.exists() {
-            let mut walker = WalkDir::new(path).into_iter();
-            while let Some(entry_res) = walker.next() {
-                let entry = match entry_res {
-                    Ok(e) => e,
-                    Err(_) => continue,
-                };
-
-                let entry_path = entry.path();
-                // Check for .vst3 extension
-                if entry_path.extension().map_or(false, |ext| ext == "vst3") {
-                    // CRITICAL FIX: If it's a bundle (directory), do NOT recurse into it.
-                    // This prevents finding the inner binary as a separate entry later.
-                    if entry_path.is_dir() {
-                        walker.skip_current_dir();
-                    }
+    let user_paths = search_paths::load_user_paths(config_dir).unwrap_or_else(|e| {
+        log::error!("Failed to load VST3 search paths: {:#}", e);
+        Vec::new()
+    });
+    let folders = search_paths::search_paths(&user_paths);
+    log::info!(
+        "Searching VST3 folders: {:?}",
+        folders
+            .iter()
+            .filter(|f| f.exists)
+            .map(|f| &f.path)
+            .collect::<Vec<_>>()
+    );
+    let binaries = search_paths::find_vst3_binaries(&folders);
+    let total = binaries.len();
+    // Per binary, in folder order, so the list does not depend on which scan finished first
+    let mut found: Vec<Vec<VstPlugin>> = vec![Vec::new(); total];
+    let mut done = 0;
+    let mut report = |path: &str, result: ScanFileResult| {
+        done += 1;
+        on_progress(&ScanProgress {
+            done,
+            total,
+            current_path: path.to_string(),
+            result,
+        });
+    };
 
-                    let mut final_path = entry_path.to_path_buf();
-                    let name = entry_path
-                        .file_stem()
-                        .map(|s| s.to_string_lossy().to_string())
-                        .unwrap_or_else(|| "Unknown Plugin".to_string());
-
-                    // If it's a directory (Bundle), look for the binary
-                    if entry_path.is_dir() {
-                        let binary_path = entry_path
-                            .join("Contents/x86_64-win")
-                            .join(format!("{}.vst3", name));
-                        if binary_path.exists() {
-                            final_path = binary_path;
-                        } else {
-                            // Fallback: search safely inside architecture dir
-                            // Note: we can't use the main walker for this as we skipped the dir,
-                            // so we do a localized search here.
-                            let arch_dir = entry_path.join("Contents/x86_64-win");
-                            if arch_dir.exists() {
-                                if let Ok(mut entries) = std::fs::read_dir(arch_dir) {
-                                    if let Some(Ok(inner)) = entries.find(|e| {
-                                        e.as_ref().ok().map_or(false, |dir_entry| {
-                                            dir_entry
-                                                .path()
-                                                .extension()
-                                                .map_or(false, |ext| ext == "vst3")
-                                        })
-                                    }) {
-                                        final_path = inner.path();
-                                    }
-                                }
-                            }
-                        }
-                    }
+    let mut pending = Vec::new();
+    for (index, path) in binaries.iter().enumerate() {
+        if blacklist.is_blocked(path) {
+            log::warn!("Skipping blacklisted plugin: {}", path);
+            report(
+                path,
+                ScanFileResult {
+                    status: ScanStatus::Blacklisted,
+                    plugins: Vec::new(),
+                    error: blacklist.get(path).and_then(|e| e.error.clone()),
+                },
+            );
+        } else if blacklist.contains(path) {
+            log::info!("Blacklisted plugin changed, scanning it again: {}", path);
+            pending.push(index);
+        } else if let Some(cached) = cache.lookup(path) {
+            found[index] = cached.clone();
+            report(
+                path,
+                ScanFileResult {
+                    status: ScanStatus::Cached,
+                    plugins: cached,
+                    error: None,
+                },
+            );
+        } else {
+            pending.push(index);
+        }
+    }
 
-                    // Only process if it points to a file now
-                    if final_path.is_file() {
-                        let path_string = final_path.to_string_lossy().to_string();
-
-                        if blacklist.contains(&path_string) {
-                            log::warn!("Skipping blacklisted plugin: {}", path_string);
-                            continue;
-                        }
-
-                        log::info!("Scanning: {:?}", final_path);
-
-                        // Per-plugin timeout (hang protection) - Increased to 30s
-                        let timeout = Duration::from_secs(30);
-                        let output = (|| {
-                            let mut command = Command::new(&scanner_path);
-                            command.arg(&path_string);
-
-                            #[cfg(windows)]
-                            {
-                                use std::os::windows::process::CommandExt;
-                                const CREATE_NO_WINDOW: u32 = 0x08000000;
-                                command.creation_flags(CREATE_NO_WINDOW);
-                            }
-
-                            let mut child = command
-                                .stdin(Stdio::null())
-                                .stdout(Stdio::piped())
-                                .stderr(Stdio::piped())
-                                .spawn()?;
-
-                            // Best-effort: kill process tree on timeout (Windows)
-                            #[cfg(windows)]
-                            let _job = {
-                                use std::os::windows::io::AsRawHandle;
-                                use windows::Win32::Foundation::HANDLE;
-
-                                let job = win_job::Job::new_kill_on_drop();
-                                if let Some(ref job) = job {
-                                    let handle = HANDLE(child.as_raw_handle());
-                                    let _ = job.assign(handle);
-                                }
-                                job
-                            };
-
-                            let start = Instant::now();
-                            loop {
-                                match child.try_wait() {
-                                    Ok(Some(_status)) => {
-                                        return child.wait_with_output();
-                                    }
-                                    Ok(None) => {
-                                        if start.elapsed() >= timeout {
-                                            let _ = child.kill();
-                                            let _ = child.wait();
-                                            return Err(io::Error::new(
-                                                io::ErrorKind::TimedOut,
-                                                "plugin_scanner timeout",
-                                            ));
-                                        }
-                                        std::thread::sleep(Duration::from_millis(10));
-                                    }
-                                    Err(e) => return Err(e),
-                                }
-                            }
-                        })();
-
-                        match output {
-                            Ok(out) => {
-                                if out.status.success() {
-                                    let stdout = String::from_utf8_lossy(&out.stdout);
-                                    match serde_json::from_str::<ScanResult>(&stdout) {
-                                        Ok(res) => {
-                                            if res.success {
-                                                plugins.push(VstPlugin {
-                                                    name: res.name,
-                                                    path: res.path,
-                                                    vendor: res.vendor,
-                                                    version: res.version,
-                                                });
-                                            } else {
-                                                log::warn!(
-                                                    "Plugin scan failed (internal): {:?} - {:?}",
-                                                    final_path,
-                                                    res.error
-                                                );
-                                            }
-                                        }
-                                        Err(e) => {
-                                            log::error!(
-                                                "Failed to parse scanner output: {} - Output: {}",
-                                                e,
-                                                stdout
-                                            );
-                                            // Bad output -> likely crash or garbage -> Blacklist (Safe Mode!)
-                                            blacklist.add(&path_string);
-                                        }
-                                    }
-                                } else {
-                                    log::warn!(
-                                        "Plugin scanner crashed or failed: {:?} (Code: {:?})",
-                                        final_path,
-                                        out.status.code()
-                                    );
-                                    // Crash -> Blacklist
-                                    blacklist.add(&path_string);
-                                }
-                            }
-                            Err(e) => {
-                                if e.kind() == std::io::ErrorKind::TimedOut {
-                                    log::warn!(
-                                        "Plugin scanner timed out: {:?} (>{:?})",
-                                        final_path,
-                                        timeout
-                                    );
-                                    blacklist.add(&path_string);
-                                } else {
-                                    log::error!("Failed to spawn scanner: {}", e);
-                                }
-                            }
-                        }
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(MAX_SCAN_WORKERS)
+        .min(pending.len());
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            let tx = tx.clone();
+            let (next, pending, binaries, scanner_path) =
+                (&next, &pending, &binaries, &scanner_path);
+            scope.spawn(move || {
+                while !control.is_cancelled() {
+                    let Some(&index) = pending.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                        break;
+                    };
+                    let outcome = scan_binary(scanner_path, &binaries[index], control);
+                    if tx.send((index, outcome)).is_err() {
+                        break;
                     }
                 }
-            }
+            });
+        }
+        drop(tx);
+
+        // Blacklist and cache are only touched here, on the calling thread
+        for (index, outcome) in rx {
+            let path = &binaries[index];
+            let Some(result) = record_outcome(path, outcome, &mut blacklist, &mut cache) else {
+                continue;
+            };
+            found[index] = result.plugins.clone();
+            report(path, result);
+        }
+    });
+
+    if control.is_cancelled() {
+        log::info!("Plugin scan cancelled after {} of {} binaries", done, total);
+        cache.save();
+        return None;
+    }
+
+    cache.retain(&binaries.iter().cloned().collect::<HashSet<_>>());
+    cache.save();
+    Some(found.into_iter().flatten().collect())
+}
+
+/// Scans one blacklisted binary again right away, changed or not. It comes off the
+/// blacklist unless it crashes, hangs or prints garbage again.
+pub fn retry_blacklisted(
+    config_dir: &Path,
+    path: &str,
+    control: &ScanControl,
+) -> Result<ScanFileResult> {
+    let mut blacklist = Blacklist::new(config_dir);
+    if !blacklist.contains(path) {
+        return Err(anyhow!("{} is not blacklisted", path));
+    }
+    let scanner_path =
+        get_scanner_path().ok_or_else(|| anyhow!("Could not find plugin_scanner"))?;
+
+    let mut cache = ScanCache::new(config_dir);
+    let outcome = scan_binary(&scanner_path, path, control);
+    let result = record_outcome(path, outcome, &mut blacklist, &mut cache)
+        .ok_or_else(|| anyhow!("cancelled"))?;
+    cache.save();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vst_host::c_api::K_VST_AUDIO_EFFECT_CLASS;
+
+    fn class(id: &str, name: &str, category: &str, sub_categories: &str) -> PluginClass {
+        PluginClass {
+            class_id: id.to_string(),
+            name: name.to_string(),
+            category: category.to_string(),
+            sub_categories: sub_categories.to_string(),
+            vendor: String::new(),
+            version: "2.1.0".to_string(),
+            sdk_version: "VST 3.7.9".to_string(),
+            capabilities: None,
         }
     }
 
-    plugins
+    #[test]
+    fn one_entry_per_effect_class() {
+        let res = ScanResult {
+            path: r"C:\VST3\Suite.vst3".to_string(),
+            name: "Suite".to_string(),
+            vendor: "Vendor".to_string(),
+            version: "0.0.0".to_string(),
+            success: true,
+            error: None,
+            classes: vec![
+                class("A1", "Suite Comp", K_VST_AUDIO_EFFECT_CLASS, "Fx|Dynamics"),
+                class("A2", "Synth", K_VST_AUDIO_EFFECT_CLASS, "Instrument|Synth"),
+                class("A3", "Suite Comp", "Component Controller Class", ""),
+                class("A4", "", K_VST_AUDIO_EFFECT_CLASS, "Fx|EQ"),
+            ],
+        };
+        let plugins = res.into_plugins();
+        assert_eq!(plugins.len(), 2);
+        assert_eq!(plugins[0].class_id, "A1");
+        assert_eq!(plugins[0].name, "Suite Comp");
+        assert_eq!(plugins[0].category, "Fx|Dynamics");
+        assert_eq!(plugins[0].vendor, "Vendor");
+        assert_eq!(plugins[0].version, "2.1.0");
+        assert_eq!(plugins[1].class_id, "A4");
+        assert_eq!(plugins[1].name, "Suite");
+        assert!(plugins.iter().all(|p| p.path == r"C:\VST3\Suite.vst3"));
+    }
+
+    #[test]
+    fn scanner_output_after_plugin_noise() {
+        let stdout = concat!(
+            "Plugin banner v2.1\n",
+            r#"{"path":"C:\\VST3\\Gate.vst3","name":"Gate","vendor":"Vendor","version":"1.0.0","#,
+            r#""success":true,"error":null,"classes":[{"class_id":"A1","name":"Gate","#,
+            r#""category":"Audio Module Class","sub_categories":"Fx|Dynamics","#,
+            r#""capabilities":{"inputs":[{"name":"In","channels":2,"aux":false},"#,
+            r#"{"name":"Key","channels":1,"aux":true}],"outputs":[{"name":"Out","channels":2,"#,
+            r#""aux":false}],"accepts_stereo":true,"accepts_mono":false,"has_sidechain":true,"#,
+            r#""latency_samples":64,"tail_samples":0,"parameter_count":12,"has_editor":true,"#,
+            r#""supports_64bit":false}}]}"#,
+            "\n"
+        );
+        let plugins = parse_scan_output(stdout).unwrap().into_plugins();
+        let caps = plugins[0].capabilities.as_ref().unwrap();
+        assert!(caps.has_sidechain);
+        assert_eq!(caps.latency_samples, 64);
+        assert!(!caps.is_mono_only());
+
+        assert!(parse_scan_output("").is_err());
+        assert!(parse_scan_output("{\"path\":").is_err());
+    }
 }
@@ -0,0 +1,304 @@
+//! Where the scanner looks for VST3 plugins.
+//!
+//! Folders are searched in this order: the ones the user added (`vst_search_paths.json` in
+//! the config dir), `VST3_PATH`, the per-user VST3 folder and the system-wide ones. A bundle
+//! (`Name.vst3/`) is resolved to the binary for this build's architecture under
+//! `Contents/<arch>`, as the VST3 module layout describes.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::fs_util::write_atomic;
+
+pub const SEARCH_PATHS_FILE: &str = "vst_search_paths.json";
+
+/// `Contents/<dir>` folders that can hold a binary this build loads, preferred first.
+#[cfg(all(windows, target_arch = "x86_64"))]
+const BUNDLE_ARCH_DIRS: &[&str] = &["x86_64-win"];
+#[cfg(all(windows, target_arch = "x86"))]
+const BUNDLE_ARCH_DIRS: &[&str] = &["x86-win"];
+#[cfg(all(windows, target_arch = "aarch64"))]
+const BUNDLE_ARCH_DIRS: &[&str] = &["arm64-win", "arm64x-win"];
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const BUNDLE_ARCH_DIRS: &[&str] = &["x86_64-linux"];
+#[cfg(all(target_os = "linux", target_arch = "x86"))]
+const BUNDLE_ARCH_DIRS: &[&str] = &["i386-linux"];
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const BUNDLE_ARCH_DIRS: &[&str] = &["aarch64-linux"];
+#[cfg(all(target_os = "linux", target_arch = "arm"))]
+const BUNDLE_ARCH_DIRS: &[&str] = &["armv7l-linux"];
+#[cfg(not(any(
+    all(
+        windows,
+        any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64")
+    ),
+    all(
+        target_os = "linux",
+        any(
+            target_arch = "x86_64",
+            target_arch = "x86",
+            target_arch = "aarch64",
+            target_arch = "arm"
+        )
+    )
+)))]
+const BUNDLE_ARCH_DIRS: &[&str] = &[];
+
+/// Extension of the binary inside a bundle. Windows also allows a bare `.vst3` DLL.
+const BINARY_EXTENSION: &str = if cfg!(windows) { "vst3" } else { "so" };
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchPathSource {
+    User,
+    /// `VST3_PATH`
+    Environment,
+    PerUser,
+    System,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchPath {
+    pub path: String,
+    pub source: SearchPathSource,
+    pub exists: bool,
+}
+
+pub fn search_paths_file(config_dir: &Path) -> PathBuf {
+    config_dir.join(SEARCH_PATHS_FILE)
+}
+
+/// Folders the user added. Empty if the file does not exist yet.
+pub fn load_user_paths(config_dir: &Path) -> Result<Vec<String>> {
+    let path = search_paths_file(config_dir);
+    let json = match fs::read(&path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    };
+    serde_json::from_slice(&json).with_context(|| format!("Invalid search path file {:?}", path))
+}
+
+pub fn save_user_paths(config_dir: &Path, paths: &[String]) -> Result<()> {
+    fs::create_dir_all(config_dir).with_context(|| format!("Failed to create {:?}", config_dir))?;
+    let path = search_paths_file(config_dir);
+    let json = serde_json::to_vec_pretty(paths)?;
+    write_atomic(&path, &json)
+}
+
+/// Every folder the scanner searches, in search order and without duplicates.
+pub fn search_paths(user_paths: &[String]) -> Vec<SearchPath> {
+    search_paths_with(user_paths, env::var_os("VST3_PATH").as_deref())
+}
+
+fn search_paths_with(user_paths: &[String], vst3_path: Option<&OsStr>) -> Vec<SearchPath> {
+    let env_paths: Vec<PathBuf> =
+        vst3_path.map_or_else(Vec::new, |v| env::split_paths(v).collect());
+    let candidates = user_paths
+        .iter()
+        .map(|p| (PathBuf::from(p), SearchPathSource::User))
+        .chain(
+            env_paths
+                .into_iter()
+                .map(|p| (p, SearchPathSource::Environment)),
+        )
+        .chain(default_dirs());
+
+    let mut seen = HashSet::new();
+    let mut paths = Vec::new();
+    for (path, source) in candidates {
+        if path.as_os_str().is_empty() || !seen.insert(path_key(&path)) {
+            continue;
+        }
+        paths.push(SearchPath {
+            exists: path.is_dir(),
+            path: path.to_string_lossy().into_owned(),
+            source,
+        });
+    }
+    paths
+}
+
+/// Compares paths the way the file system does: trailing separators are ignored, and on
+/// Windows so is case.
+fn path_key(path: &Path) -> String {
+    let normalized: PathBuf = path.components().collect();
+    let key = normalized.to_string_lossy().into_owned();
+    if cfg!(windows) {
+        key.to_lowercase()
+    } else {
+        key
+    }
+}
+
+#[cfg(windows)]
+fn default_dirs() -> Vec<(PathBuf, SearchPathSource)> {
+    let mut dirs = Vec::new();
+    if let Some(local) = env::var_os("LOCALAPPDATA") {
+        dirs.push((
+            PathBuf::from(local).join(r"Programs\Common\VST3"),
+            SearchPathSource::PerUser,
+        ));
+    }
+    let common = env::var_os("CommonProgramFiles")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(r"C:\Program Files\Common Files"));
+    dirs.push((common.join("VST3"), SearchPathSource::System));
+    dirs.push((
+        PathBuf::from(r"C:\Program Files\Steinberg\VST3"),
+        SearchPathSource::System,
+    ));
+    dirs
+}
+
+#[cfg(not(windows))]
+fn default_dirs() -> Vec<(PathBuf, SearchPathSource)> {
+    let mut dirs = Vec::new();
+    if let Some(home) = env::var_os("HOME") {
+        dirs.push((PathBuf::from(home).join(".vst3"), SearchPathSource::PerUser));
+    }
+    dirs.push((PathBuf::from("/usr/lib/vst3"), SearchPathSource::System));
+    dirs.push((
+        PathBuf::from("/usr/local/lib/vst3"),
+        SearchPathSource::System,
+    ));
+    dirs
+}
+
+fn has_binary_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == BINARY_EXTENSION)
+}
+
+/// The binary of a `Name.vst3` bundle: `Contents/<arch>/Name.<ext>`, or failing that any
+/// binary in the first architecture folder that has one.
+pub fn bundle_binary(bundle: &Path) -> Option<PathBuf> {
+    let name = bundle.file_stem()?.to_string_lossy().into_owned();
+    let arch_dirs = BUNDLE_ARCH_DIRS
+        .iter()
+        .map(|arch| bundle.join("Contents").join(arch));
+
+    for arch_dir in arch_dirs.clone() {
+        let binary = arch_dir.join(format!("{}.{}", name, BINARY_EXTENSION));
+        if binary.is_file() {
+            return Some(binary);
+        }
+    }
+    // Some vendors name the binary differently from the bundle
+    arch_dirs
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .find_map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .find(|p| p.is_file() && has_binary_extension(p))
+        })
+}
+
+/// Plugin binaries in `folders`, in search order. Each bundle counts once, as its binary
+/// for this architecture; bundles without one are skipped.
+pub fn find_vst3_binaries(folders: &[SearchPath]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut binaries = Vec::new();
+
+    for folder in folders.iter().filter(|f| f.exists) {
+        let mut walker = WalkDir::new(&folder.path).follow_links(true).into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            let entry_path = entry.path();
+            if entry_path.extension() != Some(OsStr::new("vst3")) {
+                continue;
+            }
+
+            let binary = if entry.file_type().is_dir() {
+                // The bundle's own files are not separate plugins
+                walker.skip_current_dir();
+                bundle_binary(entry_path)
+            } else if cfg!(windows) {
+                Some(entry_path.to_path_buf())
+            } else {
+                None
+            };
+
+            if let Some(binary) = binary {
+                if seen.insert(path_key(&binary)) {
+                    binaries.push(binary.to_string_lossy().into_owned());
+                }
+            }
+        }
+    }
+
+    binaries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TestDir;
+
+    #[test]
+    fn user_folders_come_first_without_duplicates() {
+        let dir = TestDir::new("search-paths-order");
+        let user = dir.join("Portable").to_string_lossy().into_owned();
+        let from_env = dir.join("FromEnv");
+        fs::create_dir_all(&user).unwrap();
+        let vst3_path = env::join_paths([from_env.clone(), PathBuf::from(&user)]).unwrap();
+
+        let paths = search_paths_with(std::slice::from_ref(&user), Some(&vst3_path));
+        assert_eq!(paths[0].path, user);
+        assert_eq!(paths[0].source, SearchPathSource::User);
+        assert!(paths[0].exists);
+        assert_eq!(paths[1].path, from_env.to_string_lossy());
+        assert_eq!(paths[1].source, SearchPathSource::Environment);
+        assert!(!paths[1].exists);
+        assert!(paths[2..].iter().all(|p| matches!(
+            p.source,
+            SearchPathSource::PerUser | SearchPathSource::System
+        )));
+
+        assert!(load_user_paths(&dir).unwrap().is_empty());
+        save_user_paths(&dir, std::slice::from_ref(&user)).unwrap();
+        assert_eq!(load_user_paths(&dir).unwrap(), vec![user]);
+    }
+
+    #[test]
+    fn bundles_resolve_to_the_binary_for_this_architecture() {
+        let Some(arch) = BUNDLE_ARCH_DIRS.first() else {
+            return;
+        };
+        let dir = TestDir::new("search-paths-bundles");
+        let contents = dir.join("Comp.vst3").join("Contents");
+        fs::create_dir_all(contents.join(arch)).unwrap();
+        fs::create_dir_all(contents.join("Resources")).unwrap();
+        let binary = contents
+            .join(arch)
+            .join(format!("Comp.{}", BINARY_EXTENSION));
+        fs::write(&binary, b"binary").unwrap();
+        fs::write(
+            contents.join("Resources").join("Nested.vst3"),
+            b"not a plugin",
+        )
+        .unwrap();
+        // Foreign architecture only
+        let other = dir.join("Other.vst3").join("Contents").join("unknown-arch");
+        fs::create_dir_all(&other).unwrap();
+        fs::write(other.join(format!("Other.{}", BINARY_EXTENSION)), b"binary").unwrap();
+
+        let folder = SearchPath {
+            path: dir.to_string_lossy().into_owned(),
+            source: SearchPathSource::User,
+            exists: true,
+        };
+        assert_eq!(
+            find_vst3_binaries(&[folder.clone(), folder]),
+            vec![binary.to_string_lossy().into_owned()]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! Steinberg `.vstpreset` files.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! "VST3"  version: i32  class id: 32 ASCII hex digits  chunk list offset: i64
+//! chunk data ...
+//! "List"  entry count: i32  { chunk id: 4 bytes, offset: i64, size: i64 } ...
+//! ```
+//!
+//! We read and write the component state ('Comp') and the controller state ('Cont'); other
+//! chunks (program data, the 'Info' XML) are skipped on import.
+
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+
+use crate::vst_host::c_api::TUID;
+
+const HEADER_ID: &[u8; 4] = b"VST3";
+const FORMAT_VERSION: i32 = 1;
+const CLASS_ID_LEN: usize = 32;
+const HEADER_LEN: usize = 4 + 4 + CLASS_ID_LEN + 8;
+const LIST_ID: &[u8; 4] = b"List";
+const COMPONENT_CHUNK: &[u8; 4] = b"Comp";
+const CONTROLLER_CHUNK: &[u8; 4] = b"Cont";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct VstPreset {
+    pub class_id: TUID,
+    pub component: Vec<u8>,
+    pub controller: Option<Vec<u8>>,
+}
+
+/// The class id as preset files spell it: the SDK's `FUID::toString`. On Windows FUIDs are
+/// COM GUIDs, whose first three fields are stored little-endian, so the string is not simply
+/// the bytes in memory order.
+pub fn class_id_to_string(cid: &TUID) -> String {
+    let mut bytes = *cid;
+    if cfg!(windows) {
+        swap_guid_fields(&mut bytes);
+    }
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+pub fn class_id_from_string(s: &str) -> Result<TUID> {
+    if s.len() != CLASS_ID_LEN || !s.is_ascii() {
+        return Err(anyhow!("Invalid class id {:?}", s));
+    }
+    let mut cid: TUID = [0; 16];
+    for (i, byte) in cid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&s[i * 2..i * 2 + 2], 16)
+            .map_err(|_| anyhow!("Invalid class id {:?}", s))?;
+    }
+    if cfg!(windows) {
+        swap_guid_fields(&mut cid);
+    }
+    Ok(cid)
+}
+
+fn swap_guid_fields(bytes: &mut TUID) {
+    bytes[0..4].reverse();
+    bytes[4..6].reverse();
+    bytes[6..8].reverse();
+}
+
+impl VstPreset {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut chunks: Vec<(&[u8; 4], &[u8])> = vec![(COMPONENT_CHUNK, &self.component)];
+        if let Some(controller) = &self.controller {
+            chunks.push((CONTROLLER_CHUNK, controller));
+        }
+
+        let mut out = Vec::with_capacity(HEADER_LEN + self.component.len() + 64);
+        out.extend_from_slice(HEADER_ID);
+        out.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        out.extend_from_slice(class_id_to_string(&self.class_id).as_bytes());
+        out.extend_from_slice(&[0; 8]); // List offset, patched below
+
+        let mut entries = Vec::with_capacity(chunks.len());
+        for (id, data) in chunks {
+            entries.push((id, out.len() as i64, data.len() as i64));
+            out.extend_from_slice(data);
+        }
+
+        let list_offset = out.len() as i64;
+        out[HEADER_LEN - 8..HEADER_LEN].copy_from_slice(&list_offset.to_le_bytes());
+        out.extend_from_slice(LIST_ID);
+        out.extend_from_slice(&(entries.len() as i32).to_le_bytes());
+        for (id, offset, size) in entries {
+            out.extend_from_slice(id);
+            out.extend_from_slice(&offset.to_le_bytes());
+            out.extend_from_slice(&size.to_le_bytes());
+        }
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_LEN || &bytes[0..4] != HEADER_ID {
+            return Err(anyhow!("Not a VST3 preset file"));
+        }
+        let class_id = std::str::from_utf8(&bytes[8..8 + CLASS_ID_LEN])
+            .map_err(|_| anyhow!("Invalid class id in preset header"))
+            .and_then(class_id_from_string)?;
+
+        let list_offset = read_i64(bytes, HEADER_LEN - 8)?;
+        let list = usize::try_from(list_offset)
+            .ok()
+            .and_then(|offset| bytes.get(offset..))
+            .filter(|list| list.len() >= 8 && &list[0..4] == LIST_ID)
+            .ok_or_else(|| anyhow!("Preset chunk list is missing or damaged"))?;
+        let count = read_i32(list, 4)?.max(0) as usize;
+
+        let mut component = None;
+        let mut controller = None;
+        for index in 0..count {
+            let entry = 8 + index * 20;
+            let id = list
+                .get(entry..entry + 4)
+                .ok_or_else(|| anyhow!("Preset chunk list is truncated"))?;
+            let offset = read_i64(list, entry + 4)?;
+            let size = read_i64(list, entry + 12)?;
+            let data = usize::try_from(offset)
+                .ok()
+                .zip(usize::try_from(size).ok())
+                .and_then(|(offset, size)| bytes.get(offset..offset.checked_add(size)?))
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Preset chunk {} lies outside the file",
+                        String::from_utf8_lossy(id)
+                    )
+                })?;
+            match id {
+                id if id == COMPONENT_CHUNK => component = Some(data.to_vec()),
+                id if id == CONTROLLER_CHUNK => controller = Some(data.to_vec()),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            class_id,
+            component: component.ok_or_else(|| anyhow!("Preset has no component state"))?,
+            controller,
+        })
+    }
+}
+
+fn read_i32(bytes: &[u8], at: usize) -> Result<i32> {
+    let raw = bytes
+        .get(at..at + 4)
+        .ok_or_else(|| anyhow!("Preset file is truncated"))?;
+    Ok(i32::from_le_bytes(raw.try_into().unwrap()))
+}
+
+fn read_i64(bytes: &[u8], at: usize) -> Result<i64> {
+    let raw = bytes
+        .get(at..at + 8)
+        .ok_or_else(|| anyhow!("Preset file is truncated"))?;
+    Ok(i64::from_le_bytes(raw.try_into().unwrap()))
+}
+
+pub fn save(path: &Path, preset: &VstPreset) -> Result<()> {
+    fs::write(path, preset.to_bytes()).with_context(|| format!("Failed to write {:?}", path))
+}
+
+pub fn load(path: &Path) -> Result<VstPreset> {
+    let bytes = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    VstPreset::from_bytes(&bytes).with_context(|| format!("Invalid preset {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CID: TUID = [
+        0x56, 0x53, 0x54, 0x41, 0x47, 0x61, 0x69, 0x6E, 0x76, 0x73, 0x74, 0x33, 0x00, 0x01, 0x02,
+        0x03,
+    ];
+
+    #[test]
+    fn round_trip() {
+        let preset = VstPreset {
+            class_id: CID,
+            component: vec![1, 2, 3, 4, 5],
+            controller: Some(vec![9, 8]),
+        };
+        let bytes = preset.to_bytes();
+        assert_eq!(&bytes[0..4], b"VST3");
+        assert_eq!(VstPreset::from_bytes(&bytes).unwrap(), preset);
+
+        let component_only = VstPreset {
+            controller: None,
+            ..preset
+        };
+        assert_eq!(
+            VstPreset::from_bytes(&component_only.to_bytes()).unwrap(),
+            component_only
+        );
+    }
+
+    #[test]
+    fn class_id_string_follows_the_sdk() {
+        let s = class_id_to_string(&CID);
+        if cfg!(windows) {
+            assert_eq!(s, "4154535661476E697673743300010203");
+        } else {
+            assert_eq!(s, "565354414761696E7673743300010203");
+        }
+        assert_eq!(class_id_from_string(&s).unwrap(), CID);
+        assert!(class_id_from_string("not a class id").is_err());
+    }
+
+    #[test]
+    fn rejects_damaged_files() {
+        let preset = VstPreset {
+            class_id: CID,
+            component: vec![7; 16],
+            controller: None,
+        };
+        let bytes = preset.to_bytes();
+
+        assert!(VstPreset::from_bytes(b"RIFF").is_err());
+        // Truncated chunk list
+        assert!(VstPreset::from_bytes(&bytes[..bytes.len() - 4]).is_err());
+        // Chunk pointing past the end
+        let mut bad = bytes.clone();
+        let list = HEADER_LEN + 16;
+        bad[list + 16..list + 24].copy_from_slice(&1000i64.to_le_bytes());
+        assert!(VstPreset::from_bytes(&bad).is_err());
+        // No component chunk
+        let mut bad = bytes;
+        bad[list + 8..list + 12].copy_from_slice(b"Info");
+        assert!(VstPreset::from_bytes(&bad).is_err());
+    }
+}
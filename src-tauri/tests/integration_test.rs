@@ -7,7 +7,11 @@ use std::time::{Duration, Instant};
 
 // Reuse types from lib if they are public, or redefine minimal ones for test to decouple.
 // Since 'vst_host_lib' exposes them, we can use them!
-use vst_host_lib::ipc::{Command as IpcCommand, OutputMessage, Response};
+use vst_host_lib::ipc::{
+    Command as IpcCommand, EngineEvent, OutputMessage, Reply, Request, RequestId, Response,
+    PROTOCOL_VERSION,
+};
+use vst_host_lib::vst_host::presets::PresetPlugin;
 
 #[test]
 fn test_audio_engine_lifecycle() {
@@ -56,7 +60,7 @@ fn test_audio_engine_lifecycle() {
                     let payload = trim.strip_prefix("IPC:").unwrap_or(trim);
                     if let Ok(msg) = serde_json::from_str::<OutputMessage>(payload) {
                         if let OutputMessage::Response(r) = msg {
-                            let _ = resp_tx.send(r);
+                            let _ = resp_tx.send(r.response);
                         }
                     }
                 }
@@ -162,3 +166,248 @@ fn test_audio_engine_lifecycle() {
         assert!(status.success(), "Audio Engine exited with error");
     }
 }
+
+#[test]
+fn test_null_host_command_protocol() {
+    // Null host: no sound card needed, the output callback runs from a timer thread.
+    let bin_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target/debug/audio_engine.exe");
+
+    let mut child = Command::new(&bin_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .expect("Failed to spawn audio_engine. MUST be built first with `cargo build`.");
+
+    let mut stdin = child.stdin.take().expect("Failed to open stdin");
+    let stdout = child.stdout.take().expect("Failed to open stdout");
+    let mut reader = BufReader::new(stdout);
+    let (resp_tx, resp_rx) = mpsc::channel::<Reply>();
+    let (evt_tx, evt_rx) = mpsc::channel::<EngineEvent>();
+
+    thread::spawn(move || {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match reader.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => {
+                    let trim = line.trim();
+                    let payload = trim.strip_prefix("IPC:").unwrap_or(trim);
+                    match serde_json::from_str::<OutputMessage>(payload) {
+                        Ok(OutputMessage::Response(r)) => {
+                            let _ = resp_tx.send(r);
+                        }
+                        Ok(OutputMessage::Event(e)) => {
+                            let _ = evt_tx.send(e);
+                        }
+                        Err(_) => {}
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    let mut next_id: RequestId = 0;
+    let mut request = |cmd: IpcCommand| -> Option<Response> {
+        next_id += 1;
+        let json = serde_json::to_string(&Request {
+            id: next_id,
+            command: cmd,
+        })
+        .unwrap();
+        writeln!(stdin, "{}", json).expect("Failed to write to stdin");
+        let reply = resp_rx.recv_timeout(Duration::from_secs(20)).ok()?;
+        assert_eq!(
+            reply.id,
+            Some(next_id),
+            "Reply id must match the request id"
+        );
+        Some(reply.response)
+    };
+
+    // Handshake: the engine reports the protocol revision this test was built against
+    match request(IpcCommand::Hello {
+        protocol_version: PROTOCOL_VERSION,
+    }) {
+        Some(Response::Version(version)) => {
+            assert_eq!(version.protocol_version, PROTOCOL_VERSION);
+            assert!(version.capabilities.hosts.iter().any(|h| h == "Null"));
+        }
+        other => panic!("Hello failed: {:?}", other),
+    }
+
+    // A. Start with a generated sine on the input
+    match request(IpcCommand::Start {
+        host: "Null".to_string(),
+        input: Some("Sine".to_string()),
+        output: None,
+        buffer_size: Some(256),
+        sample_rate: Some(48000),
+    }) {
+        Some(Response::Started {
+            sample_rate,
+            buffer_size,
+        }) => {
+            assert_eq!(sample_rate, 48000);
+            assert_eq!(buffer_size, 256);
+        }
+        r => panic!("Expected Started from Null host, got {:?}", r),
+    }
+
+    // B. The real callback runs: the sine must show up on the output meter
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut saw_output = false;
+    while Instant::now() < deadline {
+        if let Ok(EngineEvent::LevelMeter(levels)) = evt_rx.recv_timeout(Duration::from_millis(100))
+        {
+            if levels.output[0] > 0.05 && levels.output[1] > 0.05 {
+                saw_output = true;
+                break;
+            }
+        }
+    }
+    assert!(saw_output, "No output level from Null host");
+
+    // The host's supervisor relies on a heartbeat about once a second
+    let deadline = Instant::now() + Duration::from_secs(3);
+    let mut saw_heartbeat = false;
+    while Instant::now() < deadline {
+        if let Ok(EngineEvent::Heartbeat) = evt_rx.recv_timeout(Duration::from_millis(100)) {
+            saw_heartbeat = true;
+            break;
+        }
+    }
+    assert!(saw_heartbeat, "No heartbeat from engine");
+
+    // C. Parameter commands are accepted while running
+    for cmd in [
+        IpcCommand::SetInputGain { value: 0.5 },
+        IpcCommand::SetOutputGain { value: 0.8 },
+        IpcCommand::SetGain {
+            id: "missing".to_string(),
+            value: 0.5,
+        },
+        IpcCommand::SetGlobalBypass { active: true },
+        IpcCommand::SetGlobalBypass { active: false },
+    ] {
+        assert!(
+            matches!(request(cmd), Some(Response::Success)),
+            "Command rejected on Null host"
+        );
+    }
+
+    // D. A bad plugin path is an error, not a crash
+    assert!(matches!(
+        request(IpcCommand::LoadPlugin {
+            path: "Z:/does/not/exist.vst3".to_string(),
+            class_id: None,
+        }),
+        Some(Response::Error(_))
+    ));
+
+    // D1. Plugin parameters need a loaded plugin
+    for cmd in [
+        IpcCommand::GetParameters {
+            id: "missing".to_string(),
+        },
+        IpcCommand::SetParameter {
+            id: "missing".to_string(),
+            param_id: 0,
+            value: 0.5,
+        },
+    ] {
+        assert!(matches!(request(cmd), Some(Response::Error(_))));
+    }
+
+    // D2. ApplyChain is all-or-nothing: one bad entry fails the whole request
+    let missing = PresetPlugin {
+        path: "Z:/does/not/exist.vst3".to_string(),
+        class_id: None,
+        name: "Missing".to_string(),
+        vendor: String::new(),
+        version: String::new(),
+        enabled: true,
+        muted: false,
+        gain: 1.0,
+        state: None,
+        controller_state: None,
+        midi_input: None,
+    };
+    assert!(matches!(
+        request(IpcCommand::ApplyChain {
+            plugins: vec![missing]
+        }),
+        Some(Response::Error(_))
+    ));
+    match request(IpcCommand::ApplyChain {
+        plugins: Vec::new(),
+    }) {
+        Some(Response::ChainApplied(applied)) => assert!(applied.is_empty()),
+        r => panic!("Expected ChainApplied, got {:?}", r),
+    }
+
+    // E. Runtime stats
+    match request(IpcCommand::GetRuntimeStats) {
+        Some(Response::RuntimeStats {
+            active_plugin_count,
+            ..
+        }) => assert_eq!(active_plugin_count, 0),
+        r => panic!("Expected RuntimeStats, got {:?}", r),
+    }
+
+    // F. Snapshot reflects what was set above
+    match request(IpcCommand::GetSnapshot) {
+        Some(Response::Snapshot(snapshot)) => {
+            assert!(snapshot.plugins.is_empty());
+            assert_eq!(snapshot.input_gain, 0.5);
+            assert_eq!(snapshot.output_gain, 0.8);
+            assert!(!snapshot.global_bypass);
+            let audio = snapshot.audio.expect("audio config while running");
+            assert_eq!(audio.host, "Null");
+            assert_eq!(audio.input.as_deref(), Some("Sine"));
+            assert_eq!(audio.sample_rate, 48000);
+            assert_eq!(audio.buffer_size, 256);
+        }
+        r => panic!("Expected Snapshot, got {:?}", r),
+    }
+
+    // F2. Session capture for the host's autosave
+    match request(IpcCommand::GetSession) {
+        Some(Response::Session { session, ids }) => {
+            assert!(session.plugins.is_empty());
+            assert!(ids.is_empty());
+            assert_eq!(session.input_gain, 0.5);
+            assert_eq!(session.output_gain, 0.8);
+        }
+        r => panic!("Expected Session, got {:?}", r),
+    }
+    assert!(matches!(
+        request(IpcCommand::SessionSaved { ids: Vec::new() }),
+        Some(Response::Success)
+    ));
+
+    // G. Stop and exit
+    assert!(matches!(request(IpcCommand::Stop), Some(Response::Success)));
+    match request(IpcCommand::GetSnapshot) {
+        Some(Response::Snapshot(snapshot)) => assert!(snapshot.audio.is_none()),
+        r => panic!("Expected Snapshot, got {:?}", r),
+    }
+    drop(request);
+    drop(stdin);
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match child.try_wait() {
+            Ok(Some(_)) => break,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                break;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(e) => panic!("Failed to wait on child: {}", e),
+        }
+    }
+}
@@ -40,7 +40,7 @@ fn test_ott_editor_loading() {
                     let payload = trim.strip_prefix("IPC:").unwrap_or(trim);
                     if let Ok(msg) = serde_json::from_str::<OutputMessage>(payload) {
                         if let OutputMessage::Response(r) = msg {
-                            let _ = resp_tx.send(r);
+                            let _ = resp_tx.send(r.response);
                         }
                     }
                 }
@@ -86,6 +86,7 @@ fn test_ott_editor_loading() {
         &mut stdin,
         IpcCommand::LoadPlugin {
             path: ott_path.to_string(),
+            class_id: None,
         },
     );
 
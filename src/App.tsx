@@ -46,6 +46,7 @@ function AppContent() {
       ui.setCrashError(err);
       ui.setIsRecoveryModalOpen(true);
     },
+    onEngineRestarted: pluginsApi.syncFromEngine,
     onLoadPreset: pluginsApi.loadPreset
   });
 
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { PresetPlugin } from "./presets";
 
 export interface AudioDevice {
     name: string;
@@ -20,9 +21,68 @@ export interface VstPlugin {
     name: string;
     path: string;
     vendor: string;
-    category: string;
+    category: string; // Sub-categories, e.g. "Fx|Dynamics"
     version: string;
     id: string;
+    class_id?: string; // Audio module class; a .vst3 may contain several
+    sdk_version?: string;
+    capabilities?: PluginCapabilities | null; // Probed by the scanner; missing if it could not instantiate the class
+}
+
+export interface AudioBus {
+    name: string;
+    channels: number;
+    aux: boolean; // Sidechain input
+}
+
+export interface PluginCapabilities {
+    inputs: AudioBus[];
+    outputs: AudioBus[];
+    accepts_stereo: boolean;
+    accepts_mono: boolean;
+    has_sidechain: boolean;
+    latency_samples: number;
+    tail_samples: number; // 0xFFFFFFFF = infinite
+    parameter_count: number;
+    has_editor: boolean;
+    supports_64bit: boolean;
+}
+
+export type ScanStatus = 'cached' | 'scanned' | 'blacklisted' | 'failed';
+
+export interface ScanFileResult {
+    status: ScanStatus;
+    plugins: VstPlugin[];
+    error?: string | null;
+}
+
+// Payload of the "scan-progress" event, sent once per .vst3 binary
+export interface ScanProgress {
+    done: number;
+    total: number;
+    current_path: string;
+    result: ScanFileResult;
+}
+
+export type BlacklistReason = 'crash' | 'timeout' | 'bad_output' | 'unknown';
+
+// A plugin the scanner skips until its binary changes
+export interface BlacklistEntry {
+    reason: BlacklistReason;
+    exit_code?: number | null;
+    error?: string | null;
+    blacklisted_at: number; // Unix ms of the last failure
+    modified?: number | null; // Binary mtime at that failure
+    failures: number;
+}
+
+export type SearchPathSource = 'user' | 'environment' | 'per_user' | 'system';
+
+// A folder the scanner searches, in search order
+export interface SearchPath {
+    path: string;
+    source: SearchPathSource;
+    exists: boolean;
 }
 
 export interface AudioConfig {
@@ -49,14 +109,40 @@ export const audioApi = {
     stop: async () => {
         return await invoke("stop_audio");
     },
-    scanPlugins: async (): Promise<VstPlugin[]> => {
-        return await invoke("scan_plugins");
+    // Unchanged plugins come from the scan cache unless forceRescan is set
+    scanPlugins: async (forceRescan = false): Promise<VstPlugin[]> => {
+        return await invoke("scan_plugins", { forceRescan });
+    },
+    // The running scanPlugins call then rejects with "cancelled"
+    cancelPluginScan: async (): Promise<void> => {
+        return await invoke("cancel_plugin_scan");
+    },
+    getVstSearchPaths: async (): Promise<SearchPath[]> => {
+        return await invoke("get_vst_search_paths");
+    },
+    // Opens a folder dialog; rejects with "cancelled" if closed
+    addVstSearchPath: async (): Promise<SearchPath[]> => {
+        return await invoke("add_vst_search_path");
+    },
+    removeVstSearchPath: async (path: string): Promise<SearchPath[]> => {
+        return await invoke("remove_vst_search_path", { path });
+    },
+    // Keyed by binary path
+    getBlacklist: async (): Promise<Record<string, BlacklistEntry>> => {
+        return await invoke("get_blacklist");
+    },
+    removeFromBlacklist: async (path: string): Promise<void> => {
+        return await invoke("remove_from_blacklist", { path });
+    },
+    // Scans the binary again now; it stays blacklisted if the result is 'blacklisted'
+    retryBlacklistedPlugin: async (path: string): Promise<ScanFileResult> => {
+        return await invoke("retry_blacklisted_plugin", { path });
     },
     clearBlacklist: async (): Promise<void> => {
         return await invoke("clear_blacklist");
     },
-    loadPlugin: async (path: string): Promise<string> => {
-        return await invoke("load_plugin", { path });
+    loadPlugin: async (path: string, classId?: string): Promise<string> => {
+        return await invoke("load_plugin", { path, classId });
     },
     removePlugin: async (id: string) => {
         return await invoke("remove_plugin", { id });
@@ -64,6 +150,10 @@ export const audioApi = {
     reorderPlugins: async (order: string[]) => {
         return await invoke("reorder_plugins", { order });
     },
+    // Replaces the whole rack in one step; already loaded plugins are reused.
+    applyChain: async (plugins: PresetPlugin[]): Promise<AppliedPlugin[]> => {
+        return await invoke("apply_chain", { plugins });
+    },
     setBypass: async (id: string, active: boolean) => {
         return await invoke("set_bypass", { id, active });
     },
@@ -87,12 +177,48 @@ export const audioApi = {
     openEditor: async (id: string) => {
         return await invoke("open_editor", { id });
     },
+    getPluginParameters: async (id: string): Promise<PluginParameter[]> => {
+        return await invoke("get_plugin_parameters", { id });
+    },
+    // value is normalized (0.0 - 1.0); resolves to what the plugin made of it
+    setPluginParameter: async (id: string, paramId: number, value: number): Promise<ParameterValue> => {
+        return await invoke("set_plugin_parameter", { id, paramId, value });
+    },
+    // Steinberg .vstpreset files; both open a file dialog and reject with "cancelled" if closed
+    exportVstPreset: async (id: string, name: string): Promise<void> => {
+        return await invoke("export_vst_preset", { id, name });
+    },
+    importVstPreset: async (id: string): Promise<void> => {
+        return await invoke("import_vst_preset", { id });
+    },
+    // Factory programs (IUnitInfo program lists)
+    getPluginPrograms: async (id: string): Promise<ProgramList[]> => {
+        return await invoke("get_plugin_programs", { id });
+    },
+    // Resolves to the program-change parameter's new value
+    selectPluginProgram: async (id: string, listId: number, programIndex: number): Promise<ParameterValue> => {
+        return await invoke("select_plugin_program", { id, listId, programIndex });
+    },
     setGlobalMute: async (active: boolean) => {
         return await invoke("set_global_mute", { active });
     },
     toggleGlobalMute: async () => {
         return await invoke("toggle_global_mute");
     },
+    // MIDI controller mappings, saved in the config dir
+    getMidiMappings: async (): Promise<MidiMapping[]> => {
+        return await invoke("get_midi_mappings");
+    },
+    setMidiMappings: async (mappings: MidiMapping[]): Promise<void> => {
+        return await invoke("set_midi_mappings", { mappings });
+    },
+    // The next CC or note binds to target; the mapping arrives as the "midi-learned" event
+    startMidiLearn: async (target: MidiTarget): Promise<void> => {
+        return await invoke("start_midi_learn", { target });
+    },
+    cancelMidiLearn: async (): Promise<void> => {
+        return await invoke("cancel_midi_learn");
+    },
     setInputGain: async (value: number) => {
         return await invoke("set_input_gain", { value });
     },
@@ -105,11 +231,11 @@ export const audioApi = {
     setGlobalBypass: async (active: boolean) => {
         return await invoke("set_global_bypass", { active });
     },
-    getPluginState: async (id: string): Promise<string> => {
+    getPluginState: async (id: string): Promise<PluginStateData> => {
         return await invoke("get_plugin_state", { id });
     },
-    setPluginState: async (id: string, state: string) => {
-        return await invoke("set_plugin_state", { id, state });
+    setPluginState: async (id: string, data: PluginStateData) => {
+        return await invoke("set_plugin_state", { id, data });
     },
     setInputChannels: async (left: number, right: number) => {
         return await invoke("set_input_channels", { left, right });
@@ -117,6 +243,17 @@ export const audioApi = {
     setChannelScan: async (active: boolean) => {
         return await invoke("set_channel_scan", { active });
     },
+    // Tempo / time signature / playing state reported to plugins (tempo-synced effects)
+    setTransport: async (transport: TransportSettings): Promise<void> => {
+        return await invoke("set_transport", { transport });
+    },
+    getMidiPorts: async (): Promise<string[]> => {
+        return await invoke("get_midi_ports");
+    },
+    // input = null disconnects the plugin from MIDI
+    setMidiInput: async (id: string, input: MidiAssignment | null): Promise<void> => {
+        return await invoke("set_midi_input", { id, input });
+    },
     getAudioState: async (): Promise<AudioStateInfo> => {
         return await invoke("get_audio_state");
     },
@@ -128,6 +265,14 @@ export const audioApi = {
     },
     getEngineRuntimeStats: async (): Promise<EngineRuntimeStats> => {
         return await invoke("get_engine_runtime_stats");
+    },
+    /** Current engine state for resync after reload/restart (null if the engine isn't running) */
+    getEngineSnapshot: async (): Promise<EngineSnapshot | null> => {
+        return await invoke("get_engine_snapshot");
+    },
+    // Forget the engine's autosaved chain (it is otherwise restored on the next engine start)
+    discardSession: async (): Promise<void> => {
+        return await invoke("discard_session");
     }
 };
 
@@ -169,3 +314,179 @@ export interface EngineRuntimeStats {
     noiseReductionActive: boolean;
     noiseReductionMode: string;
 }
+
+export interface PluginSnapshot {
+    id: string;
+    path: string;
+    class_id: string;
+    name: string;
+    rt_index: number | null;
+    bypassed: boolean;
+    muted: boolean;
+    gain: number;
+    latency_samples: number;
+    pending_init: boolean;
+    // Unsaved changes reported by the plugin since the last session autosave
+    dirty: boolean;
+    accepts_midi: boolean; // Has an event input, so setMidiInput can route MIDI to it
+    midi_input: MidiAssignment | null;
+}
+
+export interface MidiAssignment {
+    port: string; // As listed by getMidiPorts
+    channel: number | null; // 1-16; null = all channels
+}
+
+// Plugins are named by path, class and which instance of it in chain order (0 = first),
+// since their ids change on every load
+export interface PluginRef {
+    path: string;
+    class_id?: string | null; // VstPlugin.class_id; absent or null matches any class
+    occurrence: number;
+}
+
+export type MidiTarget =
+    | { type: "Gain"; payload: { plugin: PluginRef } }
+    | { type: "Bypass"; payload: { plugin: PluginRef } }
+    | { type: "Mute"; payload: { plugin: PluginRef } }
+    | { type: "Parameter"; payload: { plugin: PluginRef; param_id: number } }
+    | { type: "GlobalMute" }
+    | { type: "InputGain" }
+    | { type: "OutputGain" }
+    | { type: "NoiseReduction" };
+
+export interface MidiMapping {
+    trigger: {
+        port: string;
+        channel: number; // 1-16
+        kind: "cc" | "note";
+        number: number; // 0-127
+    };
+    target: MidiTarget;
+    min: number; // May be above max to invert the control
+    max: number;
+    curve: "linear" | "exponential" | "logarithmic";
+    mode: "continuous" | "toggle" | "momentary";
+}
+
+export interface AppliedPlugin {
+    id: string;
+    name: string;
+    path: string;
+    reused: boolean;
+}
+
+export interface AudioConfigSnapshot {
+    host: string;
+    input: string | null;
+    output: string | null;
+    sample_rate: number;
+    buffer_size: number;
+    channels: number;
+}
+
+export interface EngineSnapshot {
+    plugins: PluginSnapshot[];
+    order: string[];
+    global_bypass: boolean;
+    global_mute: boolean;
+    input_gain: number;
+    output_gain: number;
+    noise_reduction_enabled: boolean;
+    noise_reduction_mode: string;
+    input_channels: [number, number];
+    channel_scan: boolean;
+    transport: TransportSettings;
+    audio: AudioConfigSnapshot | null;
+}
+
+export interface TransportSettings {
+    tempo: number; // BPM (20-999)
+    time_sig_numerator: number;
+    time_sig_denominator: number; // 1, 2, 4, 8, 16 or 32
+    playing: boolean;
+}
+
+export type CrashPhase = 'loading' | 'state' | 'processing' | 'editor';
+
+export interface CrashCulprit {
+    phase: CrashPhase;
+    path: string;
+    name: string;
+}
+
+// Payload of the "engine-crashed" event
+export interface EngineCrashReport {
+    reason: string;
+    culprit: CrashCulprit | null;
+    quarantine: 'bypass' | 'resetState' | 'remove' | null;
+    attempt: number;
+    // null if the engine will not be restarted automatically
+    restartInMs: number | null;
+}
+
+// Payload of the "engine-restarted" event
+export interface EngineRestarted {
+    attempt: number;
+    audio: AudioConfig | null;
+}
+
+// ParameterInfo flags (PluginParameter.flags)
+export const PARAM_CAN_AUTOMATE = 1 << 0;
+export const PARAM_IS_READ_ONLY = 1 << 1;
+export const PARAM_IS_WRAP_AROUND = 1 << 2;
+export const PARAM_IS_LIST = 1 << 3;
+export const PARAM_IS_HIDDEN = 1 << 4;
+export const PARAM_IS_PROGRAM_CHANGE = 1 << 15;
+export const PARAM_IS_BYPASS = 1 << 16;
+
+export interface PluginParameter {
+    id: number;
+    title: string;
+    short_title: string;
+    units: string;
+    // 0 = continuous, 1 = toggle, n = n + 1 discrete values
+    step_count: number;
+    default_value: number;
+    // Normalized (0.0 - 1.0)
+    value: number;
+    display: string;
+    unit_id: number;
+    flags: number;
+}
+
+export interface ParameterValue {
+    param_id: number;
+    value: number;
+    display: string;
+}
+
+// Base64 state chunks; controller_state is the edit controller's own (view settings etc.)
+export interface PluginStateData {
+    state: string;
+    controller_state?: string | null;
+}
+
+export interface ProgramList {
+    id: number;
+    name: string;
+    programs: string[];
+    param_id: number | null; // Program-change parameter; null = the list can only be shown
+    current: number | null;
+}
+
+// Payload of the "plugin-parameter-changed" event (edit made in the plugin's own editor)
+export interface PluginParameterChanged {
+    id: string;
+    param_id: number;
+    value: number;
+    display: string;
+}
+
+// Payload of the "plugin-restarted" event (plugin changed its latency or I/O setup).
+// "plugin-parameters-changed" carries just the plugin id: refetch with getPluginParameters.
+export interface PluginRestarted {
+    id: string;
+    latency_samples: number;
+    total_chain_latency_samples: number;
+}
@@ -1,7 +1,9 @@
 import { invoke } from "@tauri-apps/api/core";
+import type { MidiAssignment } from "./audio";
 
 export interface PresetPlugin {
     path: string;
+    class_id?: string;
     name: string;
     vendor: string;
     version: string;
@@ -9,6 +11,8 @@ export interface PresetPlugin {
     muted: boolean;
     gain: number;
     state?: string;
+    controller_state?: string;
+    midi_input?: MidiAssignment;
 }
 
 export interface Preset {
@@ -2,7 +2,8 @@ import React, { useEffect, useState, useMemo } from 'react';
 import { MdClose, MdRefresh, MdSettings, MdWarning, MdLightbulb, MdExtension, MdSave, MdDelete, MdPalette, MdFavorite, MdLanguage } from 'react-icons/md';
 import { ConfirmDialog } from '../../ui/confirm-dialog';
 import { openUrl } from '@tauri-apps/plugin-opener';
-import { audioApi, AudioDeviceList, EngineRuntimeStats, EngineTuningConfig } from '../../../api/audio';
+import { listen } from '@tauri-apps/api/event';
+import { audioApi, AudioDeviceList, EngineRuntimeStats, EngineTuningConfig, PluginRestarted } from '../../../api/audio';
 import { autostartApi } from '../../../api/autostart';
 import { obsApi } from '../../../api/obs';
 import { toast } from 'sonner';
@@ -12,6 +13,7 @@ import { Switch } from "@/components/ui/switch";
 import { Label } from '@/components/ui/label';
 import { useTheme } from '../../../hooks/useTheme';
 import { InputChannelSelector } from './InputChannelSelector';
+import { BlacklistManager } from './BlacklistManager';
 import { APP_INFO } from '../../../constants/appInfo';
 
 interface AudioSettingsModalProps {
@@ -116,6 +118,7 @@ export const AudioSettingsModal: React.FC<AudioSettingsModalProps> = ({
   const [engineStats, setEngineStats] = useState<EngineRuntimeStats | null>(null);
   const [systemSaving, setSystemSaving] = useState(false);
   const [statsLoading, setStatsLoading] = useState(false);
+  const [blacklistVersion, setBlacklistVersion] = useState(0);
 
   // Confirm dialog state
   const [confirmDialog, setConfirmDialog] = useState<{
@@ -190,6 +193,21 @@ export const AudioSettingsModal: React.FC<AudioSettingsModalProps> = ({
     }
   }, [isOpen, currentSampleRate, currentBufferSize]);
 
+  // A plugin that changes its latency restarts itself; keep the latency readout current
+  useEffect(() => {
+    if (!isOpen) return;
+    const unlisten = listen<PluginRestarted>('plugin-restarted', () => {
+      audioApi.getEngineRuntimeStats()
+        .then((stats) => setEngineStats(stats))
+        .catch((e) => {
+          console.error('Failed to refresh engine runtime stats', e);
+        });
+    });
+    return () => {
+      unlisten.then(f => f());
+    };
+  }, [isOpen]);
+
   // Debug effect removed (was logging props/state changes)
 
   const handleConnectObs = async () => {
@@ -1331,7 +1349,7 @@ export const AudioSettingsModal: React.FC<AudioSettingsModalProps> = ({
                 <div className="flex items-center justify-between">
                   <div>
                     <div className="text-sm font-medium text-foreground">ブラックリストの初期化</div>
-                    <div className="text-xs text-muted-foreground mt-0.5">読み込みエラーで除外されたプラグイン設定をリセットします。</div>
+                    <div className="text-xs text-muted-foreground mt-0.5">読み込みエラーで除外されたプラグイン設定をリセットします。更新されたプラグインは自動で再チェックされます。</div>
                   </div>
                   <button
                     onClick={() => {
@@ -1344,6 +1362,7 @@ export const AudioSettingsModal: React.FC<AudioSettingsModalProps> = ({
                           setLoading(true);
                           try {
                             await audioApi.clearBlacklist();
+                            setBlacklistVersion(v => v + 1);
                             toast.success("ブラックリストをクリアしました");
                           } catch (e) {
                             console.error(e);
@@ -1359,6 +1378,7 @@ export const AudioSettingsModal: React.FC<AudioSettingsModalProps> = ({
                     クリア
                   </button>
                 </div>
+                <BlacklistManager refreshKey={blacklistVersion} />
               </div>
             </div>
 
@@ -0,0 +1,108 @@
+import React, { useEffect, useState } from 'react';
+import { MdDelete, MdRefresh } from 'react-icons/md';
+import { toast } from 'sonner';
+import { audioApi, BlacklistEntry, BlacklistReason } from '../../../api/audio';
+
+interface BlacklistManagerProps {
+    // Bumped by the parent after it clears the whole list
+    refreshKey?: number;
+}
+
+const REASON_LABELS: Record<BlacklistReason, string> = {
+    crash: 'クラッシュ',
+    timeout: 'タイムアウト',
+    bad_output: '不正な応答',
+    unknown: '不明',
+};
+
+const fileName = (path: string) => path.split(/[\\/]/).pop() ?? path;
+
+export const BlacklistManager: React.FC<BlacklistManagerProps> = ({ refreshKey }) => {
+    const [entries, setEntries] = useState<[string, BlacklistEntry][]>([]);
+    const [busyPath, setBusyPath] = useState<string | null>(null);
+
+    const refresh = async () => {
+        try {
+            setEntries(Object.entries(await audioApi.getBlacklist()));
+        } catch (e) {
+            console.error("Failed to load blacklist:", e);
+        }
+    };
+
+    useEffect(() => {
+        refresh();
+    }, [refreshKey]);
+
+    const handleRetry = async (path: string) => {
+        setBusyPath(path);
+        try {
+            const result = await audioApi.retryBlacklistedPlugin(path);
+            if (result.status === 'blacklisted') {
+                toast.error(`${fileName(path)} は再び読み込みに失敗しました`);
+            } else if (result.status === 'failed') {
+                toast.warning(`${fileName(path)} をブラックリストから外しましたが、読み込めるエフェクトがありません`);
+            } else {
+                toast.success(`${fileName(path)} を読み込めるようになりました。プラグイン一覧を再スキャンしてください`);
+            }
+        } catch (e) {
+            console.error("Failed to retry blacklisted plugin:", e);
+            toast.error(typeof e === 'string' ? e : "再チェックに失敗しました");
+        } finally {
+            setBusyPath(null);
+            refresh();
+        }
+    };
+
+    const handleRemove = async (path: string) => {
+        setBusyPath(path);
+        try {
+            await audioApi.removeFromBlacklist(path);
+        } catch (e) {
+            console.error("Failed to remove blacklist entry:", e);
+            toast.error("削除に失敗しました");
+        } finally {
+            setBusyPath(null);
+            refresh();
+        }
+    };
+
+    if (entries.length === 0) {
+        return <div className="text-xs text-muted-foreground">除外されているプラグインはありません。</div>;
+    }
+
+    return (
+        <div className="space-y-1.5">
+            {entries.map(([path, entry]) => (
+                <div key={path} className="flex items-center gap-2 p-2 bg-background/50 border border-border rounded-lg">
+                    <div className="flex-1 min-w-0">
+                        <div className="text-xs font-medium text-foreground truncate" title={path}>{fileName(path)}</div>
+                        <div className="text-[10px] text-muted-foreground truncate" title={entry.error ?? undefined}>
+                            {REASON_LABELS[entry.reason]}
+                            {entry.exit_code != null && ` (終了コード ${entry.exit_code})`}
+                            {' · '}{new Date(entry.blacklisted_at).toLocaleString()}
+                            {entry.failures > 1 && ` · ${entry.failures}回`}
+                        </div>
+                    </div>
+                    <button
+                        onClick={() => handleRetry(path)}
+                        disabled={busyPath !== null}
+                        aria-label={`${fileName(path)} を再チェック`}
+                        title="再チェック"
+                        className="p-1 rounded text-muted-foreground hover:text-primary transition-colors disabled:opacity-50"
+                    >
+                        <MdRefresh className={`w-4 h-4 ${busyPath === path ? 'animate-spin' : ''}`} />
+                    </button>
+                    <button
+                        onClick={() => handleRemove(path)}
+                        disabled={busyPath !== null}
+                        aria-label={`${fileName(path)} をブラックリストから削除`}
+                        title="ブラックリストから削除（次回スキャン時に再チェック）"
+                        className="p-1 rounded text-muted-foreground hover:text-destructive transition-colors disabled:opacity-50"
+                    >
+                        <MdDelete className="w-4 h-4" />
+                    </button>
+                </div>
+            ))}
+        </div>
+    );
+};
@@ -1,7 +1,8 @@
 import React, { useEffect, useState, useMemo } from 'react';
-import { VstPlugin } from '../../../api/audio';
+import { audioApi, PluginCapabilities, ScanProgress, SearchPath, SearchPathSource, VstPlugin } from '../../../api/audio';
 import { Panel } from '../../ui/Panel/Panel';
-import { MdStar, MdStarBorder, MdHistory, MdGridView, MdClose, MdSearch, MdRefresh, MdWarning } from 'react-icons/md';
+import { MdStar, MdStarBorder, MdHistory, MdGridView, MdClose, MdSearch, MdRefresh, MdWarning, MdFolderOpen, MdAdd, MdDelete } from 'react-icons/md';
+import { toast } from 'sonner';
 
 interface PluginBrowserModalProps {
   isOpen: boolean;
@@ -9,8 +10,10 @@ interface PluginBrowserModalProps {
   onPluginSelect: (plugin: VstPlugin) => void;
   plugins: VstPlugin[];
   isLoading: boolean;
+  scanProgress?: ScanProgress | null;
   error?: string | null;
-  onScan: () => void;
+  onScan: (forceRescan?: boolean) => void;
+  onCancelScan?: () => void;
 }
 
 type TabType = 'all' | 'favorites' | 'recent';
@@ -22,6 +25,13 @@ const STORAGE_KEYS = {
 
 const MAX_RECENT = 10;
 
+const SEARCH_PATH_SOURCE_LABELS: Record<SearchPathSource, string> = {
+  user: '追加',
+  environment: 'VST3_PATH',
+  per_user: 'ユーザー',
+  system: 'システム',
+};
+
 // Map VST3 category strings to user-friendly Japanese labels
 const CATEGORY_LABELS: Record<string, string> = {
   'Fx': 'エフェクト全般',
@@ -74,20 +84,42 @@ function getSimpleCategory(category: string): string {
   return '未分類';
 }
 
+// Same rule as PluginCapabilities::is_mono_only in the backend
+function isMonoOnly(caps: PluginCapabilities): boolean {
+  if (caps.accepts_stereo) return false;
+  const mainBuses = [...caps.inputs, ...caps.outputs].filter(bus => !bus.aux);
+  return caps.accepts_mono || (mainBuses.length > 0 && mainBuses.every(bus => bus.channels === 1));
+}
+
+// Things to know before loading the plugin into the live chain
+function getCapabilityWarnings(plugin: VstPlugin): string[] {
+  const caps = plugin.capabilities;
+  if (!caps) return [];
+  const warnings: string[] = [];
+  if (isMonoOnly(caps)) warnings.push('モノラル専用');
+  if (caps.has_sidechain) warnings.push('サイドチェイン入力あり');
+  if (!caps.has_editor) warnings.push('エディタなし');
+  return warnings;
+}
+
 export const PluginBrowserModal: React.FC<PluginBrowserModalProps> = ({
   isOpen,
   onClose,
   onPluginSelect,
   plugins,
   isLoading,
+  scanProgress,
   error,
-  onScan
+  onScan,
+  onCancelScan
 }) => {
   const [searchQuery, setSearchQuery] = useState('');
   const [activeTab, setActiveTab] = useState<TabType>('all');
   const [selectedCategory, setSelectedCategory] = useState<string | null>(null);
   const [favorites, setFavorites] = useState<string[]>([]);
   const [recentPaths, setRecentPaths] = useState<string[]>([]);
+  const [showFolders, setShowFolders] = useState(false);
+  const [searchPaths, setSearchPaths] = useState<SearchPath[]>([]);
 
   // Load from localStorage
   useEffect(() => {
@@ -134,6 +166,12 @@ export const PluginBrowserModal: React.FC<PluginBrowserModalProps> = ({
     // Add to recent
     const newRecent = [plugin.path, ...recentPaths.filter(p => p !== plugin.path)].slice(0, MAX_RECENT);
     saveRecent(newRecent);
+    const caps = plugin.capabilities;
+    if (caps && isMonoOnly(caps)) {
+      toast.warning(`${plugin.name} はモノラル専用のため、ステレオ信号では正しく動作しない可能性があります`);
+    } else if (caps?.has_sidechain) {
+      toast.warning(`${plugin.name} にはサイドチェイン入力がありますが、Auralyn からは信号を送れません`);
+    }
     onPluginSelect(plugin);
   };
 
@@ -144,6 +182,38 @@ export const PluginBrowserModal: React.FC<PluginBrowserModalProps> = ({
     }
   };
 
+  useEffect(() => {
+    if (!showFolders) return;
+    audioApi.getVstSearchPaths()
+      .then(setSearchPaths)
+      .catch(e => {
+        console.error("Failed to load VST3 search paths:", e);
+        toast.error("検索フォルダの取得に失敗しました");
+      });
+  }, [showFolders]);
+
+  // Changing the folders rescans, so the list matches them right away
+  const handleAddFolder = async () => {
+    try {
+      setSearchPaths(await audioApi.addVstSearchPath());
+      onScan();
+    } catch (e) {
+      if (e === 'cancelled') return;
+      console.error("Failed to add VST3 search path:", e);
+      toast.error("フォルダの追加に失敗しました");
+    }
+  };
+
+  const handleRemoveFolder = async (path: string) => {
+    try {
+      setSearchPaths(await audioApi.removeVstSearchPath(path));
+      onScan();
+    } catch (e) {
+      console.error("Failed to remove VST3 search path:", e);
+      toast.error("フォルダの削除に失敗しました");
+    }
+  };
+
   useEffect(() => {
     if (isOpen && plugins.length === 0) {
       onScan();
@@ -249,16 +319,65 @@ export const PluginBrowserModal: React.FC<PluginBrowserModalProps> = ({
             />
           </div>
           <button
-            onClick={onScan}
+            onClick={(e) => onScan(e.shiftKey)}
             disabled={isLoading}
             aria-label="プラグインを再スキャン"
             className="px-4 py-2 bg-muted/50 border border-input rounded-lg text-muted-foreground hover:text-primary hover:border-primary/50 transition-all disabled:opacity-50"
-            title="再スキャン"
+            title="再スキャン（Shift+クリックで全プラグインを再スキャン）"
           >
             <MdRefresh className={`w-4 h-4 ${isLoading ? 'animate-spin' : ''}`} />
           </button>
+          <button
+            onClick={() => setShowFolders(!showFolders)}
+            aria-label="検索フォルダ"
+            aria-pressed={showFolders}
+            className={`px-4 py-2 border rounded-lg transition-all ${showFolders
+              ? 'bg-primary/10 text-primary border-primary/30'
+              : 'bg-muted/50 border-input text-muted-foreground hover:text-primary hover:border-primary/50'
+              }`}
+            title="検索フォルダ"
+          >
+            <MdFolderOpen className="w-4 h-4" />
+          </button>
         </div>
 
+        {/* Search Folders */}
+        {showFolders && (
+          <div className="mb-3 p-2 bg-muted/30 border border-border rounded-lg max-h-40 overflow-y-auto custom-scrollbar">
+            {searchPaths.map(folder => (
+              <div key={folder.path} className="flex items-center gap-2 px-1 py-0.5 text-xs">
+                <span className="shrink-0 w-16 text-[10px] text-muted-foreground">
+                  {SEARCH_PATH_SOURCE_LABELS[folder.source]}
+                </span>
+                <span
+                  className={`flex-1 truncate ${folder.exists ? 'text-foreground' : 'text-muted-foreground line-through'}`}
+                  title={folder.exists ? folder.path : `${folder.path}（見つかりません）`}
+                >
+                  {folder.path}
+                </span>
+                {folder.source === 'user' && (
+                  <button
+                    onClick={() => handleRemoveFolder(folder.path)}
+                    disabled={isLoading}
+                    aria-label={`${folder.path} を検索フォルダから削除`}
+                    className="p-0.5 rounded text-muted-foreground hover:text-destructive transition-colors disabled:opacity-50"
+                  >
+                    <MdDelete className="w-3.5 h-3.5" />
+                  </button>
+                )}
+              </div>
+            ))}
+            <button
+              onClick={handleAddFolder}
+              disabled={isLoading}
+              className="mt-1 flex items-center gap-1 px-1 py-0.5 text-xs text-primary hover:underline disabled:opacity-50"
+            >
+              <MdAdd className="w-3.5 h-3.5" />
+              フォルダを追加
+            </button>
+          </div>
+        )}
+
         {/* Category Filter Chips */}
         {activeTab === 'all' && categories.length > 1 && !isLoading && (
           <div className="flex gap-1.5 mb-3 flex-wrap">
@@ -293,11 +412,28 @@ export const PluginBrowserModal: React.FC<PluginBrowserModalProps> = ({
           {isLoading ? (
             <div className="flex flex-col items-center justify-center h-40 text-muted-foreground gap-3">
               <div className="w-6 h-6 border-2 border-primary border-t-transparent rounded-full animate-spin" />
-              <p className="text-sm font-bold">プラグインをスキャン中...</p>
-              <p className="text-xs opacity-70 text-center max-w-[80%] animate-pulse">
-                初回は数分かかる場合があります。<br />
-                画面が止まって見えても、そのままお待ちください。
+              <p className="text-sm font-bold">
+                プラグインをスキャン中...
+                {scanProgress && ` (${scanProgress.done} / ${scanProgress.total})`}
               </p>
+              {scanProgress ? (
+                <p className="text-xs opacity-70 text-center max-w-[80%] truncate" title={scanProgress.current_path}>
+                  {scanProgress.current_path.split(/[\\/]/).pop()}
+                </p>
+              ) : (
+                <p className="text-xs opacity-70 text-center max-w-[80%] animate-pulse">
+                  初回は数分かかる場合があります。<br />
+                  画面が止まって見えても、そのままお待ちください。
+                </p>
+              )}
+              {onCancelScan && (
+                <button
+                  onClick={onCancelScan}
+                  className="px-3 py-1 text-xs rounded-md border border-border hover:bg-muted transition-colors"
+                >
+                  スキャンを中止
+                </button>
+              )}
             </div>
           ) : error ? (
             <div className="flex flex-col items-center justify-center h-40 text-destructive gap-2">
@@ -315,6 +451,8 @@ export const PluginBrowserModal: React.FC<PluginBrowserModalProps> = ({
             <div className="grid grid-cols-1 gap-1">
               {filteredPlugins.map((plugin, idx) => {
                 const isFavorite = favorites.includes(plugin.path);
+                const warnings = getCapabilityWarnings(plugin);
+                const latency = plugin.capabilities?.latency_samples ?? 0;
                 return (
                   <div
                     key={`${plugin.id}-${idx}`}
@@ -348,6 +486,21 @@ export const PluginBrowserModal: React.FC<PluginBrowserModalProps> = ({
                           <span className="opacity-30 mx-1">|</span>
                           <span className="text-primary/70">{getCategoryLabel(plugin.category)}</span>
                         </p>
+                        {(warnings.length > 0 || latency > 0) && (
+                          <div className="flex flex-wrap gap-1 mt-1">
+                            {warnings.map(warning => (
+                              <span key={warning} className="flex items-center gap-0.5 px-1.5 py-0.5 text-[10px] bg-yellow-500/10 text-yellow-500 rounded border border-yellow-500/20">
+                                <MdWarning className="w-3 h-3" />
+                                {warning}
+                              </span>
+                            ))}
+                            {latency > 0 && (
+                              <span className="px-1.5 py-0.5 text-[10px] bg-muted text-muted-foreground rounded border border-border">
+                                レイテンシ {latency} サンプル
+                              </span>
+                            )}
+                          </div>
+                        )}
                       </div>
                     </div>
                     <div className="opacity-0 group-hover:opacity-100 transition-opacity">
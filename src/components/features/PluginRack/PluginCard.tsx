@@ -19,6 +19,7 @@ export interface Plugin {
     id: string;
     name: string;
     path: string;
+    class_id?: string;
     vendor: string;
     version: string;
     enabled: boolean;
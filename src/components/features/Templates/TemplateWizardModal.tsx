@@ -4,6 +4,9 @@ import { CHAIN_TEMPLATES, ChainTemplate } from '../../../templates/chainTemplate
 import { VstPlugin } from '../../../api/audio';
 import { toast } from 'sonner';
 
+// A .vst3 can hold several effects, so the path alone does not pick one
+const pluginKey = (p: VstPlugin) => `${p.path}|${p.class_id ?? ''}`;
+
 interface TemplateWizardModalProps {
     isOpen: boolean;
     onClose: () => void;
@@ -191,17 +194,17 @@ export const TemplateWizardModal: React.FC<TemplateWizardModalProps> = ({
                                                         className={`bg-background border rounded px-3 py-1.5 text-sm outline-none focus:border-primary max-w-[300px] w-full
                                                             ${!assigned && slot.required ? 'border-red-400' : 'border-input'}
                                                         `}
-                                                        value={assigned ? assigned.path : ""}
+                                                        value={assigned ? pluginKey(assigned) : ""}
                                                         onChange={(e) => {
-                                                            const path = e.target.value;
-                                                            const p = availablePlugins.find(pl => pl.path === path) || null;
+                                                            const key = e.target.value;
+                                                            const p = availablePlugins.find(pl => pluginKey(pl) === key) || null;
                                                             setMapping(prev => ({ ...prev, [index]: p }));
                                                         }}
                                                     >
                                                         <option value="">{isScanning ? "（スキャン中...）" : "（選択してください）"}</option>
                                                         {/* Show recommended first? complex sorting might be overkill */}
                                                         {availablePlugins.map(p => (
-                                                            <option key={p.path} value={p.path}>{p.name}</option>
+                                                            <option key={pluginKey(p)} value={pluginKey(p)}>{p.name}</option>
                                                         ))}
                                                     </select>
                                                 </div>
@@ -1,5 +1,5 @@
 import { Suspense, lazy } from 'react';
-import { VstPlugin } from '../../api/audio';
+import { ScanProgress, VstPlugin } from '../../api/audio';
 import { audioApi } from '../../api/audio';
 import type { Plugin } from '../features/PluginRack/PluginCard';
 
@@ -55,8 +55,10 @@ interface PluginsApi {
     plugins: Plugin[];
     availablePlugins: VstPlugin[];
     isScanning: boolean;
+    scanProgress: ScanProgress | null;
     error: string | null;
-    scanPlugins: () => Promise<void>;
+    scanPlugins: (forceRescan?: boolean) => Promise<void>;
+    cancelScan: () => Promise<void>;
     addPlugin: (vstPlugin: VstPlugin) => Promise<boolean>;
     loadPreset: (name: string) => Promise<boolean>;
     savePreset: (name: string) => Promise<boolean>;
@@ -117,8 +119,10 @@ export function ModalLayer({ ui, audioConfig, handleConfigUpdate, pluginsApi, on
                         onPluginSelect={handlePluginSelect}
                         plugins={pluginsApi.availablePlugins}
                         isLoading={pluginsApi.isScanning}
+                        scanProgress={pluginsApi.scanProgress}
                         error={pluginsApi.error}
                         onScan={pluginsApi.scanPlugins}
+                        onCancelScan={pluginsApi.cancelScan}
                     />
                 </Suspense>
             )}
@@ -204,12 +208,16 @@ export function ModalLayer({ ui, audioConfig, handleConfigUpdate, pluginsApi, on
                         isOpen={ui.isRecoveryModalOpen}
                         onClose={() => ui.setIsRecoveryModalOpen(false)}
                         error={ui.crashError}
-                        onClear={() => {
+                        onClear={async () => {
                             pluginsApi.resetPlugins();
+                            await audioApi.discardSession().catch(e => console.warn("Failed to discard session", e));
                             audioApi.start(audioConfig.host, audioConfig.input, audioConfig.output, audioConfig.bufferSize || 512, audioConfig.sampleRate || 48000);
                         }}
                         onRecover={async (_safeMode, excludePath) => {
                             try {
+                                // The chain is rebuilt below without the culprit; don't let the
+                                // engine restore the crashing one on start.
+                                await audioApi.discardSession();
                                 await audioApi.start(audioConfig.host, audioConfig.input, audioConfig.output, audioConfig.bufferSize || 512, audioConfig.sampleRate || 48000);
                             } catch (e) {
                                 console.error("Recovery Restart Failed", e);
@@ -2,7 +2,7 @@ import { useEffect } from 'react';
 import { getCurrentWindow } from '@tauri-apps/api/window';
 import { listen } from '@tauri-apps/api/event';
 import { toast } from 'sonner';
-import { audioApi, VstPlugin } from '../api/audio';
+import { audioApi, EngineCrashReport, VstPlugin } from '../api/audio';
 import { obsApi } from '../api/obs';
 import { presetApi } from '../api/presets';
 
@@ -10,6 +10,7 @@ interface UseAppEventsProps {
     onAddPlugin: (plugin: VstPlugin) => Promise<boolean>;
     onResetPlugins: () => void;
     onCrash: (error: string) => void;
+    onEngineRestarted: () => void;
     onLoadPreset: (name: string) => Promise<boolean>;
 }
 
@@ -17,6 +18,7 @@ export const useAppEvents = ({
     onAddPlugin,
     onResetPlugins,
     onCrash,
+    onEngineRestarted,
     onLoadPreset
 }: UseAppEventsProps) => {
 
@@ -83,6 +85,44 @@ export const useAppEvents = ({
         };
     }, [onResetPlugins, onCrash]);
 
+    // Engine Supervisor: automatic restart after a crash or hang
+    useEffect(() => {
+        const unlistenCrashed = listen<EngineCrashReport>('engine-crashed', (event) => {
+            const report = event.payload;
+            console.error("Audio engine crashed:", report);
+            const culprit = report.culprit;
+            if (report.restartInMs === null) {
+                // Gave up: 'audio-error' opens the recovery dialog, which offers to skip this plugin
+                if (culprit) localStorage.setItem('vst_host_detected_crash_plugin', culprit.path);
+                return;
+            }
+
+            let description = culprit
+                ? `${culprit.name} が原因の可能性があります。自動で再起動しています...`
+                : '自動で再起動しています...';
+            if (culprit && report.quarantine === 'bypass') {
+                description = `${culprit.name} が繰り返しクラッシュしたため、バイパスして復元します。`;
+            } else if (culprit && report.quarantine === 'resetState') {
+                description = `${culprit.name} が状態の復元中に繰り返しクラッシュしたため、初期状態・バイパスで復元します。`;
+            } else if (culprit && report.quarantine === 'remove') {
+                description = `${culprit.name} が繰り返しクラッシュしたため、チェーンから外して復元します。`;
+            }
+            toast.warning('オーディオエンジンが停止しました', { description, duration: 8000 });
+        });
+        const unlistenRestarted = listen('engine-restarted', () => {
+            onEngineRestarted();
+            toast.success('オーディオエンジンを再起動しました');
+        });
+        const unlistenFailed = listen<string>('engine-restart-failed', (event) => {
+            toast.error('オーディオエンジンの再起動に失敗しました', { description: event.payload });
+        });
+        return () => {
+            unlistenCrashed.then(f => f());
+            unlistenRestarted.then(f => f());
+            unlistenFailed.then(f => f());
+        };
+    }, [onEngineRestarted]);
+
     // Device Hot-Plug / Stream Error Listener (Auto-Recovery)
     useEffect(() => {
         let retryTimer: ReturnType<typeof setTimeout> | null = null;
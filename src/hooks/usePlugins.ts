@@ -1,14 +1,37 @@
 import { useState, useCallback, useEffect, useRef } from 'react';
-import { audioApi, VstPlugin } from '../api/audio';
+import { listen } from '@tauri-apps/api/event';
+import { audioApi, EngineSnapshot, PluginStateData, ScanProgress, VstPlugin } from '../api/audio';
 import { Plugin } from '../components/features/PluginRack/PluginCard';
 import { presetApi, PresetPlugin as ApiPresetPlugin } from '../api/presets';
 import { toast } from 'sonner';
 
+// Rack entries for the engine's chain; vendor/version come from the locally saved session.
+const pluginsFromSnapshot = (snapshot: EngineSnapshot): Plugin[] => {
+    const saved = localStorage.getItem('vst_host_session_plugins');
+    const items: any[] = saved ? JSON.parse(saved) : [];
+    return snapshot.plugins.map(p => {
+        const item = items.find(i => i.path === p.path && (!i.class_id || i.class_id === p.class_id));
+        return {
+            id: p.id,
+            name: item?.name ?? p.name,
+            path: p.path,
+            class_id: p.class_id,
+            vendor: item?.vendor ?? "",
+            version: item?.version ?? "",
+            enabled: !p.bypassed,
+            muted: p.muted,
+            gain: p.gain,
+            hasEditor: true
+        } as Plugin;
+    });
+};
+
 export const usePlugins = () => {
     const [plugins, setPlugins] = useState<Plugin[]>([]);
     const [availablePlugins, setAvailablePlugins] = useState<VstPlugin[]>([]);
     const [isLoading, setIsLoading] = useState(false);
     const [isScanning, setIsScanning] = useState(false);
+    const [scanProgress, setScanProgress] = useState<ScanProgress | null>(null);
     const [error, setError] = useState<string | null>(null);
     const [isSessionLoaded, setIsSessionLoaded] = useState(false);
 
@@ -20,21 +43,26 @@ export const usePlugins = () => {
             // console.log("Auto-saving session...");
             const sessionData = await Promise.all(plugins.map(async p => {
                 let state: string | undefined;
+                let controller_state: string | undefined;
                 try {
                     // Always try to get fresh state from backend
-                    state = await audioApi.getPluginState(p.id);
+                    const data = await audioApi.getPluginState(p.id);
+                    state = data.state;
+                    controller_state = data.controller_state ?? undefined;
                 } catch (e) {
                     console.warn(`Session save: Could not get state for ${p.id}`);
                 }
                 return {
                     path: p.path,
+                    class_id: p.class_id,
                     name: p.name,
                     vendor: p.vendor,
                     version: p.version,
                     enabled: p.enabled,
                     muted: p.muted,
                     gain: p.gain,
-                    state
+                    state,
+                    controller_state
                 };
             }));
             localStorage.setItem('vst_host_session_plugins', JSON.stringify(sessionData));
@@ -60,6 +88,21 @@ export const usePlugins = () => {
 
     const restoreSession = useCallback(async () => {
         const saved = localStorage.getItem('vst_host_session_plugins');
+
+        // The engine restores its own autosaved chain on start, and the snapshot waits until it
+        // has; adopt it instead of loading everything a second time. Loading from localStorage
+        // below makes that chain the session, so the engine won't replay its own over it.
+        try {
+            const snapshot = await audioApi.getEngineSnapshot();
+            if (snapshot && snapshot.plugins.length > 0) {
+                setPlugins(pluginsFromSnapshot(snapshot));
+                setIsSessionLoaded(true);
+                return;
+            }
+        } catch (e) {
+            console.warn("Engine snapshot unavailable, restoring from local session", e);
+        }
+
         if (saved) {
             try {
                 setIsLoading(true);
@@ -73,7 +116,7 @@ export const usePlugins = () => {
                         if (!item.path) return null;
 
                         localStorage.setItem('vst_host_pending_plugin', item.path);
-                        const id = await audioApi.loadPlugin(item.path);
+                        const id = await audioApi.loadPlugin(item.path, item.class_id);
                         localStorage.removeItem('vst_host_pending_plugin');
 
                         // Apply state (fire and forget setting updates to speed up?)
@@ -82,7 +125,7 @@ export const usePlugins = () => {
                         if (!item.enabled) updates.push(audioApi.setBypass(id, true));
                         if (item.muted) updates.push(audioApi.setMute(id, true));
                         if (item.gain !== 1.0) updates.push(audioApi.setGain(id, item.gain));
-                        if (item.state) updates.push(audioApi.setPluginState(id, item.state).catch(e => console.error(`Failed to state ${item.name}`, e)));
+                        if (item.state) updates.push(audioApi.setPluginState(id, { state: item.state, controller_state: item.controller_state }).catch(e => console.error(`Failed to state ${item.name}`, e)));
 
                         await Promise.all(updates);
 
@@ -90,6 +133,7 @@ export const usePlugins = () => {
                             id,
                             name: item.name,
                             path: item.path,
+                            class_id: item.class_id,
                             vendor: item.vendor,
                             version: item.version,
                             enabled: item.enabled,
@@ -146,7 +190,7 @@ export const usePlugins = () => {
 
                     try {
                         localStorage.setItem('vst_host_pending_plugin', item.path);
-                        const id = await audioApi.loadPlugin(item.path);
+                        const id = await audioApi.loadPlugin(item.path, item.class_id);
                         localStorage.removeItem('vst_host_pending_plugin');
 
                         // Apply state
@@ -157,7 +201,7 @@ export const usePlugins = () => {
                         // Restore Internal State
                         if (item.state) {
                             try {
-                                await audioApi.setPluginState(id, item.state);
+                                await audioApi.setPluginState(id, { state: item.state, controller_state: item.controller_state });
                             } catch (e) {
                                 console.error(`Failed to restore state for ${item.name}`, e);
                             }
@@ -167,6 +211,7 @@ export const usePlugins = () => {
                             id, // new ID
                             name: item.name,
                             path: item.path,
+                            class_id: item.class_id,
                             vendor: item.vendor,
                             version: item.version,
                             enabled: item.enabled,
@@ -192,6 +237,19 @@ export const usePlugins = () => {
         setIsSessionLoaded(true);
     }, []);
 
+    // The supervisor restarted a crashed engine and restored its chain (possibly with a
+    // quarantined plugin bypassed or removed): mirror it as is, never reload from localStorage.
+    const syncFromEngine = useCallback(async () => {
+        try {
+            const snapshot = await audioApi.getEngineSnapshot();
+            if (!snapshot) return;
+            setPlugins(pluginsFromSnapshot(snapshot));
+            setIsSessionLoaded(true);
+        } catch (e) {
+            console.error("Failed to resync plugins after engine restart", e);
+        }
+    }, []);
+
     // Restore Session (initial load)
     const restoreStarted = useRef(false);
 
@@ -202,17 +260,33 @@ export const usePlugins = () => {
         }
     }, [restoreSession]);
 
-    const scanPlugins = useCallback(async () => {
+    const scanPlugins = useCallback(async (forceRescan = false) => {
         setIsScanning(true);
+        setScanProgress(null);
         setError(null);
+        const unlisten = listen<ScanProgress>('scan-progress', (event) => {
+            setScanProgress(event.payload);
+        });
         try {
-            const result = await audioApi.scanPlugins();
+            const result = await audioApi.scanPlugins(forceRescan);
             setAvailablePlugins(result);
         } catch (err) {
+            // Cancelled by the user: keep the previous list
+            if (err === 'cancelled') return;
             console.error("Failed to scan plugins:", err);
             setError("プラグインのスキャンに失敗しました。");
         } finally {
+            unlisten.then(fn => fn());
             setIsScanning(false);
+            setScanProgress(null);
+        }
+    }, []);
+
+    const cancelScan = useCallback(async () => {
+        try {
+            await audioApi.cancelPluginScan();
+        } catch (err) {
+            console.error("Failed to cancel plugin scan:", err);
         }
     }, []);
 
@@ -222,7 +296,7 @@ export const usePlugins = () => {
             // Track pending plugin for crash recovery
             localStorage.setItem('vst_host_pending_plugin', vstPlugin.path); // Track pending
 
-            const instanceId = await audioApi.loadPlugin(vstPlugin.path);
+            const instanceId = await audioApi.loadPlugin(vstPlugin.path, vstPlugin.class_id);
 
             localStorage.removeItem('vst_host_pending_plugin'); // Cleared on success
 
@@ -230,6 +304,7 @@ export const usePlugins = () => {
                 id: instanceId,
                 name: vstPlugin.name,
                 path: vstPlugin.path,
+                class_id: vstPlugin.class_id,
                 vendor: vstPlugin.vendor,
                 version: vstPlugin.version,
                 enabled: true,
@@ -256,7 +331,7 @@ export const usePlugins = () => {
 
         try {
             // 1. Capture State for Undo
-            let savedState: string | undefined;
+            let savedState: PluginStateData | undefined;
             try {
                 savedState = await audioApi.getPluginState(id);
             } catch (e) {
@@ -277,7 +352,7 @@ export const usePlugins = () => {
                         const toastId = toast.loading(`${pluginToRemove.name} を復元中...`);
                         try {
                             // Restore Plugin
-                            const newId = await audioApi.loadPlugin(pluginToRemove.path);
+                            const newId = await audioApi.loadPlugin(pluginToRemove.path, pluginToRemove.class_id);
 
                             // Restore Parameters
                             if (!pluginToRemove.enabled) await audioApi.setBypass(newId, true);
@@ -341,7 +416,7 @@ export const usePlugins = () => {
         const reloadAllFromUi = async () => {
             const reloaded: Plugin[] = [];
             for (const p of plugins) {
-                const newId = await audioApi.loadPlugin(p.path);
+                const newId = await audioApi.loadPlugin(p.path, p.class_id);
                 if (!p.enabled) await audioApi.setBypass(newId, true);
                 if (p.muted) await audioApi.setMute(newId, true);
                 if (p.gain !== 1.0) await audioApi.setGain(newId, p.gain);
@@ -367,7 +442,7 @@ export const usePlugins = () => {
                 const toastId = toast.loading("エンジン再起動を検知。プラグインを復元中...");
                 try {
                     const reloaded = await reloadAllFromUi();
-                    const reopened = reloaded.find(p => p.path === target.path);
+                    const reopened = reloaded.find(p => p.path === target.path && p.class_id === target.class_id);
                     if (!reopened) {
                         throw new Error("復元後に対象プラグインが見つかりませんでした");
                     }
@@ -384,7 +459,7 @@ export const usePlugins = () => {
             if (message.includes("Plugin not found") && target) {
                 const toastId = toast.loading("プラグインを再読み込み中...");
                 try {
-                    const newId = await audioApi.loadPlugin(target.path);
+                    const newId = await audioApi.loadPlugin(target.path, target.class_id);
                     if (!target.enabled) await audioApi.setBypass(newId, true);
                     if (target.muted) await audioApi.setMute(newId, true);
                     if (target.gain !== 1.0) await audioApi.setGain(newId, target.gain);
@@ -437,7 +512,7 @@ export const usePlugins = () => {
             const presetPlugins: ApiPresetPlugin[] = [];
             for (const p of plugins) {
                 // Fetch state
-                let state: string | undefined;
+                let state: PluginStateData | undefined;
                 try {
                     state = await audioApi.getPluginState(p.id);
                 } catch (e) {
@@ -446,13 +521,15 @@ export const usePlugins = () => {
 
                 presetPlugins.push({
                     path: p.path,
+                    class_id: p.class_id,
                     name: p.name,
                     vendor: p.vendor,
                     version: p.version,
                     enabled: p.enabled,
                     muted: p.muted,
                     gain: p.gain,
-                    state: state || undefined
+                    state: state?.state || undefined,
+                    controller_state: state?.controller_state || undefined
                 });
             }
             await presetApi.save(name, presetPlugins);
@@ -468,55 +545,25 @@ export const usePlugins = () => {
         setIsLoading(true);
         try {
             const preset = await presetApi.load(name);
-            // Loading preset - clear current plugins and apply
-
-            // 1. Clear current plugins
-            const currentIds = plugins.map(p => p.id);
-            for (const id of currentIds) {
-                await audioApi.removePlugin(id);
-            }
-            // Clear state immediately to avoid UI mismatch during async load
-            setPlugins([]);
-
-            // 2. Load new plugins
-            let loadedPlugins: Plugin[] = [];
-            for (const p of preset.plugins) {
-                try {
-                    const instanceId = await audioApi.loadPlugin(p.path);
-
-                    // Set attributes
-                    if (!p.enabled) await audioApi.setBypass(instanceId, true);
-                    if (p.muted) await audioApi.setMute(instanceId, true);
-                    if (p.gain !== 1.0) await audioApi.setGain(instanceId, p.gain);
-
-                    // Restore State
-                    if (p.state) {
-                        try {
-                            await audioApi.setPluginState(instanceId, p.state);
-                        } catch (err) {
-                            console.error(`Failed to restore state for ${p.name}`, err);
-                            toast.error(`${p.name} の設定復元に失敗しました`);
-                        }
-                    }
-
-                    loadedPlugins.push({
-                        id: instanceId,
-                        name: p.name,
-                        path: p.path,
-                        vendor: p.vendor,
-                        version: p.version,
-                        enabled: p.enabled,
-                        hasEditor: true,
-                        muted: p.muted,
-                        gain: p.gain
-                    });
-                } catch (err) {
-                    console.error(`Failed to load plugin from preset: ${p.name}`, err);
-                    toast.error(`${p.name} の読み込みに失敗しました`);
-                }
-            }
+            // Single engine request: plugins already in the rack are kept, and if anything
+            // fails the current chain stays as it was.
+            const applied = await audioApi.applyChain(preset.plugins);
+            const loadedPlugins: Plugin[] = applied.map((a, i) => {
+                const p = preset.plugins[i];
+                return {
+                    id: a.id,
+                    name: p.name,
+                    path: p.path,
+                    class_id: p.class_id,
+                    vendor: p.vendor,
+                    version: p.version,
+                    enabled: p.enabled,
+                    hasEditor: true,
+                    muted: p.muted,
+                    gain: p.gain
+                };
+            });
             setPlugins(loadedPlugins);
-            // Also update session immediately? The useEffect will catch it.
             return true;
         } catch (e) {
             console.error("Failed to load preset:", e);
@@ -525,7 +572,7 @@ export const usePlugins = () => {
         } finally {
             setIsLoading(false);
         }
-    }, [plugins]);
+    }, []);
 
     const savePluginState = useCallback(async (id: string) => {
         try {
@@ -537,7 +584,7 @@ export const usePlugins = () => {
         }
     }, []);
 
-    const loadPluginState = useCallback(async (id: string, state: string) => {
+    const loadPluginState = useCallback(async (id: string, state: PluginStateData) => {
         try {
             await audioApi.setPluginState(id, state);
             return true;
@@ -568,12 +615,13 @@ export const usePlugins = () => {
 
                 try {
                     // Load plugin for slot
-                    const instanceId = await audioApi.loadPlugin(vst.path);
+                    const instanceId = await audioApi.loadPlugin(vst.path, vst.class_id);
 
                     loadedPlugins.push({
                         id: instanceId,
                         name: vst.name,
                         path: vst.path,
+                        class_id: vst.class_id,
                         vendor: vst.vendor,
                         version: vst.version,
                         enabled: true,
@@ -601,6 +649,7 @@ export const usePlugins = () => {
         plugins,
         availablePlugins,
         scanPlugins,
+        cancelScan,
         addPlugin,
         removePlugin,
         togglePlugin,
@@ -615,9 +664,11 @@ export const usePlugins = () => {
         applyTemplate,
         isLoading,
         isScanning,
+        scanProgress,
         error,
         restoreSession,
         recoverSession,
+        syncFromEngine,
         resetPlugins
     };
 };